@@ -20,12 +20,12 @@ fn main() -> Result<()> {
 
   println!("Starting bulb capture");
 
-  bulb_setting.set_toggled(true);
+  bulb_setting.set_toggled(true)?;
   camera.set_config(&bulb_setting).wait()?;
 
   sleep(Duration::from_secs(2));
 
-  bulb_setting.set_toggled(false);
+  bulb_setting.set_toggled(false)?;
   camera.set_config(&bulb_setting).wait()?;
 
   let mut retry = 0;
@@ -5,8 +5,8 @@ fn main() -> Result<()> {
 
   let camera = Context::new()?.autodetect_camera().wait()?;
 
-  println!("==== SUMMARY   ====\n{}", camera.summary()?);
-  println!("==== ABILITIES ====\n{:#?}", camera.abilities());
+  println!("==== SUMMARY   ====\n{}", camera.summary().wait()?);
+  println!("==== ABILITIES ====\n{:#?}", camera.abilities()?);
   println!("==== STORAGES  ====");
 
   let storages = camera.storages().wait()?;
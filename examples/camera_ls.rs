@@ -1,4 +1,4 @@
-use gphoto2::{filesys::CameraFS, Context, Result};
+use gphoto2::{filesys::CameraFS, path::CameraPath, Context, Result};
 use std::collections::HashMap;
 
 #[derive(Debug)]
@@ -8,17 +8,16 @@ struct FolderContent {
   files: Vec<String>,
 }
 
-fn list_folder_recursive(fs: &CameraFS, folder_name: &str) -> Result<FolderContent> {
-  let folders_iter = fs.list_folders(folder_name).wait()?;
+fn list_folder_recursive(fs: &CameraFS, path: &CameraPath) -> Result<FolderContent> {
+  let folders_iter = fs.list_folders(path).wait()?;
   let mut folders = HashMap::with_capacity(folders_iter.len());
 
   for folder in folders_iter {
-    let folder_full_name =
-      format!("{}/{folder}", if folder_name == "/" { "" } else { folder_name });
-    folders.insert(folder, list_folder_recursive(fs, &folder_full_name)?);
+    let content = list_folder_recursive(fs, &path.join(&folder)?)?;
+    folders.insert(folder, content);
   }
 
-  let files = fs.list_files(folder_name).wait()?.collect();
+  let files = fs.list_files(path).wait()?.collect();
 
   Ok(FolderContent { files, folders })
 }
@@ -29,7 +28,7 @@ fn main() -> Result<()> {
   let camera = Context::new()?.autodetect_camera().wait()?;
   let fs = camera.fs();
 
-  let folders = list_folder_recursive(&fs, "/")?;
+  let folders = list_folder_recursive(&fs, &CameraPath::root())?;
 
   println!("{:#?}", folders);
   Ok(())
@@ -8,7 +8,7 @@ fn main() -> Result<()> {
   let camera = Context::new()?.autodetect_camera().wait()?;
 
   let widget = camera.config().wait()?;
-  let abilities = camera.abilities();
+  let abilities = camera.abilities()?;
 
   drop(camera);
 
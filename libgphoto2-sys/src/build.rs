@@ -7,6 +7,9 @@ fn main() {
   #[cfg(feature = "test")]
   let libgphoto2_dir = libgphoto2_dir.or_else(|| Some(gphoto2_test::libgphoto2_dir().to_owned()));
 
+  #[cfg(feature = "vendored")]
+  let libgphoto2_dir = libgphoto2_dir.or_else(|| Some(build_vendored()));
+
   if let Some(libgphoto2_dir) = libgphoto2_dir {
     env::set_var("PKG_CONFIG_PATH", libgphoto2_dir.join("lib/pkgconfig"));
 
@@ -21,6 +24,11 @@ fn main() {
     .probe("libgphoto2")
     .expect("Could not find libgphoto2");
 
+  // Directory the camera drivers are installed to, exported as `DRIVER_DIR`
+  if let Ok(driver_dir) = pkg_config::get_variable("libgphoto2", "driverdir") {
+    println!("cargo:rustc-env=LIBGPHOTO2_DRIVER_DIR={driver_dir}");
+  }
+
   let bindings = bindgen::Builder::default()
     .clang_args(lib.include_paths.iter().map(|path| format!("-I{}", path.to_str().unwrap())))
     .header("src/wrapper.h")
@@ -37,3 +45,21 @@ fn main() {
   let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
   bindings.write_to_file(out_path.join("bindings.rs")).expect("Couldn't write bindings!");
 }
+
+/// Build libgphoto2 from source and return the installation prefix
+#[cfg(feature = "vendored")]
+fn build_vendored() -> PathBuf {
+  let prefix = PathBuf::from(env::var("OUT_DIR").unwrap()).join("vendored");
+
+  let status = std::process::Command::new("sh")
+    .arg("vendor.sh")
+    .env("VENDOR_PREFIX", &prefix)
+    .status()
+    .expect("Could not run vendor.sh");
+  assert!(status.success(), "Building the vendored libgphoto2 failed");
+
+  // Dependent crates can find the drivers in `DEP_GPHOTO2_ROOT`
+  println!("cargo:root={}", prefix.display());
+
+  prefix
+}
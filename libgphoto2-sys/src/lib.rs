@@ -6,5 +6,9 @@
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
+/// Directory libgphoto2 loads the camera drivers from when `CAMLIBS` isn't set,
+/// as reported by pkg-config at build time
+pub const DRIVER_DIR: Option<&str> = option_env!("LIBGPHOTO2_DRIVER_DIR");
+
 #[cfg(feature = "test")]
 pub use gphoto2_test as test_utils;
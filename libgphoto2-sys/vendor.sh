@@ -0,0 +1,78 @@
+#!/bin/sh
+# Builds libgphoto2 from source for the `vendored` feature.
+#
+# Environment:
+#   VENDOR_PREFIX                  Installation prefix (set by build.rs)
+#   LIBGPHOTO2_VENDORED_SOURCE     Use this extracted source directory instead of downloading a release
+#   LIBGPHOTO2_VENDORED_SHA256     Expected SHA-256 of the release tarball, overrides the pinned one
+#   LIBGPHOTO2_VENDORED_CAMLIBS    Camera drivers to build (default: ptp2)
+set -e
+
+version=2.5.31
+# SHA-256 of libgphoto2-$version.tar.xz as published on the release page, downloads without a
+# matching checksum are never extracted. Has to be filled in whenever the version is bumped,
+# until then the checksum must be passed in LIBGPHOTO2_VENDORED_SHA256.
+pinned_sha256=
+sha256=${LIBGPHOTO2_VENDORED_SHA256:-$pinned_sha256}
+camlibs=${LIBGPHOTO2_VENDORED_CAMLIBS:-ptp2}
+
+echo cargo:rerun-if-changed=$0
+echo cargo:rerun-if-env-changed=LIBGPHOTO2_VENDORED_SOURCE
+echo cargo:rerun-if-env-changed=LIBGPHOTO2_VENDORED_SHA256
+echo cargo:rerun-if-env-changed=LIBGPHOTO2_VENDORED_CAMLIBS
+
+# Resolved before changing the directory, relative paths are relative to libgphoto2-sys
+if [ -n "$LIBGPHOTO2_VENDORED_SOURCE" ] ; then
+	source_dir=$(cd "$LIBGPHOTO2_VENDORED_SOURCE" && pwd -P)
+fi
+
+build_dir=$VENDOR_PREFIX-build
+mkdir -p "$build_dir"
+cd "$build_dir"
+
+if [ -z "$source_dir" ] ; then
+	source_dir=$build_dir/libgphoto2-$version
+
+	if [ ! -d "$source_dir" ] ; then
+		if [ -z "$sha256" ] ; then
+			echo "No SHA-256 pinned for libgphoto2 $version in $0, set LIBGPHOTO2_VENDORED_SHA256 or LIBGPHOTO2_VENDORED_SOURCE" >&2
+			exit 1
+		fi
+
+		tarball=libgphoto2-$version.tar.xz
+		curl -fL -o "$tarball" "https://github.com/gphoto/libgphoto2/releases/download/v$version/$tarball"
+
+		if command -v sha256sum > /dev/null ; then
+			actual=$(sha256sum "$tarball" | cut -d ' ' -f 1)
+		else
+			actual=$(shasum -a 256 "$tarball" | cut -d ' ' -f 1)
+		fi
+
+		if [ "$actual" != "$sha256" ] ; then
+			echo "SHA-256 of $tarball is $actual, expected $sha256" >&2
+			rm -f "$tarball"
+			exit 1
+		fi
+
+		tar -xJf "$tarball"
+		rm -f "$tarball"
+	fi
+fi
+
+if [ ! -f "$source_dir/configure" ] ; then
+	(cd "$source_dir" && autoreconf -iv)
+fi
+
+# The drivers are shared modules loaded at runtime which link to libgphoto2 themselves, so
+# libgphoto2 is built (and linked) as a shared library as well. A statically linked copy would
+# be loaded a second time by every driver.
+"$source_dir/configure" -C \
+	--prefix="$VENDOR_PREFIX" \
+	--disable-static --enable-shared \
+	--with-camlibs="$camlibs" \
+	--disable-nls \
+	--without-libxml-2.0 --without-libcurl --without-gdlib
+
+export MAKEFLAGS=$CARGO_MAKEFLAGS
+
+make install
@@ -3,11 +3,15 @@
 //! The device abilities describe the abilities of the driver used to connect to a device.
 
 use crate::helper::{as_ref, bitflags, char_slice_to_cow};
-use crate::task::BackgroundPtr;
-use crate::{context::Context, try_gp_internal, Result};
-use std::{borrow::Cow, fmt};
+use crate::task::{BackgroundPtr, Task};
+use crate::{context::Context, helper::to_c_string, try_gp_internal, Result};
+use std::{borrow::Cow, ffi, fmt};
 
-pub(crate) struct AbilitiesList {
+/// Models supported by the installed camera drivers
+///
+/// Loading the list reads every camera driver from disk, so each [`Context`] keeps
+/// one in [`Context::abilities_list`].
+pub struct AbilitiesList {
   pub(crate) inner: BackgroundPtr<libgphoto2_sys::CameraAbilitiesList>,
 }
 
@@ -66,7 +70,9 @@ pub enum DeviceType {
 
 impl Drop for AbilitiesList {
   fn drop(&mut self) {
-    try_gp_internal!(gp_abilities_list_free(*self.inner).unwrap());
+    let inner = self.inner;
+
+    unsafe { Task::new(move || libgphoto2_sys::gp_abilities_list_free(*inner)) }.background();
   }
 }
 
@@ -108,7 +114,7 @@ impl fmt::Debug for Abilities {
   }
 }
 
-as_ref!(AbilitiesList -> libgphoto2_sys::CameraAbilitiesList, **self.inner);
+as_ref!(AbilitiesList -> libgphoto2_sys::CameraAbilitiesList, *self.inner.any_thread());
 
 as_ref!(Abilities -> libgphoto2_sys::CameraAbilities, self.inner);
 
@@ -116,10 +122,31 @@ impl AbilitiesList {
   /// Must be called from a [`Task`]
   pub(crate) fn new_inner(context: &Context) -> Result<Self> {
     try_gp_internal!(gp_abilities_list_new(&out abilities_inner)?);
-    try_gp_internal!(gp_abilities_list_load(abilities_inner, *context.inner)?);
+
+    match context.driver_dir() {
+      Some(dir) => {
+        let dir = dir.to_str().ok_or("The driver directory is not UTF-8")?;
+        try_gp_internal!(gp_abilities_list_load_dir(
+          abilities_inner,
+          to_c_string!(dir),
+          *context.inner()
+        )?);
+      }
+      None => {
+        try_gp_internal!(gp_abilities_list_load(abilities_inner, *context.inner())?);
+      }
+    }
 
     Ok(Self { inner: BackgroundPtr(abilities_inner) })
   }
+
+  /// Abilities of a model, must be called from a [`Task`]
+  pub(crate) fn lookup_model(&self, model: &str) -> Result<libgphoto2_sys::CameraAbilities> {
+    try_gp_internal!(let index = gp_abilities_list_lookup_model(*self.inner, to_c_string!(model))?);
+    try_gp_internal!(gp_abilities_list_get_abilities(*self.inner, index, &out abilities)?);
+
+    Ok(abilities)
+  }
 }
 
 impl Abilities {
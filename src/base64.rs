@@ -0,0 +1,118 @@
+//! Standard base64 with padding, used for file data in JSON
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Marks bytes which aren't in [`ALPHABET`] in [`DECODE_TABLE`]
+#[cfg(feature = "remote")]
+const INVALID: u8 = 0xff;
+
+/// Value of each byte in [`ALPHABET`], [`INVALID`] for other bytes
+#[cfg(feature = "remote")]
+const DECODE_TABLE: [u8; 256] = {
+  let mut table = [INVALID; 256];
+  let mut i = 0;
+
+  while i < ALPHABET.len() {
+    // TryFrom can't be used in constants, the values are below 64
+    #[allow(clippy::as_conversions)]
+    {
+      table[ALPHABET[i] as usize] = i as u8;
+    }
+    i += 1;
+  }
+
+  table
+};
+
+#[cfg(feature = "service")]
+pub(crate) fn encode(data: &[u8]) -> String {
+  let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+
+  for chunk in data.chunks(3) {
+    let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+    let group = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+
+    for i in 0..4 {
+      if i <= chunk.len() {
+        let index = (group >> (18 - 6 * i)) & 0x3f;
+        out.push(char::from(ALPHABET[usize::try_from(index).unwrap_or_default()]));
+      } else {
+        out.push('=');
+      }
+    }
+  }
+
+  out
+}
+
+/// Decode `text`, `None` if it isn't valid base64
+#[cfg(feature = "remote")]
+pub(crate) fn decode(text: &str) -> Option<Vec<u8>> {
+  let text = text.trim_end_matches('=').as_bytes();
+  let mut out = Vec::with_capacity(text.len() * 3 / 4);
+
+  for chunk in text.chunks(4) {
+    if chunk.len() == 1 {
+      return None;
+    }
+
+    let mut group = 0u32;
+    for (i, byte) in chunk.iter().enumerate() {
+      let value = DECODE_TABLE[usize::from(*byte)];
+      if value == INVALID {
+        return None;
+      }
+      group |= u32::from(value) << (18 - 6 * i);
+    }
+
+    out.extend_from_slice(&group.to_be_bytes()[1..chunk.len()]);
+  }
+
+  Some(out)
+}
+
+#[cfg(all(test, feature = "test"))]
+mod tests {
+  const CASES: [(&[u8], &str); 7] = [
+    (b"", ""),
+    (b"f", "Zg=="),
+    (b"fo", "Zm8="),
+    (b"foo", "Zm9v"),
+    (b"foob", "Zm9vYg=="),
+    (b"fooba", "Zm9vYmE="),
+    (b"\xff\xfe\x00?>", "//4APz4="),
+  ];
+
+  #[test]
+  #[cfg(feature = "service")]
+  fn test_encode() {
+    for (data, text) in CASES {
+      assert_eq!(super::encode(data), text);
+    }
+  }
+
+  #[test]
+  #[cfg(feature = "remote")]
+  fn test_decode() {
+    for (data, text) in CASES {
+      assert_eq!(super::decode(text).as_deref(), Some(data), "{text}");
+    }
+
+    // Padding is optional
+    assert_eq!(super::decode("Zm8").as_deref(), Some(&b"fo"[..]));
+
+    for invalid in ["Z", "Zm9vY", "Zm9v!A==", "Zm 9v", "Zm9v\u{e9}"] {
+      assert_eq!(super::decode(invalid), None, "{invalid:?}");
+    }
+  }
+
+  #[test]
+  #[cfg(all(feature = "service", feature = "remote"))]
+  fn test_round_trip() {
+    let data: Vec<u8> = (0..=255).collect();
+
+    for len in 0..data.len() {
+      assert_eq!(super::decode(&super::encode(&data[..len])).as_deref(), Some(&data[..len]));
+    }
+  }
+}
@@ -0,0 +1,151 @@
+//! Small command line tool to check cameras, built with the `cli` feature
+//!
+//! Only uses the public API of the crate, run `gphoto2-rs help` for the commands.
+
+use gphoto2::{widget::Widget, Camera, Context, Error, Result};
+use std::{env, fs, io::Write, path::Path, process::ExitCode};
+
+const USAGE: &str = "\
+Usage: gphoto2-rs [--port PORT] COMMAND [ARGS]
+
+Commands:
+  list                         List the connected cameras
+  summary                      Print the summary of the camera
+  capture [FILE]               Capture an image and download it (to its camera name by default)
+  download FOLDER NAME [FILE]  Download a file from the camera
+  ls [FOLDER]                  List the files and folders of a folder (`/` by default)
+  get-config [KEY]             Print a configuration, or all of them without a key
+  set-config KEY VALUE         Change a configuration
+  preview FILE [FRAMES]        Write FRAMES preview images (1 by default) to FILE as MJPEG
+
+Options:
+  --port PORT                  Use the camera at PORT (like `usb:001,004`) instead of the first one";
+
+fn main() -> ExitCode {
+  let mut args: Vec<String> = env::args().skip(1).collect();
+
+  let port = match args.iter().position(|arg| arg == "--port") {
+    Some(i) if i + 1 < args.len() => Some(args.drain(i..=i + 1).nth(1).unwrap()),
+    Some(_) => return usage_error("--port needs a value"),
+    None => None,
+  };
+
+  let Some((command, args)) = args.split_first() else {
+    return usage_error("Missing command");
+  };
+
+  if matches!(command.as_str(), "help" | "--help" | "-h") {
+    println!("{USAGE}");
+    return ExitCode::SUCCESS;
+  }
+
+  match run(command, args, port.as_deref()) {
+    Ok(()) => ExitCode::SUCCESS,
+    Err(error) => {
+      eprintln!("Error: {error}");
+      ExitCode::FAILURE
+    }
+  }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+  eprintln!("{message}\n\n{USAGE}");
+  ExitCode::from(2)
+}
+
+fn run(command: &str, args: &[String], port: Option<&str>) -> Result<()> {
+  let context = Context::new()?;
+
+  if command == "list" {
+    for camera in context.list_cameras().wait()? {
+      println!("{:<32} {}", camera.model, camera.port);
+    }
+    return Ok(());
+  }
+
+  let camera = match port {
+    Some(port) => {
+      let descriptor = context
+        .list_cameras()
+        .wait()?
+        .find(|camera| camera.port == port)
+        .ok_or_else(|| Error::from(format!("No camera at {port}")))?;
+      context.get_camera(&descriptor).wait()?
+    }
+    None => context.autodetect_camera().wait()?,
+  };
+
+  match (command, args) {
+    ("summary", []) => println!("{}", camera.summary().wait()?),
+    ("capture", [] | [_]) => {
+      let path = camera.capture_image().wait()?;
+      let target = args.first().cloned().unwrap_or_else(|| path.name().into_owned());
+
+      camera.fs().download_to(&path.folder(), &path.name(), Path::new(&target)).wait()?;
+      println!("Captured {}/{} to {target}", path.folder(), path.name());
+    }
+    ("download", [folder, name] | [folder, name, _]) => {
+      let target = args.get(2).unwrap_or(name);
+
+      camera.fs().download_to(folder, name, Path::new(target)).wait()?;
+      println!("Downloaded {folder}/{name} to {target}");
+    }
+    ("ls", [] | [_]) => {
+      let folder = args.first().map_or("/", String::as_str);
+
+      for subfolder in camera.fs().list_folders(folder).wait()? {
+        println!("{subfolder}/");
+      }
+      for file in camera.fs().list_files(folder).wait()? {
+        println!("{file}");
+      }
+    }
+    ("get-config", []) => print!("{}", camera.config().wait()?.to_text_tree()),
+    ("get-config", [key]) => print!("{}", camera.config_key::<Widget>(key).wait()?.to_text()),
+    ("set-config", [key, value]) => set_config(&camera, key, value)?,
+    ("preview", [file] | [file, _]) => {
+      let frames = match args.get(1) {
+        Some(frames) => frames.parse().map_err(|_| Error::from("FRAMES must be a number"))?,
+        None => 1u32,
+      };
+      let mut output = fs::File::create(file)?;
+
+      for _ in 0..frames {
+        let frame = camera.capture_preview().wait()?.get_data(&context).wait()?;
+        output.write_all(&frame)?;
+      }
+      println!("Wrote {frames} preview images to {file}");
+    }
+    _ => {
+      return Err(Error::from(format!("Unknown command or wrong arguments, see `help`\n\n{USAGE}")))
+    }
+  }
+
+  Ok(())
+}
+
+fn set_config(camera: &Camera, key: &str, value: &str) -> Result<()> {
+  let invalid = || Error::from(format!("Invalid value {value:?} for {key}"));
+
+  let widget = camera.config_key::<Widget>(key).wait()?;
+
+  match &widget {
+    Widget::Text(text) => text.set_value(value)?,
+    Widget::Range(range) => range.set_value(value.parse().map_err(|_| invalid())?)?,
+    Widget::Toggle(toggle) => toggle.set_toggled(match value {
+      "1" | "on" | "true" => true,
+      "0" | "off" | "false" => false,
+      _ => return Err(invalid()),
+    })?,
+    Widget::Radio(radio) => radio.set_choice(value)?,
+    Widget::Date(date) => date.set_timestamp(value.parse().map_err(|_| invalid())?)?,
+    Widget::Button(_) | Widget::Group(_) => {
+      return Err(Error::from(format!("{key} has no value to set")));
+    }
+  }
+
+  camera.set_config(&widget).wait()?;
+  println!("Set {key} to {value}");
+
+  Ok(())
+}
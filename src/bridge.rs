@@ -0,0 +1,166 @@
+//! Forwarding camera events to frontends as JSON
+//!
+//! An [`EventBridge`] sends the [events](crate::Camera::events) of a camera and the progress of
+//! its tasks as JSON messages to a sink, usually a WebSocket of a browser-based tethering
+//! frontend. Each message is a [`BridgeMessage`] with a `type` field:
+//!
+//! ```text
+//! {"type":"event","event":{"NewFile":{"name":"IMG_0001.JPG","folder":"/store_00010001/DCIM/100CANON"}}}
+//! {"type":"progress","id":0,"message":"Downloading IMG_0001.JPG","target":1.0,"current":0.5,"phase":"Update"}
+//! {"type":"error","message":"..."}
+//! ```
+//!
+//! ```no_run
+//! use gphoto2::{bridge::EventBridge, Context, Result};
+//! use std::sync::mpsc;
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//! let (sender, receiver) = mpsc::channel();
+//!
+//! // Would be the send function of a WebSocket
+//! let bridge = EventBridge::start(&camera, move |message| {
+//!   sender.send(message).map_err(|_| "Frontend disconnected".into())
+//! });
+//!
+//! for message in receiver {
+//!   println!("{message}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+  camera::CameraEvent,
+  context::ProgressEvent,
+  stream::{OverflowPolicy, StreamOptions},
+  Camera, Context, Result,
+};
+use std::{
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, PoisonError,
+  },
+  thread::{self, JoinHandle},
+  time::Duration,
+};
+
+/// Events buffered while the sink is busy, the camera is not polled while the buffer is full
+const EVENT_BUFFER: usize = 64;
+
+/// How often the event thread checks whether the bridge was stopped
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Message sent by an [`EventBridge`]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BridgeMessage {
+  /// Event of the camera
+  Event {
+    /// The event
+    event: CameraEvent,
+  },
+  /// Progress of a task of the camera
+  Progress(ProgressEvent),
+  /// Waiting for events failed, the bridge stops after this message
+  Error {
+    /// Description of the error
+    message: String,
+  },
+}
+
+type Sink = dyn FnMut(String) -> Result<()> + Send;
+
+struct Shared {
+  sink: Mutex<Box<Sink>>,
+  stopped: AtomicBool,
+}
+
+impl Shared {
+  /// Send a message, stopping the bridge if the sink fails
+  fn send(&self, message: &BridgeMessage) {
+    if self.stopped.load(Ordering::Acquire) {
+      return;
+    }
+
+    let result = match serde_json::to_string(message) {
+      Ok(json) => (self.sink.lock().unwrap_or_else(PoisonError::into_inner))(json),
+      Err(error) => Err(error.to_string().into()),
+    };
+
+    if let Err(error) = result {
+      log::warn!("Stopping the event bridge: {error}");
+      self.stopped.store(true, Ordering::Release);
+    }
+  }
+}
+
+/// Forwards events and progress of a camera as JSON, see the [module](self) documentation
+///
+/// The bridge stops when it is dropped or the sink returns an error.
+pub struct EventBridge {
+  shared: Arc<Shared>,
+  context: Context,
+  thread: Option<JoinHandle<()>>,
+}
+
+impl EventBridge {
+  /// Start forwarding the events of `camera` to `sink`
+  ///
+  /// The progress is reported through [`Context::set_progress_fn`] of the camera's context,
+  /// replacing a closure set before. Tasks with their own progress handler are not forwarded.
+  pub fn start<F>(camera: &Camera, sink: F) -> Self
+  where
+    F: FnMut(String) -> Result<()> + Send + 'static,
+  {
+    let shared =
+      Arc::new(Shared { sink: Mutex::new(Box::new(sink)), stopped: AtomicBool::new(false) });
+
+    camera.context.set_progress_fn({
+      let shared = shared.clone();
+      move |progress| shared.send(&BridgeMessage::Progress(progress))
+    });
+
+    let mut events = camera.events(StreamOptions::new(EVENT_BUFFER, OverflowPolicy::Block));
+
+    let thread = thread::spawn({
+      let shared = shared.clone();
+
+      move || {
+        while !shared.stopped.load(Ordering::Acquire) {
+          match events.next_timeout(STOP_POLL_INTERVAL) {
+            Some(Ok(event)) => shared.send(&BridgeMessage::Event { event }),
+            Some(Err(error)) => {
+              shared.send(&BridgeMessage::Error { message: error.to_string() });
+              shared.stopped.store(true, Ordering::Release);
+            }
+            None => {}
+          }
+        }
+      }
+    });
+
+    Self { shared, context: camera.context.clone(), thread: Some(thread) }
+  }
+
+  /// Whether the bridge stopped because of an error
+  pub fn is_stopped(&self) -> bool {
+    self.shared.stopped.load(Ordering::Acquire)
+  }
+
+  /// Stop forwarding and wait until the event thread finished
+  pub fn stop(self) {
+    drop(self);
+  }
+}
+
+impl Drop for EventBridge {
+  fn drop(&mut self) {
+    self.shared.stopped.store(true, Ordering::Release);
+    self.context.unset_progress_fn();
+
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
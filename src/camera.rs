@@ -1,21 +1,48 @@
 //! Cameras and camera events
 
 use crate::{
-  abilities::Abilities,
+  abilities::{Abilities, AbilitiesList},
+  clock,
   file::{CameraFile, CameraFilePath},
   filesys::{CameraFS, StorageInfo},
-  helper::{as_ref, char_slice_to_cow, chars_to_string, to_c_string, UninitBox},
-  port::PortInfo,
-  task::{BackgroundPtr, Task},
+  firmware::{FirmwareOptions, FirmwareReport},
+  governor::{BusyGovernor, GovernorState},
+  helper::{as_ref, char_slice_to_cow, chars_to_string, check_cancelled, to_c_string, UninitBox},
+  journal::{CaptureKind, ShotJournal},
+  keepalive::KeepAlive,
+  list::{CameraDescriptor, CameraList, CameraListIter, FileListIter},
+  liveview::LiveView,
+  metrics::Operation,
+  movie,
+  naming::{self, NamingTemplate, NamingValues},
+  port::{PortInfo, PortInfoList},
+  profile::{CameraProfile, SERIAL_NUMBER_KEY},
+  ptp_property, quirks,
+  settings::CameraSettings,
+  stream::{Stream, StreamOptions},
+  support::{capability_support, require_capability, Capability, SupportMatrix},
+  task::{run_on_worker, BackgroundPtr, Task},
+  thread::{is_worker_thread, LockQueue},
   try_gp_internal,
-  widget::{GroupWidget, Widget, WidgetBase},
+  widget::{GroupWidget, RadioWidget, Widget, WidgetBase},
   Context, Error, Result,
 };
-use std::{ffi, os::raw::c_char, time::Duration};
+use std::{
+  collections::HashMap,
+  ffi,
+  ops::Deref,
+  os::raw::c_char,
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, PoisonError, RwLock,
+  },
+  time::{Duration, Instant, SystemTime},
+};
 
 /// Event from camera
-#[derive(Debug)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CameraEvent {
   /// Unknown event
   Unknown(String),
@@ -32,6 +59,277 @@ pub enum CameraEvent {
   NewFolder(CameraFilePath),
   /// Capture completed
   CaptureComplete,
+  /// A device property of a PTP camera changed
+  ///
+  /// Decoded from the unknown events of the `ptp2` driver.
+  PropertyChanged {
+    /// PTP device property code
+    code: u16,
+    /// Name of the configuration widget of the property, if known
+    widget_name: Option<String>,
+  },
+}
+
+/// Where captured images are stored, see [`Camera::set_capture_target`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CaptureTarget {
+  /// Images are kept in the camera RAM until they are downloaded
+  ///
+  /// Downloading deletes the image from the camera, it is not written to the card.
+  InternalRam,
+  /// Images are written to the memory card and stay there after downloading
+  MemoryCard,
+}
+
+impl CaptureTarget {
+  /// Config key of the capture target
+  pub(crate) const KEY: &'static str = "capturetarget";
+
+  /// Part of the choice name (in lowercase) identifying the target
+  fn choice_keyword(self) -> &'static str {
+    match self {
+      Self::InternalRam => "ram",
+      Self::MemoryCard => "card",
+    }
+  }
+
+  /// Find the target from a choice, falling back to the order of the PTP driver
+  /// (internal RAM first) for translated choice names
+  fn from_choice(widget: &RadioWidget) -> Result<Self> {
+    let choice = widget.choice();
+
+    for target in [Self::InternalRam, Self::MemoryCard] {
+      if choice.to_lowercase().contains(target.choice_keyword()) {
+        return Ok(target);
+      }
+    }
+
+    match widget.choices_iter().position(|c| c == choice) {
+      Some(0) => Ok(Self::InternalRam),
+      Some(1) => Ok(Self::MemoryCard),
+      _ => Err(Error::from(format!("Unknown capture target {choice}"))),
+    }
+  }
+
+  fn to_choice(self, widget: &RadioWidget) -> Result<String> {
+    let choices: Vec<String> = widget.choices_iter().collect();
+
+    let by_name =
+      choices.iter().find(|choice| choice.to_lowercase().contains(self.choice_keyword()));
+    let by_index = choices.get(match self {
+      Self::InternalRam => 0,
+      Self::MemoryCard => 1,
+    });
+
+    by_name
+      .or(by_index)
+      .cloned()
+      .ok_or_else(|| Error::from(format!("The camera doesn't support capturing to {self:?}")))
+  }
+}
+
+/// Kinds of events returned by [`Camera::wait_event_filtered`]
+///
+/// [`CameraEvent::Timeout`] is always returned once the timeout expired.
+///
+/// ```
+/// use gphoto2::camera::EventMask;
+///
+/// let mask = EventMask::none().new_file(true).capture_complete(true);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventMask {
+  unknown: bool,
+  new_file: bool,
+  file_changed: bool,
+  new_folder: bool,
+  capture_complete: bool,
+  property_changed: bool,
+  log_ignored: bool,
+}
+
+impl Default for EventMask {
+  fn default() -> Self {
+    Self::all()
+  }
+}
+
+impl EventMask {
+  /// Accept all events, like [`Camera::wait_event`]
+  pub fn all() -> Self {
+    Self {
+      unknown: true,
+      new_file: true,
+      file_changed: true,
+      new_folder: true,
+      capture_complete: true,
+      property_changed: true,
+      log_ignored: false,
+    }
+  }
+
+  /// Accept no events, only the timeout ends the wait
+  pub fn none() -> Self {
+    Self {
+      unknown: false,
+      new_file: false,
+      file_changed: false,
+      new_folder: false,
+      capture_complete: false,
+      property_changed: false,
+      log_ignored: false,
+    }
+  }
+
+  /// Accept [`CameraEvent::Unknown`]
+  pub fn unknown(mut self, accept: bool) -> Self {
+    self.unknown = accept;
+    self
+  }
+
+  /// Accept [`CameraEvent::NewFile`]
+  pub fn new_file(mut self, accept: bool) -> Self {
+    self.new_file = accept;
+    self
+  }
+
+  /// Accept [`CameraEvent::FileChanged`]
+  pub fn file_changed(mut self, accept: bool) -> Self {
+    self.file_changed = accept;
+    self
+  }
+
+  /// Accept [`CameraEvent::NewFolder`]
+  pub fn new_folder(mut self, accept: bool) -> Self {
+    self.new_folder = accept;
+    self
+  }
+
+  /// Accept [`CameraEvent::CaptureComplete`]
+  pub fn capture_complete(mut self, accept: bool) -> Self {
+    self.capture_complete = accept;
+    self
+  }
+
+  /// Accept [`CameraEvent::PropertyChanged`], which some cameras report constantly
+  pub fn property_changed(mut self, accept: bool) -> Self {
+    self.property_changed = accept;
+    self
+  }
+
+  /// Log the ignored events at trace level
+  pub fn log_ignored(mut self, log: bool) -> Self {
+    self.log_ignored = log;
+    self
+  }
+
+  /// Whether the event is returned
+  pub fn matches(&self, event: &CameraEvent) -> bool {
+    match event {
+      CameraEvent::Unknown(_) => self.unknown,
+      CameraEvent::Timeout => true,
+      CameraEvent::NewFile(_) => self.new_file,
+      CameraEvent::FileChanged(_) => self.file_changed,
+      CameraEvent::NewFolder(_) => self.new_folder,
+      CameraEvent::CaptureComplete => self.capture_complete,
+      CameraEvent::PropertyChanged { .. } => self.property_changed,
+    }
+  }
+}
+
+/// Configurations fetched by [`Camera::config_filtered`]
+///
+/// Names are matched against all parts of a configuration path like `/main/imgsettings/iso`, so
+/// both sections and single configurations can be selected.
+///
+/// ```
+/// use gphoto2::camera::ConfigFilter;
+///
+/// // The image and capture settings, without the long custom function list of Canon cameras
+/// let filter =
+///   ConfigFilter::all().only("imgsettings").only("capturesettings").skip("customfuncex");
+///
+/// assert!(filter.matches("/main/imgsettings/iso"));
+/// assert!(!filter.matches("/main/capturesettings/customfuncex"));
+/// assert!(!filter.matches("/main/status/serialnumber"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigFilter {
+  only: Vec<String>,
+  skip: Vec<String>,
+}
+
+impl ConfigFilter {
+  /// Fetch all configurations, narrowed down with [`only`](Self::only) and [`skip`](Self::skip)
+  pub fn all() -> Self {
+    Self::default()
+  }
+
+  /// Only fetch configurations in or named `name`, can be called multiple times
+  pub fn only(mut self, name: &str) -> Self {
+    self.only.push(name.to_owned());
+    self
+  }
+
+  /// Don't fetch configurations in or named `name`
+  pub fn skip(mut self, name: &str) -> Self {
+    self.skip.push(name.to_owned());
+    self
+  }
+
+  /// Whether the configuration at `path` is fetched
+  pub fn matches(&self, path: &str) -> bool {
+    let contains = |names: &[String]| path.split('/').any(|part| names.iter().any(|n| n == part));
+
+    (self.only.is_empty() || contains(&self.only)) && !contains(&self.skip)
+  }
+}
+
+/// Timeout of the event polls of [`Camera::events`], the stream notices being dropped after this
+const EVENT_STREAM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Longest time a single `gp_camera_wait_for_event` call may block before cancellation is checked
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+impl CameraEvent {
+  /// Convert an event returned by `gp_camera_wait_for_event`, freeing the event data
+  unsafe fn from_raw(
+    event_type: libgphoto2_sys::CameraEventType,
+    event_data: *mut std::os::raw::c_void,
+  ) -> Self {
+    use libgphoto2_sys::CameraEventType;
+
+    match event_type {
+      CameraEventType::GP_EVENT_UNKNOWN => {
+        let s = chars_to_string(event_data.cast::<c_char>());
+
+        libc::free(event_data);
+
+        match ptp_property::parse_change(&s) {
+          Some((code, widget_name)) => CameraEvent::PropertyChanged { code, widget_name },
+          None => CameraEvent::Unknown(s),
+        }
+      }
+      CameraEventType::GP_EVENT_TIMEOUT => CameraEvent::Timeout,
+      CameraEventType::GP_EVENT_FILE_ADDED
+      | CameraEventType::GP_EVENT_FOLDER_ADDED
+      | CameraEventType::GP_EVENT_FILE_CHANGED => {
+        let file_path =
+          CameraFilePath { inner: Box::new(*event_data.cast::<libgphoto2_sys::CameraFilePath>()) };
+
+        libc::free(event_data);
+
+        match event_type {
+          CameraEventType::GP_EVENT_FILE_ADDED => CameraEvent::NewFile(file_path),
+          CameraEventType::GP_EVENT_FOLDER_ADDED => CameraEvent::NewFolder(file_path),
+          CameraEventType::GP_EVENT_FILE_CHANGED => CameraEvent::FileChanged(file_path),
+          _ => unreachable!(),
+        }
+      }
+      CameraEventType::GP_EVENT_CAPTURE_COMPLETE => CameraEvent::CaptureComplete,
+    }
+  }
 }
 
 /// Represents a camera
@@ -53,8 +351,8 @@ pub enum CameraEvent {
 /// let camera = context.autodetect_camera().wait()?;
 ///
 /// // Get some basic information about the camera
-/// println!("Camera abilities: {:?}", camera.abilities());
-/// println!("Camera summary: {}", camera.summary()?);
+/// println!("Camera abilities: {:?}", camera.abilities()?);
+/// println!("Camera summary: {}", camera.summary().wait()?);
 ///
 /// // Capture an image
 /// let image = camera.capture_image().wait()?;
@@ -81,61 +379,422 @@ pub enum CameraEvent {
 /// camera.set_config(&iso).wait()?;
 /// # Ok(())
 /// # }
+#[derive(Clone)]
 pub struct Camera {
   pub(crate) camera: BackgroundPtr<libgphoto2_sys::Camera>,
   pub(crate) context: Context,
+  pub(crate) state: Arc<CameraState>,
+  _reference: Arc<CameraRef>,
+}
+
+/// The libgphoto2 reference shared by all clones of a [`Camera`]
+///
+/// Cloning a camera never waits for the libgphoto2 thread, the camera is released once the last
+/// clone is dropped.
+struct CameraRef {
+  camera: BackgroundPtr<libgphoto2_sys::Camera>,
+  state: Arc<CameraState>,
 }
 
-impl Clone for Camera {
-  fn clone(&self) -> Self {
-    try_gp_internal!(gp_camera_ref(*self.camera).unwrap());
-    Self { camera: self.camera, context: self.context.clone() }
+/// State shared between all clones of a [`Camera`]
+pub(crate) struct CameraState {
+  /// Identifies the camera in [`Camera::lock`]
+  pub(crate) id: usize,
+  /// Set once [`Camera::init`] connected
+  initialized: AtomicBool,
+  pub(crate) keepalive: KeepAlive,
+  governor: RwLock<Option<Arc<GovernorState>>>,
+  journal: RwLock<Option<ShotJournal>>,
+}
+
+impl CameraState {
+  /// Create the state of a new camera, must be called from a [`Task`] before the camera
+  /// is initialized
+  unsafe fn register(
+    camera: BackgroundPtr<libgphoto2_sys::Camera>,
+    context: &Context,
+    initialized: bool,
+  ) -> Arc<Self> {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    let state = Arc::new_cyclic(|state| Self {
+      id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+      initialized: AtomicBool::new(initialized),
+      keepalive: KeepAlive::new(camera, context.clone(), state.clone()),
+      governor: RwLock::new(None),
+      journal: RwLock::new(None),
+    });
+    KeepAlive::register(&state);
+
+    state
+  }
+
+  /// Busy governor set with [`Camera::set_busy_governor`]
+  pub(crate) fn governor(&self) -> Option<Arc<GovernorState>> {
+    self.governor.read().unwrap_or_else(PoisonError::into_inner).clone()
+  }
+
+  /// Shot journal set with [`Camera::set_shot_journal`]
+  fn journal(&self) -> Option<ShotJournal> {
+    self.journal.read().unwrap_or_else(PoisonError::into_inner).clone()
   }
 }
 
-impl Drop for Camera {
+impl Drop for CameraRef {
   fn drop(&mut self) {
     let camera = self.camera;
+    // libgphoto2 may stop keepalive timers while freeing the camera
+    let state = self.state.clone();
+    let unref = move || {
+      let _state = state;
+
+      if let Err(error) = Error::check(unsafe { libgphoto2_sys::gp_camera_unref(*camera) }) {
+        log::error!("Could not release the camera: {error}");
+      }
+    };
+
+    // Never wait for the worker here, dropping must not block async runtimes
+    if is_worker_thread() {
+      unref();
+    } else {
+      unsafe { Task::new(unref) }.background();
+    }
+  }
+}
+
+as_ref!(Camera -> libgphoto2_sys::Camera, *self.camera.any_thread());
+
+impl Camera {
+  pub(crate) fn new(
+    camera: BackgroundPtr<libgphoto2_sys::Camera>,
+    context: Context,
+    state: Arc<CameraState>,
+  ) -> Self {
+    let reference = Arc::new(CameraRef { camera, state: state.clone() });
+
+    Self { camera, context, state, _reference: reference }
+  }
+
+  /// Open a camera without connecting to it
+  ///
+  /// Creating the camera is fast, the connection is made by [`Camera::init`] or
+  /// by the first operation on the camera.
+  ///
+  /// ```no_run
+  /// use gphoto2::{Camera, Context, Result};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let context = Context::new()?;
+  ///
+  /// let cameras = context
+  ///   .list_cameras()
+  ///   .wait()?
+  ///   .map(|camera_desc| Camera::connect_lazy(&context, &camera_desc).wait())
+  ///   .collect::<Result<Vec<_>>>()?;
+  ///
+  /// // Only the first camera is connected
+  /// cameras[0].init().wait()?;
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn connect_lazy(context: &Context, descriptor: &CameraDescriptor) -> Task<Result<Self>> {
+    context.camera_builder().descriptor(descriptor).init(false).build()
+  }
+
+  /// Wrap a `Camera` opened outside of this crate
+  ///
+  /// The returned camera takes over one reference of `camera`, which is released once the
+  /// camera and all its clones are dropped. Call `gp_camera_ref` before if the caller keeps
+  /// using the pointer. The camera is assumed to be connected already, so [`Camera::init`]
+  /// does nothing. Keepalive timers of the driver are run by this crate from now on.
+  ///
+  /// # Safety
+  ///
+  /// `camera` must be a valid `Camera` pointer and the caller must own the reference passed
+  /// to this function. It must not be used by libgphoto2 on other threads than the worker of
+  /// this crate.
+  pub unsafe fn from_raw(camera: *mut libgphoto2_sys::Camera, context: Context) -> Self {
+    let camera = BackgroundPtr(camera);
+    let state = run_on_worker({
+      let context = context.clone();
+      move || unsafe { CameraState::register(camera, &context, true) }
+    });
+
+    Self::new(camera, context, state)
+  }
+
+  /// Get the `Camera` pointer, which stays valid as long as this camera is alive
+  ///
+  /// libgphoto2 functions must only be called with the pointer inside of [`Task::raw`].
+  #[cfg(feature = "raw")]
+  pub fn as_raw(&self) -> *mut libgphoto2_sys::Camera {
+    self.camera.any_thread()
+  }
+
+  /// Get the `Camera` pointer together with a new reference to it
+  ///
+  /// The caller owns the returned reference and must release it with `gp_camera_unref`.
+  /// Keepalive timers of the driver are only run while clones of this camera are alive,
+  /// timers started by the driver afterwards are ignored.
+  pub fn into_raw(self) -> *mut libgphoto2_sys::Camera {
+    let camera = self.camera;
+
+    run_on_worker(move || unsafe {
+      try_gp_internal!(gp_camera_ref(*camera).unwrap());
+      KeepAlive::unregister(camera);
+    });
+
+    camera.any_thread()
+  }
+
+  /// Connect to the camera
+  ///
+  /// Does nothing if the camera was already connected by this method or by
+  /// [`CameraBuilder::build`]. A camera which was connected implicitly by another
+  /// operation is reconnected.
+  pub fn init(&self) -> Task<Result<()>> {
+    let camera = self.camera;
+    let context = self.context.inner();
+    let state = self.state.clone();
 
     unsafe {
-      Task::new(move || -> Result<()> {
-        try_gp_internal!(gp_camera_unref(*camera)?);
+      Task::new(move || {
+        if state.initialized.load(Ordering::Acquire) {
+          return Ok(());
+        }
+
+        // libgphoto2 can't tell whether the camera is connected, exiting first
+        // avoids connecting twice. This does nothing for unconnected cameras.
+        try_gp_internal!(gp_camera_exit(*camera, *context)?);
+        try_gp_internal!(gp_camera_init(*camera, *context)?);
+        state.initialized.store(true, Ordering::Release);
 
         Ok(())
       })
     }
-    .wait()
-    .unwrap()
+    .camera(self)
   }
-}
 
-as_ref!(Camera -> libgphoto2_sys::Camera, **self.camera);
+  /// Reconnect to the camera, to recover from I/O errors or a busy camera
+  ///
+  /// With `reset_port`, the port is reset as well (for USB this resets the device as if it
+  /// was plugged in again). Tasks of clones of this camera wait until the reset finished.
+  pub fn reset(&self, reset_port: bool) -> Task<Result<()>> {
+    let camera = self.camera;
+    let context = self.context.inner();
+    let state = self.state.clone();
 
-impl Camera {
-  pub(crate) fn new(camera: BackgroundPtr<libgphoto2_sys::Camera>, context: Context) -> Self {
-    Self { camera, context }
+    unsafe {
+      Task::new(move || {
+        state.initialized.store(false, Ordering::Release);
+        try_gp_internal!(gp_camera_exit(*camera, *context)?);
+
+        if reset_port {
+          let port = (**camera).port;
+
+          try_gp_internal!(gp_port_open(port)?);
+          let reset = Error::check(libgphoto2_sys::gp_port_reset(port));
+          try_gp_internal!(gp_port_close(port)?);
+          reset?;
+        }
+
+        try_gp_internal!(gp_camera_init(*camera, *context)?);
+        state.initialized.store(true, Ordering::Release);
+
+        Ok(())
+      })
+    }
+    .camera(self)
+  }
+
+  /// Disconnect from the camera and release it
+  ///
+  /// Dropping the camera releases it in the background, this can be waited for
+  /// to make sure the camera can be used by other programs afterwards.
+  /// Clones of this camera connect again when they are used.
+  pub fn close(self) -> Task<Result<()>> {
+    let handle = self.clone();
+
+    unsafe {
+      Task::new(move || {
+        try_gp_internal!(gp_camera_exit(*self.camera, *self.context.inner())?);
+        self.state.initialized.store(false, Ordering::Release);
+
+        // Releases the camera right away if there are no other clones, since this runs on the worker
+        drop(self);
+
+        Ok(())
+      })
+    }
+    .camera(&handle)
+  }
+
+  /// Run the operations of the returned guard without other operations of this camera in between
+  ///
+  /// Operations of the camera started by the thread holding the guard run in order, through
+  /// the guard or through any clone of the camera. Operations of the camera started by other
+  /// threads wait until the guard is dropped, operations of other cameras continue.
+  ///
+  /// Locking a camera again on the thread which holds its lock returns a guard which shares
+  /// the same lock, the lock ends once all of its guards are dropped. Locking it on another
+  /// thread waits for the current lock to end before its operations run.
+  ///
+  /// ```no_run
+  /// use gphoto2::{Context, Result};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let camera = Context::new()?.autodetect_camera().wait()?;
+  ///
+  /// let lock = camera.lock();
+  /// for exposure in ["-1", "0", "1"] {
+  ///   let compensation = lock.config_key::<gphoto2::widget::RadioWidget>("exposurecompensation").wait()?;
+  ///   compensation.set_choice(exposure)?;
+  ///   lock.set_config(&compensation).wait()?;
+  ///   lock.capture_image().wait()?;
+  /// }
+  /// drop(lock);
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn lock(&self) -> CameraLock {
+    // Clones of a guard's camera may outlive its lock
+    let held = self.context.lock_queue().filter(|queue| queue.is_active());
+    let lock_queue = match held.or_else(|| LockQueue::held(self.state.id)) {
+      Some(lock_queue) => {
+        lock_queue.enter();
+        lock_queue
+      }
+      None => LockQueue::start(self.state.id),
+    };
+
+    let mut camera = self.clone();
+    camera.context = self.context.with_lock_queue(lock_queue.clone());
+
+    CameraLock { camera, lock_queue }
   }
 
   /// Capture image
   pub fn capture_image(&self) -> Task<Result<CameraFilePath>> {
+    self.capture(libgphoto2_sys::CameraCaptureType::GP_CAPTURE_IMAGE)
+  }
+
+  /// Capture an image and download it to `dir`, named by `template`
+  ///
+  /// `seq` is the value of the `{seq}` token and `{capture_time}` is the time of the capture.
+  /// The image is downloaded in the same task, so it is on disk before other operations run. See
+  /// the [`naming`] module.
+  pub fn capture_image_named(
+    &self,
+    dir: &Path,
+    template: &NamingTemplate,
+    seq: u64,
+  ) -> Task<Result<PathBuf>> {
+    let (dir, template) = (dir.to_owned(), template.clone());
     let camera = self.camera;
-    let context = self.context.inner;
+    let context = self.context.inner();
+    let journal = self.state.journal();
 
     unsafe {
       Task::new(move || {
-        let mut inner = UninitBox::uninit();
-
-        try_gp_internal!(gp_camera_capture(
+        let capture_time = clock::to_timestamp(SystemTime::now())?;
+        let path = capture_file(
           *camera,
           libgphoto2_sys::CameraCaptureType::GP_CAPTURE_IMAGE,
-          inner.as_mut_ptr(),
-          *context
-        )?);
+          journal.as_ref(),
+          *context,
+        )?;
+        let (model, serial) = naming::camera_identity(*camera, *context)?;
+        let values = NamingValues {
+          camera_serial: serial.as_deref(),
+          camera_model: &model,
+          capture_time,
+          seq,
+          orig_name: &path.name(),
+        };
+
+        naming::download_named(*camera, &path.folder(), &values, &dir, &template, *context)
+      })
+    }
+    .camera(self)
+    .metric(Operation::Capture)
+  }
+
+  /// Record a sound with the camera microphone
+  ///
+  /// Only few cameras support this, the others fail with
+  /// [`ErrorKind::NotSupported`](crate::error::ErrorKind::NotSupported).
+  /// Voice memos attached to images can be downloaded with
+  /// [`CameraFS::download_audio`](crate::filesys::CameraFS::download_audio).
+  pub fn capture_sound(&self) -> Task<Result<CameraFilePath>> {
+    self.capture(libgphoto2_sys::CameraCaptureType::GP_CAPTURE_SOUND)
+  }
+
+  /// Record a movie with a driver which captures movies directly
+  ///
+  /// Few drivers support this (see [`CameraOperations::capture_video`](crate::abilities::CameraOperations::capture_video)),
+  /// DSLRs are recorded with [`Camera::start_movie`] and [`Camera::stop_movie`] instead.
+  pub fn capture_movie(&self) -> Task<Result<CameraFilePath>> {
+    self.capture(libgphoto2_sys::CameraCaptureType::GP_CAPTURE_MOVIE)
+  }
+
+  /// Start recording a movie
+  ///
+  /// Uses the `movie` or `movierecord` configuration, Canon EOS cameras are switched to the
+  /// movie mode first.
+  ///
+  /// ```no_run
+  /// use gphoto2::{Context, Result};
+  /// use std::{thread, time::Duration};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let camera = Context::new()?.autodetect_camera().wait()?;
+  ///
+  /// camera.start_movie().wait()?;
+  /// thread::sleep(Duration::from_secs(10));
+  /// let movie = camera.stop_movie(Duration::from_secs(30)).wait()?;
+  ///
+  /// println!("Recorded {}", movie.name());
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn start_movie(&self) -> Task<Result<()>> {
+    let camera = self.camera;
+    let context = self.context.inner();
+
+    unsafe { Task::new(move || movie::start(*camera, *context)) }.camera(self)
+  }
+
+  /// Stop recording a movie started with [`Camera::start_movie`]
+  ///
+  /// Waits up to `timeout` for the camera to report the movie file, other events received until
+  /// then are dropped.
+  pub fn stop_movie(&self, timeout: Duration) -> Task<Result<CameraFilePath>> {
+    let camera = self.camera;
+    let context = self.context.inner();
+
+    unsafe { Task::new(move || movie::stop(*camera, timeout, *context)) }.camera(self)
+  }
+
+  fn capture(
+    &self,
+    capture_type: libgphoto2_sys::CameraCaptureType,
+  ) -> Task<Result<CameraFilePath>> {
+    let camera = self.camera;
+    let context = self.context.inner();
+    let journal = self.state.journal();
 
-        Ok(CameraFilePath { inner: inner.assume_init() })
+    unsafe {
+      Task::new(move || {
+        // Few drivers record sound, give a better error than libgphoto2's generic one
+        if capture_type == libgphoto2_sys::CameraCaptureType::GP_CAPTURE_SOUND {
+          require_capability(*camera, Capability::Audio, *context)?;
+        }
+
+        capture_file(*camera, capture_type, journal.as_ref(), *context)
       })
     }
-    .context(context)
+    .camera(self)
+    .metric(Operation::Capture)
   }
 
   /// Trigger a capture, without waiting for an image to be returned.
@@ -143,16 +802,25 @@ impl Camera {
   /// The image can later be retreived by listening for the [`CameraEvent::NewFile`] event.
   pub fn trigger_capture(&self) -> Task<Result<()>> {
     let camera = self.camera;
-    let context = self.context.inner;
+    let context = self.context.inner();
+    let journal = self.state.journal();
 
     unsafe {
       Task::new(move || {
-        try_gp_internal!(gp_camera_trigger_capture(*camera, *context)?);
+        let trigger = || {
+          try_gp_internal!(gp_camera_trigger_capture(*camera, *context)?);
 
-        Ok(())
+          Ok(())
+        };
+
+        match &journal {
+          Some(journal) => journal.record(*camera, CaptureKind::Trigger, *context, trigger),
+          None => trigger(),
+        }
       })
     }
-    .context(context)
+    .camera(self)
+    .metric(Operation::Capture)
   }
 
   /// Capture a preview image
@@ -171,166 +839,442 @@ impl Camera {
   /// ```
   pub fn capture_preview(&self) -> Task<Result<CameraFile>> {
     let camera = self.camera;
-    let context = self.context.inner;
+    let context = self.context.inner();
 
     unsafe {
       Task::new(move || {
         let camera_file = CameraFile::new()?;
 
-        try_gp_internal!(gp_camera_capture_preview(*camera, *camera_file.inner, *context)?);
+        try_gp_internal!(gp_camera_capture_preview(*camera, *camera_file.inner, *context)?);
+
+        Ok(camera_file)
+      })
+    }
+    .camera(self)
+    .metric(Operation::Preview)
+  }
+
+  /// Start a [`LiveView`] on this camera
+  ///
+  /// See the [`liveview`](crate::liveview) module for an example.
+  pub fn live_view(&self) -> Task<Result<LiveView>> {
+    let camera = self.camera;
+    let context = self.context.clone();
+    let state = self.state.clone();
+
+    unsafe { Task::new(move || LiveView::start(camera, context, state)) }.camera(self)
+  }
+
+  /// Get the camera's [`Abilities`]
+  ///
+  /// The abilities contain information about the driver used, permissions and camera model
+  pub fn abilities(&self) -> Result<Abilities> {
+    let camera = self.camera;
+
+    run_on_worker(move || unsafe { camera_abilities(*camera) })
+  }
+
+  /// Check whether the camera has a capability
+  ///
+  /// Combines the driver's abilities with the configuration the camera exposes, for example
+  /// movies can be recorded either by the driver or through a `movie` config.
+  /// Helpers which need a capability fail with
+  /// [`ErrorKind::NotSupported`](crate::error::ErrorKind::NotSupported) and a message naming it.
+  pub fn can(&self, capability: Capability) -> Task<Result<bool>> {
+    let camera = self.camera;
+    let context = self.context.inner();
+
+    unsafe {
+      Task::new(move || Ok(capability_support(*camera, capability, *context)?.is_supported()))
+    }
+    .camera(self)
+  }
+
+  /// Overview of what can be done with this camera
+  ///
+  /// See the [`support`](crate::support) module.
+  pub fn support_matrix(&self) -> Result<SupportMatrix> {
+    SupportMatrix::probe(self)
+  }
+
+  /// Export all writable configurations, see the [`profile`](crate::profile) module
+  pub fn export_profile(&self) -> Result<CameraProfile> {
+    CameraProfile::export(self)
+  }
+
+  /// Apply a profile exported from a camera of the same model
+  ///
+  /// Configurations which don't exist on this camera or are read-only are skipped.
+  /// Returns the configurations which could not be set, together with their error.
+  pub fn import_profile(&self, profile: &CameraProfile) -> Result<Vec<(String, Error)>> {
+    profile.import(self)
+  }
+
+  /// Get where captured images are stored
+  pub fn capture_target(&self) -> Task<Result<CaptureTarget>> {
+    let camera = self.camera;
+    let context = self.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        let widget: RadioWidget =
+          get_single_config(*camera, CaptureTarget::KEY, *context)?.try_into()?;
+
+        CaptureTarget::from_choice(&widget)
+      })
+    }
+    .camera(self)
+  }
+
+  /// Set where captured images are stored
+  ///
+  /// With [`CaptureTarget::InternalRam`] every captured image must be downloaded, since the
+  /// camera keeps it in its buffer until then (and refuses new captures once it is full).
+  /// With [`CaptureTarget::MemoryCard`] the images stay on the card and can be downloaded later,
+  /// but [`Camera::capture_image`] may take longer since the camera writes to the card first.
+  pub fn set_capture_target(&self, target: CaptureTarget) -> Task<Result<()>> {
+    let camera = self.camera;
+    let context = self.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        let widget: RadioWidget =
+          get_single_config(*camera, CaptureTarget::KEY, *context)?.try_into()?;
+        widget.set_choice_forced(&target.to_choice(&widget)?)?;
+
+        set_single_config(*camera, &widget, *context)
+      })
+    }
+    .camera(self)
+  }
+
+  /// Read the camera clock
+  ///
+  /// Drivers expose the clock as a `datetimeutc` or `datetime` configuration, either as a
+  /// [`DateWidget`](crate::widget::DateWidget) or as text in the PTP format (`20240131T235959`),
+  /// separated (`2024/01/31 23:59:59`) or as a UNIX timestamp. All of them are supported.
+  pub fn clock(&self) -> Task<Result<SystemTime>> {
+    let camera = self.camera;
+    let context = self.context.inner();
+
+    unsafe { Task::new(move || clock::read(*camera, *context)) }.camera(self)
+  }
+
+  /// Set the camera clock, usually to [`SystemTime::now`]
+  ///
+  /// Cameras which store the local time are set to the local time of this computer.
+  pub fn sync_clock(&self, time: SystemTime) -> Task<Result<()>> {
+    let camera = self.camera;
+    let context = self.context.inner();
+
+    unsafe { Task::new(move || clock::sync(*camera, *context, time)) }.camera(self)
+  }
+
+  /// Summary of the cameras model, settings, capabilities, etc.
+  ///
+  /// Reading the summary can take a few seconds on PTP cameras.
+  pub fn summary(&self) -> Task<Result<String>> {
+    let camera = self.camera;
+    let context = self.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        try_gp_internal!(gp_camera_get_summary(*camera, &out summary, *context)?);
+
+        Ok(char_slice_to_cow(&summary.text).into_owned())
+      })
+    }
+    .camera(self)
+  }
+
+  /// Get about information about the camera
+  pub fn about(&self) -> Task<Result<String>> {
+    let camera = self.camera;
+    let context = self.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        try_gp_internal!(gp_camera_get_about(*camera, &out about, *context)?);
+
+        Ok(char_slice_to_cow(&about.text).into_owned())
+      })
+    }
+    .camera(self)
+  }
+
+  /// Get the manual of the camera
+  ///
+  /// Not all cameras support this, and will return NotSupported
+  pub fn manual(&self) -> Task<Result<String>> {
+    let camera = self.camera;
+    let context = self.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        try_gp_internal!(gp_camera_get_manual(*camera, &out manual, *context)?);
+
+        Ok(char_slice_to_cow(&manual.text).into_owned())
+      })
+    }
+    .camera(self)
+  }
+
+  /// List of storages available on the camera
+  pub fn storages(&self) -> Task<Result<Vec<StorageInfo>>> {
+    let camera = self.camera;
+    let context = self.context.inner();
+
+    let storages = move || unsafe { camera_storages(*camera, *context) };
 
-        Ok(camera_file)
-      })
+    match self.state.governor() {
+      Some(governor) => unsafe { Task::new(governor.coalesce("storages", storages)) },
+      None => unsafe { Task::new(storages) },
     }
-    .context(context)
+    .camera(self)
   }
 
-  /// Get the camera's [`Abilities`]
+  /// Wait after busy responses and merge duplicate requests, see the [`governor`](crate::governor)
+  /// module
   ///
-  /// The abilities contain information about the driver used, permissions and camera model
-  pub fn abilities(&self) -> Abilities {
-    let mut inner = UninitBox::uninit();
+  /// The governor is shared by all clones of this camera, [`None`] removes it.
+  pub fn set_busy_governor(&self, governor: Option<BusyGovernor>) {
+    *self.state.governor.write().unwrap_or_else(PoisonError::into_inner) =
+      governor.map(|governor| Arc::new(GovernorState::new(governor)));
+  }
 
-    try_gp_internal!(gp_camera_get_abilities(*self.camera, inner.as_mut_ptr()).unwrap());
+  /// Record every capture of this camera in `journal`, see the [`journal`](crate::journal) module
+  ///
+  /// The journal is shared by all clones of this camera, [`None`] removes it.
+  pub fn set_shot_journal(&self, journal: Option<ShotJournal>) {
+    *self.state.journal.write().unwrap_or_else(PoisonError::into_inner) = journal;
+  }
 
-    Abilities { inner: unsafe { inner.assume_init() } }
+  /// The journal set with [`Camera::set_shot_journal`]
+  pub fn shot_journal(&self) -> Option<ShotJournal> {
+    self.state.journal()
   }
 
-  /// Summary of the cameras model, settings, capabilities, etc.
-  pub fn summary(&self) -> Result<String> {
-    try_gp_internal!(gp_camera_get_summary(*self.camera, &out summary, *self.context.inner)?);
+  /// Upload a firmware file and start the update
+  ///
+  /// The file is uploaded to the root folder of the first storage (or the folder set in the
+  /// options) after the confirmation callback agreed. Afterwards the configured trigger is set
+  /// and events are collected until the timeout elapsed or the camera disconnected.
+  ///
+  /// **An interrupted or wrong firmware update can permanently damage the camera**, only
+  /// upload firmware files published by the manufacturer for this exact model.
+  /// See the [`firmware`](crate::firmware) module for an example.
+  pub fn upload_firmware(
+    &self,
+    path: impl AsRef<Path>,
+    options: FirmwareOptions,
+  ) -> Task<Result<FirmwareReport>> {
+    let camera = self.camera;
+    let context = self.context.inner();
+    let path = path.as_ref().to_owned();
+
+    unsafe { Task::new(move || crate::firmware::upload(*camera, &path, options, *context)) }
+      .camera(self)
+  }
 
-    Ok(char_slice_to_cow(&summary.text).into_owned())
+  /// Filesystem actions
+  pub fn fs(&self) -> CameraFS<'_> {
+    CameraFS::new(self)
   }
 
-  /// Get about information about the camera#
-  pub fn about(&self) -> Result<String> {
-    try_gp_internal!(gp_camera_get_about(*self.camera, &out about, *self.context.inner)?);
+  /// Typed access to common settings, see the [`settings`](crate::settings) module
+  pub fn settings(&self) -> CameraSettings<'_> {
+    CameraSettings { camera: self }
+  }
 
-    Ok(char_slice_to_cow(&about.text).into_owned())
+  /// Stream of camera events, received on a background thread
+  ///
+  /// Timeouts are not part of the stream. Use [`OverflowPolicy::Block`](crate::stream::OverflowPolicy::Block)
+  /// to not lose any file events, the camera isn't polled while the buffer is full.
+  pub fn events(&self, options: StreamOptions) -> Stream<Result<CameraEvent>> {
+    let camera = self.clone();
+
+    Stream::spawn(options, move |queue| {
+      while !queue.is_closed() {
+        match camera.wait_event(EVENT_STREAM_POLL_INTERVAL).wait() {
+          Ok(CameraEvent::Timeout) => {}
+          Ok(event) => {
+            if !queue.push(Ok(event)) {
+              return;
+            }
+          }
+          Err(error) => {
+            queue.push_last(Err(error));
+            return;
+          }
+        }
+      }
+    })
   }
 
-  /// Get the manual of the camera
+  /// Waits for an event on the camera until timeout
   ///
-  /// Not all cameras support this, and will return NotSupported
-  pub fn manual(&self) -> Result<String> {
-    try_gp_internal!(gp_camera_get_manual(*self.camera, &out manual, *self.context.inner)?);
+  /// The timeout is split into short polling intervals, so that [`Task::cancel`]
+  /// is honored promptly even by drivers which don't check for cancellation themselves.
+  pub fn wait_event(&self, timeout: Duration) -> Task<Result<CameraEvent>> {
+    let camera = self.camera;
+    let context = self.context.inner();
 
-    Ok(char_slice_to_cow(&manual.text).into_owned())
+    unsafe { Task::new(move || wait_for_event(*camera, timeout, *context)) }.camera(self)
   }
 
-  /// List of storages available on the camera
-  pub fn storages(&self) -> Task<Result<Vec<StorageInfo>>> {
+  /// Waits for an event accepted by `mask` until timeout
+  ///
+  /// Other events are dropped on the libgphoto2 thread, so cameras which constantly report
+  /// property changes (like Canon EOS bodies) don't wake the caller for each of them.
+  ///
+  /// ```no_run
+  /// use gphoto2::{camera::{CameraEvent, EventMask}, Context, Result};
+  /// use std::time::Duration;
+  ///
+  /// # fn main() -> Result<()> {
+  /// let camera = Context::new()?.autodetect_camera().wait()?;
+  /// let mask = EventMask::none().new_file(true);
+  ///
+  /// if let CameraEvent::NewFile(path) =
+  ///   camera.wait_event_filtered(Duration::from_secs(10), mask).wait()?
+  /// {
+  ///   println!("New file {}", path.name());
+  /// }
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn wait_event_filtered(
+    &self,
+    timeout: Duration,
+    mask: EventMask,
+  ) -> Task<Result<CameraEvent>> {
     let camera = self.camera;
-    let context = self.context.inner;
+    let context = self.context.inner();
 
     unsafe {
       Task::new(move || {
-        try_gp_internal!(gp_camera_get_storageinfo(
-          *camera,
-          &out storages_ptr,
-          &out storages_len,
-          *context
-        )?);
-
-        let storages = std::slice::from_raw_parts(
-          // We can cast pointer safely because StorageInfo is repr(transparent).
-          storages_ptr.cast::<StorageInfo>(),
-          storages_len.try_into()?,
-        );
+        let deadline = Instant::now().checked_add(timeout).ok_or("Timeout is too large")?;
 
-        let result = storages.to_vec();
+        loop {
+          let remaining = deadline.saturating_duration_since(Instant::now());
+          let event = wait_for_event(*camera, remaining, *context)?;
 
-        // Must be freed using libc deallocator rather than Rust one.
-        libc::free(storages_ptr.cast());
+          if mask.matches(&event) {
+            return Ok(event);
+          }
 
-        Ok(result)
+          if mask.log_ignored {
+            log::trace!("Ignoring event {event:?}");
+          }
+        }
       })
     }
-    .context(context)
+    .camera(self)
   }
 
-  /// Filesystem actions
-  pub fn fs(&self) -> CameraFS<'_> {
-    CameraFS::new(self)
-  }
+  /// Timeout of a single transaction with the camera
+  ///
+  /// Drivers like `ptp2` use this for every request sent to the camera,
+  /// it only applies once the camera was initialized.
+  pub fn transaction_timeout(&self) -> Task<Result<Duration>> {
+    let camera = self.camera;
 
-  /// Waits for an event on the camera until timeout
-  pub fn wait_event(&self, timeout: Duration) -> Task<Result<CameraEvent>> {
-    use libgphoto2_sys::CameraEventType;
+    unsafe {
+      Task::new(move || {
+        try_gp_internal!(gp_port_get_timeout((**camera).port, &out timeout)?);
 
-    let duration_milliseconds = timeout.as_millis();
+        Ok(Duration::from_millis(timeout.try_into()?))
+      })
+    }
+    .camera(self)
+  }
 
+  /// Change the timeout of a single transaction with the camera
+  ///
+  /// Raising this helps with slow connections (like WiFi), where the default timeouts of
+  /// the drivers are too short. Drivers still use longer timeouts for captures.
+  /// If the camera was not initialized yet (see [`Context::get_camera`]) the driver
+  /// overrides this on initialization.
+  pub fn set_transaction_timeout(&self, timeout: Duration) -> Task<Result<()>> {
     let camera = self.camera;
-    let context = self.context.inner;
 
     unsafe {
       Task::new(move || {
-        try_gp_internal!(gp_camera_wait_for_event(
-          *camera,
-          duration_milliseconds.try_into()?,
-          &out event_type,
-          &out event_data,
-          *context
-        )?);
-
-        Ok(match event_type {
-          CameraEventType::GP_EVENT_UNKNOWN => {
-            let s = chars_to_string(event_data.cast::<c_char>());
+        let timeout = timeout.as_millis().try_into().map_err(|_| "Timeout is too large")?;
 
-            libc::free(event_data);
+        try_gp_internal!(gp_port_set_timeout((**camera).port, timeout)?);
 
-            CameraEvent::Unknown(s)
-          }
-          CameraEventType::GP_EVENT_TIMEOUT => CameraEvent::Timeout,
-          CameraEventType::GP_EVENT_FILE_ADDED
-          | CameraEventType::GP_EVENT_FOLDER_ADDED
-          | CameraEventType::GP_EVENT_FILE_CHANGED => {
-            let file_path = CameraFilePath {
-              inner: Box::new(*event_data.cast::<libgphoto2_sys::CameraFilePath>()),
-            };
-
-            libc::free(event_data);
-
-            match event_type {
-              CameraEventType::GP_EVENT_FILE_ADDED => CameraEvent::NewFile(file_path),
-              CameraEventType::GP_EVENT_FOLDER_ADDED => CameraEvent::NewFolder(file_path),
-              CameraEventType::GP_EVENT_FILE_CHANGED => CameraEvent::FileChanged(file_path),
-              _ => unreachable!(),
-            }
-          }
-          CameraEventType::GP_EVENT_CAPTURE_COMPLETE => CameraEvent::CaptureComplete,
-        })
+        Ok(())
       })
     }
-    .context(context)
+    .camera(self)
   }
 
   /// Port used to connect to the camera
   pub fn port_info(&self) -> Result<PortInfo<'_>> {
-    try_gp_internal!(gp_camera_get_port_info(*self.camera, &out port_info)?);
+    let camera = self.camera;
+    run_on_worker(move || {
+      try_gp_internal!(gp_camera_get_port_info(*camera, &out port_info)?);
 
-    Ok(unsafe { PortInfo::new(port_info) })
+      unsafe { PortInfo::new(port_info) }
+    })
   }
 
   /// Get the entire camera configuration tree
   pub fn config(&self) -> Task<Result<GroupWidget>> {
     let camera = self.camera;
-    let context = self.context.inner;
+    let context = self.context.inner();
+
+    let config = move || {
+      try_gp_internal!(gp_camera_get_config(*camera, &out root_widget, *context)?);
+
+      Widget::new_owned(BackgroundPtr(root_widget)).try_into::<GroupWidget>()
+    };
+
+    match self.state.governor() {
+      Some(governor) => unsafe { Task::new(governor.coalesce("config", config)) },
+      None => unsafe { Task::new(config) },
+    }
+    .camera(self)
+    .metric(Operation::ConfigRead)
+  }
+
+  /// Get the configurations accepted by `filter`, without fetching the others
+  ///
+  /// The configurations are listed with `gp_camera_list_config` and fetched one by one, which
+  /// is much faster than [`Camera::config`] for cameras with thousands of configurations. The
+  /// tree has the same structure as the full configuration, but sections are labeled with their
+  /// names. Drivers which can't list their configurations return the full configuration.
+  pub fn config_filtered(&self, filter: ConfigFilter) -> Task<Result<GroupWidget>> {
+    let camera = self.camera;
+    let context = self.context.inner();
 
     unsafe {
       Task::new(move || {
-        try_gp_internal!(gp_camera_get_config(*camera, &out root_widget, *context)?);
+        let list = CameraList::new()?;
+        let status = libgphoto2_sys::gp_camera_list_config(*camera, *list.inner, *context);
+
+        if status == libgphoto2_sys::GP_ERROR_NOT_SUPPORTED {
+          try_gp_internal!(gp_camera_get_config(*camera, &out root_widget, *context)?);
+          return Widget::new_owned(BackgroundPtr(root_widget)).try_into::<GroupWidget>();
+        }
+        Error::check(status)?;
 
-        Widget::new_owned(BackgroundPtr(root_widget)).try_into::<GroupWidget>()
+        let paths: Vec<_> = FileListIter::new(list).filter(|path| filter.matches(path)).collect();
+        filtered_config(*camera, &paths, *context)
       })
     }
-    .context(context)
+    .camera(self)
+    .metric(Operation::ConfigRead)
   }
 
   /// Get a single configuration by name.
   /// Pass either a specific widget type as a generic parameter or [`Widget`]
   /// if you're not sure what this config represents.
+  ///
+  /// The key is resolved through the [alias table](crate::quirks) for this camera's model first.
+  /// Drivers (or libgphoto2 versions) which can't get single configs are handled by
+  /// searching the full configuration.
   // TODO: Get rid of the 'static lifetime
   pub fn config_key<T: TryFrom<Widget> + 'static + Send>(&self, key: &str) -> Task<Result<T>>
   where
@@ -338,28 +1282,23 @@ impl Camera {
   {
     let key = key.to_owned();
     let camera = self.camera;
-    let context = self.context.inner;
+    let context = self.context.inner();
 
-    unsafe {
-      Task::new(move || {
-        try_gp_internal!(gp_camera_get_single_config(
-          *camera,
-          to_c_string!(&*key),
-          &out widget,
-          *context
-        )?);
-
-        Ok(Widget::new_owned(BackgroundPtr(widget)).try_into()?)
-      })
-    }
-    .context(context)
+    unsafe { Task::new(move || Ok(get_single_config(*camera, &key, *context)?.try_into()?)) }
+      .camera(self)
+      .metric(Operation::ConfigRead)
   }
 
   /// Apply a full config object to the camera.
   pub fn set_all_config(&self, config: &GroupWidget) -> Task<Result<()>> {
-    let config = config.clone();
+    self.set_all_config_owned(config.clone())
+  }
+
+  /// Like [`Camera::set_all_config`], but moves the configuration into the task instead of
+  /// taking another reference to it
+  pub fn set_all_config_owned(&self, config: GroupWidget) -> Task<Result<()>> {
     let camera = self.camera;
-    let context = self.context.inner;
+    let context = self.context.inner();
 
     unsafe {
       Task::new(move || {
@@ -368,28 +1307,416 @@ impl Camera {
         Ok(())
       })
     }
-    .context(self.context.inner)
+    .camera(self)
+    .metric(Operation::ConfigWrite)
   }
 
   /// Set a single configuration widget to the camera
+  ///
+  /// Like [`Camera::config_key`] this falls back to the full configuration
+  /// for drivers which can't set single configs.
   pub fn set_config(&self, config: &WidgetBase) -> Task<Result<()>> {
-    let config = config.clone();
+    self.set_config_inner(config.clone())
+  }
+
+  /// Like [`Camera::set_config`], but moves the widget into the task instead of
+  /// taking another reference to it
+  pub fn set_config_owned(&self, config: Widget) -> Task<Result<()>> {
+    self.set_config_inner(config.into_base())
+  }
+
+  fn set_config_inner(&self, config: WidgetBase) -> Task<Result<()>> {
     let camera = self.camera;
-    let context = self.context.inner;
+    let context = self.context.inner();
 
-    unsafe {
-      Task::new(move || {
-        try_gp_internal!(gp_camera_set_single_config(
-          *camera,
-          to_c_string!(config.name()),
-          *config.inner,
-          *context
-        )?);
+    unsafe { Task::new(move || set_single_config(*camera, &config, *context)) }
+      .camera(self)
+      .metric(Operation::ConfigWrite)
+  }
+}
 
-        Ok(())
-      })
+/// Storages of an opened camera
+pub(crate) unsafe fn camera_storages(
+  camera: *mut libgphoto2_sys::Camera,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<Vec<StorageInfo>> {
+  try_gp_internal!(gp_camera_get_storageinfo(camera, &out storages_ptr, &out storages_len, context)?);
+
+  let storages = std::slice::from_raw_parts(
+    // We can cast pointer safely because StorageInfo is repr(transparent).
+    storages_ptr.cast::<StorageInfo>(),
+    storages_len.try_into()?,
+  );
+
+  let result = storages.to_vec();
+
+  // Must be freed using libc deallocator rather than Rust one.
+  libc::free(storages_ptr.cast());
+
+  Ok(result)
+}
+
+/// Capture a file, recorded in `journal`, must be called from a [`Task`]
+unsafe fn capture_file(
+  camera: *mut libgphoto2_sys::Camera,
+  capture_type: libgphoto2_sys::CameraCaptureType,
+  journal: Option<&ShotJournal>,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<CameraFilePath> {
+  use libgphoto2_sys::CameraCaptureType;
+
+  let capture = || {
+    let mut inner = UninitBox::uninit();
+
+    try_gp_internal!(gp_camera_capture(camera, capture_type, inner.as_mut_ptr(), context)?);
+
+    Ok(CameraFilePath { inner: inner.assume_init() })
+  };
+
+  let kind = match capture_type {
+    CameraCaptureType::GP_CAPTURE_IMAGE => CaptureKind::Image,
+    CameraCaptureType::GP_CAPTURE_MOVIE => CaptureKind::Movie,
+    CameraCaptureType::GP_CAPTURE_SOUND => CaptureKind::Sound,
+  };
+
+  match journal {
+    Some(journal) => journal.record(camera, kind, context, capture),
+    None => capture(),
+  }
+}
+
+/// Abilities of an opened camera
+pub(crate) unsafe fn camera_abilities(camera: *mut libgphoto2_sys::Camera) -> Result<Abilities> {
+  let mut inner = UninitBox::uninit();
+
+  try_gp_internal!(gp_camera_get_abilities(camera, inner.as_mut_ptr())?);
+
+  Ok(Abilities { inner: inner.assume_init() })
+}
+
+/// Wait for an event, checking for cancellation at least every [`EVENT_POLL_INTERVAL`]
+pub(crate) unsafe fn wait_for_event(
+  camera: *mut libgphoto2_sys::Camera,
+  timeout: Duration,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<CameraEvent> {
+  use libgphoto2_sys::CameraEventType;
+
+  let deadline = Instant::now().checked_add(timeout).ok_or("Timeout is too large")?;
+
+  loop {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+
+    try_gp_internal!(gp_camera_wait_for_event(
+      camera,
+      remaining.min(EVENT_POLL_INTERVAL).as_millis().try_into()?,
+      &out event_type,
+      &out event_data,
+      context
+    )?);
+
+    if event_type != CameraEventType::GP_EVENT_TIMEOUT || remaining <= EVENT_POLL_INTERVAL {
+      return Ok(CameraEvent::from_raw(event_type, event_data));
+    }
+
+    check_cancelled(context)?;
+  }
+}
+
+/// Get a single config, searching the full config tree if the driver doesn't support this
+///
+/// The key is resolved through the [alias table](crate::quirks) for the model of the camera.
+pub(crate) unsafe fn get_single_config(
+  camera: *mut libgphoto2_sys::Camera,
+  key: &str,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<Widget> {
+  let key = &*quirks::resolve_config_key(&camera_abilities(camera)?.model(), key);
+  let mut widget = std::ptr::null_mut();
+  let status =
+    libgphoto2_sys::gp_camera_get_single_config(camera, to_c_string!(key), &mut widget, context);
+
+  if status != libgphoto2_sys::GP_ERROR_NOT_SUPPORTED {
+    Error::check(status)?;
+    return Ok(Widget::new_owned(BackgroundPtr(widget)));
+  }
+
+  try_gp_internal!(gp_camera_get_config(camera, &out root, context)?);
+  Widget::new_owned(BackgroundPtr(root)).try_into::<GroupWidget>()?.get_child_by_name(key)
+}
+
+/// Serial number of the camera, [`None`] if it reports none, must be called from a [`Task`]
+pub(crate) unsafe fn serial_number(
+  camera: *mut libgphoto2_sys::Camera,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Option<String> {
+  match get_single_config(camera, SERIAL_NUMBER_KEY, context) {
+    Ok(Widget::Text(serial)) => Some(serial.value()).filter(|serial| !serial.is_empty()),
+    _ => None,
+  }
+}
+
+/// Configuration tree of the widgets at `paths`, must be called from a [`Task`]
+unsafe fn filtered_config(
+  camera: *mut libgphoto2_sys::Camera,
+  paths: &[String],
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<GroupWidget> {
+  use libgphoto2_sys::CameraWidgetType;
+
+  try_gp_internal!(gp_widget_new(
+    CameraWidgetType::GP_WIDGET_WINDOW,
+    to_c_string!("Camera and Driver Configuration"),
+    &out root
+  )?);
+  // Owns the tree from here on, appended widgets are freed with it
+  let root = Widget::new_owned(BackgroundPtr(root)).try_into::<GroupWidget>()?;
+  try_gp_internal!(gp_widget_set_name(*root.inner, to_c_string!("main"))?);
+
+  let mut sections = HashMap::new();
+
+  for path in paths {
+    check_cancelled(context)?;
+
+    let (section_path, name) = path.rsplit_once('/').unwrap_or(("", path));
+    let mut parent = *root.inner;
+    let mut section_key = String::new();
+
+    // The first part is the root itself
+    for section in section_path.split('/').filter(|part| !part.is_empty()).skip(1) {
+      section_key.push('/');
+      section_key.push_str(section);
+
+      parent = match sections.get(&section_key) {
+        Some(&section) => section,
+        None => {
+          try_gp_internal!(gp_widget_new(
+            CameraWidgetType::GP_WIDGET_SECTION,
+            to_c_string!(section),
+            &out section_widget
+          )?);
+          try_gp_internal!(gp_widget_append(parent, section_widget)?);
+          try_gp_internal!(gp_widget_set_name(section_widget, to_c_string!(section))?);
+
+          sections.insert(section_key.clone(), section_widget);
+          section_widget
+        }
+      };
+    }
+
+    try_gp_internal!(gp_camera_get_single_config(camera, to_c_string!(name), &out widget, context)?);
+    try_gp_internal!(gp_widget_append(parent, widget)?);
+  }
+
+  Ok(root)
+}
+
+/// Set a single config, setting the full config tree if the driver doesn't support this
+pub(crate) unsafe fn set_single_config(
+  camera: *mut libgphoto2_sys::Camera,
+  config: &WidgetBase,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<()> {
+  let name = config.name();
+  let status = libgphoto2_sys::gp_camera_set_single_config(
+    camera,
+    to_c_string!(name.as_str()),
+    *config.inner,
+    context,
+  );
+
+  if status != libgphoto2_sys::GP_ERROR_NOT_SUPPORTED {
+    Error::check(status)?;
+    return Ok(());
+  }
+
+  try_gp_internal!(gp_camera_get_config(camera, &out root, context)?);
+  let root = Widget::new_owned(BackgroundPtr(root)).try_into::<GroupWidget>()?;
+
+  config.copy_value_to(&*root.get_child_by_name(&name)?)?;
+  try_gp_internal!(gp_camera_set_config(camera, *root.inner, context)?);
+
+  Ok(())
+}
+
+/// Exclusive access to a camera, see [`Camera::lock`]
+pub struct CameraLock {
+  camera: Camera,
+  lock_queue: Arc<LockQueue>,
+}
+
+impl Drop for CameraLock {
+  fn drop(&mut self) {
+    self.lock_queue.leave();
+  }
+}
+
+impl Deref for CameraLock {
+  type Target = Camera;
+
+  fn deref(&self) -> &Camera {
+    &self.camera
+  }
+}
+
+/// Builder for opening a [`Camera`], created with [`Context::camera_builder`]
+///
+/// Without a model or port, the first detected camera is opened.
+///
+/// ```no_run
+/// use gphoto2::{Context, Result};
+///
+/// # fn main() -> Result<()> {
+/// let context = Context::new()?;
+///
+/// let camera = context
+///   .camera_builder()
+///   .model("Nikon DSC D750")
+///   .port("usb:001,004")
+///   .build()
+///   .wait()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct CameraBuilder {
+  context: Context,
+  model: Option<String>,
+  port: Option<String>,
+  abilities_list: Option<Arc<AbilitiesList>>,
+  port_info_list: Option<Arc<PortInfoList>>,
+  init: bool,
+  #[cfg(feature = "gvfs")]
+  release_gvfs: Option<Box<crate::gvfs::ConfirmUnmount>>,
+}
+
+impl CameraBuilder {
+  pub(crate) fn new(context: Context) -> Self {
+    Self {
+      context,
+      model: None,
+      port: None,
+      abilities_list: None,
+      port_info_list: None,
+      init: true,
+      #[cfg(feature = "gvfs")]
+      release_gvfs: None,
+    }
+  }
+
+  /// Use the model and port of a detected camera
+  pub fn descriptor(self, descriptor: &CameraDescriptor) -> Self {
+    self.model(&descriptor.model).port(&descriptor.port)
+  }
+
+  /// Use the driver for `model`, as listed by [`Context::list_cameras`]
+  pub fn model(mut self, model: &str) -> Self {
+    self.model = Some(model.to_owned());
+    self
+  }
+
+  /// Connect to the port at `path`, for example `usb:001,004` or `ptpip:192.168.1.1`
+  pub fn port(mut self, path: &str) -> Self {
+    self.port = Some(path.to_owned());
+    self
+  }
+
+  /// Look up the model in `abilities_list` instead of [`Context::abilities_list`]
+  pub fn abilities_list(mut self, abilities_list: Arc<AbilitiesList>) -> Self {
+    self.abilities_list = Some(abilities_list);
+    self
+  }
+
+  /// Look up the port in `port_info_list` instead of [`Context::port_info_list`]
+  pub fn port_info_list(mut self, port_info_list: Arc<PortInfoList>) -> Self {
+    self.port_info_list = Some(port_info_list);
+    self
+  }
+
+  /// Whether to connect to the camera in [`build`](Self::build) (the default)
+  ///
+  /// When disabled, the camera connects in [`Camera::init`] or on the first operation
+  /// instead, so connection errors are only reported then.
+  pub fn init(mut self, init: bool) -> Self {
+    self.init = init;
+    self
+  }
+
+  /// Unmount the camera before connecting if gvfs mounted it and `confirm` returns true
+  ///
+  /// Claiming the camera is retried for a few seconds after unmounting it, until gvfs
+  /// released the device. `confirm` runs on the libgphoto2 thread, see the
+  /// [`gvfs`](crate::gvfs) module.
+  #[cfg(feature = "gvfs")]
+  pub fn release_gvfs(
+    mut self,
+    confirm: impl Fn(&crate::gvfs::GvfsMount) -> bool + Send + 'static,
+  ) -> Self {
+    self.release_gvfs = Some(Box::new(confirm));
+    self
+  }
+
+  /// Open the camera
+  pub fn build(self) -> Task<Result<Camera>> {
+    let context = self.context.clone();
+
+    unsafe { Task::new(move || self.build_inner()) }.context(&context)
+  }
+
+  /// Must be called from a [`Task`]
+  pub(crate) unsafe fn build_inner(&self) -> Result<Camera> {
+    try_gp_internal!(gp_camera_new(&out camera)?);
+    let camera = BackgroundPtr(camera);
+    let state = CameraState::register(camera, &self.context, self.init);
+    // Releases the camera if the setup fails
+    let camera = Camera::new(camera, self.context.clone(), state);
+
+    self.setup(*camera.camera)?;
+
+    Ok(camera)
+  }
+
+  /// Must be called from a [`Task`]
+  fn setup(&self, camera: *mut libgphoto2_sys::Camera) -> Result<()> {
+    let (model, port) = match (&self.model, &self.port) {
+      // Detect the camera here, gp_camera_init would load the driver lists again
+      (None, None) if self.init => {
+        let CameraDescriptor { model, port } =
+          CameraListIter::new(self.context.detect_cameras_inner()?)
+            .next()
+            .ok_or_else(|| Error::new(libgphoto2_sys::GP_ERROR_MODEL_NOT_FOUND, None))?;
+        (Some(model), Some(port))
+      }
+      (model, port) => (model.clone(), port.clone()),
+    };
+
+    if let Some(model) = model {
+      let abilities_list = match &self.abilities_list {
+        Some(abilities_list) => abilities_list.clone(),
+        None => self.context.abilities_list_inner()?,
+      };
+      try_gp_internal!(gp_camera_set_abilities(camera, abilities_list.lookup_model(&model)?)?);
+    }
+
+    if let Some(port) = &port {
+      let port_info_list = match &self.port_info_list {
+        Some(port_info_list) => port_info_list.clone(),
+        None => self.context.port_info_list_inner()?,
+      };
+      try_gp_internal!(gp_camera_set_port_info(camera, port_info_list.lookup_path(port)?.inner)?);
+    }
+
+    if self.init {
+      #[cfg(feature = "gvfs")]
+      if let (Some(confirm), Some(port)) = (&self.release_gvfs, &port) {
+        return unsafe {
+          crate::gvfs::init_camera(camera, port, confirm.as_ref(), *self.context.inner())
+        };
+      }
+
+      try_gp_internal!(gp_camera_init(camera, *self.context.inner())?);
     }
-    .context(context)
+
+    Ok(())
   }
 }
 
@@ -407,13 +1734,13 @@ mod tests {
 
   #[test]
   fn test_abilities() {
-    let abilities = sample_camera().abilities();
+    let abilities = sample_camera().abilities().unwrap();
     insta::assert_debug_snapshot!(abilities);
   }
 
   #[test]
   fn test_summary() {
-    let mut summary = sample_camera().summary().unwrap_or_default();
+    let mut summary = sample_camera().summary().wait().unwrap_or_default();
 
     // Summary contains dynamic timestamp, find and remove it for snapshotting.
     let prefix = "Date & Time(0x5011):(readwrite) (type=0xffff)";
@@ -434,13 +1761,13 @@ mod tests {
 
   #[test]
   fn test_about() {
-    let about = sample_camera().about().unwrap_or_default();
+    let about = sample_camera().about().wait().unwrap_or_default();
     insta::assert_snapshot!(about);
   }
 
   #[test]
   fn test_manual() {
-    let manual = sample_camera().manual().unwrap_or_default();
+    let manual = sample_camera().manual().wait().unwrap_or_default();
     insta::assert_snapshot!(manual);
   }
 
@@ -529,6 +1856,50 @@ mod tests {
     insta::assert_debug_snapshot!(storage_folders);
   }
 
+  #[test]
+  fn test_storage_fs() {
+    let camera = sample_camera();
+
+    for storage in camera.storages().wait().unwrap() {
+      let base = storage.base_directory().unwrap();
+      let storage_fs = storage.fs(&camera).unwrap();
+
+      assert_eq!(
+        storage_fs.list_folders("").wait().unwrap().collect::<Vec<_>>(),
+        camera.fs().list_folders(&base).wait().unwrap().collect::<Vec<_>>()
+      );
+      assert!(storage_fs.list_files("..").wait().is_err());
+    }
+  }
+
+  #[test]
+  fn test_autodetect_matching() {
+    let context = crate::sample_context();
+    let model = sample_camera().abilities().unwrap().model().into_owned();
+
+    let camera = context.autodetect_camera_matching(move |camera| camera.model == model).wait();
+    assert!(camera.is_ok());
+
+    assert!(context.autodetect_camera_matching(|_| false).wait().is_err());
+    assert!(context.autodetect_by_serial("no such serial").wait().is_err());
+  }
+
+  #[test]
+  fn test_shot_journal() {
+    let camera = sample_camera();
+    let journal = crate::journal::ShotJournal::new();
+    camera.set_shot_journal(Some(journal.clone()));
+
+    let path = camera.capture_image().wait().unwrap();
+    camera.set_shot_journal(None);
+    camera.capture_image().wait().unwrap();
+
+    let records = journal.records();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].path.as_ref(), Some(&path));
+    assert!(records[0].error.is_none());
+  }
+
   #[test]
   fn test_port_info() {
     let camera = sample_camera();
@@ -550,7 +1921,7 @@ mod tests {
       .unwrap()
       .try_into::<TextWidget>()
       .unwrap()
-      .set_value("(omitted timestamp)")
+      .set_value_forced("(omitted timestamp)")
       .unwrap();
 
     widget_tree
@@ -558,8 +1929,97 @@ mod tests {
       .unwrap()
       .try_into::<DateWidget>()
       .unwrap()
-      .set_timestamp(42);
+      .set_timestamp_forced(42);
 
     insta::assert_debug_snapshot!(widget_tree);
   }
+
+  #[test]
+  fn test_widget_path() {
+    let widget_tree = sample_camera().config().wait().unwrap();
+    let datetime = widget_tree.get_child_by_name("datetime").unwrap();
+
+    assert_eq!(datetime.parent().unwrap().path() + "/datetime", datetime.path());
+    assert_eq!(datetime.root().name(), widget_tree.name());
+  }
+
+  #[test]
+  fn test_date_widget_datetime() {
+    use crate::widget::DateWidget;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let widget_tree = sample_camera().config().wait().unwrap();
+    let datetime =
+      widget_tree.get_child_by_name("datetime").unwrap().try_into::<DateWidget>().unwrap();
+
+    let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    datetime.set_datetime_forced(time).unwrap();
+    assert_eq!(datetime.datetime(), time);
+    assert_eq!(datetime.timestamp(), 1_700_000_000);
+
+    assert!(datetime.set_datetime_forced(UNIX_EPOCH + Duration::from_secs(1 << 32)).is_err());
+  }
+
+  #[test]
+  fn test_spec_tree() {
+    use crate::widget::WidgetKind;
+
+    let widget_tree = sample_camera().config().wait().unwrap();
+    let spec = widget_tree.spec_tree();
+    let datetime = widget_tree.get_child_by_name("datetime").unwrap();
+
+    let WidgetKind::Section { children } = &spec.kind else { panic!("Root is no section") };
+    assert_eq!(children.len(), widget_tree.children_count());
+    assert_eq!(spec.path, widget_tree.path());
+    assert!(matches!(datetime.spec().kind, WidgetKind::Date { .. }));
+  }
+
+  #[test]
+  fn test_find_descendant() {
+    let widget_tree = sample_camera().config().wait().unwrap();
+
+    let by_name = widget_tree.find_descendant_by_name("datetime").unwrap();
+    assert_eq!(by_name.path(), widget_tree.get_child_by_name("datetime").unwrap().path());
+
+    let by_label = widget_tree.find_descendant_by_label(&by_name.label()).unwrap();
+    assert_eq!(by_label.label(), by_name.label());
+
+    assert!(widget_tree.find_descendant_by_name("no such widget").is_err());
+  }
+
+  #[test]
+  fn test_task_polling() {
+    let mut task = sample_camera().storages();
+
+    let storages = task.wait_timeout(std::time::Duration::from_secs(10)).unwrap().unwrap();
+    assert!(!storages.is_empty());
+    assert!(!task.is_finished());
+    assert!(task.try_get().is_none());
+  }
+
+  #[test]
+  fn test_lock() {
+    let camera = sample_camera();
+
+    let lock = camera.lock();
+    // Started by another thread, queued behind the lock until it is dropped
+    let (send_queued, recv_queued) = std::sync::mpsc::channel();
+    let other = camera.clone();
+    let outside = std::thread::spawn(move || {
+      let mut task = other.storages();
+      send_queued.send(task.wait_timeout(std::time::Duration::from_millis(100)).is_none()).unwrap();
+      task.wait().unwrap()
+    });
+    assert!(recv_queued.recv().unwrap());
+
+    // The thread holding the lock can use the camera itself and lock it again
+    assert!(!camera.storages().wait().unwrap().is_empty());
+    let nested = camera.lock();
+    assert!(!nested.storages().wait().unwrap().is_empty());
+    drop(nested);
+    assert!(!lock.storages().wait().unwrap().is_empty());
+    drop(lock);
+
+    assert!(!outside.join().unwrap().is_empty());
+  }
 }
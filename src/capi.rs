@@ -0,0 +1,436 @@
+//! C API for using the crate from other languages
+//!
+//! The functions use opaque handles for contexts, cameras and tasks, so applications in C, C++,
+//! Swift or Python (with cffi) get the threading of this crate instead of calling libgphoto2
+//! directly. A header can be generated with [cbindgen](https://github.com/mozilla/cbindgen), the
+//! library is built with `cargo rustc --release --features capi --crate-type cdylib`.
+//!
+//! ```c
+//! gp2rs_context *context;
+//! if (gp2rs_context_new(&context) < 0) {
+//!   fprintf(stderr, "%s\n", gp2rs_last_error());
+//!   return 1;
+//! }
+//!
+//! gp2rs_task *task = gp2rs_autodetect_camera(context);
+//! if (gp2rs_task_wait(task) < 0) { /* ... */ }
+//! gp2rs_camera *camera = gp2rs_task_take_camera(task);
+//! gp2rs_task_free(task);
+//!
+//! task = gp2rs_camera_capture_image(camera);
+//! if (gp2rs_task_wait(task) == 0) {
+//!   char *folder, *name;
+//!   gp2rs_task_take_path(task, &folder, &name);
+//!   printf("Captured %s/%s\n", folder, name);
+//!   gp2rs_string_free(folder);
+//!   gp2rs_string_free(name);
+//! }
+//! gp2rs_task_free(task);
+//!
+//! gp2rs_camera_free(camera);
+//! gp2rs_context_free(context);
+//! ```
+//!
+//! Functions returning `int` return 0 or a negative `GP_ERROR_*` code, the message of the last
+//! error of the calling thread is returned by [`gp2rs_last_error`]. Handles must be freed with
+//! their `_free` function, strings returned by this API with [`gp2rs_string_free`].
+//!
+//! Panics never unwind into the caller, the function fails with `GP_ERROR` (or returns NULL)
+//! and sets the last error instead.
+
+#![allow(non_camel_case_types)]
+
+use crate::{file::CameraFilePath, task::Task, Camera, Context, Error, Result};
+use std::{
+  cell::RefCell,
+  ffi::{CStr, CString},
+  os::raw::{c_char, c_int},
+  panic::{catch_unwind, AssertUnwindSafe},
+  path::Path,
+  ptr,
+};
+
+thread_local! {
+  static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Context handle
+pub struct gp2rs_context(Context);
+
+/// Camera handle
+pub struct gp2rs_camera(Camera);
+
+/// Result of a finished task
+enum Output {
+  Unit,
+  Camera(Camera),
+  Path(CameraFilePath),
+  Text(String),
+}
+
+/// Task handle, started by the first [`gp2rs_task_wait`] or [`gp2rs_task_poll`]
+pub struct gp2rs_task {
+  task: Option<Task<Result<Output>>>,
+  /// Set once the task finished, kept when the output is taken
+  status: Option<Result<()>>,
+  output: Option<Output>,
+}
+
+impl gp2rs_task {
+  fn new(
+    context: &Context,
+    fun: impl FnOnce() -> Result<Output> + Send + 'static,
+  ) -> *mut gp2rs_task {
+    // Nested tasks run inline, cancelling the outer task cancels the libgphoto2 call
+    let task = Task::run_nested(context, fun);
+
+    Box::into_raw(Box::new(gp2rs_task { task: Some(task), status: None, output: None }))
+  }
+
+  fn finish(&mut self, result: Result<Output>) {
+    self.task = None;
+    self.status = Some(match result {
+      Ok(output) => {
+        self.output = Some(output);
+        Ok(())
+      }
+      Err(error) => Err(error),
+    });
+  }
+
+  /// Status code of the result, 1 if the task didn't finish yet
+  fn status(&self) -> c_int {
+    match &self.status {
+      None => 1,
+      Some(Ok(())) => 0,
+      Some(Err(error)) => fail(error.clone()),
+    }
+  }
+
+  /// Take the successful result, setting the last error otherwise
+  fn take_output(&mut self) -> std::result::Result<Output, c_int> {
+    match (&self.status, self.output.take()) {
+      (Some(Err(error)), _) => Err(fail(error.clone())),
+      (Some(Ok(())), Some(output)) => Ok(output),
+      (Some(Ok(())), None) => Err(fail(Error::from("The result of the task was taken"))),
+      (None, _) => Err(fail(Error::from("The task didn't finish"))),
+    }
+  }
+}
+
+/// Run the body of an exported function, a panic returns `on_panic` and sets the last error
+fn guard<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+  catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|_| {
+    fail(Error::new(libgphoto2_sys::GP_ERROR, Some("gphoto2-rs panicked".to_owned())));
+    on_panic
+  })
+}
+
+fn set_last_error(error: &Error) {
+  let message = CString::new(error.to_string().replace('\0', " ")).unwrap_or_default();
+
+  LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Report an error, returning its code
+fn fail(error: Error) -> c_int {
+  set_last_error(&error);
+  error.code()
+}
+
+fn into_c_string(value: &str) -> *mut c_char {
+  CString::new(value.replace('\0', " ")).unwrap_or_default().into_raw()
+}
+
+unsafe fn str_arg<'a>(value: *const c_char, name: &str) -> Result<&'a str> {
+  if value.is_null() {
+    return Err(Error::new(
+      libgphoto2_sys::GP_ERROR_BAD_PARAMETERS,
+      Some(format!("{name} is NULL")),
+    ));
+  }
+
+  CStr::from_ptr(value).to_str().map_err(|_| {
+    Error::new(libgphoto2_sys::GP_ERROR_BAD_PARAMETERS, Some(format!("{name} is not UTF-8")))
+  })
+}
+
+/// Message of the last error on this thread, NULL if there was none
+///
+/// The string stays valid until the next error on this thread.
+#[no_mangle]
+pub extern "C" fn gp2rs_last_error() -> *const c_char {
+  guard(ptr::null(), || {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+  })
+}
+
+/// Free a string returned by this API
+///
+/// # Safety
+/// `string` must be NULL or a string returned by this API which was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_string_free(string: *mut c_char) {
+  guard((), || {
+    if !string.is_null() {
+      drop(CString::from_raw(string));
+    }
+  })
+}
+
+/// Create a context, see [`Context::new`]
+///
+/// # Safety
+/// `out` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_context_new(out: *mut *mut gp2rs_context) -> c_int {
+  guard(libgphoto2_sys::GP_ERROR, || match Context::new() {
+    Ok(context) => {
+      *out = Box::into_raw(Box::new(gp2rs_context(context)));
+      0
+    }
+    Err(error) => fail(error),
+  })
+}
+
+/// Free a context, cameras of the context stay usable
+///
+/// # Safety
+/// `context` must be NULL or a context of this API which was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_context_free(context: *mut gp2rs_context) {
+  guard((), || {
+    if !context.is_null() {
+      drop(Box::from_raw(context));
+    }
+  })
+}
+
+/// Task detecting the first camera, take it with [`gp2rs_task_take_camera`]
+///
+/// # Safety
+/// `context` must be a context of this API which was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_autodetect_camera(context: *const gp2rs_context) -> *mut gp2rs_task {
+  guard(ptr::null_mut(), || {
+    let context = &(*context).0;
+    let task = context.autodetect_camera();
+
+    gp2rs_task::new(context, move || task.wait().map(Output::Camera))
+  })
+}
+
+/// Free a camera
+///
+/// # Safety
+/// `camera` must be NULL or a camera of this API which was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_camera_free(camera: *mut gp2rs_camera) {
+  guard((), || {
+    if !camera.is_null() {
+      drop(Box::from_raw(camera));
+    }
+  })
+}
+
+/// Task capturing an image, take its path with [`gp2rs_task_take_path`]
+///
+/// # Safety
+/// `camera` must be a camera of this API which was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_camera_capture_image(
+  camera: *const gp2rs_camera,
+) -> *mut gp2rs_task {
+  guard(ptr::null_mut(), || {
+    let camera = &(*camera).0;
+    let task = camera.capture_image();
+
+    gp2rs_task::new(&camera.context, move || task.wait().map(Output::Path))
+  })
+}
+
+/// Task triggering a capture without waiting for the image
+///
+/// # Safety
+/// `camera` must be a camera of this API which was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_camera_trigger_capture(
+  camera: *const gp2rs_camera,
+) -> *mut gp2rs_task {
+  guard(ptr::null_mut(), || {
+    let camera = &(*camera).0;
+    let task = camera.trigger_capture();
+
+    gp2rs_task::new(&camera.context, move || task.wait().map(|()| Output::Unit))
+  })
+}
+
+/// Task reading the summary of the camera, take it with [`gp2rs_task_take_string`]
+///
+/// # Safety
+/// `camera` must be a camera of this API which was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_camera_summary(camera: *const gp2rs_camera) -> *mut gp2rs_task {
+  guard(ptr::null_mut(), || {
+    let camera = &(*camera).0;
+    let task = camera.summary();
+
+    gp2rs_task::new(&camera.context, move || task.wait().map(Output::Text))
+  })
+}
+
+/// Task downloading a file of the camera to `path`
+///
+/// Returns NULL if an argument is invalid.
+///
+/// # Safety
+/// `camera` must be a camera of this API which was not freed, the strings must be NUL
+/// terminated.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_camera_download(
+  camera: *const gp2rs_camera,
+  folder: *const c_char,
+  name: *const c_char,
+  path: *const c_char,
+) -> *mut gp2rs_task {
+  guard(ptr::null_mut(), || {
+    let camera = &(*camera).0;
+
+    let args =
+      (|| Ok((str_arg(folder, "folder")?, str_arg(name, "name")?, str_arg(path, "path")?)))();
+    let (folder, name, path) = match args {
+      Ok(args) => args,
+      Err(error) => {
+        fail(error);
+        return ptr::null_mut();
+      }
+    };
+
+    let task = camera.fs().download_to(folder, name, Path::new(path));
+
+    gp2rs_task::new(&camera.context, move || task.wait().map(|_| Output::Unit))
+  })
+}
+
+/// Block until the task finished, returns its status
+///
+/// # Safety
+/// `task` must be a task of this API which was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_task_wait(task: *mut gp2rs_task) -> c_int {
+  guard(libgphoto2_sys::GP_ERROR, || {
+    let task = &mut *task;
+
+    if let Some(running) = task.task.take() {
+      task.finish(running.wait());
+    }
+
+    task.status()
+  })
+}
+
+/// Start the task if needed and return 1 while it runs, its status once it finished
+///
+/// # Safety
+/// `task` must be a task of this API which was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_task_poll(task: *mut gp2rs_task) -> c_int {
+  guard(libgphoto2_sys::GP_ERROR, || {
+    let task = &mut *task;
+
+    if let Some(result) = task.task.as_mut().and_then(Task::try_get) {
+      task.finish(result);
+    }
+
+    task.status()
+  })
+}
+
+/// Cancel the task, it finishes with `GP_ERROR_CANCEL` if the driver supports cancellation
+///
+/// # Safety
+/// `task` must be a task of this API which was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_task_cancel(task: *const gp2rs_task) {
+  guard((), || {
+    if let Some(running) = &(*task).task {
+      running.cancel();
+    }
+  })
+}
+
+/// Take the camera of a finished [`gp2rs_autodetect_camera`] task, NULL on errors
+///
+/// # Safety
+/// `task` must be a task of this API which was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_task_take_camera(task: *mut gp2rs_task) -> *mut gp2rs_camera {
+  guard(ptr::null_mut(), || match (*task).take_output() {
+    Ok(Output::Camera(camera)) => Box::into_raw(Box::new(gp2rs_camera(camera))),
+    Ok(_) => {
+      fail(Error::new(
+        libgphoto2_sys::GP_ERROR_BAD_PARAMETERS,
+        Some("The task has no camera".to_owned()),
+      ));
+      ptr::null_mut()
+    }
+    Err(_) => ptr::null_mut(),
+  })
+}
+
+/// Take the text result of a finished task, NULL on errors
+///
+/// # Safety
+/// `task` must be a task of this API which was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_task_take_string(task: *mut gp2rs_task) -> *mut c_char {
+  guard(ptr::null_mut(), || match (*task).take_output() {
+    Ok(Output::Text(text)) => into_c_string(&text),
+    Ok(_) => {
+      fail(Error::new(
+        libgphoto2_sys::GP_ERROR_BAD_PARAMETERS,
+        Some("The task has no text".to_owned()),
+      ));
+      ptr::null_mut()
+    }
+    Err(_) => ptr::null_mut(),
+  })
+}
+
+/// Take the path of a finished capture task
+///
+/// # Safety
+/// `task` must be a task of this API which was not freed, `folder` and `name` must be valid
+/// pointers.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_task_take_path(
+  task: *mut gp2rs_task,
+  folder: *mut *mut c_char,
+  name: *mut *mut c_char,
+) -> c_int {
+  guard(libgphoto2_sys::GP_ERROR, || match (*task).take_output() {
+    Ok(Output::Path(path)) => {
+      *folder = into_c_string(&path.folder());
+      *name = into_c_string(&path.name());
+      0
+    }
+    Ok(_) => fail(Error::new(
+      libgphoto2_sys::GP_ERROR_BAD_PARAMETERS,
+      Some("The task has no path".to_owned()),
+    )),
+    Err(code) => code,
+  })
+}
+
+/// Free a task, a started task keeps running in the background
+///
+/// # Safety
+/// `task` must be NULL or a task of this API which was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn gp2rs_task_free(task: *mut gp2rs_task) {
+  guard((), || {
+    if !task.is_null() {
+      drop(Box::from_raw(task));
+    }
+  })
+}
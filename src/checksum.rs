@@ -0,0 +1,128 @@
+//! Checksums of downloaded files
+//!
+//! USB transfers over flaky cables can corrupt files without an error. With
+//! [`CameraFS::download_checked`](crate::filesys::CameraFS::download_checked) the checksum of the
+//! received data is computed on the libgphoto2 thread and optionally compared with a second
+//! download of the same file.
+//!
+//! ```no_run
+//! use gphoto2::{checksum::Crc32, Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//!
+//! let (file, checksum) = camera
+//!   .fs()
+//!   .download_checked::<Crc32>("/store_00010001/DCIM/100CANON", "IMG_0001.CR2", true)
+//!   .wait()?;
+//! println!("CRC32 {checksum}");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+
+/// Hash function computing a [`Checksum`]
+///
+/// Implemented by [`Crc32`] and, with the `sha2` feature, by `sha2::Sha256`.
+pub trait ChecksumHasher: Default + Send + 'static {
+  /// Add data to the checksum
+  fn update(&mut self, data: &[u8]);
+
+  /// The checksum of all added data
+  fn finish(self) -> Checksum;
+}
+
+/// Checksum of a file, displayed as lowercase hex
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Checksum(Vec<u8>);
+
+impl Checksum {
+  /// Wrap the bytes of a checksum
+  pub fn new(bytes: Vec<u8>) -> Self {
+    Self(bytes)
+  }
+
+  /// Bytes of the checksum
+  pub fn as_bytes(&self) -> &[u8] {
+    &self.0
+  }
+
+  /// Compute the checksum of `data` with `H`
+  pub fn compute<H: ChecksumHasher>(data: &[u8]) -> Self {
+    let mut hasher = H::default();
+    hasher.update(data);
+    hasher.finish()
+  }
+}
+
+impl fmt::Display for Checksum {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+  }
+}
+
+/// CRC-32 lookup table of the reversed IEEE polynomial
+const CRC32_TABLE: [u32; 256] = {
+  let mut table = [0u32; 256];
+  let (mut index, mut value) = (0, 0u32);
+
+  while index < table.len() {
+    let mut crc = value;
+    let mut bit = 0;
+
+    while bit < 8 {
+      crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+      bit += 1;
+    }
+
+    table[index] = crc;
+    index += 1;
+    value += 1;
+  }
+
+  table
+};
+
+/// CRC-32 (IEEE) like used by zip and PNG, fast but only meant to detect transfer errors
+///
+/// ```
+/// use gphoto2::checksum::{Checksum, Crc32};
+///
+/// assert_eq!(Checksum::compute::<Crc32>(b"123456789").to_string(), "cbf43926");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Crc32 {
+  crc: u32,
+}
+
+impl Default for Crc32 {
+  fn default() -> Self {
+    Self { crc: u32::MAX }
+  }
+}
+
+impl ChecksumHasher for Crc32 {
+  fn update(&mut self, data: &[u8]) {
+    for &byte in data {
+      let index = usize::from(self.crc.to_le_bytes()[0] ^ byte);
+      self.crc = (self.crc >> 8) ^ CRC32_TABLE[index];
+    }
+  }
+
+  fn finish(self) -> Checksum {
+    Checksum((!self.crc).to_be_bytes().to_vec())
+  }
+}
+
+#[cfg(feature = "sha2")]
+impl ChecksumHasher for sha2::Sha256 {
+  fn update(&mut self, data: &[u8]) {
+    sha2::Digest::update(self, data);
+  }
+
+  fn finish(self) -> Checksum {
+    Checksum(sha2::Digest::finalize(self).to_vec())
+  }
+}
@@ -0,0 +1,281 @@
+//! Camera clock, see [`Camera::clock`](crate::Camera::clock) and [`Camera::sync_clock`](crate::Camera::sync_clock)
+//!
+//! Drivers expose the clock in different ways:
+//! - Newer Canon models have a `datetimeutc` date widget holding a UNIX timestamp
+//! - Most drivers have a `datetime` date widget, libgphoto2 converts it to the local time
+//!   of the camera using the time zone of this computer
+//! - Some PTP cameras have a `datetime` text widget with the raw value, either in the PTP
+//!   format (`20240131T235959`, optionally with a `Z` suffix for UTC), separated
+//!   (`2024/01/31 23:59:59`) or as a UNIX timestamp
+
+use crate::{
+  camera::{get_single_config, set_single_config},
+  widget::Widget,
+  Error, Result,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Config keys of the clock, in the order they are tried
+pub(crate) const CLOCK_KEYS: [&str; 2] = ["datetimeutc", "datetime"];
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+unsafe fn clock_widget(
+  camera: *mut libgphoto2_sys::Camera,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<Widget> {
+  let mut last_error = None;
+
+  for key in CLOCK_KEYS {
+    match get_single_config(camera, key, context) {
+      Ok(widget) => return Ok(widget),
+      Err(error) => last_error = Some(error),
+    }
+  }
+
+  Err(last_error.unwrap_or_else(|| Error::from("The camera has no clock")))
+}
+
+pub(crate) unsafe fn read(
+  camera: *mut libgphoto2_sys::Camera,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<SystemTime> {
+  let timestamp = match clock_widget(camera, context)? {
+    Widget::Date(widget) => widget.timestamp().into(),
+    Widget::Text(widget) => parse_text(&widget.value())?,
+    widget => return Err(Error::from(format!("Unsupported clock widget {widget:?}"))),
+  };
+
+  Ok(from_timestamp(timestamp))
+}
+
+pub(crate) unsafe fn sync(
+  camera: *mut libgphoto2_sys::Camera,
+  context: *mut libgphoto2_sys::GPContext,
+  time: SystemTime,
+) -> Result<()> {
+  let timestamp = to_timestamp(time)?;
+  let widget = clock_widget(camera, context)?;
+
+  match &widget {
+    Widget::Date(widget) => widget.set_timestamp_forced(
+      timestamp
+        .try_into()
+        .map_err(|_| Error::from("The time can't be represented by the camera clock"))?,
+    ),
+    Widget::Text(widget) => widget.set_value_forced(&format_text(&widget.value(), timestamp)?)?,
+    widget => return Err(Error::from(format!("Unsupported clock widget {widget:?}"))),
+  }
+
+  set_single_config(camera, &widget, context)
+}
+
+pub(crate) fn to_timestamp(time: SystemTime) -> Result<i64> {
+  let seconds = match time.duration_since(UNIX_EPOCH) {
+    Ok(since) => i64::try_from(since.as_secs()).ok(),
+    Err(before) => i64::try_from(before.duration().as_secs()).ok().map(|seconds| -seconds),
+  };
+
+  seconds.ok_or_else(|| Error::from("The time is out of range"))
+}
+
+pub(crate) fn from_timestamp(timestamp: i64) -> SystemTime {
+  let duration = Duration::from_secs(timestamp.unsigned_abs());
+
+  if timestamp >= 0 {
+    UNIX_EPOCH + duration
+  } else {
+    UNIX_EPOCH - duration
+  }
+}
+
+/// Offset of the local time zone from UTC in seconds
+#[cfg(unix)]
+fn local_offset(timestamp: i64) -> i64 {
+  // time_t is 32 bit on some platforms
+  let Some(time) = libc::time_t::try_from(timestamp).ok() else {
+    return 0;
+  };
+  let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+
+  if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+    return 0;
+  }
+
+  // c_long is 32 bit on some platforms
+  #[allow(clippy::useless_conversion)]
+  tm.tm_gmtoff.into()
+}
+
+/// Offset of the local time zone from UTC in seconds
+#[cfg(not(unix))]
+fn local_offset(_timestamp: i64) -> i64 {
+  0
+}
+
+/// Date and time from a timestamp, `(year, month, day, hour, minute, second)`
+pub(crate) fn to_civil(timestamp: i64) -> (i64, i64, i64, i64, i64, i64) {
+  let days = timestamp.div_euclid(SECONDS_PER_DAY);
+  let seconds = timestamp.rem_euclid(SECONDS_PER_DAY);
+
+  // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+  let z = days + 719_468;
+  let era = z.div_euclid(146_097);
+  let doe = z.rem_euclid(146_097);
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = doy - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = yoe + era * 400 + i64::from(month <= 2);
+
+  (year, month, day, seconds / 3600, seconds % 3600 / 60, seconds % 60)
+}
+
+/// Timestamp from a date and time, the inverse of [`to_civil`]
+fn from_civil(year: i64, month: i64, day: i64, hour: i64, minute: i64, second: i64) -> i64 {
+  // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = year.div_euclid(400);
+  let yoe = year.rem_euclid(400);
+  let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+  let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+  let days = era * 146_097 + doe - 719_468;
+
+  days * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second
+}
+
+/// Local time to a timestamp
+fn from_local(local: i64) -> i64 {
+  // The offset at the local time is close enough to find the offset at the actual time
+  let estimate = local - local_offset(local);
+  local - local_offset(estimate)
+}
+
+fn is_ptp_format(text: &str) -> bool {
+  text.len() >= 15 && text.as_bytes()[8] == b'T'
+}
+
+fn is_separated_format(text: &str) -> bool {
+  text.len() >= 19 && matches!(text.as_bytes()[10], b' ' | b'T')
+}
+
+/// Format a timestamp like the current value of a text clock
+fn format_text(current: &str, timestamp: i64) -> Result<String> {
+  let current = current.trim();
+
+  if !current.is_empty() && current.bytes().all(|c| c.is_ascii_digit()) {
+    return Ok(timestamp.to_string());
+  }
+
+  if is_ptp_format(current) {
+    let utc = current.ends_with('Z');
+    let offset = if utc { 0 } else { local_offset(timestamp) };
+    let (year, month, day, hour, minute, second) = to_civil(timestamp + offset);
+
+    return Ok(format!(
+      "{year:04}{month:02}{day:02}T{hour:02}{minute:02}{second:02}{}",
+      if utc { "Z" } else { "" }
+    ));
+  }
+
+  if is_separated_format(current) {
+    let separators: Vec<char> =
+      [4, 7, 10, 13, 16].iter().filter_map(|&i| current.get(i..)?.chars().next()).collect();
+    let (year, month, day, hour, minute, second) = to_civil(timestamp + local_offset(timestamp));
+
+    if let [date1, date2, middle, time1, time2] = separators[..] {
+      return Ok(format!(
+        "{year:04}{date1}{month:02}{date2}{day:02}{middle}{hour:02}{time1}{minute:02}{time2}{second:02}"
+      ));
+    }
+  }
+
+  Err(Error::from(format!("Unknown clock format {current:?}")))
+}
+
+/// Parse the value of a text clock
+fn parse_text(text: &str) -> Result<i64> {
+  let text = text.trim();
+  let invalid = || Error::from(format!("Unknown clock format {text:?}"));
+
+  if !text.is_empty() && text.bytes().all(|c| c.is_ascii_digit()) {
+    return text.parse().map_err(|_| invalid());
+  }
+
+  let number = |start: usize, len: usize| -> Result<i64> {
+    text.get(start..start + len).and_then(|digits| digits.parse().ok()).ok_or_else(invalid)
+  };
+
+  let (fields, utc) = if is_ptp_format(text) {
+    ([(0, 4), (4, 2), (6, 2), (9, 2), (11, 2), (13, 2)], text.ends_with('Z'))
+  } else if is_separated_format(text) {
+    ([(0, 4), (5, 2), (8, 2), (11, 2), (14, 2), (17, 2)], false)
+  } else {
+    return Err(invalid());
+  };
+
+  let [year, month, day, hour, minute, second] = fields.map(|(start, len)| number(start, len));
+  let local = from_civil(year?, month?, day?, hour?, minute?, second?);
+
+  Ok(if utc { local } else { from_local(local) })
+}
+
+#[cfg(all(test, feature = "test"))]
+mod tests {
+  use super::*;
+
+  // 2024-01-31 23:59:59 UTC
+  const TIMESTAMP: i64 = 1_706_745_599;
+
+  #[test]
+  fn test_civil() {
+    assert_eq!(to_civil(TIMESTAMP), (2024, 1, 31, 23, 59, 59));
+    assert_eq!(to_civil(0), (1970, 1, 1, 0, 0, 0));
+    assert_eq!(to_civil(-1), (1969, 12, 31, 23, 59, 59));
+    assert_eq!(to_civil(951_782_400), (2000, 2, 29, 0, 0, 0));
+
+    for timestamp in [TIMESTAMP, 0, -1, 951_782_400, 4_102_444_800] {
+      let (year, month, day, hour, minute, second) = to_civil(timestamp);
+      assert_eq!(from_civil(year, month, day, hour, minute, second), timestamp);
+    }
+  }
+
+  #[test]
+  fn test_timestamp() {
+    for timestamp in [TIMESTAMP, 0, -86_400] {
+      assert_eq!(to_timestamp(from_timestamp(timestamp)).unwrap(), timestamp);
+    }
+  }
+
+  #[test]
+  fn test_unix_format() {
+    assert_eq!(parse_text(" 1706745599 ").unwrap(), TIMESTAMP);
+    assert_eq!(format_text("1700000000", TIMESTAMP).unwrap(), "1706745599");
+  }
+
+  #[test]
+  fn test_ptp_utc_format() {
+    assert_eq!(parse_text("20240131T235959Z").unwrap(), TIMESTAMP);
+    assert_eq!(format_text("20230101T000000Z", TIMESTAMP).unwrap(), "20240131T235959Z");
+  }
+
+  #[test]
+  fn test_local_formats() {
+    // Depends on the local time zone, so only the round trip is checked
+    for current in ["20230101T000000", "2023/01/01 00:00:00", "2023-01-01T00:00:00"] {
+      let text = format_text(current, TIMESTAMP).unwrap();
+
+      assert_eq!(text.len(), current.len());
+      assert_eq!(parse_text(&text).unwrap(), TIMESTAMP, "{text}");
+    }
+  }
+
+  #[test]
+  fn test_unknown_format() {
+    assert!(parse_text("").is_err());
+    assert!(parse_text("yesterday").is_err());
+    assert!(parse_text("2024/01/31 23:59:xx").is_err());
+    assert!(format_text("now", TIMESTAMP).is_err());
+  }
+}
@@ -1,18 +1,30 @@
 //! Library context
+#[cfg(feature = "metrics")]
+use crate::metrics::{Metrics, MetricsSnapshot};
 use crate::{
   abilities::AbilitiesList,
-  camera::Camera,
+  camera::{serial_number, Camera, CameraBuilder},
   helper::{as_ref, chars_to_string, to_c_string},
   list::CameraList,
   list::{CameraDescriptor, CameraListIter},
+  logging::{self, LogLevel},
+  platform,
   port::PortInfoList,
-  task::{BackgroundPtr, Task},
+  task::{run_on_worker, BackgroundPtr, Task},
+  thread::{HandlerScope, LockQueue, ThreadManager},
   try_gp_internal, Error, Result,
 };
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
 use std::ffi;
-use std::ops::DerefMut;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::ops::{Deref, DerefMut};
 use std::os::raw::{c_char, c_float, c_uint, c_void};
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
 
 /// Progress handler trait
 pub trait ProgressHandler: 'static + Send {
@@ -28,11 +40,159 @@ pub trait ProgressHandler: 'static + Send {
   fn stop(&mut self, id: u32);
 }
 
+/// Phase of a progress reported in a [`ProgressEvent`]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ProgressPhase {
+  /// The progress has started, `current` is 0
+  Start,
+  /// The progress has updated
+  Update,
+  /// The progress has stopped
+  Stop,
+}
+
+/// Progress update passed to closures set with [`Task::on_progress`] or [`Context::set_progress_fn`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProgressEvent {
+  /// Unique ID of the progress
+  pub id: u32,
+  /// Message given when the progress was started
+  pub message: String,
+  /// Target value of the progress
+  pub target: f32,
+  /// Current value of the progress
+  pub current: f32,
+  /// What happened to the progress
+  pub phase: ProgressPhase,
+}
+
+impl ProgressEvent {
+  /// Progress as a fraction in `0.0..=1.0`
+  pub fn fraction(&self) -> f32 {
+    if self.target > 0.0 {
+      (self.current / self.target).clamp(0.0, 1.0)
+    } else {
+      0.0
+    }
+  }
+}
+
+/// [`ProgressHandler`] doing the ID bookkeeping for a closure
+pub(crate) struct ProgressFn<F> {
+  func: F,
+  progresses: HashMap<u32, (String, f32, f32)>,
+  next_progress_id: u32,
+}
+
+impl<F> ProgressFn<F>
+where
+  F: FnMut(ProgressEvent) + 'static + Send,
+{
+  pub(crate) fn new(func: F) -> Self {
+    Self { func, progresses: HashMap::new(), next_progress_id: 0 }
+  }
+
+  fn emit(&mut self, id: u32, phase: ProgressPhase) {
+    if let Some((message, target, current)) = self.progresses.get(&id) {
+      (self.func)(ProgressEvent {
+        id,
+        message: message.clone(),
+        target: *target,
+        current: *current,
+        phase,
+      });
+    }
+  }
+}
+
+impl<F> ProgressHandler for ProgressFn<F>
+where
+  F: FnMut(ProgressEvent) + 'static + Send,
+{
+  fn start(&mut self, target: f32, message: String) -> u32 {
+    let id = self.next_progress_id;
+
+    self.next_progress_id = self.next_progress_id.wrapping_add(1);
+    self.progresses.insert(id, (message, target, 0.0));
+    self.emit(id, ProgressPhase::Start);
+    id
+  }
+
+  fn update(&mut self, id: u32, progress: f32) {
+    if let Some((_, _, current)) = self.progresses.get_mut(&id) {
+      *current = progress;
+    }
+    self.emit(id, ProgressPhase::Update);
+  }
+
+  fn stop(&mut self, id: u32) {
+    self.emit(id, ProgressPhase::Stop);
+    self.progresses.remove(&id);
+  }
+}
+
+/// Progress handler shared by all tasks of a context
+struct SharedProgressHandler(Arc<Mutex<dyn ProgressHandler>>);
+
+impl ProgressHandler for SharedProgressHandler {
+  fn start(&mut self, target: f32, message: String) -> u32 {
+    self.0.lock().unwrap_or_else(PoisonError::into_inner).start(target, message)
+  }
+
+  fn update(&mut self, id: u32, progress: f32) {
+    self.0.lock().unwrap_or_else(PoisonError::into_inner).update(id, progress)
+  }
+
+  fn stop(&mut self, id: u32) {
+    self.0.lock().unwrap_or_else(PoisonError::into_inner).stop(id)
+  }
+}
+
 /// Cancel handler trait
 pub(crate) trait CancelHandler: 'static + Send {
   fn cancel(&mut self) -> bool;
 }
 
+/// Answer to a question asked by a driver
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum Feedback {
+  /// Continue with the operation
+  Ok,
+  /// Cancel the operation
+  Cancel,
+}
+
+/// Question handler trait
+///
+/// Some drivers ask for confirmation before performing an operation (eg. deleting all files),
+/// without a question handler libgphoto2 always answers with [`Feedback::Ok`].
+///
+/// This is implemented for all closures taking the question and returning [`Feedback`].
+pub trait QuestionHandler: 'static + Send {
+  /// Answer the question asked by the driver
+  fn question(&mut self, question: String) -> Feedback;
+}
+
+impl<F> QuestionHandler for F
+where
+  F: FnMut(String) -> Feedback + 'static + Send,
+{
+  fn question(&mut self, question: String) -> Feedback {
+    self(question)
+  }
+}
+
+impl From<Feedback> for libgphoto2_sys::GPContextFeedback {
+  fn from(feedback: Feedback) -> Self {
+    match feedback {
+      Feedback::Ok => Self::GP_CONTEXT_FEEDBACK_OK,
+      Feedback::Cancel => Self::GP_CONTEXT_FEEDBACK_CANCEL,
+    }
+  }
+}
+
 /// Context used internally by libgphoto2
 ///
 /// ## Example
@@ -53,68 +213,214 @@ pub(crate) trait CancelHandler: 'static + Send {
 ///
 /// ```
 pub struct Context {
-  pub(crate) inner: BackgroundPtr<libgphoto2_sys::GPContext>,
-  progress_handler: Option<Arc<Mutex<dyn ProgressHandler>>>,
-  cancel_handler: Option<Arc<Mutex<dyn CancelHandler>>>,
+  shared: Arc<ContextShared>,
+  /// Set for the context of a [`CameraLock`](crate::camera::CameraLock), its tasks are run there
+  lock_queue: Option<Arc<LockQueue>>,
+}
+
+/// Set once a context exists, see [`set_env_var`]
+static CONTEXT_CREATED: AtomicBool = AtomicBool::new(false);
+
+/// State shared between all clones of a [`Context`]
+///
+/// Owns the reference to the `GPContext`, so cloning a [`Context`] never
+/// touches the (non-atomic) libgphoto2 refcount from the caller thread
+struct ContextShared {
+  inner: BackgroundPtr<libgphoto2_sys::GPContext>,
+  handlers: Mutex<ContextHandlers>,
+  cancellation_latency: Mutex<Option<Duration>>,
+  operation_timeout: Option<Duration>,
+  driver_lists: Mutex<DriverLists>,
+  driver_dir: Option<DriverDir>,
+  #[cfg(feature = "metrics")]
+  metrics: Arc<Metrics>,
+}
+
+/// Driver lists loaded on first use, see [`Context::refresh_driver_lists`]
+#[derive(Default)]
+struct DriverLists {
+  abilities: Option<Arc<AbilitiesList>>,
+  ports: Option<Arc<PortInfoList>>,
 }
 
-impl Drop for Context {
+type StatusFn = dyn FnMut(String) + Send;
+pub(crate) type CancelFn = dyn FnMut() -> bool + Send;
+
+/// Handlers currently registered on the `GPContext`
+///
+/// libgphoto2 only receives raw pointers to the [`Mutex`]es inside these [`Arc`]s,
+/// so they must be kept alive here for as long as the callbacks are registered
+#[derive(Default)]
+struct ContextHandlers {
+  progress: Option<Arc<Mutex<dyn ProgressHandler>>>,
+  cancel: Option<Arc<Mutex<dyn CancelHandler>>>,
+  /// Used by tasks which don't have their own progress handler
+  default_progress: Option<Arc<Mutex<dyn ProgressHandler>>>,
+  /// Checked by all tasks in addition to their own cancellation
+  default_cancel: Option<Arc<Mutex<CancelFn>>>,
+  idle: Option<Arc<Mutex<dyn FnMut() + Send>>>,
+  status: Option<Arc<Mutex<StatusFn>>>,
+  question: Option<Arc<Mutex<dyn QuestionHandler>>>,
+}
+
+impl Drop for ContextShared {
   fn drop(&mut self) {
     let context = self.inner;
+    let handlers = std::mem::take(self.handlers.get_mut().unwrap_or_else(PoisonError::into_inner));
 
     unsafe {
-      Task::new(move || libgphoto2_sys::gp_context_unref(*context));
+      Task::new(move || {
+        // The GPContext may outlive this struct if it was passed on with `Context::into_raw`,
+        // the callbacks must not point to the handlers dropped below
+        if handlers.progress.is_some() {
+          libgphoto2_sys::gp_context_set_progress_funcs(
+            *context,
+            None,
+            None,
+            None,
+            std::ptr::null_mut(),
+          );
+        }
+        if handlers.cancel.is_some() {
+          libgphoto2_sys::gp_context_set_cancel_func(*context, None, std::ptr::null_mut());
+        }
+        if handlers.idle.is_some() {
+          libgphoto2_sys::gp_context_set_idle_func(*context, None, std::ptr::null_mut());
+        }
+        if handlers.status.is_some() {
+          libgphoto2_sys::gp_context_set_status_func(*context, None, std::ptr::null_mut());
+        }
+        if handlers.question.is_some() {
+          libgphoto2_sys::gp_context_set_question_func(*context, None, std::ptr::null_mut());
+        }
+
+        libgphoto2_sys::gp_context_unref(*context);
+        drop(handlers);
+      })
     }
+    .background();
   }
 }
 
 impl Clone for Context {
   fn clone(&self) -> Self {
-    unsafe {
-      libgphoto2_sys::gp_context_ref(*self.inner);
-    }
-
-    Self {
-      inner: self.inner,
-      progress_handler: self.progress_handler.clone(),
-      cancel_handler: self.cancel_handler.clone(),
-    }
+    Self { shared: self.shared.clone(), lock_queue: self.lock_queue.clone() }
   }
 }
 
-as_ref!(Context -> libgphoto2_sys::GPContext, **self.inner);
-
-// TODO: once CoerceUnsized is stable, make this a function.
-macro_rules! alloc_handler {
-  ($handler:expr) => {{
-    let mut handler = Arc::new(Mutex::new($handler));
+as_ref!(Context -> libgphoto2_sys::GPContext, *self.shared.inner.any_thread());
 
-    // Now that handler is on the heap, the pointer should be stable.
-    // Also, we know that there are and won't be other mutable references to it,
-    // so we can safely retrieve a raw mutable pointer from the Mutex
-    // and give it to the C code.
-    let handler_ptr: *mut _ = Arc::get_mut(&mut handler).unwrap().get_mut().unwrap();
+/// Moves a handler to the heap and returns it together with the pointer passed to libgphoto2
+///
+/// The pointer is valid for as long as the returned [`Arc`] is alive, callbacks
+/// must only access the handler through [`lock_handler`]
+fn alloc_handler<H>(handler: H) -> (Arc<Mutex<H>>, *mut c_void) {
+  let handler = Arc::new(Mutex::new(handler));
+  let handler_ptr = Arc::as_ptr(&handler).cast_mut().cast::<c_void>();
 
-    (handler, handler_ptr.cast::<c_void>())
-  }};
+  (handler, handler_ptr)
 }
 
 impl Context {
+  /// Create a [`ContextBuilder`] to customize the context
+  pub fn builder() -> ContextBuilder {
+    ContextBuilder::new()
+  }
+
   /// Create a new context
   pub fn new() -> Result<Self> {
-    #[cfg(feature = "extended_logs")]
-    crate::helper::hook_gp_log();
+    Self::builder().build()
+  }
 
-    let context_ptr = unsafe { libgphoto2_sys::gp_context_new() };
+  /// Wrap a `GPContext` created outside of this crate
+  ///
+  /// The returned context takes over one reference of `context`, which is released once the
+  /// context and all its clones are dropped. Call `gp_context_ref` before if the caller keeps
+  /// using the pointer. Callbacks already registered on the `GPContext` are kept until a handler
+  /// is set with this crate.
+  ///
+  /// # Safety
+  ///
+  /// `context` must be a valid `GPContext` pointer and the caller must own the reference passed
+  /// to this function. It must not be used by libgphoto2 on other threads than the worker of
+  /// this crate.
+  pub unsafe fn from_raw(context: *mut libgphoto2_sys::GPContext) -> Self {
+    Self::from_parts(context, ContextHandlers::default(), None, None)
+  }
 
-    if context_ptr.is_null() {
-      return Err(Error::new(libgphoto2_sys::GP_ERROR_NO_MEMORY, None));
+  /// Get the `GPContext` pointer together with a new reference to it
+  ///
+  /// The caller owns the returned reference and must release it with `gp_context_unref`.
+  /// Handlers set with this crate are removed from the `GPContext` when the last clone of this
+  /// context is dropped, settings like [`ContextBuilder::operation_timeout`] are not kept.
+  pub fn into_raw(self) -> *mut libgphoto2_sys::GPContext {
+    let context = self.inner();
+
+    run_on_worker(move || unsafe { libgphoto2_sys::gp_context_ref(*context) });
+
+    context.any_thread()
+  }
+
+  /// The `GPContext`, only to be used on the worker thread
+  pub(crate) fn inner(&self) -> BackgroundPtr<libgphoto2_sys::GPContext> {
+    self.shared.inner
+  }
+
+  fn from_parts(
+    context: *mut libgphoto2_sys::GPContext,
+    handlers: ContextHandlers,
+    operation_timeout: Option<Duration>,
+    driver_dir: Option<DriverDir>,
+  ) -> Self {
+    let inner = BackgroundPtr(context);
+    CONTEXT_CREATED.store(true, Ordering::Release);
+
+    Self {
+      shared: Arc::new(ContextShared {
+        inner,
+        handlers: Mutex::new(handlers),
+        cancellation_latency: Default::default(),
+        operation_timeout,
+        driver_lists: Default::default(),
+        driver_dir,
+        #[cfg(feature = "metrics")]
+        metrics: Default::default(),
+      }),
+      lock_queue: None,
     }
+  }
 
-    #[cfg(not(feature = "extended_logs"))]
-    crate::helper::hook_gp_context_log_func(context_ptr);
+  /// Create a context and open the virtual camera, serving the files in `vcam_dir`
+  ///
+  /// The `vcamera` feature links a libgphoto2 built from source where USB is replaced by a
+  /// virtual PTP camera, which makes it possible to write integration tests and demos without
+  /// hardware. Real USB cameras can't be used in this build.
+  ///
+  /// The directory is set in the `VCAMERADIR` environment variable, so it is shared by all
+  /// virtual cameras of the process. Like [`ContextBuilder::iolibs_dir`], it can only be set
+  /// before the first context is created, afterwards calling this with another directory fails
+  /// with [`ErrorKind::BadParameters`](crate::error::ErrorKind::BadParameters).
+  ///
+  /// ```no_run
+  /// use gphoto2::{Context, Result};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let camera = Context::virtual_camera("tests/vcamera")?;
+  /// let path = camera.capture_image().wait()?;
+  /// println!("Captured {}", path.name());
+  /// # Ok(())
+  /// # }
+  /// ```
+  #[cfg(feature = "vcamera")]
+  pub fn virtual_camera(vcam_dir: impl AsRef<Path>) -> Result<Camera> {
+    let vcam_dir = vcam_dir.as_ref();
+    if !vcam_dir.is_dir() {
+      return Err(Error::from(format!("{} is not a directory", vcam_dir.display())));
+    }
 
-    Ok(Self { inner: BackgroundPtr(context_ptr), progress_handler: None, cancel_handler: None })
+    set_env_var("VCAMERADIR", vcam_dir)?;
+
+    Self::new()?.autodetect_camera().wait()
   }
 
   /// Lists all available cameras and their ports
@@ -122,17 +428,10 @@ impl Context {
   /// Returns a list of (camera_name, port_path)
   /// which can be used in [`Context::get_camera`].
   pub fn list_cameras(&self) -> Task<Result<CameraListIter>> {
-    let context = self.clone().inner;
-
-    unsafe {
-      Task::new(move || {
-        let camera_list = CameraList::new()?;
-        try_gp_internal!(gp_camera_autodetect(*camera_list.inner, *context)?);
+    let context = self.clone();
 
-        Ok(CameraListIter::new(camera_list))
-      })
-    }
-    .context(self.inner)
+    unsafe { Task::new(move || Ok(CameraListIter::new(context.detect_cameras_inner()?))) }
+      .context(self)
   }
 
   /// Auto chooses a camera
@@ -143,7 +442,7 @@ impl Context {
   /// # fn main() -> Result<()> {
   /// let context = Context::new()?;
   /// if let Ok(camera) = context.autodetect_camera().wait() {
-  ///   println!("Successfully autodetected camera '{}'", camera.abilities().model());
+  ///   println!("Successfully autodetected camera '{}'", camera.abilities()?.model());
   /// } else {
   ///   println!("Could not autodetect camera");
   /// }
@@ -151,17 +450,72 @@ impl Context {
   /// # }
   /// ```
   pub fn autodetect_camera(&self) -> Task<Result<Camera>> {
+    self.camera_builder().build()
+  }
+
+  /// Initialize the first detected camera for which `predicate` returns `true`
+  ///
+  /// ```no_run
+  /// use gphoto2::{Context, Result};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let camera = Context::new()?
+  ///   .autodetect_camera_matching(|camera| camera.model.starts_with("Nikon"))
+  ///   .wait()?;
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn autodetect_camera_matching<F>(&self, mut predicate: F) -> Task<Result<Camera>>
+  where
+    F: FnMut(&CameraDescriptor) -> bool + Send + 'static,
+  {
     let context = self.clone();
 
     unsafe {
       Task::new(move || {
-        try_gp_internal!(gp_camera_new(&out camera_ptr)?);
-        try_gp_internal!(gp_camera_init(camera_ptr, *context.inner)?);
+        let descriptor = CameraListIter::new(context.detect_cameras_inner()?)
+          .find(|descriptor| predicate(descriptor))
+          .ok_or_else(|| {
+            Error::new(
+              libgphoto2_sys::GP_ERROR_MODEL_NOT_FOUND,
+              Some("No detected camera matches".to_owned()),
+            )
+          })?;
+
+        context.camera_builder().descriptor(&descriptor).build_inner()
+      })
+    }
+    .context(self)
+  }
 
-        Ok(Camera::new(BackgroundPtr(camera_ptr), context))
+  /// Initialize the detected camera with the serial number `serial`
+  ///
+  /// Distinguishes several cameras of the same model. Each detected camera is initialized to read
+  /// its serial number, the cameras which don't match are released again. Cameras which can't be
+  /// initialized, e.g. because another program uses them, are skipped.
+  pub fn autodetect_by_serial(&self, serial: &str) -> Task<Result<Camera>> {
+    let context = self.clone();
+    let serial = serial.to_owned();
+
+    unsafe {
+      Task::new(move || {
+        for descriptor in CameraListIter::new(context.detect_cameras_inner()?) {
+          let Ok(camera) = context.camera_builder().descriptor(&descriptor).build_inner() else {
+            continue;
+          };
+
+          if serial_number(*camera.camera, *context.inner()).as_deref() == Some(&serial) {
+            return Ok(camera);
+          }
+        }
+
+        Err(Error::new(
+          libgphoto2_sys::GP_ERROR_MODEL_NOT_FOUND,
+          Some(format!("No detected camera has the serial number {serial}")),
+        ))
       })
-      .context(self.inner)
     }
+    .context(self)
   }
 
   /// Initialize a camera knowing its model name and port path
@@ -178,39 +532,164 @@ impl Context {
   /// # Ok(())
   /// # }
   pub fn get_camera(&self, camera_descriptor: &CameraDescriptor) -> Task<Result<Camera>> {
+    self.camera_builder().descriptor(camera_descriptor).init(false).build()
+  }
+
+  /// Create a builder for opening a camera with more options than [`Context::get_camera`]
+  pub fn camera_builder(&self) -> CameraBuilder {
+    CameraBuilder::new(self.clone())
+  }
+
+  /// Load the models supported by the camera drivers
+  ///
+  /// Unlike [`Context::abilities_list`], this always loads a new list.
+  pub fn load_abilities_list(&self) -> Task<Result<AbilitiesList>> {
     let context = self.clone();
-    let camera_descriptor = camera_descriptor.clone();
 
-    unsafe {
-      Task::new(move || {
-        let abilities_list = AbilitiesList::new_inner(&context)?;
-        let port_info_list = PortInfoList::new_inner()?;
+    unsafe { Task::new(move || AbilitiesList::new_inner(&context)) }.context(self)
+  }
 
-        try_gp_internal!(gp_camera_new(&out camera)?);
+  /// Load the ports found by the port drivers
+  ///
+  /// Unlike [`Context::port_info_list`], this always loads a new list.
+  pub fn load_port_info_list(&self) -> Task<Result<PortInfoList>> {
+    unsafe { Task::new(PortInfoList::new_inner) }.context(self)
+  }
 
-        try_gp_internal!(let model_index = gp_abilities_list_lookup_model(
-          *abilities_list.inner,
-          to_c_string!(camera_descriptor.model.as_str())
-        )?);
+  /// Models supported by the camera drivers, loaded on first use
+  ///
+  /// The list is shared by all clones of this context and used to open and detect cameras.
+  pub fn abilities_list(&self) -> Task<Result<Arc<AbilitiesList>>> {
+    let context = self.clone();
 
-        try_gp_internal!(gp_abilities_list_get_abilities(
-          *abilities_list.inner,
-          model_index,
-          &out model_abilities
-        )?);
-        try_gp_internal!(gp_camera_set_abilities(camera, model_abilities)?);
+    unsafe { Task::new(move || context.abilities_list_inner()) }.context(self)
+  }
 
-        try_gp_internal!(let p = gp_port_info_list_lookup_path(
-          port_info_list.inner,
-          to_c_string!(camera_descriptor.port.as_str())
-        )?);
-        let port_info = port_info_list.get_port_info(p)?;
-        try_gp_internal!(gp_camera_set_port_info(camera, port_info.inner)?);
+  /// Ports found by the port drivers, loaded on first use
+  ///
+  /// The list is shared by all clones of this context and used to open cameras.
+  pub fn port_info_list(&self) -> Task<Result<Arc<PortInfoList>>> {
+    let context = self.clone();
 
-        Ok(Camera::new(BackgroundPtr(camera), context))
-      })
+    unsafe { Task::new(move || context.port_info_list_inner()) }.context(self)
+  }
+
+  /// Load the driver lists again on their next use
+  ///
+  /// Required after installing camera or port drivers while the program is running.
+  pub fn refresh_driver_lists(&self) {
+    *self.shared.driver_lists.lock().unwrap_or_else(PoisonError::into_inner) = Default::default();
+  }
+
+  /// Whether clones of this context (for example of cameras) are alive
+  pub(crate) fn is_shared(&self) -> bool {
+    Arc::strong_count(&self.shared) > 1
+  }
+
+  pub(crate) fn lock_queue(&self) -> Option<Arc<LockQueue>> {
+    self.lock_queue.clone()
+  }
+
+  /// Clone of this context whose tasks are run in `lock_queue`
+  pub(crate) fn with_lock_queue(&self, lock_queue: Arc<LockQueue>) -> Self {
+    Self { lock_queue: Some(lock_queue), ..self.clone() }
+  }
+
+  /// Directory to load the camera drivers from instead of the default one
+  pub(crate) fn driver_dir(&self) -> Option<&Path> {
+    self.shared.driver_dir.as_ref().map(|dir| dir.path.as_path())
+  }
+
+  /// Must be called from a [`Task`]
+  pub(crate) fn abilities_list_inner(&self) -> Result<Arc<AbilitiesList>> {
+    let mut lists = self.shared.driver_lists.lock().unwrap_or_else(PoisonError::into_inner);
+
+    match &lists.abilities {
+      Some(abilities_list) => Ok(abilities_list.clone()),
+      None => Ok(lists.abilities.insert(Arc::new(AbilitiesList::new_inner(self)?)).clone()),
     }
-    .context(self.inner)
+  }
+
+  /// Must be called from a [`Task`]
+  pub(crate) fn port_info_list_inner(&self) -> Result<Arc<PortInfoList>> {
+    let mut lists = self.shared.driver_lists.lock().unwrap_or_else(PoisonError::into_inner);
+
+    match &lists.ports {
+      Some(port_info_list) => Ok(port_info_list.clone()),
+      None => Ok(lists.ports.insert(Arc::new(PortInfoList::new_inner()?)).clone()),
+    }
+  }
+
+  /// Detect connected cameras like `gp_camera_autodetect`, but with the cached abilities list
+  ///
+  /// Must be called from a [`Task`].
+  pub(crate) fn detect_cameras_inner(&self) -> Result<CameraList> {
+    let abilities_list = self.abilities_list_inner()?;
+    // Loading the ports enumerates the USB devices, so this list can't be cached
+    let port_info_list = PortInfoList::new_inner()?;
+
+    let detected = CameraList::new()?;
+    try_gp_internal!(gp_abilities_list_detect(
+      *abilities_list.inner,
+      *port_info_list.inner,
+      *detected.inner,
+      *self.inner()
+    )?);
+
+    // Skip the generic USB port, which is reported in addition to the actual device, and
+    // cameras found by drivers loaded twice
+    let camera_list = CameraList::new()?;
+    let mut seen = HashSet::new();
+    for CameraDescriptor { model, port } in CameraListIter::new(detected) {
+      let port = platform::normalize_port_path(&port).into_owned();
+
+      if port != "usb:" && seen.insert((model.clone(), port.clone())) {
+        try_gp_internal!(gp_list_append(
+          *camera_list.inner,
+          to_c_string!(model.as_str()),
+          to_c_string!(port.as_str())
+        )?);
+      }
+    }
+
+    Ok(camera_list)
+  }
+
+  /// Set a closure receiving the progress of all tasks of this context
+  ///
+  /// Tasks with their own progress handler (see [`Task::on_progress`] and
+  /// [`Task::set_progress_handler`]) don't report to this closure.
+  ///
+  /// ```no_run
+  /// use gphoto2::{context::ProgressPhase, Context, Result};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let context = Context::new()?;
+  ///
+  /// context.set_progress_fn(|event| match event.phase {
+  ///   ProgressPhase::Stop => println!("{}: done", event.message),
+  ///   _ => println!("{}: {:.0}%", event.message, event.fraction() * 100.0),
+  /// });
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn set_progress_fn<F>(&self, func: F)
+  where
+    F: FnMut(ProgressEvent) + 'static + Send,
+  {
+    self.handlers().default_progress = Some(Arc::new(Mutex::new(ProgressFn::new(func))));
+  }
+
+  /// Remove the closure set with [`Context::set_progress_fn`]
+  pub fn unset_progress_fn(&self) {
+    self.handlers().default_progress = None;
+  }
+
+  /// Progress handler to use for tasks without their own handler
+  pub(crate) fn default_progress_handler(&self) -> Option<Box<dyn ProgressHandler>> {
+    let default_progress = self.handlers().default_progress.clone()?;
+
+    Some(Box::new(SharedProgressHandler(default_progress)))
   }
 
   /// Set context progress functions
@@ -221,14 +700,16 @@ impl Context {
   /// # Example
   ///
   /// An example can be found in the examples directory
-  pub(crate) fn set_progress_handlers<H: ProgressHandler>(&mut self, handler: H) {
+  pub(crate) fn set_progress_handlers<H: ProgressHandler>(&self, handler: H) {
     unsafe extern "C" fn start_func<H: ProgressHandler>(
       _ctx: *mut libgphoto2_sys::GPContext,
       target: c_float,
       message: *const c_char,
       data: *mut c_void,
     ) -> c_uint {
-      as_handler::<H>(data).start(target, chars_to_string(message))
+      platform::start_progress(data, target, chars_to_string(message), |target, message| {
+        lock_handler::<H>(data).start(target, message)
+      })
     }
 
     unsafe extern "C" fn update_func<H: ProgressHandler>(
@@ -237,7 +718,7 @@ impl Context {
       current: c_float,
       data: *mut c_void,
     ) {
-      as_handler::<H>(data).update(id, current)
+      lock_handler::<H>(data).update(id, platform::progress_value(data, id, current))
     }
 
     unsafe extern "C" fn stop_func<H: ProgressHandler>(
@@ -245,14 +726,15 @@ impl Context {
       id: c_uint,
       data: *mut c_void,
     ) {
-      as_handler::<H>(data).stop(id)
+      platform::stop_progress(data, id);
+      lock_handler::<H>(data).stop(id)
     }
 
-    let (progress_handler, progress_handler_ptr) = alloc_handler!(handler);
+    let (progress_handler, progress_handler_ptr) = alloc_handler(handler);
 
     unsafe {
       libgphoto2_sys::gp_context_set_progress_funcs(
-        *self.inner,
+        *self.inner(),
         Some(start_func::<H>),
         Some(update_func::<H>),
         Some(stop_func::<H>),
@@ -260,10 +742,10 @@ impl Context {
       );
     }
 
-    self.progress_handler = Some(progress_handler);
+    self.handlers().progress = Some(progress_handler);
   }
 
-  pub(crate) fn set_cancel_handler<H>(&mut self, handler: H)
+  pub(crate) fn set_cancel_handler<H>(&self, handler: H)
   where
     H: CancelHandler,
   {
@@ -273,30 +755,30 @@ impl Context {
       _ctx: *mut libgphoto2_sys::GPContext,
       data: *mut c_void,
     ) -> GPContextFeedback {
-      if as_handler::<H>(data).cancel() {
+      if lock_handler::<H>(data).cancel() {
         GPContextFeedback::GP_CONTEXT_FEEDBACK_CANCEL
       } else {
         GPContextFeedback::GP_CONTEXT_FEEDBACK_OK
       }
     }
 
-    let (cancel_handler, cancel_handler_ptr) = alloc_handler!(handler);
+    let (cancel_handler, cancel_handler_ptr) = alloc_handler(handler);
 
     unsafe {
       libgphoto2_sys::gp_context_set_cancel_func(
-        *self.inner,
+        *self.inner(),
         Some(handle_cancel::<H>),
         cancel_handler_ptr,
       );
     }
 
-    self.cancel_handler = Some(cancel_handler);
+    self.handlers().cancel = Some(cancel_handler);
   }
 
-  pub(crate) fn unset_progress_handlers(&mut self) {
+  pub(crate) fn unset_progress_handlers(&self) {
     unsafe {
       libgphoto2_sys::gp_context_set_progress_funcs(
-        *self.inner,
+        *self.inner(),
         None,
         None,
         None,
@@ -304,26 +786,551 @@ impl Context {
       );
     }
 
-    self.progress_handler = None;
+    self.handlers().progress = None;
   }
 
-  pub(crate) fn unset_cancel_handlers(&mut self) {
+  pub(crate) fn unset_cancel_handlers(&self) {
     unsafe {
-      libgphoto2_sys::gp_context_set_cancel_func(*self.inner, None, std::ptr::null_mut());
+      libgphoto2_sys::gp_context_set_cancel_func(*self.inner(), None, std::ptr::null_mut());
+    }
+
+    self.handlers().cancel = None;
+  }
+
+  /// Set a closure which is called by drivers while they are waiting during long operations
+  ///
+  /// This can be used to keep a user interface responsive or to do other periodic work.
+  /// The closure is called on the libgphoto2 worker thread and must not wait on other tasks.
+  pub fn set_idle_handler<F>(&self, handler: F)
+  where
+    F: FnMut() + 'static + Send,
+  {
+    unsafe extern "C" fn idle_func<F: FnMut() + 'static + Send>(
+      _ctx: *mut libgphoto2_sys::GPContext,
+      data: *mut c_void,
+    ) {
+      (*lock_handler::<F>(data))()
+    }
+
+    let (idle_handler, idle_handler_ptr) = alloc_handler(handler);
+    let idle_handler_ptr = BackgroundPtr(idle_handler_ptr);
+
+    self.on_worker(move |context| {
+      unsafe {
+        libgphoto2_sys::gp_context_set_idle_func(
+          *context.inner(),
+          Some(idle_func::<F>),
+          *idle_handler_ptr,
+        );
+      }
+
+      context.handlers().idle = Some(idle_handler);
+    });
+  }
+
+  /// Remove the closure set with [`Context::set_idle_handler`]
+  pub fn unset_idle_handler(&self) {
+    self.on_worker(|context| {
+      unsafe {
+        libgphoto2_sys::gp_context_set_idle_func(*context.inner(), None, std::ptr::null_mut());
+      }
+
+      context.handlers().idle = None;
+    });
+  }
+
+  /// Set the handler answering questions asked by drivers
+  ///
+  /// ```no_run
+  /// use gphoto2::{context::Feedback, Context, Result};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let context = Context::new()?;
+  ///
+  /// context.set_question_handler(|question: String| {
+  ///   println!("Driver asked: {question}, answering no");
+  ///   Feedback::Cancel
+  /// });
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn set_question_handler<H: QuestionHandler>(&self, handler: H) {
+    use libgphoto2_sys::GPContextFeedback;
+
+    unsafe extern "C" fn question_func<H: QuestionHandler>(
+      _ctx: *mut libgphoto2_sys::GPContext,
+      question: *const c_char,
+      data: *mut c_void,
+    ) -> GPContextFeedback {
+      lock_handler::<H>(data).question(chars_to_string(question)).into()
+    }
+
+    let (question_handler, question_handler_ptr) = alloc_handler(handler);
+    let question_handler_ptr = BackgroundPtr(question_handler_ptr);
+
+    self.on_worker(move |context| {
+      unsafe {
+        libgphoto2_sys::gp_context_set_question_func(
+          *context.inner(),
+          Some(question_func::<H>),
+          *question_handler_ptr,
+        );
+      }
+
+      context.handlers().question = Some(question_handler);
+    });
+  }
+
+  /// Remove the handler set with [`Context::set_question_handler`]
+  pub fn unset_question_handler(&self) {
+    self.on_worker(|context| {
+      unsafe {
+        libgphoto2_sys::gp_context_set_question_func(*context.inner(), None, std::ptr::null_mut());
+      }
+
+      context.handlers().question = None;
+    });
+  }
+
+  /// Set a closure receiving status messages from drivers
+  ///
+  /// Drivers report what they are currently doing (like `"Capturing image"`) through these,
+  /// which can be shown to users. The messages are still logged at the info level.
+  ///
+  /// ```no_run
+  /// use gphoto2::{Context, Result};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let context = Context::new()?;
+  ///
+  /// context.set_status_handler(|status| println!("Camera status: {status}"));
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn set_status_handler<F>(&self, handler: F)
+  where
+    F: FnMut(String) + 'static + Send,
+  {
+    unsafe extern "C" fn status_func<F: FnMut(String) + 'static + Send>(
+      _ctx: *mut libgphoto2_sys::GPContext,
+      text: *const c_char,
+      data: *mut c_void,
+    ) {
+      let status = chars_to_string(text);
+      logging::dispatch(LogLevel::Verbose, "context", &status);
+
+      (*lock_handler::<F>(data))(status)
+    }
+
+    let (status_handler, status_handler_ptr) = alloc_handler(handler);
+    let status_handler_ptr = BackgroundPtr(status_handler_ptr);
+
+    self.on_worker(move |context| {
+      unsafe {
+        libgphoto2_sys::gp_context_set_status_func(
+          *context.inner(),
+          Some(status_func::<F>),
+          *status_handler_ptr,
+        );
+      }
+
+      context.handlers().status = Some(status_handler);
+    });
+  }
+
+  /// Remove the closure set with [`Context::set_status_handler`]
+  ///
+  /// Status messages are only logged afterwards.
+  pub fn unset_status_handler(&self) {
+    unsafe extern "C" fn log_status_func(
+      _ctx: *mut libgphoto2_sys::GPContext,
+      text: *const c_char,
+      _data: *mut c_void,
+    ) {
+      logging::dispatch(LogLevel::Verbose, "context", &chars_to_string(text));
     }
 
-    self.cancel_handler = None;
+    self.on_worker(|context| {
+      unsafe {
+        libgphoto2_sys::gp_context_set_status_func(
+          *context.inner(),
+          Some(log_status_func),
+          std::ptr::null_mut(),
+        );
+      }
+
+      context.handlers().status = None;
+    });
+  }
+
+  /// Time between [`Task::cancel`] and the end of the most recently cancelled task of this context
+  ///
+  /// Useful to verify how quickly a driver reacts to cancellation requests.
+  pub fn cancellation_latency(&self) -> Option<Duration> {
+    *self.shared.cancellation_latency.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  pub(crate) fn record_cancellation_latency(&self, latency: Duration) {
+    *self.shared.cancellation_latency.lock().unwrap_or_else(PoisonError::into_inner) =
+      Some(latency);
+  }
+
+  /// Counts and durations of the camera operations of this context and its clones
+  ///
+  /// See the [`metrics`](crate::metrics) module for the recorded operations.
+  #[cfg(feature = "metrics")]
+  pub fn metrics(&self) -> MetricsSnapshot {
+    self.shared.metrics.snapshot()
+  }
+
+  /// Clear the statistics returned by [`Context::metrics`]
+  #[cfg(feature = "metrics")]
+  pub fn reset_metrics(&self) {
+    self.shared.metrics.reset();
+  }
+
+  #[cfg(feature = "metrics")]
+  pub(crate) fn metrics_recorder(&self) -> Arc<Metrics> {
+    self.shared.metrics.clone()
+  }
+
+  /// Timeout after which tasks of this context are cancelled
+  pub(crate) fn operation_timeout(&self) -> Option<Duration> {
+    self.shared.operation_timeout
+  }
+
+  /// Cancel closure set with [`ContextBuilder::cancel_fn`]
+  pub(crate) fn default_cancel_fn(&self) -> Option<Arc<Mutex<CancelFn>>> {
+    self.handlers().default_cancel.clone()
+  }
+
+  /// Run a function with this context on the worker thread, without waiting for it.
+  ///
+  /// Used for (un)registering handlers, so they are never replaced while a task is running.
+  fn on_worker(&self, fun: impl FnOnce(&Context) + 'static + Send) {
+    let context = self.clone();
+
+    unsafe { Task::new(move || fun(&context)) }.background();
+  }
+
+  fn handlers(&self) -> MutexGuard<'_, ContextHandlers> {
+    self.shared.handlers.lock().unwrap_or_else(PoisonError::into_inner)
   }
 }
 
-impl Context {
-  pub(crate) fn from_ptr(ptr: BackgroundPtr<libgphoto2_sys::GPContext>) -> Self {
-    Self { cancel_handler: None, inner: ptr, progress_handler: None }
+/// Builder for a [`Context`] with custom settings
+///
+/// ```no_run
+/// use gphoto2::{logging::LogLevel, Context, Result};
+/// use std::time::Duration;
+///
+/// # fn main() -> Result<()> {
+/// let context = Context::builder()
+///   .camlibs_dir("/Applications/App.app/Contents/Frameworks/libgphoto2/camlibs")
+///   .iolibs_dir("/Applications/App.app/Contents/Frameworks/libgphoto2_port/iolibs")
+///   .log_level(LogLevel::Verbose)
+///   .operation_timeout(Duration::from_secs(30))
+///   .progress_fn(|event| println!("{}: {:.0}%", event.message, event.fraction() * 100.0))
+///   .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct ContextBuilder {
+  camlibs_dir: Option<PathBuf>,
+  iolibs_dir: Option<PathBuf>,
+  log_level: Option<LogLevel>,
+  operation_timeout: Option<Duration>,
+  progress: Option<Arc<Mutex<dyn ProgressHandler>>>,
+  cancel: Option<Arc<Mutex<CancelFn>>>,
+  worker_stack_size: Option<usize>,
+  only_drivers: Option<Vec<String>>,
+}
+
+impl ContextBuilder {
+  /// Create a builder with the default settings
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Directory the camera drivers (camlibs) of this context are loaded from
+  ///
+  /// Other contexts keep loading them from the `CAMLIBS` environment variable or the
+  /// directory libgphoto2 was built with.
+  pub fn camlibs_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+    self.camlibs_dir = Some(dir.into());
+    self
+  }
+
+  /// Directory libgphoto2 loads the port drivers (iolibs) from
+  ///
+  /// libgphoto2 reads this directory from the `IOLIBS` environment variable, which
+  /// is set by [`ContextBuilder::build`] and affects all contexts of the process. Since
+  /// libgphoto2 may read the environment at any time once a context exists, it can only be set
+  /// for the first context of the process: [`ContextBuilder::build`] fails with
+  /// [`ErrorKind::BadParameters`](crate::error::ErrorKind::BadParameters) if a context was
+  /// already created and `IOLIBS` is set to another directory.
+  pub fn iolibs_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+    self.iolibs_dir = Some(dir.into());
+    self
+  }
+
+  /// Limit libgphoto2 messages to `level`
+  ///
+  /// This is a shortcut for [`logging::set_domain_level`] with an empty domain,
+  /// so it affects all contexts of the process.
+  pub fn log_level(mut self, level: LogLevel) -> Self {
+    self.log_level = Some(level);
+    self
+  }
+
+  /// Cancel tasks which are running for longer than `timeout`
+  ///
+  /// Like [`Task::cancel`], this only interrupts drivers at points where they check for
+  /// cancellation.
+  pub fn operation_timeout(mut self, timeout: Duration) -> Self {
+    self.operation_timeout = Some(timeout);
+    self
+  }
+
+  /// Closure receiving the progress of all tasks, see [`Context::set_progress_fn`]
+  pub fn progress_fn<F>(mut self, func: F) -> Self
+  where
+    F: FnMut(ProgressEvent) + 'static + Send,
+  {
+    self.progress = Some(Arc::new(Mutex::new(ProgressFn::new(func))));
+    self
+  }
+
+  /// Closure which cancels all running tasks when it returns `true`
+  ///
+  /// It is called from the libgphoto2 worker thread whenever a driver checks for cancellation.
+  pub fn cancel_fn<F>(mut self, func: F) -> Self
+  where
+    F: FnMut() -> bool + 'static + Send,
+  {
+    self.cancel = Some(Arc::new(Mutex::new(func)));
+    self
+  }
+
+  /// Stack size of the thread running all libgphoto2 calls
+  ///
+  /// The thread is shared by all contexts and started on first use,
+  /// so this only has an effect on the first context of the process.
+  pub fn worker_stack_size(mut self, size: usize) -> Self {
+    self.worker_stack_size = Some(size);
+    self
+  }
+
+  /// Only load the given camera drivers, like `ptp2` for most current cameras
+  ///
+  /// Loading every installed driver takes most of the time needed to open the first camera.
+  /// The drivers are looked up in [`camlibs_dir`](Self::camlibs_dir), `CAMLIBS` or the
+  /// directory libgphoto2 was built with, in this order.
+  pub fn only_drivers<I, S>(mut self, drivers: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    self.only_drivers = Some(drivers.into_iter().map(Into::into).collect());
+    self
+  }
+
+  /// Create the context
+  pub fn build(self) -> Result<Context> {
+    let driver_dir = match &self.only_drivers {
+      Some(drivers) => {
+        let camlibs_dir = self
+          .camlibs_dir
+          .clone()
+          .or_else(|| std::env::var_os("CAMLIBS").map(PathBuf::from))
+          .or_else(|| libgphoto2_sys::DRIVER_DIR.map(PathBuf::from))
+          .ok_or("The camera driver directory is unknown, set it with camlibs_dir")?;
+
+        Some(DriverDir::new(&camlibs_dir, drivers)?)
+      }
+      None => self.camlibs_dir.map(|path| DriverDir { path, temporary: false }),
+    };
+
+    if let Some(dir) = &self.iolibs_dir {
+      set_env_var("IOLIBS", dir)?;
+    }
+
+    if let Some(stack_size) = self.worker_stack_size {
+      if !ThreadManager::start(Some(stack_size)) {
+        log::warn!("The libgphoto2 worker is already running, ignoring its stack size");
+      }
+    }
+
+    if let Some(level) = self.log_level {
+      logging::set_domain_level("", Some(level));
+    }
+
+    #[cfg(feature = "extended_logs")]
+    crate::helper::hook_gp_log();
+
+    let context_ptr = unsafe { libgphoto2_sys::gp_context_new() };
+
+    if context_ptr.is_null() {
+      return Err(Error::new(libgphoto2_sys::GP_ERROR_NO_MEMORY, None));
+    }
+
+    #[cfg(not(feature = "extended_logs"))]
+    crate::helper::hook_gp_context_log_func(context_ptr);
+
+    let handlers = ContextHandlers {
+      default_progress: self.progress,
+      default_cancel: self.cancel,
+      ..Default::default()
+    };
+
+    Ok(Context::from_parts(context_ptr, handlers, self.operation_timeout, driver_dir))
+  }
+}
+
+/// Directory the camera drivers of a context are loaded from, see [`ContextBuilder::camlibs_dir`]
+struct DriverDir {
+  path: PathBuf,
+  /// Links to the drivers selected with [`ContextBuilder::only_drivers`]
+  ///
+  /// Cameras load their driver from this directory as well, so it is removed with the context.
+  temporary: bool,
+}
+
+impl DriverDir {
+  fn new(camlibs_dir: &Path, drivers: &[String]) -> Result<Self> {
+    // Seeded from the randomness of the OS, so other users can't create the directory before
+    let random = RandomState::new().build_hasher().finish();
+    let dir = std::env::temp_dir().join(format!("gphoto2-rs-{random:016x}"));
+
+    // Fails if the directory exists, so the drivers are never loaded from a foreign directory
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(&dir)?;
+    // Removes the directory if a driver is missing
+    let driver_dir = Self { path: dir, temporary: true };
+
+    for driver in drivers {
+      let mut found = false;
+
+      // Drivers can consist of several files, like ptp2.so and ptp2.la
+      for entry in fs::read_dir(camlibs_dir)? {
+        let path = entry?.path();
+
+        if path.file_stem().is_some_and(|stem| stem == driver.as_str()) {
+          link_driver(&path, &driver_dir.path.join(entry_name(&path)?))?;
+          found = true;
+        }
+      }
+
+      if !found {
+        return Err(Error::from(format!("Driver {driver} not found in {}", camlibs_dir.display())));
+      }
+    }
+
+    Ok(driver_dir)
+  }
+}
+
+impl Drop for DriverDir {
+  fn drop(&mut self) {
+    if !self.temporary {
+      return;
+    }
+
+    if let Err(error) = fs::remove_dir_all(&self.path) {
+      log::warn!("Could not remove driver directory {}: {error}", self.path.display());
+    }
   }
 }
 
-unsafe fn as_handler<H>(data: *mut c_void) -> &'static mut H {
-  &mut *data.cast()
+fn entry_name(path: &Path) -> Result<&ffi::OsStr> {
+  path.file_name().ok_or_else(|| Error::from(format!("{} has no file name", path.display())))
+}
+
+#[cfg(unix)]
+fn link_driver(driver: &Path, link: &Path) -> Result<()> {
+  Ok(std::os::unix::fs::symlink(driver, link)?)
+}
+
+#[cfg(not(unix))]
+fn link_driver(driver: &Path, link: &Path) -> Result<()> {
+  fs::copy(driver, link)?;
+  Ok(())
+}
+
+/// Set an environment variable libgphoto2 reads a directory from
+///
+/// libgphoto2 may read the environment on the worker thread at any time once a context exists,
+/// so other values can only be set before the first context is created.
+fn set_env_var(name: &str, path: &Path) -> Result<()> {
+  if std::env::var_os(name).is_some_and(|value| value == path.as_os_str()) {
+    return Ok(());
+  }
+
+  if CONTEXT_CREATED.load(Ordering::Acquire) {
+    return Err(Error::new(
+      libgphoto2_sys::GP_ERROR_BAD_PARAMETERS,
+      Some(format!("{name} can only be set before the first context is created")),
+    ));
+  }
+
+  put_env_var(name, path)
+}
+
+#[cfg(unix)]
+fn put_env_var(name: &str, path: &Path) -> Result<()> {
+  std::env::set_var(name, path);
+  Ok(())
+}
+
+/// [`std::env::set_var`] doesn't update the C environment on Windows, which libgphoto2 reads
+#[cfg(not(unix))]
+fn put_env_var(name: &str, path: &Path) -> Result<()> {
+  let path = path.to_str().ok_or_else(|| Error::from(format!("{name} path is not UTF-8")))?;
+  // putenv keeps the pointer in the environment, so the string must never be freed
+  let env_var: &'static ffi::CStr =
+    Box::leak(ffi::CString::new(format!("{name}={path}"))?.into_boxed_c_str());
+
+  if unsafe { libc::putenv(env_var.as_ptr().cast_mut()) } != 0 {
+    return Err(std::io::Error::last_os_error().into());
+  }
+
+  Ok(())
+}
+
+/// Locks the handler behind a pointer created by [`alloc_handler`]
+///
+/// A poisoned lock (a previous callback panicked) is recovered rather than
+/// propagated, since panicking across the FFI boundary would abort
+unsafe fn lock_handler<'a, H>(data: *mut c_void) -> HandlerGuard<'a, H> {
+  let handler = &*data.cast::<Mutex<H>>();
+
+  HandlerGuard {
+    _scope: HandlerScope::enter(),
+    guard: handler.lock().unwrap_or_else(PoisonError::into_inner),
+  }
+}
+
+/// Locked handler, tasks waited for while it is alive are reported as reentrant calls
+struct HandlerGuard<'a, H> {
+  guard: MutexGuard<'a, H>,
+  _scope: HandlerScope,
+}
+
+impl<H> Deref for HandlerGuard<'_, H> {
+  type Target = H;
+
+  fn deref(&self) -> &H {
+    &self.guard
+  }
+}
+
+impl<H> DerefMut for HandlerGuard<'_, H> {
+  fn deref_mut(&mut self) -> &mut H {
+    &mut self.guard
+  }
 }
 
 impl ProgressHandler for Box<dyn ProgressHandler> {
@@ -376,10 +1383,6 @@ mod tests {
       fn start(&mut self, target: f32, message: String) -> u32 {
         let id = self.next_progress_id;
 
-        // For some reason, gphoto2 discovers each dynamic library twice on Windows.
-        #[cfg(windows)]
-        let target = target / 2.0;
-
         self.next_progress_id += 1;
         writeln!(
           self.log_lines,
@@ -0,0 +1,198 @@
+//! Finding out why a camera can't be opened
+//!
+//! Opening a USB camera fails with [`ErrorKind::IoUsbClaim`](crate::error::ErrorKind::IoUsbClaim)
+//! when another program already claimed it or the user may not open the device. On Linux
+//! [`check_device_access`] looks for the usual causes: desktops auto-mounting the camera with
+//! gvfs, other programs using it and missing udev permissions. Other platforms and non-USB ports
+//! are not checked.
+//!
+//! ```no_run
+//! use gphoto2::{diagnostics::check_device_access, error::ErrorKind, Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let context = Context::new()?;
+//! let descriptor = context.list_cameras().wait()?.next().ok_or("No camera found")?;
+//!
+//! match context.get_camera(&descriptor).wait() {
+//!   Err(error) if error.kind() == ErrorKind::IoUsbClaim => {
+//!     check_device_access(&descriptor)?;
+//!     Err(error)
+//!   }
+//!   result => result.map(|_| ()),
+//! }
+//! # }
+//! ```
+
+use crate::{list::CameraDescriptor, port::UsbBusDevice, Error};
+use std::{error, fmt, path::PathBuf};
+
+/// Process which has a device open
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DeviceUser {
+  /// Process ID
+  pub pid: u32,
+  /// Name of the executable
+  pub name: String,
+}
+
+/// Reason a camera can't be opened
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum AccessProblem {
+  /// The device node does not exist, the camera was disconnected or got a new address
+  DeviceMissing {
+    /// Path of the device node
+    path: PathBuf,
+  },
+  /// The current user can't open the device node
+  PermissionDenied {
+    /// Path of the device node
+    path: PathBuf,
+  },
+  /// gvfs mounted the camera and claimed it
+  Gvfs(DeviceUser),
+  /// Another program has the camera open
+  InUse(DeviceUser),
+}
+
+impl AccessProblem {
+  /// How the problem can be solved
+  pub fn hint(&self) -> String {
+    match self {
+      Self::DeviceMissing { .. } => "Reconnect the camera and detect it again".to_owned(),
+      Self::PermissionDenied { path } => format!(
+        "Install the udev rules of libgphoto2 (generated by `print-camera-list udev-rules`) or \
+         add the user to the group owning {}",
+        path.display()
+      ),
+      Self::Gvfs(_) => "Unmount the camera in the file manager or with `gio mount -s gphoto2`, \
+                        stopping gvfs-gphoto2-volume-monitor prevents it from being mounted again"
+        .to_owned(),
+      Self::InUse(user) => {
+        format!("Close {} (pid {}), only one program can use the camera", user.name, user.pid)
+      }
+    }
+  }
+}
+
+impl fmt::Display for AccessProblem {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::DeviceMissing { path } => write!(f, "{} does not exist", path.display()),
+      Self::PermissionDenied { path } => write!(f, "No permission to open {}", path.display()),
+      Self::Gvfs(user) => write!(f, "The camera is mounted by {} (pid {})", user.name, user.pid),
+      Self::InUse(user) => write!(f, "The camera is used by {} (pid {})", user.name, user.pid),
+    }
+  }
+}
+
+/// Problems found by [`check_device_access`]
+///
+/// Converts into an [`Error`] of kind [`IoUsbClaim`](crate::error::ErrorKind::IoUsbClaim) with
+/// the problems and hints as message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AccessError {
+  /// Port of the camera
+  pub port: String,
+  /// Everything preventing access, at least one entry
+  pub problems: Vec<AccessProblem>,
+}
+
+impl fmt::Display for AccessError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Can't access the camera at {}", self.port)?;
+
+    for problem in &self.problems {
+      write!(f, "\n- {problem}: {}", problem.hint())?;
+    }
+
+    Ok(())
+  }
+}
+
+impl error::Error for AccessError {}
+
+impl From<AccessError> for Error {
+  fn from(error: AccessError) -> Self {
+    Error::new(libgphoto2_sys::GP_ERROR_IO_USB_CLAIM, Some(error.to_string()))
+  }
+}
+
+/// Check whether the camera can be opened by this process
+///
+/// Returns the found problems as error. Finding no problem doesn't guarantee that the camera
+/// can be opened, processes of other users are not visible for example.
+pub fn check_device_access(camera: &CameraDescriptor) -> Result<(), AccessError> {
+  let Some(usb) = UsbBusDevice::parse(&camera.port) else {
+    return Ok(());
+  };
+
+  let problems = find_problems(usb);
+
+  if problems.is_empty() {
+    Ok(())
+  } else {
+    Err(AccessError { port: camera.port.clone(), problems })
+  }
+}
+
+#[cfg(target_os = "linux")]
+fn find_problems(usb: UsbBusDevice) -> Vec<AccessProblem> {
+  use std::{ffi::CString, fs, os::unix::ffi::OsStrExt};
+
+  let path = PathBuf::from(format!("/dev/bus/usb/{:03}/{:03}", usb.bus, usb.device));
+
+  if !path.exists() {
+    return vec![AccessProblem::DeviceMissing { path }];
+  }
+
+  let mut problems = Vec::new();
+
+  let writable = CString::new(path.as_os_str().as_bytes())
+    .is_ok_and(|c_path| unsafe { libc::access(c_path.as_ptr(), libc::R_OK | libc::W_OK) } == 0);
+  if !writable {
+    problems.push(AccessProblem::PermissionDenied { path: path.clone() });
+  }
+
+  let own_pid = std::process::id();
+  let processes = fs::read_dir("/proc").into_iter().flatten().flatten();
+
+  for process in processes {
+    let Some(pid) = process.file_name().to_str().and_then(|pid| pid.parse::<u32>().ok()) else {
+      continue;
+    };
+    if pid == own_pid {
+      continue;
+    }
+
+    // Only the processes of the current user are readable without root
+    let Ok(fds) = fs::read_dir(process.path().join("fd")) else {
+      continue;
+    };
+    let has_device_open =
+      fds.flatten().any(|fd| fs::read_link(fd.path()).is_ok_and(|target| target == path));
+    if !has_device_open {
+      continue;
+    }
+
+    let name = fs::read_to_string(process.path().join("comm"))
+      .map(|name| name.trim_end().to_owned())
+      .unwrap_or_default();
+    let user = DeviceUser { pid, name };
+
+    problems.push(if user.name.starts_with("gvfs") {
+      AccessProblem::Gvfs(user)
+    } else {
+      AccessProblem::InUse(user)
+    });
+  }
+
+  problems
+}
+
+#[cfg(not(target_os = "linux"))]
+fn find_problems(_usb: UsbBusDevice) -> Vec<AccessProblem> {
+  Vec::new()
+}
@@ -10,7 +10,10 @@ use serde::Serialize;
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// Error type
+///
+/// New kinds are added as libgphoto2 gains error codes, so matches need a wildcard arm.
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ErrorKind {
   /// GP_ERROR or something else
   Other,
@@ -66,19 +69,58 @@ pub enum ErrorKind {
   UnknownPort,
   /// Couldn't claim USB device.
   IoUsbClaim,
+  /// The operation was cancelled
+  Cancelled,
 }
 
 /// General error
-#[derive(PartialEq, Eq)]
+#[derive(Clone)]
 pub struct Error {
   error: c_int,
   info: Option<String>,
+  #[cfg(feature = "error_log")]
+  debug_log: Vec<String>,
+}
+
+impl PartialEq for Error {
+  fn eq(&self, other: &Self) -> bool {
+    self.error == other.error && self.info == other.info
+  }
 }
 
+impl Eq for Error {}
+
 impl Error {
   /// Creates a new error from a gphoto internal error
+  ///
+  /// With the `error_log` feature the most recent libgphoto2 log lines are attached to it.
   pub fn new(error: c_int, info: Option<String>) -> Self {
-    Self { error, info }
+    Self {
+      error,
+      info,
+      #[cfg(feature = "error_log")]
+      debug_log: crate::logging::recent_lines(),
+    }
+  }
+
+  /// Error which did not originate from libgphoto2, no log is attached
+  fn other(error: c_int, info: String) -> Self {
+    Self {
+      error,
+      info: Some(info),
+      #[cfg(feature = "error_log")]
+      debug_log: Vec::new(),
+    }
+  }
+
+  /// libgphoto2 log lines emitted right before this error occurred, oldest first
+  ///
+  /// The number of lines kept can be changed with
+  /// [`set_error_log_lines`](crate::logging::set_error_log_lines).
+  /// Enable the `extended_logs` feature to include driver (e.g. PTP) traces.
+  #[cfg(feature = "error_log")]
+  pub fn debug_log(&self) -> &[String] {
+    &self.debug_log
   }
 
   /// Checks the status code and creates a new error if non-zero.
@@ -90,6 +132,11 @@ impl Error {
     }
   }
 
+  /// The `GP_ERROR_*` code of libgphoto2
+  pub fn code(&self) -> c_int {
+    self.error
+  }
+
   /// Map the gphoto type to an [`ErrorKind`]
   pub fn kind(&self) -> ErrorKind {
     match self.error {
@@ -119,6 +166,7 @@ impl Error {
       libgphoto2_sys::GP_ERROR_IO_USB_CLEAR_HALT => ErrorKind::IoUsbClearHalt,
       libgphoto2_sys::GP_ERROR_IO_USB_FIND => ErrorKind::IoUsbFind,
       libgphoto2_sys::GP_ERROR_IO_LOCK => ErrorKind::IoLock,
+      libgphoto2_sys::GP_ERROR_CANCEL => ErrorKind::Cancelled,
 
       libgphoto2_sys::GP_ERROR => ErrorKind::Other,
       _ => ErrorKind::Other,
@@ -138,19 +186,19 @@ impl Serialize for Error {
 
 impl From<std::io::Error> for Error {
   fn from(err: std::io::Error) -> Self {
-    Self { error: libgphoto2_sys::GP_ERROR_IO, info: Some(err.to_string()) }
+    Self::other(libgphoto2_sys::GP_ERROR_IO, err.to_string())
   }
 }
 
 impl From<std::ffi::NulError> for Error {
   fn from(err: std::ffi::NulError) -> Self {
-    Self { error: libgphoto2_sys::GP_ERROR_BAD_PARAMETERS, info: Some(err.to_string()) }
+    Self::other(libgphoto2_sys::GP_ERROR_BAD_PARAMETERS, err.to_string())
   }
 }
 
 impl From<std::num::TryFromIntError> for Error {
   fn from(err: std::num::TryFromIntError) -> Self {
-    Self { error: libgphoto2_sys::GP_ERROR, info: Some(err.to_string()) }
+    Self::other(libgphoto2_sys::GP_ERROR, err.to_string())
   }
 }
 
@@ -162,7 +210,7 @@ impl From<std::convert::Infallible> for Error {
 
 impl From<String> for Error {
   fn from(message: String) -> Self {
-    Self { error: libgphoto2_sys::GP_ERROR, info: Some(message) }
+    Self::other(libgphoto2_sys::GP_ERROR, message)
   }
 }
 
@@ -0,0 +1,191 @@
+//! Shot metadata from EXIF
+//!
+//! [`CameraFS::exif`](crate::filesys::CameraFS::exif) downloads only the EXIF block of a file,
+//! which is a lot faster than downloading the whole image right after a capture.
+//!
+//! ```no_run
+//! use gphoto2::{Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//! let path = camera.capture_image().wait()?;
+//!
+//! let exif = camera.fs().exif(&path.folder(), &path.name()).wait()?;
+//! println!("ISO {:?}, {:?}s at f/{:?}", exif.iso, exif.exposure_time, exif.f_number);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{Error, Result};
+use ::exif::{In, Reader, Tag, Value};
+use std::io::Cursor;
+
+/// Header of the APP1 segment, some drivers return the EXIF block including it
+const APP1_HEADER: &[u8] = b"Exif\0\0";
+
+/// Commonly used EXIF fields of a shot
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExifInfo {
+  /// Manufacturer of the camera
+  pub make: Option<String>,
+  /// Model of the camera
+  pub model: Option<String>,
+  /// Model of the lens
+  pub lens_model: Option<String>,
+  /// Exposure time in seconds
+  pub exposure_time: Option<f64>,
+  /// Aperture as F-number
+  pub f_number: Option<f64>,
+  /// Focal length in millimeters
+  pub focal_length: Option<f64>,
+  /// ISO sensitivity
+  pub iso: Option<u32>,
+  /// Time the shot was taken
+  pub date_time: Option<ExifDateTime>,
+  /// Location the shot was taken at
+  pub gps: Option<GpsPosition>,
+}
+
+/// Date and time of a shot, in the time zone configured on the camera
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExifDateTime {
+  /// Year
+  pub year: u16,
+  /// Month (1-12)
+  pub month: u8,
+  /// Day of the month (1-31)
+  pub day: u8,
+  /// Hour (0-23)
+  pub hour: u8,
+  /// Minute (0-59)
+  pub minute: u8,
+  /// Second (0-59)
+  pub second: u8,
+  /// Fraction of the second in nanoseconds, if the camera records it
+  pub nanosecond: Option<u32>,
+  /// Offset from UTC in minutes, if the camera records it
+  pub offset: Option<i16>,
+}
+
+/// GPS position of a shot
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GpsPosition {
+  /// Latitude in degrees, negative values are south
+  pub latitude: f64,
+  /// Longitude in degrees, negative values are west
+  pub longitude: f64,
+  /// Altitude in meters, negative values are below sea level
+  pub altitude: Option<f64>,
+}
+
+impl ExifInfo {
+  /// Parse an EXIF block, a JPEG or a TIFF based raw file
+  pub fn parse(data: &[u8]) -> Result<Self> {
+    let reader = Reader::new();
+
+    let data = data.strip_prefix(APP1_HEADER).unwrap_or(data);
+    let exif = if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+      reader.read_raw(data.to_vec())
+    } else {
+      reader.read_from_container(&mut Cursor::new(data))
+    }
+    .map_err(|e| Error::from(format!("Could not parse EXIF: {e}")))?;
+
+    let field = |tag| exif.get_field(tag, In::PRIMARY).map(|field| &field.value);
+    let ascii = |tag| match field(tag)? {
+      Value::Ascii(values) => values.first().map(Vec::as_slice),
+      _ => None,
+    };
+    let text = |tag| {
+      let text = String::from_utf8_lossy(ascii(tag)?).trim().to_owned();
+      (!text.is_empty()).then_some(text)
+    };
+    let rational = |tag| match field(tag)? {
+      Value::Rational(values) => values.first().map(|value| value.to_f64()),
+      _ => None,
+    };
+
+    let iso = field(Tag::PhotographicSensitivity)
+      .or_else(|| field(Tag::ISOSpeed))
+      .and_then(|value| value.get_uint(0));
+
+    let date_time =
+      ascii(Tag::DateTimeOriginal).or_else(|| ascii(Tag::DateTime)).and_then(|value| {
+        let mut date_time = ::exif::DateTime::from_ascii(value).ok()?;
+
+        // Both are optional, invalid values are left out
+        if let Some(subsec) = ascii(Tag::SubSecTimeOriginal) {
+          date_time.parse_subsec(subsec).ok();
+        }
+        if let Some(offset) = ascii(Tag::OffsetTimeOriginal) {
+          date_time.parse_offset(offset).ok();
+        }
+
+        Some(ExifDateTime {
+          year: date_time.year,
+          month: date_time.month,
+          day: date_time.day,
+          hour: date_time.hour,
+          minute: date_time.minute,
+          second: date_time.second,
+          nanosecond: date_time.nanosecond,
+          offset: date_time.offset,
+        })
+      });
+
+    Ok(Self {
+      make: text(Tag::Make),
+      model: text(Tag::Model),
+      lens_model: text(Tag::LensModel),
+      exposure_time: rational(Tag::ExposureTime),
+      f_number: rational(Tag::FNumber),
+      focal_length: rational(Tag::FocalLength),
+      iso,
+      date_time,
+      gps: gps_position(&exif),
+    })
+  }
+}
+
+fn gps_position(exif: &::exif::Exif) -> Option<GpsPosition> {
+  let field = |tag| exif.get_field(tag, In::PRIMARY).map(|field| &field.value);
+
+  // Degrees, minutes and seconds, negated for the south or west reference
+  let coordinate = |tag, ref_tag, negative_ref: u8| {
+    let Value::Rational(dms) = field(tag)? else {
+      return None;
+    };
+    let degrees: f64 =
+      dms.iter().zip([1.0, 60.0, 3600.0]).map(|(value, div)| value.to_f64() / div).sum();
+
+    match field(ref_tag) {
+      Some(Value::Ascii(values)) if values.first()?.first() == Some(&negative_ref) => {
+        Some(-degrees)
+      }
+      _ => Some(degrees),
+    }
+  };
+
+  let altitude = match field(Tag::GPSAltitude) {
+    Some(Value::Rational(values)) => values.first().map(|altitude| {
+      let below_sea_level =
+        field(Tag::GPSAltitudeRef).and_then(|value| value.get_uint(0)) == Some(1);
+
+      if below_sea_level {
+        -altitude.to_f64()
+      } else {
+        altitude.to_f64()
+      }
+    }),
+    _ => None,
+  };
+
+  Some(GpsPosition {
+    latitude: coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S')?,
+    longitude: coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W')?,
+    altitude,
+  })
+}
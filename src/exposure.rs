@@ -0,0 +1,226 @@
+//! Exposure values in the spellings of cameras
+//!
+//! Drivers report exposure settings as strings which differ between vendors, like `1/125`,
+//! `0.8s` or `30"` for shutter speeds and `f/5.6` or `5.6` for apertures. The types of this
+//! module parse all of them and format a single spelling. They are used by
+//! [`CameraSettings`](crate::settings::CameraSettings) and work with
+//! [`RadioWidget::choices_as`](crate::widget::RadioWidget::choices_as).
+//!
+//! ```
+//! use gphoto2::exposure::{Aperture, ExposureCompensation, Iso, ShutterSpeed};
+//! use std::time::Duration;
+//!
+//! let shutter_speed: ShutterSpeed = "0.8s".parse().unwrap();
+//! assert_eq!(shutter_speed.duration(), Some(Duration::from_millis(800)));
+//! assert_eq!("1/125".parse::<ShutterSpeed>().unwrap().to_string(), "1/125");
+//! assert_eq!("bulb".parse::<ShutterSpeed>().unwrap(), ShutterSpeed::Bulb);
+//!
+//! assert_eq!("f/5.6".parse::<Aperture>().unwrap(), Aperture::FNumber(5.6));
+//! assert_eq!("Auto ISO".parse::<Iso>().unwrap(), Iso::Auto);
+//! assert_eq!("+0.3".parse::<ExposureCompensation>().unwrap().stops(), 0.3);
+//! ```
+
+use crate::{widget::parse_number, Error, Result};
+use std::{fmt, str::FromStr, time::Duration};
+
+fn invalid(kind: &str, value: &str) -> Error {
+  Error::new(libgphoto2_sys::GP_ERROR_BAD_PARAMETERS, Some(format!("Invalid {kind} {value:?}")))
+}
+
+/// Shutter speed (exposure time)
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShutterSpeed {
+  /// Chosen by the camera
+  Auto,
+  /// Open while the shutter button is pressed
+  Bulb,
+  /// Opened and closed by two presses of the shutter button
+  Time,
+  /// Exposure time in seconds
+  Seconds(f64),
+}
+
+impl ShutterSpeed {
+  /// Shutter speed of `duration`
+  pub fn from_duration(duration: Duration) -> Self {
+    Self::Seconds(duration.as_secs_f64())
+  }
+
+  /// Exposure time in seconds, [`None`] if not fixed
+  pub fn seconds(self) -> Option<f64> {
+    match self {
+      Self::Seconds(seconds) => Some(seconds),
+      Self::Auto | Self::Bulb | Self::Time => None,
+    }
+  }
+
+  /// Exposure time, [`None`] if not fixed
+  pub fn duration(self) -> Option<Duration> {
+    self.seconds().and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+  }
+}
+
+impl From<Duration> for ShutterSpeed {
+  fn from(duration: Duration) -> Self {
+    Self::from_duration(duration)
+  }
+}
+
+impl FromStr for ShutterSpeed {
+  type Err = Error;
+
+  fn from_str(value: &str) -> Result<Self> {
+    match value.trim().to_lowercase().as_str() {
+      "auto" => Ok(Self::Auto),
+      "bulb" | "b" => Ok(Self::Bulb),
+      "time" | "t" => Ok(Self::Time),
+      _ => parse_number(value)
+        .filter(|seconds| *seconds > 0.0)
+        .map(Self::Seconds)
+        .ok_or_else(|| invalid("shutter speed", value)),
+    }
+  }
+}
+
+impl fmt::Display for ShutterSpeed {
+  /// Fractions like `1/125` below 0.3 seconds, seconds like `0.8s` above
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match *self {
+      Self::Auto => f.write_str("Auto"),
+      Self::Bulb => f.write_str("Bulb"),
+      Self::Time => f.write_str("Time"),
+      Self::Seconds(seconds) if seconds < 0.3 => {
+        write!(f, "1/{}", (1.0 / seconds * 10.0).round() / 10.0)
+      }
+      Self::Seconds(seconds) => write!(f, "{}s", (seconds * 10.0).round() / 10.0),
+    }
+  }
+}
+
+/// Aperture as f-number
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Aperture {
+  /// Chosen by the camera or lens, also used for lenses without electronic contacts
+  Auto,
+  /// The f-number, like `5.6` for f/5.6
+  FNumber(f64),
+}
+
+impl Aperture {
+  /// The f-number, [`None`] if not fixed
+  pub fn f_number(self) -> Option<f64> {
+    match self {
+      Self::FNumber(f_number) => Some(f_number),
+      Self::Auto => None,
+    }
+  }
+}
+
+impl FromStr for Aperture {
+  type Err = Error;
+
+  fn from_str(value: &str) -> Result<Self> {
+    if value.to_lowercase().contains("auto") {
+      return Ok(Self::Auto);
+    }
+
+    parse_number(value)
+      .filter(|f_number| *f_number > 0.0)
+      .map(Self::FNumber)
+      .ok_or_else(|| invalid("aperture", value))
+  }
+}
+
+impl fmt::Display for Aperture {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Auto => f.write_str("Auto"),
+      Self::FNumber(f_number) => write!(f, "f/{f_number}"),
+    }
+  }
+}
+
+/// ISO speed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Iso {
+  /// Chosen by the camera
+  Auto,
+  /// Fixed ISO speed, like `100`
+  Value(u32),
+}
+
+impl Iso {
+  /// The ISO speed, [`None`] if not fixed
+  pub fn value(self) -> Option<u32> {
+    match self {
+      Self::Value(iso) => Some(iso),
+      Self::Auto => None,
+    }
+  }
+}
+
+impl FromStr for Iso {
+  type Err = Error;
+
+  fn from_str(value: &str) -> Result<Self> {
+    let lower = value.trim().to_lowercase();
+
+    if lower.contains("auto") {
+      return Ok(Self::Auto);
+    }
+
+    lower
+      .trim_start_matches("iso")
+      .trim()
+      .parse()
+      .ok()
+      .filter(|iso| *iso > 0)
+      .map(Self::Value)
+      .ok_or_else(|| invalid("ISO", value))
+  }
+}
+
+impl fmt::Display for Iso {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Auto => f.write_str("Auto"),
+      Self::Value(iso) => write!(f, "{iso}"),
+    }
+  }
+}
+
+/// Exposure compensation in stops
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExposureCompensation(pub f64);
+
+impl ExposureCompensation {
+  /// The compensation in stops, negative values darken the image
+  pub fn stops(self) -> f64 {
+    self.0
+  }
+}
+
+impl FromStr for ExposureCompensation {
+  type Err = Error;
+
+  fn from_str(value: &str) -> Result<Self> {
+    parse_number(value).map(Self).ok_or_else(|| invalid("exposure compensation", value))
+  }
+}
+
+impl fmt::Display for ExposureCompensation {
+  /// Stops with sign and one decimal, like `+0.3`
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let stops = (self.0 * 10.0).round() / 10.0;
+
+    if stops == 0.0 {
+      f.write_str("0")
+    } else {
+      write!(f, "{stops:+}")
+    }
+  }
+}
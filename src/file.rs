@@ -6,17 +6,27 @@ use serde::ser::SerializeMap;
 use crate::{
   error::Error,
   helper::{as_ref, char_slice_to_cow, chars_to_string, IntoUnixFd},
-  task::{BackgroundPtr, Task},
+  orientation::Orientation,
+  task::{run_on_worker, BackgroundPtr, SharedRef, Task},
   try_gp_internal, Context, Result,
 };
-use std::{borrow::Cow, fmt, fs, path::Path};
+use std::{
+  borrow::Cow,
+  ffi, fmt, fs,
+  hash::{Hash, Hasher},
+  os::raw::c_char,
+  path::Path,
+  sync::Arc,
+};
 
 /// Represents a path of a file on a camera
+#[derive(Clone)]
 pub struct CameraFilePath {
   pub(crate) inner: Box<libgphoto2_sys::CameraFilePath>,
 }
 
 /// Type of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum FileType {
   /// Preview of an image
@@ -36,25 +46,11 @@ pub enum FileType {
 /// File on a camera
 ///
 /// To download the file use [`CameraFS`](crate::filesys::CameraFS)
+#[derive(Clone)]
 pub struct CameraFile {
   pub(crate) inner: BackgroundPtr<libgphoto2_sys::CameraFile>,
   pub(crate) is_from_disk: bool,
-}
-
-impl Drop for CameraFile {
-  fn drop(&mut self) {
-    unsafe {
-      libgphoto2_sys::gp_file_unref(*self.inner);
-    }
-  }
-}
-
-impl Clone for CameraFile {
-  fn clone(&self) -> Self {
-    try_gp_internal!(gp_file_ref(*self.inner).unwrap());
-
-    Self { inner: self.inner, is_from_disk: self.is_from_disk }
-  }
+  _reference: Arc<SharedRef<libgphoto2_sys::CameraFile>>,
 }
 
 impl From<libgphoto2_sys::CameraFileType> for FileType {
@@ -86,6 +82,24 @@ impl serde::Serialize for CameraFilePath {
   }
 }
 
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CameraFilePath {
+  fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+  where
+    D: serde::Deserializer<'de>,
+  {
+    #[derive(serde::Deserialize)]
+    struct Path {
+      folder: String,
+      name: String,
+    }
+
+    let path = Path::deserialize(deserializer)?;
+
+    Self::new(&path.folder, &path.name).map_err(serde::de::Error::custom)
+  }
+}
+
 #[allow(clippy::from_over_into)]
 impl Into<libgphoto2_sys::CameraFileType> for FileType {
   fn into(self) -> libgphoto2_sys::CameraFileType {
@@ -102,6 +116,22 @@ impl Into<libgphoto2_sys::CameraFileType> for FileType {
   }
 }
 
+// Compared by folder and name, the bytes after the terminating NUL don't matter
+impl PartialEq for CameraFilePath {
+  fn eq(&self, other: &Self) -> bool {
+    self.folder() == other.folder() && self.name() == other.name()
+  }
+}
+
+impl Eq for CameraFilePath {}
+
+impl Hash for CameraFilePath {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.folder().hash(state);
+    self.name().hash(state);
+  }
+}
+
 impl fmt::Debug for CameraFilePath {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     f.debug_struct("CameraFilePath")
@@ -111,11 +141,21 @@ impl fmt::Debug for CameraFilePath {
   }
 }
 
-as_ref!(CameraFile -> libgphoto2_sys::CameraFile, **self.inner);
+as_ref!(CameraFile -> libgphoto2_sys::CameraFile, *self.inner.any_thread());
 
 as_ref!(CameraFilePath -> libgphoto2_sys::CameraFilePath, self.inner);
 
 impl CameraFilePath {
+  /// Create a path from a folder and file name
+  pub fn new(folder: &str, name: &str) -> Result<Self> {
+    Ok(Self {
+      inner: Box::new(libgphoto2_sys::CameraFilePath {
+        name: str_to_char_array(name)?,
+        folder: str_to_char_array(folder)?,
+      }),
+    })
+  }
+
   /// Get the name of the file's folder
   pub fn folder(&self) -> Cow<str> {
     char_slice_to_cow(&self.inner.folder)
@@ -127,11 +167,35 @@ impl CameraFilePath {
   }
 }
 
+/// Copy a string to a NUL terminated char array
+fn str_to_char_array<const N: usize>(value: &str) -> Result<[c_char; N]> {
+  let value = ffi::CString::new(value)?;
+  let bytes = value.as_bytes_with_nul();
+
+  if bytes.len() > N {
+    return Err(Error::from(format!("{value:?} is longer than {} bytes", N - 1)));
+  }
+
+  let mut chars = [0; N];
+  for (char, byte) in chars.iter_mut().zip(bytes) {
+    *char = c_char::from_ne_bytes([*byte]);
+  }
+
+  Ok(chars)
+}
+
 impl CameraFile {
+  /// Take over a reference of `file`
+  fn wrap(file: *mut libgphoto2_sys::CameraFile, is_from_disk: bool) -> Self {
+    let inner = BackgroundPtr(file);
+
+    Self { inner, is_from_disk, _reference: SharedRef::new(inner, libgphoto2_sys::gp_file_unref) }
+  }
+
   pub(crate) fn new() -> Result<Self> {
     try_gp_internal!(gp_file_new(&out camera_file_ptr)?);
 
-    Ok(Self { inner: BackgroundPtr(camera_file_ptr), is_from_disk: false })
+    Ok(Self::wrap(camera_file_ptr, false))
   }
 
   pub(crate) fn new_file(path: &Path) -> Result<Self> {
@@ -142,51 +206,94 @@ impl CameraFile {
     let fd = fs::File::create(path)?.into_unix_fd();
 
     try_gp_internal!(gp_file_new_from_fd(&out camera_file_ptr, fd)?);
-    Ok(Self { inner: BackgroundPtr(camera_file_ptr), is_from_disk: true })
+    Ok(Self::wrap(camera_file_ptr, true))
+  }
+
+  /// Wrap a `CameraFile` created outside of this crate
+  ///
+  /// The returned file takes over one reference of `file`, call `gp_file_ref` before if the
+  /// caller keeps using the pointer.
+  ///
+  /// # Safety
+  ///
+  /// `file` must be a valid `CameraFile` pointer stored in memory (created with `gp_file_new`),
+  /// and the caller must own the reference passed to this function.
+  #[cfg(feature = "raw")]
+  pub unsafe fn from_raw(file: *mut libgphoto2_sys::CameraFile) -> Self {
+    Self::wrap(file, false)
+  }
+
+  /// Get the `CameraFile` pointer, which stays valid as long as this file is alive
+  ///
+  /// libgphoto2 functions must only be called with the pointer inside of [`Task::raw`].
+  #[cfg(feature = "raw")]
+  pub fn as_raw(&self) -> *mut libgphoto2_sys::CameraFile {
+    self.inner.any_thread()
   }
 
   /// Get the data of the file
   pub fn get_data(&self, context: &Context) -> Task<Result<Box<[u8]>>> {
     let file = self.clone();
 
-    unsafe {
-      Task::new(move || {
-        try_gp_internal!(gp_file_get_data_and_size(*file.inner, &out data, &out size)?);
+    unsafe { Task::new(move || file.read_data()) }.context(context)
+  }
 
-        let data_slice: Box<[u8]> =
-          std::slice::from_raw_parts(data.cast::<u8>(), size.try_into()?).into();
+  /// Copy the data of the file, must be called from the libgphoto2 thread
+  pub(crate) unsafe fn read_data(&self) -> Result<Box<[u8]>> {
+    try_gp_internal!(gp_file_get_data_and_size(*self.inner, &out data, &out size)?);
 
-        if file.is_from_disk {
-          // Casting a *const pointer to *mut is still unstable
-          #[allow(clippy::as_conversions)]
-          libc::free((data as *mut i8).cast())
-        }
+    let data_slice: Box<[u8]> =
+      std::slice::from_raw_parts(data.cast::<u8>(), size.try_into()?).into();
 
-        Ok(data_slice)
-      })
+    if self.is_from_disk {
+      // Casting a *const pointer to *mut is still unstable
+      #[allow(clippy::as_conversions)]
+      libc::free((data as *mut i8).cast())
     }
-    .context(context.inner)
+
+    Ok(data_slice)
+  }
+
+  /// Read the [`Orientation`] from the file's EXIF data
+  ///
+  /// Returns [`None`] for files without orientation information.
+  pub fn orientation(&self, context: &Context) -> Task<Result<Option<Orientation>>> {
+    let file = self.clone();
+
+    unsafe { Task::new(move || Ok(Orientation::detect(&file.read_data()?))) }.context(context)
   }
 
   /// File name
   pub fn name(&self) -> String {
-    try_gp_internal!(gp_file_get_name(*self.inner, &out file_name).unwrap());
+    let file = self.inner;
 
-    chars_to_string(file_name)
+    run_on_worker(move || {
+      try_gp_internal!(gp_file_get_name(*file, &out file_name).unwrap());
+
+      chars_to_string(file_name)
+    })
   }
 
   /// File mime type
   pub fn mime_type(&self) -> String {
-    try_gp_internal!(gp_file_get_mime_type(*self.inner, &out mime_type).unwrap());
+    let file = self.inner;
+
+    run_on_worker(move || {
+      try_gp_internal!(gp_file_get_mime_type(*file, &out mime_type).unwrap());
 
-    chars_to_string(mime_type)
+      chars_to_string(mime_type)
+    })
   }
 
   /// File modification time
   pub fn mtime(&self) -> libc::time_t {
-    try_gp_internal!(gp_file_get_mtime(*self.inner, &out mtime).unwrap());
+    let file = self.inner;
+
+    run_on_worker(move || {
+      try_gp_internal!(gp_file_get_mtime(*file, &out mtime).unwrap());
 
-    mtime
+      mtime
+    })
   }
 
   /// File size
@@ -201,7 +308,7 @@ impl CameraFile {
         Ok(size.into())
       })
     }
-    .context(context.inner)
+    .context(context)
   }
 }
 
@@ -1,14 +1,31 @@
 //! Camera filesystem and storages
 
+#[cfg(feature = "exif")]
+use crate::exif::ExifInfo;
 use crate::{
+  checksum::{Checksum, ChecksumHasher},
+  clock,
+  error::ErrorKind,
   file::{CameraFile, FileType},
-  helper::{bitflags, char_slice_to_cow, to_c_string, UninitBox},
+  helper::{bitflags, char_slice_to_cow, check_cancelled, to_c_string, UninitBox},
   list::{CameraList, FileListIter},
+  metadata,
+  metrics::Operation,
+  naming::{self, NamingTemplate, NamingValues},
+  orientation::Orientation,
+  path::{CameraPath, IntoCameraPath},
   task::Task,
+  thumbnail::Thumbnail,
   try_gp_internal, Camera, Error, Result,
 };
 use libgphoto2_sys::time_t;
-use std::{borrow::Cow, ffi, fmt, fs, path::Path};
+use std::{
+  borrow::Cow,
+  ffi, fmt, fs,
+  io::{self, Read},
+  path::{Path, PathBuf},
+  time::SystemTime,
+};
 
 macro_rules! storage_info {
   ($(# $attr:tt)* $name:ident: $bitflag_ty:ident, |$inner:ident: $inner_ty:ident| { $($(# $field_attr:tt)* $field:ident: $ty:ty = $bitflag:ident, $expr:expr;)* }) => {
@@ -53,7 +70,7 @@ macro_rules! storage_info {
 }
 
 /// Hardware storage type
-#[derive(Debug, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum StorageType {
   /// Unknown storage type
@@ -69,7 +86,7 @@ pub enum StorageType {
 }
 
 /// Type of the filesystem hierarchy
-#[derive(Debug, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum FilesystemType {
   /// Unknown filesystem type
@@ -83,7 +100,7 @@ pub enum FilesystemType {
 }
 
 /// Access types of storage
-#[derive(Debug, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum AccessType {
   /// Read/Write
@@ -165,6 +182,8 @@ storage_info!(
 pub struct FileInfo {
   // It's fairly large, so we want to keep it on the heap.
   pub(crate) inner: Box<libgphoto2_sys::CameraFileInfo>,
+  /// Folder and name the info was read for
+  path: String,
 }
 
 impl FileInfo {
@@ -182,6 +201,26 @@ impl FileInfo {
   pub fn audio(&self) -> &FileInfoAudio {
     FileInfoAudio::from_inner_ref(&self.inner.audio)
   }
+
+  /// Storage-qualified path of the file, like `/store_00010001/DCIM/100CANON/IMG_0001.JPG`
+  ///
+  /// libgphoto2 doesn't expose PTP object handles, files are identified by this path instead.
+  /// With the PTP driver, the first folder names the storage (see [`FileInfo::storage_id`]), so
+  /// the path is unique across all storages of the camera and stays valid until the file is
+  /// moved or deleted. Raw PTP tools can look the object handle up by its storage and name.
+  pub fn object_id(&self) -> &str {
+    &self.path
+  }
+
+  /// PTP storage ID of the file, from the `store_XXXXXXXX` folder of [`FileInfo::object_id`]
+  ///
+  /// [`None`] for drivers which don't name their storages like the PTP driver.
+  pub fn storage_id(&self) -> Option<u32> {
+    let storage = self.path.trim_start_matches('/').split('/').next()?;
+    let id = storage.strip_prefix("store_").filter(|id| id.len() == 8)?;
+
+    u32::from_str_radix(id, 16).ok()
+  }
 }
 
 impl fmt::Debug for FileInfo {
@@ -194,6 +233,321 @@ impl fmt::Debug for FileInfo {
   }
 }
 
+/// Order of the entries listed by [`CameraFS::list_files_ordered`] and [`CameraFS::list_folders_ordered`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ListOrder {
+  /// Order returned by the driver, usually the order on the storage
+  #[default]
+  Driver,
+  /// Sorted by name
+  Ascending,
+  /// Sorted by name in reverse
+  Descending,
+}
+
+/// Filter for [`CameraFS::list_files_filtered`]
+///
+/// A file is listed if it matches all the set conditions. Conditions on the [`FileInfo`]
+/// don't match if the camera doesn't report the field.
+///
+/// ```no_run
+/// use gphoto2::{filesys::FileFilter, Context, Result};
+///
+/// # fn main() -> Result<()> {
+/// let camera = Context::new()?.autodetect_camera().wait()?;
+///
+/// let filter = FileFilter::new().extension("cr2").extension("cr3").min_size(1024 * 1024);
+/// for file in camera.fs().list_files_filtered("/store_00010001/DCIM/100CANON", filter).wait()? {
+///   println!("{file}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileFilter {
+  extensions: Vec<String>,
+  mime_types: Vec<String>,
+  min_size: Option<u64>,
+  max_size: Option<u64>,
+  modified_after: Option<time_t>,
+  modified_before: Option<time_t>,
+}
+
+impl FileFilter {
+  /// Create a filter matching every file
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Only match files with this extension (case insensitive, without the dot)
+  ///
+  /// Can be called multiple times to match any of the extensions.
+  pub fn extension(mut self, extension: &str) -> Self {
+    self.extensions.push(extension.trim_start_matches('.').to_ascii_lowercase());
+    self
+  }
+
+  /// Only match files with this mime type, like `image/jpeg` or `image/*`
+  ///
+  /// Can be called multiple times to match any of the types.
+  pub fn mime_type(mut self, mime_type: &str) -> Self {
+    self.mime_types.push(mime_type.to_ascii_lowercase());
+    self
+  }
+
+  /// Only match files with at least this size in bytes
+  pub fn min_size(mut self, size: u64) -> Self {
+    self.min_size = Some(size);
+    self
+  }
+
+  /// Only match files with at most this size in bytes
+  pub fn max_size(mut self, size: u64) -> Self {
+    self.max_size = Some(size);
+    self
+  }
+
+  /// Only match files modified at or after this UNIX timestamp
+  pub fn modified_after(mut self, mtime: time_t) -> Self {
+    self.modified_after = Some(mtime);
+    self
+  }
+
+  /// Only match files modified at or before this UNIX timestamp
+  pub fn modified_before(mut self, mtime: time_t) -> Self {
+    self.modified_before = Some(mtime);
+    self
+  }
+
+  fn needs_info(&self) -> bool {
+    !self.mime_types.is_empty()
+      || self.min_size.is_some()
+      || self.max_size.is_some()
+      || self.modified_after.is_some()
+      || self.modified_before.is_some()
+  }
+
+  fn matches_name(&self, name: &str) -> bool {
+    self.extensions.is_empty()
+      || name.rsplit_once('.').is_some_and(|(_, extension)| {
+        self.extensions.iter().any(|expected| extension.eq_ignore_ascii_case(expected))
+      })
+  }
+
+  // Option::is_none_or needs Rust 1.82
+  #[allow(clippy::unnecessary_map_or)]
+  fn matches_info(&self, info: &FileInfoFile) -> bool {
+    let mime_type_matches = self.mime_types.is_empty()
+      || info.mime_type().is_some_and(|mime_type| {
+        let mime_type = mime_type.to_ascii_lowercase();
+
+        self.mime_types.iter().any(|expected| match expected.strip_suffix('*') {
+          Some(prefix) => mime_type.starts_with(prefix),
+          None => mime_type == *expected,
+        })
+      });
+
+    let size = info.size();
+    let mtime = info.mtime();
+
+    mime_type_matches
+      && self.min_size.map_or(true, |min| size.is_some_and(|size| size >= min))
+      && self.max_size.map_or(true, |max| size.is_some_and(|size| size <= max))
+      && self.modified_after.map_or(true, |after| mtime.is_some_and(|mtime| mtime >= after))
+      && self.modified_before.map_or(true, |before| mtime.is_some_and(|mtime| mtime <= before))
+  }
+}
+
+#[derive(Clone, Copy)]
+enum ListKind {
+  Files,
+  Folders,
+}
+
+/// Download a file to memory or `path`, must be called from a [`Task`]
+pub(crate) unsafe fn get_camera_file(
+  camera: *mut libgphoto2_sys::Camera,
+  folder: &str,
+  file: &str,
+  type_: FileType,
+  path: Option<&Path>,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<CameraFile> {
+  let camera_file = match path {
+    Some(dest_path) => CameraFile::new_file(dest_path)?,
+    None => CameraFile::new()?,
+  };
+
+  try_gp_internal!(gp_camera_file_get(
+    camera,
+    to_c_string!(folder),
+    to_c_string!(file),
+    type_.into(),
+    *camera_file.inner,
+    context
+  )
+  .map_err(|e| {
+    if let Some(path) = path {
+      if let Err(error) = fs::remove_file(path) {
+        return Into::<Error>::into(error);
+      }
+    }
+
+    e
+  })?);
+
+  Ok(camera_file)
+}
+
+/// Metadata XML of a file, [`None`] if the camera doesn't support metadata
+unsafe fn get_metadata(
+  camera: *mut libgphoto2_sys::Camera,
+  folder: &str,
+  file: &str,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<Option<String>> {
+  match get_camera_file(camera, folder, file, FileType::Metadata, None, context) {
+    // Not decoded lossily, the XML may be written back to the camera
+    Ok(metadata) => String::from_utf8(metadata.read_data()?.into_vec()).map(Some).map_err(|_| {
+      Error::new(
+        libgphoto2_sys::GP_ERROR_CORRUPTED_DATA,
+        Some(format!("The metadata of {file} is not valid UTF-8")),
+      )
+    }),
+    Err(error) if error.kind() == ErrorKind::NotSupported => Ok(None),
+    Err(error) => Err(error),
+  }
+}
+
+/// Upload `data` as file of type `type_`, must be called from a [`Task`]
+unsafe fn put_camera_file(
+  camera: *mut libgphoto2_sys::Camera,
+  folder: &str,
+  filename: &str,
+  type_: FileType,
+  data: &[u8],
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<()> {
+  let file = CameraFile::new()?;
+
+  try_gp_internal!(gp_file_append(*file.inner, data.as_ptr().cast(), data.len().try_into()?)?);
+  try_gp_internal!(gp_camera_folder_put_file(
+    camera,
+    to_c_string!(folder),
+    to_c_string!(filename),
+    type_.into(),
+    *file.inner,
+    context
+  )?);
+
+  Ok(())
+}
+
+/// Information of `file` in `folder`, must be called from a [`Task`]
+unsafe fn get_file_info(
+  camera: *mut libgphoto2_sys::Camera,
+  folder: &str,
+  file: &str,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<FileInfo> {
+  let mut inner = UninitBox::uninit();
+
+  try_gp_internal!(gp_camera_file_get_info(
+    camera,
+    to_c_string!(folder),
+    to_c_string!(file),
+    inner.as_mut_ptr(),
+    context
+  )?);
+
+  Ok(FileInfo {
+    inner: inner.assume_init(),
+    path: format!("{}/{file}", folder.trim_end_matches('/')),
+  })
+}
+
+/// List the files or folders in `folder`, must be called from a [`Task`]
+unsafe fn list_folder_entries(
+  camera: *mut libgphoto2_sys::Camera,
+  folder: &str,
+  kind: ListKind,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<CameraList> {
+  let list = CameraList::new()?;
+
+  match kind {
+    ListKind::Files => {
+      try_gp_internal!(gp_camera_folder_list_files(
+        camera,
+        to_c_string!(folder),
+        *list.inner,
+        context
+      )?);
+    }
+    ListKind::Folders => {
+      try_gp_internal!(gp_camera_folder_list_folders(
+        camera,
+        to_c_string!(folder),
+        *list.inner,
+        context
+      )?);
+    }
+  }
+
+  Ok(list)
+}
+
+/// Size of the chunks appended by [`CameraFS::upload_from_reader`]
+const UPLOAD_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Upload a file read in chunks, `progress` is called with the number of bytes read so far
+pub(crate) unsafe fn put_file_from_reader(
+  camera: *mut libgphoto2_sys::Camera,
+  folder: &str,
+  filename: &str,
+  mut reader: impl Read,
+  size_hint: Option<u64>,
+  mut progress: impl FnMut(u64),
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<()> {
+  let file = CameraFile::new()?;
+
+  let chunk_size = size_hint
+    .and_then(|size| usize::try_from(size).ok())
+    .map_or(UPLOAD_CHUNK_SIZE, |size| size.clamp(1, UPLOAD_CHUNK_SIZE));
+  let mut chunk = vec![0; chunk_size];
+  let mut total: u64 = 0;
+
+  loop {
+    let read = match reader.read(&mut chunk) {
+      Ok(0) => break,
+      Ok(read) => read,
+      Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+      Err(error) => return Err(error.into()),
+    };
+
+    try_gp_internal!(gp_file_append(*file.inner, chunk.as_ptr().cast(), read.try_into()?)?);
+    total += u64::try_from(read)?;
+    progress(total);
+    check_cancelled(context)?;
+  }
+
+  if let Some(expected) = size_hint.filter(|&expected| expected != total) {
+    return Err(Error::from(format!("Expected {expected} bytes but read {total} bytes")));
+  }
+
+  try_gp_internal!(gp_camera_folder_put_file(
+    camera,
+    to_c_string!(folder),
+    to_c_string!(filename),
+    FileType::Normal.into(),
+    *file.inner,
+    context
+  )?);
+
+  Ok(())
+}
+
 /// File system actions for a camera
 pub struct CameraFS<'a> {
   pub(crate) camera: &'a Camera,
@@ -268,170 +622,549 @@ impl<'a> CameraFS<'a> {
   }
 
   /// Delete a file
-  pub fn delete_file(&self, folder: &str, file: &str) -> Task<Result<()>> {
+  pub fn delete_file(&self, folder: impl IntoCameraPath, file: &str) -> Task<Result<()>> {
     let camera = self.camera.camera;
-    let context = self.camera.context.inner;
-    let (folder, file) = (folder.to_owned(), file.to_owned());
+    let context = self.camera.context.inner();
+    let (folder, file) = (folder.into_camera_path(), file.to_owned());
 
     unsafe {
       Task::new(move || {
+        let folder = folder?;
+
         try_gp_internal!(gp_camera_file_delete(
           *camera,
-          to_c_string!(folder),
+          to_c_string!(folder.as_str()),
           to_c_string!(file),
           *context
         )?);
         Ok(())
       })
     }
-    .context(context)
+    .camera(self.camera)
   }
 
   /// Get information of a file
-  pub fn file_info(&self, folder: &str, file: &str) -> Task<Result<FileInfo>> {
+  pub fn file_info(&self, folder: impl IntoCameraPath, file: &str) -> Task<Result<FileInfo>> {
     let camera = self.camera.camera;
-    let context = self.camera.context.inner;
-    let (folder, file) = (folder.to_owned(), file.to_owned());
-
-    unsafe {
-      Task::new(move || {
-        let mut inner = UninitBox::uninit();
+    let context = self.camera.context.inner();
+    let (folder, file) = (folder.into_camera_path(), file.to_owned());
 
-        try_gp_internal!(gp_camera_file_get_info(
-          *camera,
-          to_c_string!(folder),
-          to_c_string!(file),
-          inner.as_mut_ptr(),
-          *context
-        )?);
-
-        Ok(FileInfo { inner: inner.assume_init() })
-      })
-    }
-    .context(context)
+    unsafe { Task::new(move || get_file_info(*camera, &folder?, &file, *context)) }
+      .camera(self.camera)
   }
 
   /// Downloads a file from the camera
-  pub fn download_to(&self, folder: &str, file: &str, path: &Path) -> Task<Result<CameraFile>> {
+  pub fn download_to(
+    &self,
+    folder: impl IntoCameraPath,
+    file: &str,
+    path: &Path,
+  ) -> Task<Result<CameraFile>> {
     self.to_camera_file(folder, file, FileType::Normal, Some(path))
   }
 
   /// Downloads a camera file to memory
-  pub fn download(&self, folder: &str, file: &str) -> Task<Result<CameraFile>> {
+  pub fn download(&self, folder: impl IntoCameraPath, file: &str) -> Task<Result<CameraFile>> {
     self.to_camera_file(folder, file, FileType::Normal, None)
   }
 
+  /// Downloads a camera file to memory and computes the checksum of the received data
+  ///
+  /// With `reread`, the file is downloaded a second time and an error with
+  /// [`ErrorKind::CorruptedData`] is returned if the checksums differ. See the
+  /// [`checksum`](crate::checksum) module.
+  pub fn download_checked<H: ChecksumHasher>(
+    &self,
+    folder: impl IntoCameraPath,
+    file: &str,
+    reread: bool,
+  ) -> Task<Result<(CameraFile, Checksum)>> {
+    let (folder, file) = (folder.into_camera_path(), file.to_owned());
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        let folder = folder?;
+
+        let camera_file =
+          get_camera_file(*camera, &folder, &file, FileType::Normal, None, *context)?;
+        let checksum = Checksum::compute::<H>(&camera_file.read_data()?);
+
+        if reread {
+          check_cancelled(*context)?;
+          let again = get_camera_file(*camera, &folder, &file, FileType::Normal, None, *context)?;
+          let checksum_again = Checksum::compute::<H>(&again.read_data()?);
+
+          if checksum != checksum_again {
+            return Err(Error::new(
+              libgphoto2_sys::GP_ERROR_CORRUPTED_DATA,
+              Some(format!(
+                "{folder}/{file} changed between two downloads ({checksum} and {checksum_again})"
+              )),
+            ));
+          }
+        }
+
+        Ok((camera_file, checksum))
+      })
+    }
+    .camera(self.camera)
+    .metric(Operation::Download)
+  }
+
+  /// Downloads all files of a folder to `dir`, named by `template`
+  ///
+  /// `{seq}` counts from 1 in the order of the files on the camera and `{capture_time}` is the
+  /// modification time reported by the camera. Existing files are never overwritten, see the
+  /// [`naming`] module. Returns the paths of the downloaded files.
+  pub fn download_folder_named(
+    &self,
+    folder: impl IntoCameraPath,
+    dir: &Path,
+    template: &NamingTemplate,
+  ) -> Task<Result<Vec<PathBuf>>> {
+    let (folder, dir, template) = (folder.into_camera_path(), dir.to_owned(), template.clone());
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        let folder = folder?;
+
+        let (model, serial) = naming::camera_identity(*camera, *context)?;
+        let list = list_folder_entries(*camera, &folder, ListKind::Files, *context)?;
+
+        (1..)
+          .zip(FileListIter::new(list))
+          .map(|(seq, file)| {
+            check_cancelled(*context)?;
+            let info = get_file_info(*camera, &folder, &file, *context)?;
+            let capture_time = match info.file().mtime() {
+              // time_t is 32 bit on some platforms
+              #[allow(clippy::useless_conversion)]
+              Some(mtime) => i64::from(mtime),
+              None => clock::to_timestamp(SystemTime::now())?,
+            };
+
+            let values = NamingValues {
+              camera_serial: serial.as_deref(),
+              camera_model: &model,
+              capture_time,
+              seq,
+              orig_name: &file,
+            };
+
+            naming::download_named(*camera, &folder, &values, &dir, &template, *context)
+          })
+          .collect()
+      })
+    }
+    .camera(self.camera)
+    .metric(Operation::Download)
+  }
+
   /// Downloads a preview into memory
-  pub fn download_preview(&self, folder: &str, file: &str) -> Task<Result<CameraFile>> {
+  pub fn download_preview(
+    &self,
+    folder: impl IntoCameraPath,
+    file: &str,
+  ) -> Task<Result<CameraFile>> {
     self.to_camera_file(folder, file, FileType::Preview, None)
   }
 
+  /// Downloads the audio attached to a file (like a voice memo) into memory
+  ///
+  /// Whether a file has audio is reported by [`FileInfo::audio`].
+  pub fn download_audio(
+    &self,
+    folder: impl IntoCameraPath,
+    file: &str,
+  ) -> Task<Result<CameraFile>> {
+    self.to_camera_file(folder, file, FileType::Audio, None)
+  }
+
   /// Downloads the EXIF block into memory
-  pub fn download_exif(&self, folder: &str, file: &str) -> Task<Result<CameraFile>> {
+  pub fn download_exif(&self, folder: impl IntoCameraPath, file: &str) -> Task<Result<CameraFile>> {
     self.to_camera_file(folder, file, FileType::Exif, None)
   }
 
+  /// Download the preview (thumbnail) of a file
+  ///
+  /// With the `image` feature, the [`Thumbnail`] can be decoded as well.
+  pub fn thumbnail(&self, folder: impl IntoCameraPath, file: &str) -> Task<Result<Thumbnail>> {
+    let (folder, file) = (folder.into_camera_path(), file.to_owned());
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        let folder = folder?;
+
+        let preview = get_camera_file(*camera, &folder, &file, FileType::Preview, None, *context)?;
+
+        Ok(Thumbnail::new(preview.mime_type(), preview.read_data()?))
+      })
+    }
+    .camera(self.camera)
+    .metric(Operation::Download)
+  }
+
+  /// Download the EXIF block of a file and parse the shot metadata
+  #[cfg(feature = "exif")]
+  pub fn exif(&self, folder: impl IntoCameraPath, file: &str) -> Task<Result<ExifInfo>> {
+    let (folder, file) = (folder.into_camera_path(), file.to_owned());
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        let folder = folder?;
+
+        let exif = get_camera_file(*camera, &folder, &file, FileType::Exif, None, *context)?;
+
+        ExifInfo::parse(&exif.read_data()?)
+      })
+    }
+    .camera(self.camera)
+    .metric(Operation::Download)
+  }
+
+  /// Downloads the metadata of a file into memory
+  ///
+  /// For MTP devices this is an XML document of the object properties of the file.
+  pub fn download_metadata(
+    &self,
+    folder: impl IntoCameraPath,
+    file: &str,
+  ) -> Task<Result<CameraFile>> {
+    self.to_camera_file(folder, file, FileType::Metadata, None)
+  }
+
+  /// Rating of a file in stars (0 to 5)
+  ///
+  /// Read from the `Rating` object property of MTP devices, [`None`] if the camera doesn't
+  /// expose a rating for the file.
+  pub fn rating(&self, folder: impl IntoCameraPath, file: &str) -> Task<Result<Option<u8>>> {
+    let (folder, file) = (folder.into_camera_path(), file.to_owned());
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        let metadata = get_metadata(*camera, &folder?, &file, *context)?;
+
+        Ok(
+          metadata
+            .as_deref()
+            .and_then(|xml| metadata::property(xml, "Rating"))
+            .and_then(|rating| rating.parse().ok())
+            .map(metadata::rating_to_stars),
+        )
+      })
+    }
+    .camera(self.camera)
+  }
+
+  /// Write the rating of a file in stars (0 to 5) to the camera
+  ///
+  /// Only supported by cameras exposing the `Rating` object property of MTP, see
+  /// [`rating`](Self::rating).
+  pub fn set_rating(&self, folder: impl IntoCameraPath, file: &str, stars: u8) -> Task<Result<()>> {
+    let (folder, file) = (folder.into_camera_path(), file.to_owned());
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        let folder = folder?;
+
+        let rating = metadata::stars_to_rating(stars).ok_or_else(|| {
+          Error::new(
+            libgphoto2_sys::GP_ERROR_BAD_PARAMETERS,
+            Some(format!("{stars} is not a rating from 0 to 5 stars")),
+          )
+        })?;
+
+        let metadata = get_metadata(*camera, &folder, &file, *context)?
+          .and_then(|xml| metadata::set_property(&xml, "Rating", &rating.to_string()))
+          .ok_or_else(|| {
+            Error::new(
+              libgphoto2_sys::GP_ERROR_NOT_SUPPORTED,
+              Some(format!("The camera has no rating for {file}")),
+            )
+          })?;
+
+        put_camera_file(*camera, &folder, &file, FileType::Metadata, metadata.as_bytes(), *context)
+      })
+    }
+    .camera(self.camera)
+  }
+
+  /// Read the [`Orientation`] of a file from its EXIF block, without downloading the file
+  ///
+  /// Returns [`None`] if the camera doesn't provide EXIF blocks or the block has no
+  /// orientation.
+  pub fn orientation(
+    &self,
+    folder: impl IntoCameraPath,
+    file: &str,
+  ) -> Task<Result<Option<Orientation>>> {
+    let (folder, file) = (folder.into_camera_path(), file.to_owned());
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        match get_camera_file(*camera, &folder?, &file, FileType::Exif, None, *context) {
+          Ok(exif) => Ok(Orientation::detect(&exif.read_data()?)),
+          Err(error) if error.kind() == ErrorKind::NotSupported => Ok(None),
+          Err(error) => Err(error),
+        }
+      })
+    }
+    .camera(self.camera)
+  }
+
   /// Upload a file to the camera
   #[allow(clippy::boxed_local)]
-  pub fn upload_file(&self, folder: &str, filename: &str, data: Box<[u8]>) -> Task<Result<()>> {
+  pub fn upload_file(
+    &self,
+    folder: impl IntoCameraPath,
+    filename: &str,
+    data: Box<[u8]>,
+  ) -> Task<Result<()>> {
     let camera = self.camera.camera;
-    let context = self.camera.context.inner;
+    let context = self.camera.context.inner();
 
-    let (folder, filename) = (folder.to_owned(), filename.to_owned());
+    let (folder, filename) = (folder.into_camera_path(), filename.to_owned());
 
     unsafe {
       Task::new(move || {
-        try_gp_internal!(gp_file_new(&out file)?);
-        try_gp_internal!(gp_file_append(file, data.as_ptr().cast(), data.len().try_into()?)?);
-        try_gp_internal!(gp_camera_folder_put_file(
-          *camera,
-          to_c_string!(folder),
-          to_c_string!(filename),
-          FileType::Normal.into(),
-          file,
-          *context
-        )?);
+        put_camera_file(*camera, &folder?, &filename, FileType::Normal, &data, *context)
+      })
+    }
+    .camera(self.camera)
+    .metric(Operation::Upload)
+  }
 
-        Ok(())
+  /// Upload a file to the camera, reading the data from `reader`
+  ///
+  /// The data is appended to the file in chunks on the libgphoto2 thread, so it is never
+  /// collected in a separate buffer. If `size_hint` is set (like the `Content-Length` of a
+  /// download), a stream of a different size is not uploaded.
+  pub fn upload_from_reader<R>(
+    &self,
+    folder: impl IntoCameraPath,
+    filename: &str,
+    reader: R,
+    size_hint: Option<u64>,
+  ) -> Task<Result<()>>
+  where
+    R: Read + Send + 'static,
+  {
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    let (folder, filename) = (folder.into_camera_path(), filename.to_owned());
+
+    unsafe {
+      Task::new(move || {
+        put_file_from_reader(*camera, &folder?, &filename, reader, size_hint, |_| {}, *context)
       })
     }
-    .context(context)
+    .camera(self.camera)
+    .metric(Operation::Upload)
   }
 
   /// Delete all files in a folder
-  pub fn delete_all_in_folder(&self, folder: &str) -> Task<Result<()>> {
+  pub fn delete_all_in_folder(&self, folder: impl IntoCameraPath) -> Task<Result<()>> {
     let camera = self.camera.camera;
-    let context = self.camera.context.inner;
-    let folder = folder.to_owned();
+    let context = self.camera.context.inner();
+    let folder = folder.into_camera_path();
 
     unsafe {
       Task::new(move || {
-        try_gp_internal!(gp_camera_folder_delete_all(*camera, to_c_string!(folder), *context)?);
+        let folder = folder?;
+
+        try_gp_internal!(gp_camera_folder_delete_all(
+          *camera,
+          to_c_string!(folder.as_str()),
+          *context
+        )?);
         Ok(())
       })
     }
-    .context(context)
+    .camera(self.camera)
   }
 
   /// List files in a folder
-  pub fn list_files(&self, folder: &str) -> Task<Result<FileListIter>> {
+  pub fn list_files(&self, folder: impl IntoCameraPath) -> Task<Result<FileListIter>> {
+    self.list_files_ordered(folder, ListOrder::Driver)
+  }
+
+  /// List files in a folder in the given order
+  pub fn list_files_ordered(
+    &self,
+    folder: impl IntoCameraPath,
+    order: ListOrder,
+  ) -> Task<Result<FileListIter>> {
+    self.list(folder, ListKind::Files, order)
+  }
+
+  /// List the files in a folder matching a filter
+  ///
+  /// The file information is only requested if the filter needs it, all on the libgphoto2 thread.
+  pub fn list_files_filtered(
+    &self,
+    folder: impl IntoCameraPath,
+    filter: FileFilter,
+  ) -> Task<Result<Vec<String>>> {
     let camera = self.camera.camera;
-    let context = self.camera.context.inner;
+    let context = self.camera.context.inner();
 
-    let folder = folder.to_owned();
+    let folder = folder.into_camera_path();
 
     unsafe {
       Task::new(move || {
-        let file_list = CameraList::new()?;
+        let folder = folder?;
 
-        try_gp_internal!(gp_camera_folder_list_files(
-          *camera,
-          to_c_string!(folder),
-          *file_list.inner,
-          *context
-        )?);
+        let mut files = Vec::new();
+
+        let list = list_folder_entries(*camera, &folder, ListKind::Files, *context)?;
+
+        for file in FileListIter::new(list) {
+          if !filter.matches_name(&file) {
+            continue;
+          }
 
-        Ok(FileListIter::new(file_list))
+          check_cancelled(*context)?;
+          if filter.needs_info()
+            && !filter.matches_info(get_file_info(*camera, &folder, &file, *context)?.file())
+          {
+            continue;
+          }
+
+          files.push(file);
+        }
+
+        Ok(files)
       })
     }
-    .context(context)
+    .camera(self.camera)
+  }
+
+  /// List the files in a folder together with their information
+  ///
+  /// Unlike calling [`CameraFS::file_info`] for each file, all information is requested in a
+  /// single task.
+  pub fn list_files_with_info(
+    &self,
+    folder: impl IntoCameraPath,
+  ) -> Task<Result<Vec<(String, FileInfo)>>> {
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    let folder = folder.into_camera_path();
+
+    unsafe {
+      Task::new(move || {
+        let folder = folder?;
+
+        let list = list_folder_entries(*camera, &folder, ListKind::Files, *context)?;
+
+        FileListIter::new(list)
+          .map(|file| {
+            let info = get_file_info(*camera, &folder, &file, *context)?;
+            Ok((file, info))
+          })
+          .collect()
+      })
+    }
+    .camera(self.camera)
+  }
+
+  /// Count the files in a folder, without transferring their names to Rust
+  pub fn count_files(&self, folder: impl IntoCameraPath) -> Task<Result<usize>> {
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    let folder = folder.into_camera_path();
+
+    unsafe {
+      Task::new(move || {
+        Ok(list_folder_entries(*camera, &folder?, ListKind::Files, *context)?.len())
+      })
+    }
+    .camera(self.camera)
   }
 
   /// List folders in a folder
-  pub fn list_folders(&self, folder: &str) -> Task<Result<FileListIter>> {
+  pub fn list_folders(&self, folder: impl IntoCameraPath) -> Task<Result<FileListIter>> {
+    self.list_folders_ordered(folder, ListOrder::Driver)
+  }
+
+  /// List folders in a folder in the given order
+  pub fn list_folders_ordered(
+    &self,
+    folder: impl IntoCameraPath,
+    order: ListOrder,
+  ) -> Task<Result<FileListIter>> {
+    self.list(folder, ListKind::Folders, order)
+  }
+
+  fn list(
+    &self,
+    folder: impl IntoCameraPath,
+    kind: ListKind,
+    order: ListOrder,
+  ) -> Task<Result<FileListIter>> {
     let camera = self.camera.camera;
-    let context = self.camera.context.inner;
+    let context = self.camera.context.inner();
 
-    let folder = folder.to_owned();
+    let folder = folder.into_camera_path();
 
     unsafe {
       Task::new(move || {
-        let folder_list = CameraList::new()?;
+        let folder = folder?;
 
-        try_gp_internal!(gp_camera_folder_list_folders(
-          *camera,
-          to_c_string!(folder),
-          *folder_list.inner,
-          *context
-        )?);
+        let list = list_folder_entries(*camera, &folder, kind, *context)?;
 
-        Ok(FileListIter::new(folder_list))
+        let list = match order {
+          ListOrder::Driver => list,
+          ListOrder::Ascending => {
+            list.sort()?;
+            list
+          }
+          ListOrder::Descending => {
+            list.sort()?;
+            list.reversed()?
+          }
+        };
+
+        Ok(FileListIter::new(list))
       })
     }
-    .context(context)
+    .camera(self.camera)
   }
 
   /// Creates a new folder
-  pub fn create_directory(&self, parent_folder: &str, new_folder: &str) -> Task<Result<()>> {
-    let (parent_folder, new_folder) = (parent_folder.to_owned(), new_folder.to_owned());
+  pub fn create_directory(
+    &self,
+    parent_folder: impl IntoCameraPath,
+    new_folder: &str,
+  ) -> Task<Result<()>> {
+    let (parent_folder, new_folder) = (parent_folder.into_camera_path(), new_folder.to_owned());
     let camera = self.camera.camera;
-    let context = self.camera.context.inner;
+    let context = self.camera.context.inner();
 
     unsafe {
       Task::new(move || {
+        let parent_folder = parent_folder?;
+
         try_gp_internal!(gp_camera_folder_make_dir(
           *camera,
-          to_c_string!(parent_folder),
+          to_c_string!(parent_folder.as_str()),
           to_c_string!(new_folder),
           *context
         )?);
@@ -439,20 +1172,22 @@ impl<'a> CameraFS<'a> {
         Ok(())
       })
     }
-    .context(context)
+    .camera(self.camera)
   }
 
   /// Removes a folder
-  pub fn remove_directory(&self, parent: &str, to_remove: &str) -> Task<Result<()>> {
-    let (parent, to_remove) = (parent.to_owned(), to_remove.to_owned());
+  pub fn remove_directory(&self, parent: impl IntoCameraPath, to_remove: &str) -> Task<Result<()>> {
+    let (parent, to_remove) = (parent.into_camera_path(), to_remove.to_owned());
     let camera = self.camera.camera;
-    let context = self.camera.context.inner;
+    let context = self.camera.context.inner();
 
     unsafe {
       Task::new(move || {
+        let parent = parent?;
+
         try_gp_internal!(gp_camera_folder_remove_dir(
           *camera,
-          to_c_string!(parent),
+          to_c_string!(parent.as_str()),
           to_c_string!(to_remove),
           *context
         )?);
@@ -460,7 +1195,7 @@ impl<'a> CameraFS<'a> {
         Ok(())
       })
     }
-    .context(context)
+    .camera(self.camera)
   }
 }
 
@@ -468,43 +1203,108 @@ impl<'a> CameraFS<'a> {
 impl CameraFS<'_> {
   fn to_camera_file(
     &self,
-    folder: &str,
+    folder: impl IntoCameraPath,
     file: &str,
     type_: FileType,
     path: Option<&Path>,
   ) -> Task<Result<CameraFile>> {
-    let (folder, file, path) = (folder.to_owned(), file.to_owned(), path.map(ToOwned::to_owned));
+    let (folder, file, path) =
+      (folder.into_camera_path(), file.to_owned(), path.map(ToOwned::to_owned));
     let camera = self.camera.camera;
-    let context = self.camera.context.inner;
+    let context = self.camera.context.inner();
 
     unsafe {
-      Task::new(move || {
-        let camera_file = match &path {
-          Some(dest_path) => CameraFile::new_file(dest_path)?,
-          None => CameraFile::new()?,
-        };
+      Task::new(move || get_camera_file(*camera, &folder?, &file, type_, path.as_deref(), *context))
+    }
+    .camera(self.camera)
+    .metric(Operation::Download)
+  }
+}
 
-        try_gp_internal!(gp_camera_file_get(
-          *camera,
-          to_c_string!(folder),
-          to_c_string!(file),
-          type_.into(),
-          *camera_file.inner,
-          *context
-        )
-        .map_err(|e| {
-          if let Some(path) = path {
-            if let Err(error) = fs::remove_file(path) {
-              return Into::<Error>::into(error);
-            }
-          }
+/// Folder relative to the base directory of a storage
+struct StorageFolder {
+  base: CameraPath,
+  relative: String,
+}
 
-          e
-        })?);
+impl IntoCameraPath for StorageFolder {
+  fn into_camera_path(self) -> Result<CameraPath> {
+    let path = self.base.join(self.relative.trim_start_matches('/'))?;
 
-        Ok(camera_file)
-      })
+    if !path.components().take(self.base.components().count()).eq(self.base.components()) {
+      return Err(Error::new(
+        libgphoto2_sys::GP_ERROR_BAD_PARAMETERS,
+        Some(format!("{} is outside of the storage {}", self.relative, self.base)),
+      ));
     }
-    .context(context)
+
+    Ok(path)
+  }
+}
+
+impl StorageInfo {
+  /// Filesystem actions on this storage, with folders relative to its base directory
+  pub fn fs<'a>(&self, camera: &'a Camera) -> Result<StorageFS<'a>> {
+    let base = self.base_directory().ok_or("The storage has no base directory")?;
+
+    Ok(StorageFS { fs: camera.fs(), base: CameraPath::new(&base)? })
+  }
+}
+
+/// File system actions limited to one storage, see [`StorageInfo::fs`]
+///
+/// Folders are relative to the base directory of the storage, `""` is the base directory
+/// itself. Paths leaving the storage with `..` are rejected.
+///
+/// Transfers from different storages still run one after another: libgphoto2 talks to a
+/// camera over a single PTP session, which handles one operation at a time.
+pub struct StorageFS<'a> {
+  fs: CameraFS<'a>,
+  base: CameraPath,
+}
+
+impl StorageFS<'_> {
+  /// Base directory of the storage
+  pub fn base_directory(&self) -> &CameraPath {
+    &self.base
+  }
+
+  fn folder(&self, relative: &str) -> StorageFolder {
+    StorageFolder { base: self.base.clone(), relative: relative.to_owned() }
+  }
+
+  /// Names of the files in a folder, see [`CameraFS::list_files`]
+  pub fn list_files(&self, folder: &str) -> Task<Result<FileListIter>> {
+    self.fs.list_files(self.folder(folder))
+  }
+
+  /// Names of the subfolders of a folder, see [`CameraFS::list_folders`]
+  pub fn list_folders(&self, folder: &str) -> Task<Result<FileListIter>> {
+    self.fs.list_folders(self.folder(folder))
+  }
+
+  /// Get information of a file, see [`CameraFS::file_info`]
+  pub fn file_info(&self, folder: &str, file: &str) -> Task<Result<FileInfo>> {
+    self.fs.file_info(self.folder(folder), file)
+  }
+
+  /// Download a file to memory, see [`CameraFS::download`]
+  pub fn download(&self, folder: &str, file: &str) -> Task<Result<CameraFile>> {
+    self.fs.download(self.folder(folder), file)
+  }
+
+  /// Download a file to `path`, see [`CameraFS::download_to`]
+  pub fn download_to(&self, folder: &str, file: &str, path: &Path) -> Task<Result<CameraFile>> {
+    self.fs.download_to(self.folder(folder), file, path)
+  }
+
+  /// Upload a file, see [`CameraFS::upload_file`]
+  pub fn upload_file(&self, folder: &str, filename: &str, data: Box<[u8]>) -> Task<Result<()>> {
+    self.fs.upload_file(self.folder(folder), filename, data)
+  }
+
+  /// Delete a file, see [`CameraFS::delete_file`]
+  pub fn delete_file(&self, folder: &str, file: &str) -> Task<Result<()>> {
+    self.fs.delete_file(self.folder(folder), file)
   }
 }
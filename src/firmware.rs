@@ -0,0 +1,230 @@
+//! Firmware updates, see [`Camera::upload_firmware`](crate::Camera::upload_firmware)
+//!
+//! Cameras which can be updated over USB expect the firmware file in the root folder of a
+//! storage, some additionally need a configuration to start the update. Interrupting an update
+//! can brick the camera, so nothing is uploaded unless the confirmation callback agrees.
+//!
+//! ```no_run
+//! use gphoto2::{firmware::FirmwareOptions, Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//!
+//! let options = FirmwareOptions::new(|info| {
+//!   println!("Flashing {} ({} bytes) to the {}", info.file_name, info.size, info.model);
+//!   info.model.starts_with("Canon")
+//! })
+//! .on_progress(|sent, total| println!("{sent}/{total} bytes"));
+//!
+//! let report = camera.upload_firmware("CCF21102.FIR", options).wait()?;
+//! println!("Camera disconnected: {}", report.disconnected);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+  camera::{
+    camera_abilities, camera_storages, get_single_config, set_single_config, wait_for_event,
+    CameraEvent,
+  },
+  error::ErrorKind,
+  filesys::put_file_from_reader,
+  ops::ConfigValue,
+  path::CameraPath,
+  Error, Result,
+};
+use std::{
+  fmt, fs,
+  path::Path,
+  time::{Duration, Instant},
+};
+
+/// Default time to wait for events after the upload
+const DEFAULT_EVENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+type ConfirmFn = dyn FnOnce(&FirmwareInfo) -> bool + Send;
+type ProgressFn = dyn FnMut(u64, u64) + Send;
+
+/// Firmware file about to be uploaded, passed to the confirmation callback
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareInfo {
+  /// Model of the camera
+  pub model: String,
+  /// Name of the file on the camera
+  pub file_name: String,
+  /// Size of the file in bytes
+  pub size: u64,
+  /// Folder the file is uploaded to
+  pub folder: CameraPath,
+}
+
+/// Result of a firmware upload
+#[derive(Debug)]
+pub struct FirmwareReport {
+  /// Folder the file was uploaded to
+  pub folder: CameraPath,
+  /// Events reported by the camera after the upload
+  pub events: Vec<CameraEvent>,
+  /// Whether the camera stopped responding while waiting for events, which is expected
+  /// for cameras rebooting into the update
+  pub disconnected: bool,
+}
+
+/// Settings of [`Camera::upload_firmware`](crate::Camera::upload_firmware)
+pub struct FirmwareOptions {
+  confirm: Box<ConfirmFn>,
+  progress: Option<Box<ProgressFn>>,
+  folder: Option<CameraPath>,
+  file_name: Option<String>,
+  trigger: Option<(String, ConfigValue)>,
+  event_timeout: Duration,
+}
+
+impl FirmwareOptions {
+  /// Upload the firmware if `confirm` returns true
+  ///
+  /// `confirm` runs on the libgphoto2 thread right before the upload, it must not wait for
+  /// other tasks.
+  pub fn new(confirm: impl FnOnce(&FirmwareInfo) -> bool + Send + 'static) -> Self {
+    Self {
+      confirm: Box::new(confirm),
+      progress: None,
+      folder: None,
+      file_name: None,
+      trigger: None,
+      event_timeout: DEFAULT_EVENT_TIMEOUT,
+    }
+  }
+
+  /// Called with the number of bytes read and the file size while the file is uploaded
+  pub fn on_progress(mut self, progress: impl FnMut(u64, u64) + Send + 'static) -> Self {
+    self.progress = Some(Box::new(progress));
+    self
+  }
+
+  /// Upload to this folder instead of the root folder of the first storage
+  pub fn folder(mut self, folder: CameraPath) -> Self {
+    self.folder = Some(folder);
+    self
+  }
+
+  /// Name of the file on the camera, defaults to the name of the local file
+  pub fn file_name(mut self, file_name: &str) -> Self {
+    self.file_name = Some(file_name.to_owned());
+    self
+  }
+
+  /// Set a configuration after the upload to start the update
+  pub fn trigger(mut self, key: &str, value: ConfigValue) -> Self {
+    self.trigger = Some((key.to_owned(), value));
+    self
+  }
+
+  /// How long to wait for events after the upload, defaults to 30 seconds
+  pub fn event_timeout(mut self, timeout: Duration) -> Self {
+    self.event_timeout = timeout;
+    self
+  }
+}
+
+impl fmt::Debug for FirmwareOptions {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("FirmwareOptions")
+      .field("folder", &self.folder)
+      .field("file_name", &self.file_name)
+      .field("trigger", &self.trigger)
+      .field("event_timeout", &self.event_timeout)
+      .finish_non_exhaustive()
+  }
+}
+
+/// Root folder of the first storage
+unsafe fn default_folder(
+  camera: *mut libgphoto2_sys::Camera,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<CameraPath> {
+  let storages = camera_storages(camera, context)?;
+  let base_directory = storages
+    .iter()
+    .find_map(|storage| storage.base_directory())
+    .ok_or("The camera has no storage for the firmware")?;
+
+  CameraPath::new(&base_directory)
+}
+
+pub(crate) unsafe fn upload(
+  camera: *mut libgphoto2_sys::Camera,
+  path: &Path,
+  options: FirmwareOptions,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<FirmwareReport> {
+  let FirmwareOptions { confirm, mut progress, folder, file_name, trigger, event_timeout } =
+    options;
+
+  let file = fs::File::open(path)?;
+  let size = file.metadata()?.len();
+  let file_name = match file_name {
+    Some(file_name) => file_name,
+    None => path
+      .file_name()
+      .and_then(|name| name.to_str())
+      .ok_or_else(|| Error::from(format!("{} has no valid file name", path.display())))?
+      .to_owned(),
+  };
+  let folder = match folder {
+    Some(folder) => folder,
+    None => default_folder(camera, context)?,
+  };
+
+  let info =
+    FirmwareInfo { model: camera_abilities(camera)?.model().into_owned(), file_name, size, folder };
+
+  if !confirm(&info) {
+    return Err(Error::new(
+      libgphoto2_sys::GP_ERROR_CANCEL,
+      Some("The firmware update was not confirmed".into()),
+    ));
+  }
+
+  put_file_from_reader(
+    camera,
+    &info.folder,
+    &info.file_name,
+    file,
+    Some(size),
+    |sent| {
+      if let Some(progress) = &mut progress {
+        progress(sent, size);
+      }
+    },
+    context,
+  )?;
+
+  if let Some((key, value)) = trigger {
+    let widget = get_single_config(camera, &key, context)?;
+    value.apply_to(&widget)?;
+    set_single_config(camera, &widget, context)?;
+  }
+
+  let deadline = Instant::now().checked_add(event_timeout).ok_or("Timeout is too large")?;
+  let mut events = Vec::new();
+  let mut disconnected = false;
+
+  loop {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+
+    match wait_for_event(camera, remaining, context) {
+      Ok(CameraEvent::Timeout) if remaining.is_zero() => break,
+      Ok(CameraEvent::Timeout) => {}
+      Ok(event) => events.push(event),
+      Err(error) if error.kind() == ErrorKind::Cancelled => return Err(error),
+      Err(error) => {
+        log::info!("The camera stopped responding after the firmware upload: {error}");
+        disconnected = true;
+        break;
+      }
+    }
+  }
+
+  Ok(FirmwareReport { folder: info.folder, events, disconnected })
+}
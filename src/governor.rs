@@ -0,0 +1,246 @@
+//! Backing off from busy cameras, see [`Camera::set_busy_governor`](crate::Camera::set_busy_governor)
+//!
+//! Some cameras (Canon bodies in particular) report [`ErrorKind::CameraBusy`] while they process
+//! a shot and can get stuck if requests keep arriving. With a governor, operations of a camera
+//! wait for a growing backoff after a busy response, and duplicate requests which are still
+//! queued (like several [`Camera::config`](crate::Camera::config) calls) run only once.
+//!
+//! Waiting operations are put aside by the libgphoto2 thread, operations of other cameras run
+//! in the meantime.
+//!
+//! ```no_run
+//! use gphoto2::{governor::BusyGovernor, Context, Result};
+//! use std::time::Duration;
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//!
+//! camera.set_busy_governor(Some(BusyGovernor::new().max_backoff(Duration::from_secs(5))));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{error::ErrorKind, Result};
+use std::{
+  any::Any,
+  collections::HashMap,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, OnceLock, PoisonError,
+  },
+  time::{Duration, Instant},
+};
+
+/// Settings of the busy governor of a camera
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BusyGovernor {
+  initial_backoff: Duration,
+  max_backoff: Duration,
+  coalesce: bool,
+}
+
+impl Default for BusyGovernor {
+  fn default() -> Self {
+    Self {
+      initial_backoff: Duration::from_millis(100),
+      max_backoff: Duration::from_secs(2),
+      coalesce: true,
+    }
+  }
+}
+
+impl BusyGovernor {
+  /// Governor with a backoff from 100 ms to 2 s which merges duplicate requests
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Backoff after the first busy response, doubled on each further one
+  pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+    self.initial_backoff = backoff;
+    self
+  }
+
+  /// Longest backoff
+  pub fn max_backoff(mut self, backoff: Duration) -> Self {
+    self.max_backoff = backoff;
+    self
+  }
+
+  /// Whether duplicate requests which didn't start yet share the result of the first one
+  ///
+  /// Merged [`Camera::config`](crate::Camera::config) calls return the same configuration tree,
+  /// so changes to it are visible to all callers.
+  pub fn coalesce(mut self, coalesce: bool) -> Self {
+    self.coalesce = coalesce;
+    self
+  }
+}
+
+struct Backoff {
+  until: Option<Instant>,
+  next: Duration,
+}
+
+/// Result shared by merged requests
+struct Slot<T> {
+  started: AtomicBool,
+  result: OnceLock<Result<T>>,
+}
+
+/// Governor of a camera, shared by its tasks
+pub(crate) struct GovernorState {
+  settings: BusyGovernor,
+  backoff: Mutex<Backoff>,
+  pending: Mutex<HashMap<&'static str, Arc<dyn Any + Send + Sync>>>,
+}
+
+impl GovernorState {
+  pub(crate) fn new(settings: BusyGovernor) -> Self {
+    let next = settings.initial_backoff;
+
+    Self {
+      settings,
+      backoff: Mutex::new(Backoff { until: None, next }),
+      pending: Default::default(),
+    }
+  }
+
+  /// End of the backoff of a previous busy response, [`None`] if operations can run now
+  pub(crate) fn backoff_until(&self) -> Option<Instant> {
+    let until = self.backoff.lock().unwrap_or_else(PoisonError::into_inner).until;
+
+    until.filter(|&until| until > Instant::now())
+  }
+
+  /// Run an operation on the worker and start a backoff if the camera was busy
+  ///
+  /// The worker waits for [`GovernorState::backoff_until`] before running the operation.
+  pub(crate) fn run<T>(&self, fun: impl FnOnce() -> Result<T>) -> Result<T> {
+    let result = fun();
+
+    let mut backoff = self.backoff.lock().unwrap_or_else(PoisonError::into_inner);
+    match &result {
+      Err(error) if error.kind() == ErrorKind::CameraBusy => {
+        log::debug!("The camera is busy, backing off for {:?}", backoff.next);
+        backoff.until = Instant::now().checked_add(backoff.next);
+        backoff.next = (backoff.next * 2).min(self.settings.max_backoff);
+      }
+      _ => *backoff = Backoff { until: None, next: self.settings.initial_backoff },
+    }
+
+    result
+  }
+
+  /// Merge `fun` with a queued request of the same kind, must be called when creating the task
+  pub(crate) fn coalesce<T>(
+    &self,
+    key: &'static str,
+    fun: impl FnOnce() -> Result<T> + Send + 'static,
+  ) -> impl FnOnce() -> Result<T> + Send + 'static
+  where
+    T: Clone + Send + Sync + 'static,
+  {
+    let slot = if self.settings.coalesce {
+      let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+      let queued = pending
+        .get(key)
+        .and_then(|slot| slot.clone().downcast::<Slot<T>>().ok())
+        .filter(|slot| !slot.started.load(Ordering::Acquire));
+
+      queued.unwrap_or_else(|| {
+        let slot = Arc::new(Slot { started: AtomicBool::new(false), result: OnceLock::new() });
+        pending.insert(key, slot.clone());
+        slot
+      })
+    } else {
+      Arc::new(Slot { started: AtomicBool::new(false), result: OnceLock::new() })
+    };
+
+    move || {
+      // Requests created from now on run again, since the result may have changed
+      slot.started.store(true, Ordering::Release);
+      slot.result.get_or_init(fun).clone()
+    }
+  }
+}
+
+#[cfg(all(test, feature = "test"))]
+mod tests {
+  use super::*;
+  use crate::Error;
+
+  fn busy() -> Result<()> {
+    Err(Error::new(libgphoto2_sys::GP_ERROR_CAMERA_BUSY, None))
+  }
+
+  fn next_backoff(state: &GovernorState) -> Duration {
+    state.backoff.lock().unwrap().next
+  }
+
+  #[test]
+  fn test_backoff() {
+    let state = GovernorState::new(
+      BusyGovernor::new()
+        .initial_backoff(Duration::from_millis(100))
+        .max_backoff(Duration::from_millis(350)),
+    );
+    assert_eq!(state.backoff_until(), None);
+
+    let start = Instant::now();
+    state.run(busy).unwrap_err();
+    let until = state.backoff_until().unwrap();
+    assert!(until >= start + Duration::from_millis(100));
+    assert!(until <= Instant::now() + Duration::from_millis(100));
+
+    // Doubled on each further busy response, up to the maximum
+    assert_eq!(next_backoff(&state), Duration::from_millis(200));
+    state.run(busy).unwrap_err();
+    assert_eq!(next_backoff(&state), Duration::from_millis(350));
+    state.run(busy).unwrap_err();
+    assert_eq!(next_backoff(&state), Duration::from_millis(350));
+
+    // Other errors and successes reset the backoff
+    state.run(|| Err::<(), _>(Error::new(libgphoto2_sys::GP_ERROR_IO, None))).unwrap_err();
+    assert_eq!(state.backoff_until(), None);
+    assert_eq!(next_backoff(&state), Duration::from_millis(100));
+
+    state.run(busy).unwrap_err();
+    state.run(busy).unwrap_err();
+    state.run(|| Ok(())).unwrap();
+    assert_eq!(state.backoff_until(), None);
+    assert_eq!(next_backoff(&state), Duration::from_millis(100));
+  }
+
+  #[test]
+  fn test_coalesce() {
+    let state = GovernorState::new(BusyGovernor::new());
+    let runs = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let request = |value: u32| {
+      let runs = runs.clone();
+      state.coalesce("key", move || {
+        runs.fetch_add(1, Ordering::Relaxed);
+        Ok(value)
+      })
+    };
+
+    // Queued requests share the result of the first one which runs
+    let (first, second) = (request(1), request(2));
+    assert_eq!(second().unwrap(), 2);
+    assert_eq!(first().unwrap(), 2);
+    assert_eq!(runs.load(Ordering::Relaxed), 1);
+
+    // A started request is never reused, later ones run again
+    let third = request(3);
+    assert_eq!(third().unwrap(), 3);
+    assert_eq!(runs.load(Ordering::Relaxed), 2);
+
+    // Different keys and disabled coalescing always run
+    let other = state.coalesce("other", || Ok(4));
+    assert_eq!((request(5)().unwrap(), other().unwrap()), (5, 4));
+
+    let state = GovernorState::new(BusyGovernor::new().coalesce(false));
+    let (first, second) = (state.coalesce("key", || Ok(1)), state.coalesce("key", || Ok(2)));
+    assert_eq!((first().unwrap(), second().unwrap()), (1, 2));
+  }
+}
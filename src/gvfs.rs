@@ -0,0 +1,174 @@
+//! Releasing cameras mounted by gvfs, see [`CameraBuilder::release_gvfs`](crate::camera::CameraBuilder::release_gvfs)
+//!
+//! GNOME and other Linux desktops mount cameras as soon as they are plugged in, the gvfs
+//! process then holds the USB device and opening the camera fails with
+//! [`ErrorKind::IoUsbClaim`]. The mount can be found and removed through the gvfs D-Bus
+//! interface, which is what `gio mount -u` does.
+//!
+//! ```no_run
+//! use gphoto2::{Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let context = Context::new()?;
+//! let descriptor = context.list_cameras().wait()?.next().ok_or("No camera found")?;
+//!
+//! if let Some(mount) = gphoto2::gvfs::find_mount(&descriptor.port)? {
+//!   println!("Unmounting {}", mount.display_name);
+//!   mount.unmount()?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{error::ErrorKind, Error, Result};
+use std::{collections::HashMap, thread, time::Duration};
+use zbus::{
+  blocking::Connection,
+  zvariant::{OwnedObjectPath, OwnedValue, Value},
+};
+
+/// How often claiming the camera is tried after unmounting it
+const CLAIM_ATTEMPTS: u32 = 5;
+/// Time gvfs gets to release the device between the attempts
+const CLAIM_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// `(dbus_id, obj_path, display_name, stable_name, x_content_types, icon, symbolic_icon,
+/// preferred_filename_encoding, user_visible, fuse_mountpoint, mount_spec, default_location)`
+type MountInfo = (
+  String,
+  OwnedObjectPath,
+  String,
+  String,
+  String,
+  String,
+  String,
+  String,
+  bool,
+  Vec<u8>,
+  (Vec<u8>, HashMap<String, OwnedValue>),
+  Vec<u8>,
+);
+
+/// Callback deciding whether a mount may be removed
+pub(crate) type ConfirmUnmount = dyn Fn(&GvfsMount) -> bool + Send;
+
+/// Camera mounted by gvfs
+#[derive(Debug, Clone)]
+pub struct GvfsMount {
+  /// Name shown by file managers
+  pub display_name: String,
+  /// Port of the camera, like `usb:001,004`
+  pub port: String,
+  dbus_id: String,
+  obj_path: OwnedObjectPath,
+}
+
+fn dbus_error(error: zbus::Error) -> Error {
+  Error::from(format!("Could not talk to gvfs: {error}"))
+}
+
+/// Mount spec values are NUL terminated byte strings
+fn spec_value(spec: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+  let Value::Array(array) = &**spec.get(key)? else {
+    return None;
+  };
+  let bytes: Vec<u8> = array
+    .iter()
+    .map_while(|value| match value {
+      Value::U8(0) => None,
+      Value::U8(byte) => Some(*byte),
+      _ => None,
+    })
+    .collect();
+
+  String::from_utf8(bytes).ok()
+}
+
+/// Find the gvfs mount of the camera at `port`
+///
+/// Returns [`None`] if the camera is not mounted or gvfs is not running.
+pub fn find_mount(port: &str) -> Result<Option<GvfsMount>> {
+  let connection = Connection::session().map_err(dbus_error)?;
+
+  let reply = match connection.call_method(
+    Some("org.gtk.vfs.Daemon"),
+    "/org/gtk/vfs/mounttracker",
+    Some("org.gtk.vfs.MountTracker"),
+    "ListMounts2",
+    &(false,),
+  ) {
+    Ok(reply) => reply,
+    // gvfs is not installed or not running
+    Err(zbus::Error::MethodError(..)) => return Ok(None),
+    Err(error) => return Err(dbus_error(error)),
+  };
+  let mounts: Vec<MountInfo> = reply.body().deserialize().map_err(dbus_error)?;
+
+  // gvfs stores the port as host, e.g. `gphoto2://[usb:001,004]/`
+  let host = format!("[{port}]");
+
+  Ok(mounts.into_iter().find_map(|mount| {
+    let (dbus_id, obj_path, display_name, .., (_, spec), _) = mount;
+
+    (spec_value(&spec, "type")? == "gphoto2" && spec_value(&spec, "host")? == host)
+      .then(|| GvfsMount { display_name, port: port.to_owned(), dbus_id, obj_path })
+  }))
+}
+
+impl GvfsMount {
+  /// Unmount the camera like `gio mount -u`, which lets gvfs release the device
+  pub fn unmount(&self) -> Result<()> {
+    let connection = Connection::session().map_err(dbus_error)?;
+
+    // gvfs uses this path if there is no mount operation to ask the user
+    let no_mount_operation = ("", OwnedObjectPath::try_from("/org/gtk/vfs/void").unwrap(), 0u32);
+
+    connection
+      .call_method(
+        Some(self.dbus_id.as_str()),
+        &self.obj_path,
+        Some("org.gtk.vfs.Mount"),
+        "Unmount",
+        &no_mount_operation,
+      )
+      .map_err(dbus_error)?;
+
+    Ok(())
+  }
+}
+
+/// Connect to the camera, unmounting it first if gvfs holds it and `confirm` agrees
+///
+/// Must be called from a [`Task`](crate::task::Task).
+pub(crate) unsafe fn init_camera(
+  camera: *mut libgphoto2_sys::Camera,
+  port: &str,
+  confirm: &ConfirmUnmount,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<()> {
+  let released = match find_mount(port) {
+    Ok(Some(mount)) if confirm(&mount) => {
+      mount.unmount()?;
+      true
+    }
+    Ok(_) => false,
+    Err(error) => {
+      log::warn!("Could not look up gvfs mounts: {error}");
+      false
+    }
+  };
+
+  let mut attempt = 1;
+
+  loop {
+    match Error::check(libgphoto2_sys::gp_camera_init(camera, context)) {
+      Err(error)
+        if released && error.kind() == ErrorKind::IoUsbClaim && attempt < CLAIM_ATTEMPTS =>
+      {
+        attempt += 1;
+        thread::sleep(CLAIM_RETRY_DELAY);
+      }
+      result => return result.map(|_| ()),
+    }
+  }
+}
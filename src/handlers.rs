@@ -0,0 +1,219 @@
+//! Ready-made progress handlers
+//!
+//! The handlers are attached to a task with [`Task::with_progress_handler`]. With the
+//! `indicatif` feature, `indicatif()` renders the progress as terminal bars.
+//!
+//! ```no_run
+//! use gphoto2::{handlers, Context, Result};
+//! use std::{io, path::Path};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//!
+//! camera
+//!   .fs()
+//!   .download_to("/store_00010001/DCIM/100CANON", "IMG_0001.JPG", Path::new("IMG_0001.JPG"))
+//!   .with_progress_handler(handlers::write_lines(io::stderr()))
+//!   .wait()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Task::with_progress_handler`]: crate::task::Task::with_progress_handler
+
+use crate::context::{ProgressEvent, ProgressFn, ProgressHandler, ProgressPhase};
+use std::{
+  collections::HashMap,
+  io::Write,
+  sync::{Arc, Mutex, PoisonError},
+};
+
+type EventFn = Box<dyn FnMut(ProgressEvent) + Send>;
+
+/// Implement [`ProgressHandler`] by forwarding to the `handler` field
+macro_rules! forward_progress_handler {
+  ($ty:ty) => {
+    impl ProgressHandler for $ty {
+      fn start(&mut self, target: f32, message: String) -> u32 {
+        self.handler.start(target, message)
+      }
+
+      fn update(&mut self, id: u32, progress: f32) {
+        self.handler.update(id, progress)
+      }
+
+      fn stop(&mut self, id: u32) {
+        self.handler.stop(id)
+      }
+    }
+  };
+}
+
+/// Progress as whole percents
+// The fraction is clamped to 0..=1, the cast can't overflow
+#[allow(clippy::as_conversions)]
+fn percent(event: &ProgressEvent) -> u32 {
+  (event.fraction() * 100.0).round() as u32
+}
+
+/// Writes a line like `Downloading IMG_0001.JPG: 40%` whenever the progress advanced by a step
+pub struct WriteProgress {
+  handler: ProgressFn<EventFn>,
+}
+
+impl WriteProgress {
+  /// Write to `writer` every `step` percents
+  ///
+  /// Write errors are ignored, the progress is only informational.
+  pub fn new<W: Write + Send + 'static>(mut writer: W, step: u32) -> Self {
+    let step = step.max(1);
+    let mut written = HashMap::new();
+
+    let handler = ProgressFn::<EventFn>::new(Box::new(move |event: ProgressEvent| {
+      let percent = percent(&event);
+
+      match event.phase {
+        ProgressPhase::Start => {
+          written.insert(event.id, 0);
+          let _ = writeln!(writer, "{}: 0%", event.message);
+        }
+        ProgressPhase::Update => {
+          let last = written.entry(event.id).or_default();
+
+          if percent >= *last + step {
+            *last = percent - percent % step;
+            let _ = writeln!(writer, "{}: {percent}%", event.message);
+          }
+        }
+        ProgressPhase::Stop => {
+          written.remove(&event.id);
+          let _ = writeln!(writer, "{}: done", event.message);
+        }
+      }
+    }));
+
+    Self { handler }
+  }
+}
+
+forward_progress_handler!(WriteProgress);
+
+/// Write progress lines to `writer` every 10 percents, see [`WriteProgress`]
+pub fn write_lines<W: Write + Send + 'static>(writer: W) -> WriteProgress {
+  WriteProgress::new(writer, 10)
+}
+
+/// Records all progress events without output, for tests
+///
+/// Clones share the recorded events, so a clone can be kept to inspect the events after the
+/// handler was moved into a task.
+#[derive(Clone)]
+pub struct CollectProgress {
+  events: Arc<Mutex<Vec<ProgressEvent>>>,
+  handler: Arc<Mutex<ProgressFn<EventFn>>>,
+}
+
+impl CollectProgress {
+  /// Handler without recorded events
+  pub fn new() -> Self {
+    let events = Arc::new(Mutex::new(Vec::new()));
+
+    let handler = ProgressFn::<EventFn>::new(Box::new({
+      let events = events.clone();
+      move |event| events.lock().unwrap_or_else(PoisonError::into_inner).push(event)
+    }));
+
+    Self { events, handler: Arc::new(Mutex::new(handler)) }
+  }
+
+  /// Events recorded so far
+  pub fn events(&self) -> Vec<ProgressEvent> {
+    self.events.lock().unwrap_or_else(PoisonError::into_inner).clone()
+  }
+}
+
+impl Default for CollectProgress {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl ProgressHandler for CollectProgress {
+  fn start(&mut self, target: f32, message: String) -> u32 {
+    self.handler.lock().unwrap_or_else(PoisonError::into_inner).start(target, message)
+  }
+
+  fn update(&mut self, id: u32, progress: f32) {
+    self.handler.lock().unwrap_or_else(PoisonError::into_inner).update(id, progress)
+  }
+
+  fn stop(&mut self, id: u32) {
+    self.handler.lock().unwrap_or_else(PoisonError::into_inner).stop(id)
+  }
+}
+
+/// Record progress events for tests, see [`CollectProgress`]
+pub fn collect() -> CollectProgress {
+  CollectProgress::new()
+}
+
+/// Renders each progress as a bar of an [`indicatif::MultiProgress`]
+#[cfg(feature = "indicatif")]
+pub struct IndicatifProgress {
+  multi: indicatif::MultiProgress,
+  handler: ProgressFn<EventFn>,
+}
+
+#[cfg(feature = "indicatif")]
+impl IndicatifProgress {
+  /// Steps of the bars, the progress of libgphoto2 is a float
+  const BAR_LENGTH: u64 = 1000;
+
+  /// Add the bars to `multi`, so they can be shown together with bars of the application
+  pub fn new(multi: indicatif::MultiProgress) -> Self {
+    let style = indicatif::ProgressStyle::with_template("{msg} [{bar:40}] {percent}%")
+      .unwrap_or_else(|_| indicatif::ProgressStyle::default_bar());
+    let mut bars = HashMap::new();
+
+    let handler = ProgressFn::<EventFn>::new(Box::new({
+      let multi = multi.clone();
+
+      move |event: ProgressEvent| match event.phase {
+        ProgressPhase::Start => {
+          let bar = multi.add(indicatif::ProgressBar::new(Self::BAR_LENGTH));
+          bar.set_style(style.clone());
+          bar.set_message(event.message);
+          bars.insert(event.id, bar);
+        }
+        ProgressPhase::Update => {
+          if let Some(bar) = bars.get(&event.id) {
+            // The fraction is clamped to 0..=1, the cast can't overflow
+            #[allow(clippy::as_conversions)]
+            bar.set_position((event.fraction() * Self::BAR_LENGTH as f32) as u64);
+          }
+        }
+        ProgressPhase::Stop => {
+          if let Some(bar) = bars.remove(&event.id) {
+            bar.finish_and_clear();
+          }
+        }
+      }
+    }));
+
+    Self { multi, handler }
+  }
+
+  /// The [`indicatif::MultiProgress`] of the bars
+  pub fn multi_progress(&self) -> &indicatif::MultiProgress {
+    &self.multi
+  }
+}
+
+#[cfg(feature = "indicatif")]
+forward_progress_handler!(IndicatifProgress);
+
+/// Render progress bars with indicatif, see [`IndicatifProgress`]
+#[cfg(feature = "indicatif")]
+pub fn indicatif() -> IndicatifProgress {
+  IndicatifProgress::new(indicatif::MultiProgress::new())
+}
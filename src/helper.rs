@@ -1,3 +1,4 @@
+use crate::{logging, Error, Result};
 use std::{
   borrow::Cow,
   ffi,
@@ -43,74 +44,68 @@ impl IntoUnixFd for File {
 
 #[cfg(feature = "extended_logs")]
 pub fn hook_gp_log() {
-  use libgphoto2_sys::GPLogLevel;
-  use log::LevelFilter;
-
   unsafe extern "C" fn log_function(
     level: libgphoto2_sys::GPLogLevel,
     domain: *const std::os::raw::c_char,
     message: *const std::os::raw::c_char,
     _data: *mut ffi::c_void,
   ) {
-    let log_level = match level {
-      GPLogLevel::GP_LOG_ERROR => log::Level::Error,
-      GPLogLevel::GP_LOG_DEBUG => log::Level::Debug,
-      GPLogLevel::GP_LOG_VERBOSE => log::Level::Info,
-      GPLogLevel::GP_LOG_DATA => log::Level::Trace,
-    };
-
-    let target = format!("gphoto2::{}", chars_to_string(domain));
-
-    log::log!(target: &target, log_level, "{}", chars_to_string(message));
+    logging::dispatch(level.into(), &chars_to_string(domain), &chars_to_string(message));
   }
 
-  let max_log_level = match log::STATIC_MAX_LEVEL {
-    LevelFilter::Debug | LevelFilter::Warn => GPLogLevel::GP_LOG_DEBUG,
-    LevelFilter::Error => GPLogLevel::GP_LOG_ERROR,
-    LevelFilter::Info => GPLogLevel::GP_LOG_VERBOSE,
-    LevelFilter::Trace => GPLogLevel::GP_LOG_DATA,
-    LevelFilter::Off => return,
+  let Some(max_log_level) = logging::max_level() else {
+    return;
   };
 
   HOOK_LOG_FUNCTION.call_once(|| unsafe {
-    libgphoto2_sys::gp_log_add_func(max_log_level, Some(log_function), std::ptr::null_mut());
+    libgphoto2_sys::gp_log_add_func(max_log_level.into(), Some(log_function), std::ptr::null_mut());
   });
 }
 
 #[cfg(not(feature = "extended_logs"))]
 pub fn hook_gp_context_log_func(context: *mut libgphoto2_sys::GPContext) {
-  use log::Level;
+  use crate::logging::LogLevel;
 
-  unsafe extern "C" fn log_func(
+  unsafe extern "C" fn error_func(
     _context: *mut libgphoto2_sys::GPContext,
     message: *const c_char,
-    log_level: *mut ffi::c_void,
+    _data: *mut ffi::c_void,
   ) {
-    let log_level: Level = std::mem::transmute(log_level);
+    logging::dispatch(LogLevel::Error, "context", &chars_to_string(message));
+  }
 
-    log::log!(target: "gphoto2", log_level, "{}", chars_to_string(message));
+  unsafe extern "C" fn status_func(
+    _context: *mut libgphoto2_sys::GPContext,
+    message: *const c_char,
+    _data: *mut ffi::c_void,
+  ) {
+    logging::dispatch(LogLevel::Verbose, "context", &chars_to_string(message));
   }
 
   HOOK_LOG_FUNCTION.call_once(|| unsafe {
-    if log::log_enabled!(log::Level::Error) {
-      let log_level_as_ptr = std::mem::transmute(log::Level::Error);
+    libgphoto2_sys::gp_context_set_error_func(context, Some(error_func), std::ptr::null_mut());
 
-      libgphoto2_sys::gp_context_set_error_func(context, Some(log_func), log_level_as_ptr);
+    // `gp_context_message` seems to be used also for error messages.
+    libgphoto2_sys::gp_context_set_message_func(context, Some(error_func), std::ptr::null_mut());
 
-      // `gp_context_message` seems to be used also for error messages.
-      libgphoto2_sys::gp_context_set_message_func(context, Some(log_func), log_level_as_ptr);
-    }
-
-    if log::log_enabled!(log::Level::Info) {
-      libgphoto2_sys::gp_context_set_status_func(
-        context,
-        Some(log_func),
-        std::mem::transmute(log::Level::Info),
-      );
-    }
+    libgphoto2_sys::gp_context_set_status_func(context, Some(status_func), std::ptr::null_mut());
   });
 }
 
+/// Fail with `GP_ERROR_CANCEL` if the task running with `context` was cancelled
+///
+/// Drivers only check for cancellation during some transfers, so calls running several
+/// libgphoto2 operations (or uploading chunks) check between them. Must be called from a task.
+pub unsafe fn check_cancelled(context: *mut libgphoto2_sys::GPContext) -> Result<()> {
+  use libgphoto2_sys::GPContextFeedback;
+
+  if libgphoto2_sys::gp_context_cancel(context) == GPContextFeedback::GP_CONTEXT_FEEDBACK_CANCEL {
+    return Err(Error::new(libgphoto2_sys::GP_ERROR_CANCEL, None));
+  }
+
+  Ok(())
+}
+
 pub struct UninitBox<T> {
   inner: Box<MaybeUninit<T>>,
 }
@@ -0,0 +1,205 @@
+//! Audit trail of the captures of a camera, see [`Camera::set_shot_journal`](crate::Camera::set_shot_journal)
+//!
+//! A [`ShotJournal`] attached to a camera records every capture with the time, the values of
+//! some settings right before the capture and the resulting file or error. With the `serde`
+//! feature the records can be serialized, e.g. to keep a log of a tethered studio session.
+//!
+//! ```no_run
+//! use gphoto2::{journal::ShotJournal, Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//! let journal = ShotJournal::new().settings(["iso", "shutterspeed", "aperture"]);
+//! camera.set_shot_journal(Some(journal.clone()));
+//!
+//! camera.capture_image().wait()?;
+//!
+//! for record in journal.records() {
+//!   println!("{:?}: {:?} {:?}", record.kind, record.path, record.settings);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{camera::get_single_config, file::CameraFilePath, ops::ConfigValue, Result};
+use std::{
+  fmt,
+  sync::{Arc, Mutex, PoisonError},
+  time::SystemTime,
+};
+
+/// Settings recorded by [`ShotJournal::new`]
+const DEFAULT_SETTINGS: &[&str] = &[
+  "iso",
+  "shutterspeed",
+  "aperture",
+  "f-number",
+  "exposurecompensation",
+  "whitebalance",
+  "imageformat",
+];
+
+type RecordCallback = Arc<dyn Fn(&ShotRecord) + Send + Sync>;
+
+/// Kind of a recorded capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CaptureKind {
+  /// [`Camera::capture_image`](crate::Camera::capture_image) and the helpers using it
+  Image,
+  /// [`Camera::capture_sound`](crate::Camera::capture_sound)
+  Sound,
+  /// [`Camera::capture_movie`](crate::Camera::capture_movie)
+  Movie,
+  /// [`Camera::trigger_capture`](crate::Camera::trigger_capture), which reports no file
+  Trigger,
+}
+
+/// A single capture of a [`ShotJournal`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ShotRecord {
+  /// When the capture was started
+  pub timestamp: SystemTime,
+  /// Kind of the capture
+  pub kind: CaptureKind,
+  /// Values of the journaled settings before the capture, settings the camera doesn't have are
+  /// missing
+  pub settings: Vec<(String, ConfigValue)>,
+  /// File created by the capture
+  pub path: Option<CameraFilePath>,
+  /// Error message if the capture failed
+  pub error: Option<String>,
+}
+
+struct JournalState {
+  settings: Vec<String>,
+  records: Mutex<Vec<ShotRecord>>,
+  on_record: Option<RecordCallback>,
+}
+
+/// Records the captures of a camera, see the [module](self) documentation
+///
+/// Clones share the records, so a clone can be kept to read the records of an attached journal.
+#[derive(Clone)]
+pub struct ShotJournal {
+  state: Arc<JournalState>,
+}
+
+impl ShotJournal {
+  /// Journal recording ISO, shutter speed, aperture, exposure compensation, white balance and
+  /// image format
+  pub fn new() -> Self {
+    Self {
+      state: Arc::new(JournalState {
+        settings: DEFAULT_SETTINGS.iter().map(|&key| key.to_owned()).collect(),
+        records: Mutex::new(Vec::new()),
+        on_record: None,
+      }),
+    }
+  }
+
+  /// Record the configurations `keys` instead of the default settings
+  ///
+  /// Every configuration is read before each capture, which delays the capture.
+  pub fn settings<S: Into<String>>(self, keys: impl IntoIterator<Item = S>) -> Self {
+    self.map_state(|state| state.settings = keys.into_iter().map(Into::into).collect())
+  }
+
+  /// Call `callback` with every new record, e.g. to append it to a file right away
+  ///
+  /// The callback runs on the libgphoto2 thread and should return quickly.
+  pub fn on_record(self, callback: impl Fn(&ShotRecord) + Send + Sync + 'static) -> Self {
+    self.map_state(|state| state.on_record = Some(Arc::new(callback)))
+  }
+
+  /// Records so far, oldest first
+  pub fn records(&self) -> Vec<ShotRecord> {
+    self.state.records.lock().unwrap_or_else(PoisonError::into_inner).clone()
+  }
+
+  /// Remove all records
+  pub fn clear(&self) {
+    self.state.records.lock().unwrap_or_else(PoisonError::into_inner).clear();
+  }
+
+  fn map_state(self, change: impl FnOnce(&mut JournalState)) -> Self {
+    let mut state = JournalState {
+      settings: self.state.settings.clone(),
+      records: Mutex::new(self.records()),
+      on_record: self.state.on_record.clone(),
+    };
+    change(&mut state);
+
+    Self { state: Arc::new(state) }
+  }
+
+  /// Run `capture` and record it, must be called from a [`Task`](crate::task::Task)
+  pub(crate) unsafe fn record<T: Captured>(
+    &self,
+    camera: *mut libgphoto2_sys::Camera,
+    kind: CaptureKind,
+    context: *mut libgphoto2_sys::GPContext,
+    capture: impl FnOnce() -> Result<T>,
+  ) -> Result<T> {
+    let timestamp = SystemTime::now();
+    let settings = self
+      .state
+      .settings
+      .iter()
+      .filter_map(|key| {
+        let widget = get_single_config(camera, key, context).ok()?;
+        Some((key.clone(), ConfigValue::from_widget(&widget).ok()?))
+      })
+      .collect();
+
+    let result = capture();
+
+    let record = ShotRecord {
+      timestamp,
+      kind,
+      settings,
+      path: result.as_ref().ok().and_then(Captured::path),
+      error: result.as_ref().err().map(ToString::to_string),
+    };
+
+    if let Some(on_record) = &self.state.on_record {
+      on_record(&record);
+    }
+    self.state.records.lock().unwrap_or_else(PoisonError::into_inner).push(record);
+
+    result
+  }
+}
+
+impl Default for ShotJournal {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl fmt::Debug for ShotJournal {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("ShotJournal")
+      .field("settings", &self.state.settings)
+      .field("records", &self.state.records.lock().unwrap_or_else(PoisonError::into_inner).len())
+      .finish()
+  }
+}
+
+/// Result of a journaled capture
+pub(crate) trait Captured {
+  fn path(&self) -> Option<CameraFilePath>;
+}
+
+impl Captured for CameraFilePath {
+  fn path(&self) -> Option<CameraFilePath> {
+    Some(self.clone())
+  }
+}
+
+impl Captured for () {
+  fn path(&self) -> Option<CameraFilePath> {
+    None
+  }
+}
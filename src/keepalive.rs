@@ -0,0 +1,144 @@
+//! Keepalive timers requested by camera drivers
+//!
+//! Some drivers (like PTP/IP and several Canon models) register a function with
+//! `gp_camera_start_timeout` which has to be called periodically, or the camera closes the
+//! connection when idle. The timers run on their own threads and queue the function on the
+//! libgphoto2 thread.
+
+use crate::{
+  camera::CameraState,
+  task::{BackgroundPtr, Task},
+  Context, Error,
+};
+use std::{
+  collections::HashMap,
+  os::raw::{c_uint, c_void},
+  sync::{
+    atomic::{AtomicBool, AtomicU32, Ordering},
+    Arc, Mutex, PoisonError, Weak,
+  },
+  thread,
+  time::Duration,
+};
+
+/// Timers of a single camera
+pub(crate) struct KeepAlive {
+  camera: BackgroundPtr<libgphoto2_sys::Camera>,
+  context: Context,
+  state: Weak<CameraState>,
+  /// Whether the timer is still running, by ID
+  timers: Mutex<HashMap<c_uint, Arc<AtomicBool>>>,
+  next_id: AtomicU32,
+}
+
+impl KeepAlive {
+  pub(crate) fn new(
+    camera: BackgroundPtr<libgphoto2_sys::Camera>,
+    context: Context,
+    state: Weak<CameraState>,
+  ) -> Self {
+    Self { camera, context, state, timers: Default::default(), next_id: AtomicU32::new(1) }
+  }
+
+  /// Let the driver start timers, must be called from a [`Task`] before the camera is initialized
+  ///
+  /// The state must stay alive until the camera is freed, libgphoto2 stops the timers then.
+  pub(crate) unsafe fn register(state: &Arc<CameraState>) {
+    libgphoto2_sys::gp_camera_set_timeout_funcs(
+      *state.keepalive.camera,
+      Some(start_timeout),
+      Some(stop_timeout),
+      Arc::as_ptr(state).cast_mut().cast(),
+    );
+  }
+
+  /// Stop the driver from starting new timers, must be called from a [`Task`]
+  ///
+  /// Used when the camera may outlive its state. Running timers stop once the state is dropped.
+  pub(crate) unsafe fn unregister(camera: BackgroundPtr<libgphoto2_sys::Camera>) {
+    libgphoto2_sys::gp_camera_set_timeout_funcs(*camera, None, None, std::ptr::null_mut());
+  }
+
+  fn start(&self, interval: Duration, func: libgphoto2_sys::CameraTimeoutFunc) -> c_uint {
+    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+    let running = Arc::new(AtomicBool::new(true));
+    self.timers.lock().unwrap_or_else(PoisonError::into_inner).insert(id, running.clone());
+
+    let camera = self.camera;
+    let context = self.context.clone();
+    let state = self.state.clone();
+    let camera_id = state.upgrade().map(|state| state.id);
+
+    let spawned = thread::Builder::new().name("gphoto2-keepalive".to_owned()).spawn(move || {
+      while running.load(Ordering::Relaxed) && state.strong_count() > 0 {
+        thread::sleep(interval);
+
+        let state = state.clone();
+        let running = running.clone();
+        let context_ptr = context.inner();
+
+        let mut task = unsafe {
+          Task::new(move || {
+            // The camera is freed once the state is gone
+            if state.upgrade().is_none() || !running.load(Ordering::Relaxed) {
+              return;
+            }
+
+            if let Some(func) = func {
+              if let Err(error) = Error::check(func(*camera, *context_ptr)) {
+                log::warn!("Camera keepalive failed: {error}");
+              }
+            }
+          })
+        }
+        .context(&context);
+        // Waits while the camera is locked, like other operations of the camera
+        if let Some(camera_id) = camera_id {
+          task = task.camera_id(camera_id);
+        }
+        task.background();
+      }
+    });
+
+    if let Err(error) = spawned {
+      log::error!("Could not start the camera keepalive: {error}");
+    }
+
+    id
+  }
+
+  fn stop(&self, id: c_uint) {
+    if let Some(running) = self.timers.lock().unwrap_or_else(PoisonError::into_inner).remove(&id) {
+      running.store(false, Ordering::Relaxed);
+    }
+  }
+}
+
+impl Drop for KeepAlive {
+  fn drop(&mut self) {
+    for running in self.timers.get_mut().unwrap_or_else(PoisonError::into_inner).values() {
+      running.store(false, Ordering::Relaxed);
+    }
+  }
+}
+
+unsafe extern "C" fn start_timeout(
+  _camera: *mut libgphoto2_sys::Camera,
+  timeout: c_uint,
+  func: libgphoto2_sys::CameraTimeoutFunc,
+  data: *mut c_void,
+) -> c_uint {
+  let state = &*data.cast::<CameraState>();
+
+  state.keepalive.start(Duration::from_secs(timeout.into()), func)
+}
+
+unsafe extern "C" fn stop_timeout(
+  _camera: *mut libgphoto2_sys::Camera,
+  id: c_uint,
+  data: *mut c_void,
+) {
+  let state = &*data.cast::<CameraState>();
+
+  state.keepalive.stop(id);
+}
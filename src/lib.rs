@@ -4,16 +4,65 @@
 #![deny(clippy::as_conversions)]
 
 pub mod abilities;
+#[cfg(any(feature = "service", feature = "remote"))]
+pub(crate) mod base64;
+#[cfg(feature = "bridge")]
+pub mod bridge;
 pub mod camera;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod checksum;
+pub(crate) mod clock;
 pub mod context;
+pub mod diagnostics;
 pub mod error;
+#[cfg(feature = "exif")]
+pub mod exif;
+pub mod exposure;
 pub mod file;
 pub mod filesys;
+pub mod firmware;
+pub mod governor;
+#[cfg(feature = "gvfs")]
+pub mod gvfs;
+pub mod handlers;
 pub(crate) mod helper;
+pub mod journal;
+pub(crate) mod keepalive;
 pub mod list;
+pub mod liveview;
+pub mod logging;
+pub(crate) mod metadata;
+pub mod metrics;
+pub mod mock;
+pub(crate) mod movie;
+pub mod naming;
+#[cfg(feature = "napi")]
+pub mod napi;
+pub mod ops;
+pub mod orientation;
+pub mod path;
+pub(crate) mod platform;
+pub mod pool;
 pub mod port;
+pub mod profile;
+pub(crate) mod ptp_property;
+pub mod quirks;
+#[cfg(feature = "remote")]
+pub mod remote;
+#[cfg(feature = "service")]
+pub mod service;
+pub mod session;
+pub mod settings;
+pub mod settings_store;
+pub mod stream;
+pub mod support;
 pub mod task;
 pub(crate) mod thread;
+pub mod thumbnail;
+pub mod version;
+#[cfg(feature = "webcam")]
+pub mod webcam;
 pub mod widget;
 
 use std::ffi::CStr;
@@ -46,6 +95,13 @@ pub fn library_version() -> Option<&'static str> {
   }
 }
 
+/// Get the version and optional capabilities of the loaded libgphoto2
+///
+/// This allows checking for features at runtime, see the [`version`] module.
+pub fn library_features() -> version::LibraryFeatures {
+  version::library_features()
+}
+
 #[cfg(all(test, feature = "test"))]
 fn sample_context() -> Context {
   use std::sync::Once;
@@ -72,3 +128,12 @@ fn sample_context() -> Context {
 fn test_version() {
   insta::assert_snapshot!(library_version().unwrap());
 }
+
+#[cfg(all(test, feature = "test"))]
+#[test]
+fn test_library_features() {
+  let features = library_features();
+
+  assert_eq!(Some(features.version), version::LibraryVersion::parse(library_version().unwrap()));
+  assert!(features.single_config);
+}
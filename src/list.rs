@@ -1,6 +1,10 @@
 //! List of cameras and ports
 
-use crate::{helper::chars_to_string, task::BackgroundPtr, try_gp_internal, Result};
+use crate::{
+  helper::chars_to_string,
+  task::{BackgroundPtr, Task},
+  try_gp_internal, Result,
+};
 use std::{ops::Range, os::raw::c_int};
 
 pub(crate) struct CameraList {
@@ -9,7 +13,10 @@ pub(crate) struct CameraList {
 
 impl Drop for CameraList {
   fn drop(&mut self) {
-    try_gp_internal!(gp_list_unref(*self.inner).unwrap());
+    let list = self.inner;
+
+    // The iterators owning lists are dropped outside of tasks
+    unsafe { Task::new(move || libgphoto2_sys::gp_list_unref(*list)) }.background();
   }
 }
 
@@ -20,17 +27,41 @@ impl CameraList {
     Ok(Self { inner: BackgroundPtr(list) })
   }
 
+  // Lists are only read once a task returned them, so no other thread can change them
   fn range(&self) -> Range<c_int> {
-    0..unsafe { libgphoto2_sys::gp_list_count(*self.inner) }
+    0..unsafe { libgphoto2_sys::gp_list_count(self.inner.any_thread()) }
+  }
+
+  pub(crate) fn len(&self) -> usize {
+    self.range().len()
+  }
+
+  /// Sort the entries by name
+  pub(crate) fn sort(&self) -> Result<()> {
+    try_gp_internal!(gp_list_sort(*self.inner)?);
+    Ok(())
+  }
+
+  /// Copy of the list with the entries in reverse order
+  pub(crate) fn reversed(&self) -> Result<Self> {
+    let reversed = Self::new()?;
+
+    for i in self.range().rev() {
+      try_gp_internal!(gp_list_get_name(*self.inner, i, &out name)?);
+      try_gp_internal!(gp_list_get_value(*self.inner, i, &out value)?);
+      try_gp_internal!(gp_list_append(*reversed.inner, name, value)?);
+    }
+
+    Ok(reversed)
   }
 
   fn get_name_at_unchecked(&self, i: c_int) -> String {
-    try_gp_internal!(gp_list_get_name(*self.inner, i, &out name).unwrap());
+    try_gp_internal!(gp_list_get_name(self.inner.any_thread(), i, &out name).unwrap());
     chars_to_string(name)
   }
 
   fn get_value_at_unchecked(&self, i: c_int) -> String {
-    try_gp_internal!(gp_list_get_value(*self.inner, i, &out value).unwrap());
+    try_gp_internal!(gp_list_get_value(self.inner.any_thread(), i, &out value).unwrap());
     chars_to_string(value)
   }
 }
@@ -43,12 +74,6 @@ macro_rules! camera_list_iter {
       range: Range<c_int>,
     }
 
-    impl $ty {
-      pub(crate) fn new(list: CameraList) -> Self {
-        Self { range: list.range(), list }
-      }
-    }
-
     impl Iterator for $ty {
       type Item = $item_ty;
 
@@ -59,6 +84,10 @@ macro_rules! camera_list_iter {
       fn size_hint(&self) -> (usize, Option<usize>) {
         self.range.size_hint()
       }
+
+      fn nth(&mut $self, n: usize) -> Option<Self::Item> {
+        $self.range.nth(n).map(|$i| $get_item)
+      }
     }
 
     impl ExactSizeIterator for $ty {
@@ -89,7 +118,33 @@ camera_list_iter!(
   }
 );
 
+impl CameraListIter {
+  pub(crate) fn new(list: CameraList) -> Self {
+    Self { range: list.range(), list }
+  }
+}
+
 camera_list_iter!(
   /// Iterator over filenames.
   |self: FileListIter, i| -> String { self.list.get_name_at_unchecked(i) }
 );
+
+camera_list_iter!(
+  /// Iterator over names and values of a list.
+  |self: ListIter, i| -> (String, String) {
+    (self.list.get_name_at_unchecked(i), self.list.get_value_at_unchecked(i))
+  }
+);
+
+impl FileListIter {
+  pub(crate) fn new(list: CameraList) -> Self {
+    Self { range: list.range(), list }
+  }
+
+  /// Yield the values stored next to the names as well
+  ///
+  /// Some drivers store additional information about files and folders in the values.
+  pub fn with_values(self) -> ListIter {
+    ListIter { list: self.list, range: self.range }
+  }
+}
@@ -0,0 +1,422 @@
+//! Live view (preview streaming)
+//!
+//! ```no_run
+//! use gphoto2::{Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//! let live_view = camera.live_view().wait()?;
+//!
+//! for _ in 0..10 {
+//!   let frame = live_view.capture_frame().wait()?;
+//!   println!("Got a {} byte frame", frame.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Without waiting on tasks, frames can also be passed to a callback with [`LiveView::on_frame`]:
+//!
+//! ```no_run
+//! use gphoto2::{Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//!
+//! let frames = camera.live_view().wait()?.on_frame(|frame| {
+//!   println!("Got a {} byte frame", frame.len());
+//! });
+//!
+//! std::thread::sleep(std::time::Duration::from_secs(10));
+//! println!("Skipped {} frames", frames.skipped_frames());
+//! frames.stop()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+  camera::CameraState,
+  file::CameraFile,
+  helper::to_c_string,
+  stream::{Stream, StreamOptions},
+  task::{BackgroundPtr, Task},
+  try_gp_internal,
+  widget::{ToggleWidget, Widget, WidgetBase},
+  Camera, Context, Error, Result,
+};
+use std::{
+  ffi,
+  io::Write,
+  sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+  thread::{self, JoinHandle},
+  time::{Duration, Instant},
+};
+
+/// Boundary between the frames written by [`LiveView::write_mjpeg`]
+pub const MJPEG_BOUNDARY: &str = "gphoto2-frame";
+
+/// Content type of the stream written by [`LiveView::write_mjpeg`], for HTTP responses
+pub const MJPEG_CONTENT_TYPE: &str = "multipart/x-mixed-replace; boundary=gphoto2-frame";
+
+/// Name of the config which keeps the viewfinder (and the mirror) up on most cameras
+const VIEWFINDER_KEY: &str = "viewfinder";
+
+/// Active live view of a camera
+///
+/// While this exists the camera's viewfinder is kept enabled (if the camera has one),
+/// which makes capturing preview frames a lot faster. The viewfinder is disabled again on drop.
+pub struct LiveView {
+  camera: Camera,
+  viewfinder: Option<ToggleWidget>,
+}
+
+impl Drop for LiveView {
+  fn drop(&mut self) {
+    if let Some(viewfinder) = &self.viewfinder {
+      if viewfinder.set_toggled(false).is_ok() {
+        self.camera.set_config(viewfinder).background();
+      }
+    }
+  }
+}
+
+impl LiveView {
+  /// Enable the viewfinder, must be called from the libgphoto2 thread
+  pub(crate) unsafe fn start(
+    camera: BackgroundPtr<libgphoto2_sys::Camera>,
+    context: Context,
+    state: Arc<CameraState>,
+  ) -> Result<Self> {
+    let mut widget = std::ptr::null_mut();
+
+    // Cameras without a viewfinder config can still capture previews
+    let viewfinder = if libgphoto2_sys::gp_camera_get_single_config(
+      *camera,
+      to_c_string!(VIEWFINDER_KEY),
+      &mut widget,
+      *context.inner(),
+    ) == libgphoto2_sys::GP_OK
+    {
+      Widget::new_owned(BackgroundPtr(widget))
+        .try_into::<ToggleWidget>()
+        .ok()
+        .filter(|viewfinder| !viewfinder.readonly())
+    } else {
+      None
+    };
+
+    if let Some(viewfinder) = &viewfinder {
+      let base: &WidgetBase = viewfinder;
+      viewfinder.set_toggled(true)?;
+
+      try_gp_internal!(gp_camera_set_single_config(
+        *camera,
+        to_c_string!(VIEWFINDER_KEY),
+        *base.inner,
+        *context.inner()
+      )?);
+    }
+
+    // Only take a reference once nothing can fail
+    try_gp_internal!(gp_camera_ref(*camera)?);
+
+    Ok(Self { camera: Camera::new(camera, context, state), viewfinder })
+  }
+
+  /// Camera this live view belongs to
+  pub fn camera(&self) -> &Camera {
+    &self.camera
+  }
+
+  /// Capture a single preview frame
+  ///
+  /// Most cameras return JPEG data here.
+  pub fn capture_frame(&self) -> Task<Result<Box<[u8]>>> {
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        let camera_file = CameraFile::new()?;
+
+        try_gp_internal!(gp_camera_capture_preview(*camera, *camera_file.inner, *context)?);
+
+        camera_file.read_data()
+      })
+    }
+    .camera(&self.camera)
+  }
+
+  /// Write preview frames as an MJPEG stream until writing or capturing fails
+  ///
+  /// The stream is `multipart/x-mixed-replace` ([`MJPEG_CONTENT_TYPE`]), which browsers show
+  /// in an `<img>` and ffmpeg reads with `-f mpjpeg`. Returns the error which stopped the
+  /// stream, usually because the client disconnected.
+  ///
+  /// ```no_run
+  /// use gphoto2::{liveview::MJPEG_CONTENT_TYPE, Context, Result};
+  /// use std::{io::Write, net::TcpListener};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let live_view = Context::new()?.autodetect_camera().wait()?.live_view().wait()?;
+  ///
+  /// for stream in TcpListener::bind("127.0.0.1:8080")?.incoming() {
+  ///   let mut stream = stream?;
+  ///   write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: {MJPEG_CONTENT_TYPE}\r\n\r\n")?;
+  ///
+  ///   if let Err(error) = live_view.write_mjpeg(&mut stream) {
+  ///     println!("Stream ended: {error}");
+  ///   }
+  /// }
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn write_mjpeg(&self, writer: &mut impl Write) -> Result<()> {
+    loop {
+      let frame = self.capture_frame().wait()?;
+
+      if !frame.starts_with(&[0xff, 0xd8]) {
+        return Err(Error::from("The camera doesn't capture JPEG previews"));
+      }
+
+      write!(
+        writer,
+        "--{MJPEG_BOUNDARY}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+        frame.len()
+      )?;
+      writer.write_all(&frame)?;
+      writer.write_all(b"\r\n")?;
+      writer.flush()?;
+    }
+  }
+
+  /// Capture frames on a background thread, buffered according to `options`
+  ///
+  /// Use [`StreamOptions::latest`] to only ever get the most recent frame.
+  pub fn preview_stream(self, options: StreamOptions) -> Stream<Result<Box<[u8]>>> {
+    Stream::spawn(options, move |queue| {
+      while !queue.is_closed() {
+        match self.capture_frame().wait() {
+          Ok(frame) => {
+            if !queue.push(Ok(frame)) {
+              return;
+            }
+          }
+          Err(error) => {
+            queue.push_last(Err(error));
+            return;
+          }
+        }
+      }
+    })
+  }
+
+  /// Pass every captured frame to `callback` until the returned [`FrameCallback`] is stopped
+  ///
+  /// Frames are captured on a separate thread and the callback is called from another one.
+  /// If the callback is slower than the camera, only the most recent frame is kept
+  /// and the others are skipped, so frames never pile up.
+  pub fn on_frame<F>(self, mut callback: F) -> FrameCallback
+  where
+    F: FnMut(&[u8]) + 'static + Send,
+  {
+    let slot = Arc::new(FrameSlot::default());
+
+    let capture = thread::spawn({
+      let slot = slot.clone();
+
+      move || loop {
+        if slot.state().stop_requested {
+          break;
+        }
+
+        let started = Instant::now();
+        let frame = self.capture_frame().wait();
+        let captured = Instant::now();
+
+        let mut state = slot.state();
+        match frame {
+          Ok(frame) => {
+            state.frame_captured(started, captured);
+
+            if state.frame.replace((frame, captured)).is_some() {
+              state.stats.skipped_frames += 1;
+            }
+          }
+          Err(error) => {
+            state.error = Some(error);
+            state.stop_requested = true;
+          }
+        }
+        slot.changed.notify_all();
+      }
+    });
+
+    let deliver = thread::spawn({
+      let slot = slot.clone();
+
+      move || loop {
+        let frame = {
+          let mut state = slot.state();
+
+          loop {
+            if state.stop_requested {
+              return;
+            }
+
+            if let Some((frame, captured)) = state.frame.take() {
+              state.stats.frame_delivered(captured);
+              break frame;
+            }
+
+            state = slot.changed.wait(state).unwrap_or_else(PoisonError::into_inner);
+          }
+        };
+
+        callback(&frame);
+      }
+    });
+
+    FrameCallback { slot, threads: vec![capture, deliver] }
+  }
+}
+
+/// Most recent frame shared between the capture and the callback thread
+#[derive(Default)]
+struct FrameSlot {
+  state: Mutex<FrameSlotState>,
+  changed: Condvar,
+}
+
+#[derive(Default)]
+struct FrameSlotState {
+  /// Most recent frame and when it was captured
+  frame: Option<(Box<[u8]>, Instant)>,
+  stats: LiveViewStats,
+  last_capture: Option<Instant>,
+  /// Running average of the time between captured frames
+  frame_interval: Duration,
+  stop_requested: bool,
+  error: Option<Error>,
+}
+
+/// Weight of a new sample in the running averages
+const SMOOTHING: f64 = 0.1;
+
+fn smooth(average: Duration, sample: Duration, first: bool) -> Duration {
+  if first {
+    sample
+  } else {
+    average.mul_f64(1.0 - SMOOTHING) + sample.mul_f64(SMOOTHING)
+  }
+}
+
+/// Running statistics of a [`FrameCallback`]
+///
+/// Averages favor recent frames, so they follow changes within a few seconds. If
+/// [`capture_time`](Self::capture_time) is close to the frame interval the camera (or USB)
+/// is the bottleneck, if frames are skipped the callback is.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LiveViewStats {
+  /// Frames captured from the camera
+  pub captured_frames: u64,
+  /// Frames passed to the callback
+  pub delivered_frames: u64,
+  /// Frames which were replaced before the callback could receive them
+  pub skipped_frames: u64,
+  /// Captured frames per second
+  pub frames_per_second: f64,
+  /// Average time the camera takes to return a frame
+  pub capture_time: Duration,
+  /// Average time between a frame being captured and passed to the callback
+  pub delivery_latency: Duration,
+}
+
+impl FrameSlotState {
+  fn frame_captured(&mut self, started: Instant, captured: Instant) {
+    let stats = &mut self.stats;
+
+    stats.capture_time = smooth(stats.capture_time, captured - started, stats.captured_frames == 0);
+    stats.captured_frames += 1;
+
+    if let Some(last_capture) = self.last_capture.replace(captured) {
+      self.frame_interval =
+        smooth(self.frame_interval, captured - last_capture, stats.captured_frames == 2);
+      stats.frames_per_second = 1.0 / self.frame_interval.as_secs_f64().max(f64::EPSILON);
+    }
+  }
+}
+
+impl LiveViewStats {
+  fn frame_delivered(&mut self, captured: Instant) {
+    let first = self.delivered_frames == 0;
+
+    self.delivered_frames += 1;
+    self.delivery_latency = smooth(self.delivery_latency, captured.elapsed(), first);
+  }
+}
+
+impl FrameSlot {
+  fn state(&self) -> MutexGuard<'_, FrameSlotState> {
+    self.state.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  fn request_stop(&self) {
+    self.state().stop_requested = true;
+    self.changed.notify_all();
+  }
+}
+
+/// Frame callback registered with [`LiveView::on_frame`]
+///
+/// Dropping this stops the live view, use [`FrameCallback::stop`] to get the capture error (if any).
+pub struct FrameCallback {
+  slot: Arc<FrameSlot>,
+  threads: Vec<JoinHandle<()>>,
+}
+
+impl Drop for FrameCallback {
+  fn drop(&mut self) {
+    self.slot.request_stop();
+
+    for thread in self.threads.drain(..) {
+      // A panic in the callback has already been reported
+      let _ = thread.join();
+    }
+  }
+}
+
+impl FrameCallback {
+  /// Number of frames which were replaced before the callback could receive them
+  pub fn skipped_frames(&self) -> u64 {
+    self.slot.state().stats.skipped_frames
+  }
+
+  /// Frame rate, latency and skipped frames so far
+  pub fn stats(&self) -> LiveViewStats {
+    self.slot.state().stats
+  }
+
+  /// Whether frames are still being captured
+  ///
+  /// This becomes false after capturing a frame failed.
+  pub fn is_running(&self) -> bool {
+    !self.slot.state().stop_requested
+  }
+
+  /// Stop capturing and wait for the callback to return
+  ///
+  /// Returns the error which stopped capturing early, if any.
+  pub fn stop(mut self) -> Result<()> {
+    self.slot.request_stop();
+
+    for thread in self.threads.drain(..) {
+      let _ = thread.join();
+    }
+
+    match self.slot.state().error.take() {
+      Some(error) => Err(error),
+      None => Ok(()),
+    }
+  }
+}
@@ -0,0 +1,217 @@
+//! Logging of libgphoto2 messages
+//!
+//! Messages from libgphoto2 are forwarded to the [`log`](https://docs.rs/log) crate by default,
+//! using `gphoto2::<domain>` as target, where the domain is the libgphoto2 module which emitted
+//! the message (`context` for messages reported through a [`Context`](crate::Context)).
+//! With the `tracing` feature they are emitted as [`tracing`](https://docs.rs/tracing) events
+//! with the `gphoto2` target and a `domain` field instead.
+//!
+//! Additionally a custom sink can be registered to capture driver logs, and the
+//! level can be limited per domain:
+//!
+//! ```no_run
+//! use gphoto2::logging::{self, LogLevel};
+//!
+//! logging::set_domain_level("ptp2", Some(LogLevel::Error));
+//! logging::set_log_sink(|level, domain, message| {
+//!   eprintln!("[{level:?}] {domain}: {message}");
+//! });
+//! ```
+//!
+//! Without the `extended_logs` feature only messages reported through a context are received.
+//!
+//! With the `error_log` feature the most recent messages are kept in memory and attached
+//! to libgphoto2 errors, see `Error::debug_log`.
+
+#[cfg(feature = "error_log")]
+use std::collections::VecDeque;
+use std::{
+  collections::BTreeMap,
+  sync::{Mutex, PoisonError, RwLock},
+};
+
+/// Level of a libgphoto2 log message, ordered from least to most verbose
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum LogLevel {
+  /// Errors
+  Error,
+  /// Status messages
+  Verbose,
+  /// Debug messages
+  Debug,
+  /// Dumps of transferred data
+  Data,
+}
+
+type LogSink = Box<dyn FnMut(LogLevel, &str, &str) + Send>;
+
+static DOMAIN_LEVELS: RwLock<BTreeMap<String, Option<LogLevel>>> = RwLock::new(BTreeMap::new());
+static LOG_SINK: Mutex<Option<LogSink>> = Mutex::new(None);
+
+#[cfg(feature = "error_log")]
+static RECENT_LINES: Mutex<RecentLines> =
+  Mutex::new(RecentLines { lines: VecDeque::new(), capacity: 50 });
+
+/// Ring buffer of the last log lines
+#[cfg(feature = "error_log")]
+struct RecentLines {
+  lines: VecDeque<String>,
+  capacity: usize,
+}
+
+impl From<libgphoto2_sys::GPLogLevel> for LogLevel {
+  fn from(level: libgphoto2_sys::GPLogLevel) -> Self {
+    use libgphoto2_sys::GPLogLevel;
+
+    match level {
+      GPLogLevel::GP_LOG_ERROR => Self::Error,
+      GPLogLevel::GP_LOG_VERBOSE => Self::Verbose,
+      GPLogLevel::GP_LOG_DEBUG => Self::Debug,
+      GPLogLevel::GP_LOG_DATA => Self::Data,
+    }
+  }
+}
+
+impl From<LogLevel> for libgphoto2_sys::GPLogLevel {
+  fn from(level: LogLevel) -> Self {
+    match level {
+      LogLevel::Error => Self::GP_LOG_ERROR,
+      LogLevel::Verbose => Self::GP_LOG_VERBOSE,
+      LogLevel::Debug => Self::GP_LOG_DEBUG,
+      LogLevel::Data => Self::GP_LOG_DATA,
+    }
+  }
+}
+
+impl From<LogLevel> for log::Level {
+  fn from(level: LogLevel) -> Self {
+    match level {
+      LogLevel::Error => Self::Error,
+      LogLevel::Verbose => Self::Info,
+      LogLevel::Debug => Self::Debug,
+      LogLevel::Data => Self::Trace,
+    }
+  }
+}
+
+/// Limit the messages of a domain to `level`, [`None`] disables the domain
+///
+/// The level also applies to all subdomains (`ptp2` includes `ptp2/usb`), the most specific
+/// domain configured wins. The empty domain applies to all messages.
+pub fn set_domain_level(domain: &str, level: Option<LogLevel>) {
+  DOMAIN_LEVELS.write().unwrap_or_else(PoisonError::into_inner).insert(domain.to_owned(), level);
+}
+
+/// Remove the limit set with [`set_domain_level`]
+pub fn reset_domain_level(domain: &str) {
+  DOMAIN_LEVELS.write().unwrap_or_else(PoisonError::into_inner).remove(domain);
+}
+
+/// Register a function receiving all libgphoto2 messages which aren't filtered out
+///
+/// Messages are still forwarded to the logging backend. The sink is called from
+/// the libgphoto2 worker thread and must not wait on tasks.
+pub fn set_log_sink<F>(sink: F)
+where
+  F: FnMut(LogLevel, &str, &str) + 'static + Send,
+{
+  *LOG_SINK.lock().unwrap_or_else(PoisonError::into_inner) = Some(Box::new(sink));
+}
+
+/// Remove the sink set with [`set_log_sink`]
+pub fn unset_log_sink() {
+  *LOG_SINK.lock().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Set how many log lines are attached to errors, defaults to 50
+#[cfg(feature = "error_log")]
+pub fn set_error_log_lines(lines: usize) {
+  let mut recent = RECENT_LINES.lock().unwrap_or_else(PoisonError::into_inner);
+
+  recent.capacity = lines;
+  while recent.lines.len() > lines {
+    recent.lines.pop_front();
+  }
+}
+
+#[cfg(feature = "error_log")]
+pub(crate) fn recent_lines() -> Vec<String> {
+  RECENT_LINES.lock().unwrap_or_else(PoisonError::into_inner).lines.iter().cloned().collect()
+}
+
+/// Most verbose level enabled in the logging backend at compile time
+#[cfg(feature = "extended_logs")]
+pub(crate) fn max_level() -> Option<LogLevel> {
+  #[cfg(not(feature = "tracing"))]
+  let level = log::STATIC_MAX_LEVEL.to_level().map(|level| match level {
+    log::Level::Error | log::Level::Warn => LogLevel::Error,
+    log::Level::Info => LogLevel::Verbose,
+    log::Level::Debug => LogLevel::Debug,
+    log::Level::Trace => LogLevel::Data,
+  });
+
+  #[cfg(feature = "tracing")]
+  let level = tracing::level_filters::STATIC_MAX_LEVEL.into_level().map(|level| match level {
+    tracing::Level::ERROR | tracing::Level::WARN => LogLevel::Error,
+    tracing::Level::INFO => LogLevel::Verbose,
+    tracing::Level::DEBUG => LogLevel::Debug,
+    tracing::Level::TRACE => LogLevel::Data,
+  });
+
+  level
+}
+
+fn domain_enabled(level: LogLevel, domain: &str) -> bool {
+  let levels = DOMAIN_LEVELS.read().unwrap_or_else(PoisonError::into_inner);
+
+  let configured = levels
+    .iter()
+    .filter(|(configured, _)| {
+      configured.is_empty()
+        || domain
+          .strip_prefix(configured.as_str())
+          .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    })
+    .max_by_key(|(configured, _)| configured.len());
+
+  match configured {
+    Some((_, max_level)) => max_level.is_some_and(|max_level| level <= max_level),
+    None => true,
+  }
+}
+
+/// Forward a libgphoto2 message to the sink and the logging backend
+pub(crate) fn dispatch(level: LogLevel, domain: &str, message: &str) {
+  // Errors get the full trace, regardless of the domain filters
+  #[cfg(feature = "error_log")]
+  {
+    let mut recent = RECENT_LINES.lock().unwrap_or_else(PoisonError::into_inner);
+
+    if recent.capacity > 0 {
+      if recent.lines.len() == recent.capacity {
+        recent.lines.pop_front();
+      }
+      recent.lines.push_back(format!("[{level:?}] {domain}: {message}"));
+    }
+  }
+
+  if !domain_enabled(level, domain) {
+    return;
+  }
+
+  if let Some(sink) = LOG_SINK.lock().unwrap_or_else(PoisonError::into_inner).as_mut() {
+    sink(level, domain, message);
+  }
+
+  #[cfg(not(feature = "tracing"))]
+  log::log!(target: &format!("gphoto2::{domain}"), level.into(), "{}", message);
+
+  #[cfg(feature = "tracing")]
+  match level {
+    LogLevel::Error => tracing::error!(target: "gphoto2", domain, "{}", message),
+    LogLevel::Verbose => tracing::info!(target: "gphoto2", domain, "{}", message),
+    LogLevel::Debug => tracing::debug!(target: "gphoto2", domain, "{}", message),
+    LogLevel::Data => tracing::trace!(target: "gphoto2", domain, "{}", message),
+  }
+}
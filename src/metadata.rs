@@ -0,0 +1,116 @@
+//! Object properties of MTP devices
+//!
+//! The `ptp2` driver returns the object properties of a file as XML for the metadata file type,
+//! one element per property like `<Rating property="dc8a" type="0004">50</Rating>`. Uploading
+//! the changed XML with the same file type writes the properties back to the device.
+
+/// Ratings in stars (0 to 5) as stored in the MTP `Rating` property (0 to 99)
+const STAR_VALUES: [u16; 6] = [0, 1, 25, 50, 75, 99];
+
+/// Value of the MTP `Rating` property for `stars`
+pub(crate) fn stars_to_rating(stars: u8) -> Option<u16> {
+  STAR_VALUES.get(usize::from(stars)).copied()
+}
+
+/// Stars of a value of the MTP `Rating` property, rounded to the nearest star
+pub(crate) fn rating_to_stars(rating: u16) -> u8 {
+  (0u8..)
+    .zip(STAR_VALUES)
+    .min_by_key(|(_, value)| value.abs_diff(rating))
+    .map(|(stars, _)| stars)
+    .unwrap_or_default()
+}
+
+/// Range of the text of the element `name` in `xml`, ignoring namespace prefixes
+fn element_text(xml: &str, name: &str) -> Option<(usize, usize)> {
+  let mut offset = 0;
+
+  while let Some(start) = xml[offset..].find('<').map(|start| offset + start + 1) {
+    offset = start;
+
+    let tag_end = start + xml[start..].find('>')?;
+    let tag = &xml[start..tag_end];
+    let tag_name = tag.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or_default();
+    let local_name = tag_name.rsplit(':').next().unwrap_or_default();
+
+    if local_name != name || tag.ends_with('/') {
+      continue;
+    }
+
+    let text_start = tag_end + 1;
+    let text_end = text_start + xml[text_start..].find("</")?;
+
+    return Some((text_start, text_end));
+  }
+
+  None
+}
+
+/// Text of the element `name` in `xml`
+pub(crate) fn property<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+  element_text(xml, name).map(|(start, end)| xml[start..end].trim())
+}
+
+/// Replace the text of the element `name`, `None` if `xml` has no such element
+pub(crate) fn set_property(xml: &str, name: &str, value: &str) -> Option<String> {
+  let (start, end) = element_text(xml, name)?;
+
+  Some(format!("{}{value}{}", &xml[..start], &xml[end..]))
+}
+
+#[cfg(all(test, feature = "test"))]
+mod tests {
+  use super::*;
+
+  const XML: &str = r#"<?xml version="1.0"?>
+<x:objectproperties xmlns:x="urn:ptp">
+  <x:Name property="dc44" type="ffff">IMG_0001.JPG</x:Name>
+  <x:Rating property="dc8a" type="0004"> 50 </x:Rating>
+</x:objectproperties>"#;
+
+  #[test]
+  fn test_property() {
+    assert_eq!(property(XML, "Rating"), Some("50"));
+    assert_eq!(property(XML, "Name"), Some("IMG_0001.JPG"));
+    assert_eq!(property("<Rating>25</Rating>", "Rating"), Some("25"));
+
+    // Self-closing elements have no text, later elements are still found
+    assert_eq!(property("<Rating/>", "Rating"), None);
+    assert_eq!(property(r#"<Rating type="0004" />"#, "Rating"), None);
+    assert_eq!(property("<Rating/><Rating>75</Rating>", "Rating"), Some("75"));
+
+    assert_eq!(property(XML, "Keywords"), None);
+    assert_eq!(property("<RatingCount>3</RatingCount>", "Rating"), None);
+    assert_eq!(property("<Rating>50", "Rating"), None);
+  }
+
+  #[test]
+  fn test_set_property() {
+    let xml = set_property(XML, "Rating", "99").unwrap();
+    assert_eq!(property(&xml, "Rating"), Some("99"));
+    assert_eq!(xml, XML.replace(" 50 ", "99"));
+
+    assert_eq!(set_property("<Rating/>", "Rating", "99"), None);
+    assert_eq!(set_property(XML, "Keywords", "99"), None);
+  }
+
+  #[test]
+  fn test_stars() {
+    assert_eq!(stars_to_rating(0), Some(0));
+    assert_eq!(stars_to_rating(3), Some(50));
+    assert_eq!(stars_to_rating(5), Some(99));
+    assert_eq!(stars_to_rating(6), None);
+
+    for stars in 0..=5 {
+      assert_eq!(rating_to_stars(stars_to_rating(stars).unwrap()), stars);
+    }
+
+    // Rounded to the nearest star, ties to the lower one
+    assert_eq!(rating_to_stars(12), 1);
+    assert_eq!(rating_to_stars(13), 1);
+    assert_eq!(rating_to_stars(37), 2);
+    assert_eq!(rating_to_stars(38), 3);
+    assert_eq!(rating_to_stars(100), 5);
+    assert_eq!(rating_to_stars(u16::MAX), 5);
+  }
+}
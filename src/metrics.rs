@@ -0,0 +1,133 @@
+//! Counts and durations of camera operations
+//!
+//! With the `metrics` feature every context records how often captures, downloads, uploads and
+//! configuration operations ran, how many failed and how long they took. The numbers can be read
+//! with `Context::metrics` and are also reported to the [`metrics`](https://docs.rs/metrics)
+//! crate as `gphoto2_operations_total` (labels `operation` and `result`) and
+//! `gphoto2_operation_duration_seconds` (label `operation`).
+//!
+//! The durations only include the time on the libgphoto2 thread, not the time waiting for it.
+
+#[cfg(feature = "metrics")]
+use std::{
+  collections::HashMap,
+  sync::{Mutex, PoisonError},
+  time::Duration,
+};
+
+/// Kind of a recorded operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Operation {
+  /// Image, sound or movie capture, including [`Camera::trigger_capture`](crate::Camera::trigger_capture)
+  Capture,
+  /// Preview capture, used by live views
+  Preview,
+  /// File download
+  Download,
+  /// File upload
+  Upload,
+  /// Reading configurations
+  ConfigRead,
+  /// Writing configurations
+  ConfigWrite,
+}
+
+impl Operation {
+  /// Name used as label of the `metrics` crate
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Capture => "capture",
+      Self::Preview => "preview",
+      Self::Download => "download",
+      Self::Upload => "upload",
+      Self::ConfigRead => "config_read",
+      Self::ConfigWrite => "config_write",
+    }
+  }
+}
+
+/// Statistics of one kind of operation
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OperationStats {
+  /// Number of finished operations
+  pub count: u64,
+  /// Number of operations which returned an error
+  pub errors: u64,
+  /// Sum of all durations
+  pub total_duration: Duration,
+  /// Longest duration
+  pub max_duration: Duration,
+}
+
+#[cfg(feature = "metrics")]
+impl OperationStats {
+  /// Average duration, [`None`] if no operation finished yet
+  pub fn average_duration(&self) -> Option<Duration> {
+    let count = u32::try_from(self.count).ok().filter(|&count| count > 0)?;
+
+    Some(self.total_duration / count)
+  }
+}
+
+/// Statistics of all operations of a context, created by
+/// [`Context::metrics`](crate::Context::metrics)
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MetricsSnapshot {
+  operations: HashMap<Operation, OperationStats>,
+}
+
+#[cfg(feature = "metrics")]
+impl MetricsSnapshot {
+  /// Statistics of an operation, all zero if it never ran
+  pub fn get(&self, operation: Operation) -> OperationStats {
+    self.operations.get(&operation).copied().unwrap_or_default()
+  }
+
+  /// Statistics of all operations which ran at least once
+  pub fn iter(&self) -> impl Iterator<Item = (Operation, &OperationStats)> {
+    self.operations.iter().map(|(operation, stats)| (*operation, stats))
+  }
+}
+
+/// Recorder shared by a context and its tasks
+#[cfg(feature = "metrics")]
+#[derive(Default)]
+pub(crate) struct Metrics {
+  operations: Mutex<HashMap<Operation, OperationStats>>,
+}
+
+#[cfg(feature = "metrics")]
+impl Metrics {
+  pub(crate) fn record(&self, operation: Operation, duration: Duration, success: bool) {
+    {
+      let mut operations = self.operations.lock().unwrap_or_else(PoisonError::into_inner);
+      let stats = operations.entry(operation).or_default();
+
+      stats.count += 1;
+      stats.errors += u64::from(!success);
+      stats.total_duration += duration;
+      stats.max_duration = stats.max_duration.max(duration);
+    }
+
+    let result = if success { "ok" } else { "error" };
+    ::metrics::counter!("gphoto2_operations_total", "operation" => operation.name(), "result" => result)
+      .increment(1);
+    ::metrics::histogram!("gphoto2_operation_duration_seconds", "operation" => operation.name())
+      .record(duration.as_secs_f64());
+  }
+
+  pub(crate) fn snapshot(&self) -> MetricsSnapshot {
+    MetricsSnapshot {
+      operations: self.operations.lock().unwrap_or_else(PoisonError::into_inner).clone(),
+    }
+  }
+
+  pub(crate) fn reset(&self) {
+    self.operations.lock().unwrap_or_else(PoisonError::into_inner).clear();
+  }
+}
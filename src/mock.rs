@@ -0,0 +1,154 @@
+//! Fake camera for unit tests
+//!
+//! [`MockCamera`] implements [`CameraOps`] without talking to libgphoto2. Configurations and
+//! files are kept in memory, captures and events are returned in the order they were scripted.
+//!
+//! ```
+//! use gphoto2::{mock::MockCamera, ops::{CameraOps, ConfigValue}, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = MockCamera::new();
+//! camera.insert_config("iso", ConfigValue::Choice("100".into()));
+//! camera.push_capture("/store_00010001/DCIM/100CANON", "IMG_0001.JPG", b"jpeg data".to_vec())?;
+//!
+//! camera.set_config_value("iso", &ConfigValue::Choice("400".into()))?;
+//! let path = camera.capture_image()?;
+//!
+//! assert_eq!(camera.download(&path.folder(), &path.name())?, b"jpeg data");
+//! assert_eq!(camera.config_value("iso")?, ConfigValue::Choice("400".into()));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+  camera::CameraEvent,
+  file::CameraFilePath,
+  ops::{CameraOps, ConfigValue},
+  Error, Result,
+};
+use std::{
+  collections::{BTreeMap, HashMap, VecDeque},
+  sync::{Mutex, MutexGuard, PoisonError},
+  time::Duration,
+};
+
+/// In-memory [`CameraOps`] implementation with scripted responses
+#[derive(Default)]
+pub struct MockCamera {
+  state: Mutex<MockState>,
+}
+
+#[derive(Default)]
+struct MockState {
+  captures: VecDeque<Result<(CameraFilePath, Vec<u8>)>>,
+  events: VecDeque<CameraEvent>,
+  config: HashMap<String, ConfigValue>,
+  /// Files by folder and name
+  files: BTreeMap<String, BTreeMap<String, Vec<u8>>>,
+}
+
+impl MockCamera {
+  /// Create a camera without configurations or files
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  fn state(&self) -> MutexGuard<'_, MockState> {
+    self.state.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  /// Add a configuration, which can then be read and changed
+  pub fn insert_config(&self, key: &str, value: ConfigValue) {
+    self.state().config.insert(key.to_owned(), value);
+  }
+
+  /// Add a file to the camera storage
+  pub fn insert_file(&self, folder: &str, name: &str, data: Vec<u8>) {
+    self.state().files.entry(folder.to_owned()).or_default().insert(name.to_owned(), data);
+  }
+
+  /// Let the next capture create a file with `data`
+  pub fn push_capture(&self, folder: &str, name: &str, data: Vec<u8>) -> Result<()> {
+    let path = CameraFilePath::new(folder, name)?;
+    self.state().captures.push_back(Ok((path, data)));
+
+    Ok(())
+  }
+
+  /// Let the next capture fail with `error`
+  pub fn push_capture_error(&self, error: Error) {
+    self.state().captures.push_back(Err(error));
+  }
+
+  /// Queue an event returned by [`CameraOps::wait_event`]
+  pub fn push_event(&self, event: CameraEvent) {
+    self.state().events.push_back(event);
+  }
+}
+
+impl CameraOps for MockCamera {
+  fn capture_image(&self) -> Result<CameraFilePath> {
+    let mut state = self.state();
+
+    let (path, data) =
+      state.captures.pop_front().unwrap_or_else(|| Err(Error::from("No capture was scripted")))?;
+
+    state
+      .files
+      .entry(path.folder().into_owned())
+      .or_default()
+      .insert(path.name().into_owned(), data);
+    state.events.push_back(CameraEvent::CaptureComplete);
+
+    Ok(path)
+  }
+
+  fn config_value(&self, key: &str) -> Result<ConfigValue> {
+    self.state().config.get(key).cloned().ok_or_else(|| config_not_found(key))
+  }
+
+  fn set_config_value(&self, key: &str, value: &ConfigValue) -> Result<()> {
+    let mut state = self.state();
+
+    state.config.get_mut(key).ok_or_else(|| config_not_found(key))?.update(value)
+  }
+
+  fn list_files(&self, folder: &str) -> Result<Vec<String>> {
+    let state = self.state();
+    let files = state
+      .files
+      .get(folder)
+      .ok_or_else(|| Error::new(libgphoto2_sys::GP_ERROR_DIRECTORY_NOT_FOUND, None))?;
+
+    Ok(files.keys().cloned().collect())
+  }
+
+  fn download(&self, folder: &str, file: &str) -> Result<Vec<u8>> {
+    self
+      .state()
+      .files
+      .get(folder)
+      .and_then(|files| files.get(file))
+      .cloned()
+      .ok_or_else(|| Error::new(libgphoto2_sys::GP_ERROR_FILE_NOT_FOUND, None))
+  }
+
+  fn delete_file(&self, folder: &str, file: &str) -> Result<()> {
+    self
+      .state()
+      .files
+      .get_mut(folder)
+      .and_then(|files| files.remove(file))
+      .map(|_| ())
+      .ok_or_else(|| Error::new(libgphoto2_sys::GP_ERROR_FILE_NOT_FOUND, None))
+  }
+
+  /// Returns the next queued event, or [`CameraEvent::Timeout`] right away if there is none
+  fn wait_event(&self, _timeout: Duration) -> Result<CameraEvent> {
+    Ok(self.state().events.pop_front().unwrap_or(CameraEvent::Timeout))
+  }
+}
+
+fn config_not_found(key: &str) -> Error {
+  Error::new(libgphoto2_sys::GP_ERROR_BAD_PARAMETERS, Some(format!("Config {key} not found")))
+}
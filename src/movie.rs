@@ -0,0 +1,100 @@
+//! Movie recording, see [`Camera::start_movie`](crate::Camera::start_movie)
+//!
+//! Most DSLRs can't record movies through `gp_camera_capture`, instead recording is started and
+//! stopped with a configuration. Canon EOS cameras additionally have to be switched to the
+//! movie mode with `eosmoviemode` first.
+
+use crate::{
+  camera::{get_single_config, set_single_config, wait_for_event, CameraEvent},
+  file::CameraFilePath,
+  support::{require_capability, Capability},
+  widget::Widget,
+  Error, Result,
+};
+use std::time::{Duration, Instant};
+
+/// Configurations starting and stopping the recording, in order of preference
+pub(crate) const RECORD_KEYS: &[&str] = &["movie", "movierecord"];
+/// Configuration switching Canon EOS cameras to the movie mode
+const MOVIE_MODE_KEY: &str = "eosmoviemode";
+
+/// Set an on/off configuration, which is a toggle on most drivers
+unsafe fn set_switch(
+  camera: *mut libgphoto2_sys::Camera,
+  widget: &Widget,
+  on: bool,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<()> {
+  match widget {
+    Widget::Toggle(toggle) => toggle.set_toggled_forced(on),
+    Widget::Radio(radio) => {
+      let keywords: &[&str] = if on { &["on", "start", "1"] } else { &["off", "stop", "0"] };
+      let choice = radio
+        .choices_iter()
+        .find(|choice| keywords.iter().any(|keyword| choice.eq_ignore_ascii_case(keyword)))
+        .ok_or_else(|| Error::from(format!("Unknown choices of {}", radio.name())))?;
+
+      radio.set_choice_forced(&choice)?;
+    }
+    Widget::Text(text) => text.set_value_forced(if on { "1" } else { "0" })?,
+    _ => return Err(Error::from(format!("Unsupported movie config {widget:?}"))),
+  }
+
+  set_single_config(camera, widget, context)
+}
+
+unsafe fn record_widget(
+  camera: *mut libgphoto2_sys::Camera,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<Widget> {
+  RECORD_KEYS.iter().find_map(|key| get_single_config(camera, key, context).ok()).ok_or_else(|| {
+    Error::new(
+      libgphoto2_sys::GP_ERROR_NOT_SUPPORTED,
+      Some("The camera has no configuration to record movies, try Camera::capture_movie".into()),
+    )
+  })
+}
+
+pub(crate) unsafe fn start(
+  camera: *mut libgphoto2_sys::Camera,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<()> {
+  require_capability(camera, Capability::Movie, context)?;
+
+  if let Ok(movie_mode) = get_single_config(camera, MOVIE_MODE_KEY, context) {
+    set_switch(camera, &movie_mode, true, context)?;
+  }
+
+  set_switch(camera, &record_widget(camera, context)?, true, context)
+}
+
+pub(crate) unsafe fn stop(
+  camera: *mut libgphoto2_sys::Camera,
+  timeout: Duration,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<CameraFilePath> {
+  set_switch(camera, &record_widget(camera, context)?, false, context)?;
+
+  let deadline = Instant::now().checked_add(timeout).ok_or("Timeout is too large")?;
+
+  let path = loop {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+
+    match wait_for_event(camera, remaining, context)? {
+      CameraEvent::NewFile(path) => break path,
+      CameraEvent::Timeout if remaining.is_zero() => {
+        return Err(Error::new(
+          libgphoto2_sys::GP_ERROR_TIMEOUT,
+          Some("The camera didn't report the movie file".into()),
+        ))
+      }
+      _ => {}
+    }
+  };
+
+  if let Ok(movie_mode) = get_single_config(camera, MOVIE_MODE_KEY, context) {
+    set_switch(camera, &movie_mode, false, context)?;
+  }
+
+  Ok(path)
+}
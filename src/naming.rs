@@ -0,0 +1,342 @@
+//! File names of downloaded files
+//!
+//! A [`NamingTemplate`] builds the name of a downloaded file from tokens in braces:
+//!
+//! | Token             | Value                                                       |
+//! |-------------------|-------------------------------------------------------------|
+//! | `{camera_serial}` | Serial number of the camera, `unknown` if it reports none   |
+//! | `{camera_model}`  | Model of the camera                                         |
+//! | `{capture_time}`  | Time of the capture like `20240131_142501`                  |
+//! | `{seq}`           | Number of the file in the batch, `{seq:4}` pads to 4 digits |
+//! | `{orig_name}`     | Name of the file on the camera                              |
+//! | `{orig_stem}`     | Name of the file on the camera without extension            |
+//! | `{ext}`           | Extension of the file on the camera, without the dot        |
+//!
+//! `{{` and `}}` are literal braces. The template may contain `/` to sort the files into
+//! subdirectories, separators in the token values are replaced by `_`. If a file with the
+//! rendered name exists, `_1`, `_2`, ... is added before the extension so no file is overwritten.
+//!
+//! ```no_run
+//! use gphoto2::{naming::NamingTemplate, Context, Result};
+//! use std::path::Path;
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//! let template = NamingTemplate::parse("{capture_time}_{camera_serial}_{seq:4}.{ext}")?;
+//!
+//! let files = camera
+//!   .fs()
+//!   .download_folder_named("/store_00010001/DCIM/100CANON", Path::new("shoot"), &template)
+//!   .wait()?;
+//! println!("Downloaded {} files", files.len());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+  camera::{camera_abilities, serial_number},
+  clock,
+  file::FileType,
+  filesys::get_camera_file,
+  Error, Result,
+};
+use std::{
+  fmt, fs,
+  path::{Path, PathBuf},
+  str::FromStr,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+  CameraSerial,
+  CameraModel,
+  CaptureTime,
+  Seq { width: usize },
+  OrigName,
+  OrigStem,
+  Ext,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+  Literal(String),
+  Token(Token),
+}
+
+/// Template of file names, see the [module](self) documentation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamingTemplate {
+  template: String,
+  parts: Vec<Part>,
+}
+
+/// Values of the tokens of a [`NamingTemplate`]
+#[derive(Debug, Clone)]
+pub struct NamingValues<'a> {
+  /// Serial number of the camera
+  pub camera_serial: Option<&'a str>,
+  /// Model of the camera
+  pub camera_model: &'a str,
+  /// Time of the capture as unix timestamp
+  pub capture_time: i64,
+  /// Number of the file in the batch
+  pub seq: u64,
+  /// Name of the file on the camera
+  pub orig_name: &'a str,
+}
+
+impl NamingTemplate {
+  /// Parse a template, fails on unknown tokens and unbalanced braces
+  pub fn parse(template: &str) -> Result<Self> {
+    let invalid = |reason: String| {
+      Error::new(
+        libgphoto2_sys::GP_ERROR_BAD_PARAMETERS,
+        Some(format!("Invalid naming template {template:?}: {reason}")),
+      )
+    };
+
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+      match c {
+        '{' if chars.peek() == Some(&'{') => {
+          chars.next();
+          literal.push('{');
+        }
+        '}' if chars.peek() == Some(&'}') => {
+          chars.next();
+          literal.push('}');
+        }
+        '{' => {
+          let mut token = String::new();
+
+          loop {
+            match chars.next() {
+              Some('}') => break,
+              Some(c) => token.push(c),
+              None => return Err(invalid("unclosed {".to_owned())),
+            }
+          }
+
+          if !literal.is_empty() {
+            parts.push(Part::Literal(std::mem::take(&mut literal)));
+          }
+
+          parts.push(Part::Token(
+            parse_token(&token).ok_or_else(|| invalid(format!("unknown token {{{token}}}")))?,
+          ));
+        }
+        '}' => return Err(invalid("unmatched }".to_owned())),
+        c => literal.push(c),
+      }
+    }
+
+    if !literal.is_empty() {
+      parts.push(Part::Literal(literal));
+    }
+
+    if parts.is_empty() {
+      return Err(invalid("empty template".to_owned()));
+    }
+
+    Ok(Self { template: template.to_owned(), parts })
+  }
+
+  /// File name for `values`, relative to the download directory
+  ///
+  /// ```
+  /// use gphoto2::naming::{NamingTemplate, NamingValues};
+  ///
+  /// let template = NamingTemplate::parse("{capture_time}_{seq:3}_{orig_stem}.{ext}").unwrap();
+  /// let values = NamingValues {
+  ///   camera_serial: None,
+  ///   camera_model: "Canon EOS 80D",
+  ///   capture_time: 1_706_711_101,
+  ///   seq: 7,
+  ///   orig_name: "IMG_0001.CR2",
+  /// };
+  ///
+  /// assert_eq!(template.render(&values), "20240131_142501_007_IMG_0001.CR2");
+  /// ```
+  pub fn render(&self, values: &NamingValues) -> String {
+    let (stem, ext) = match values.orig_name.rsplit_once('.') {
+      Some((stem, ext)) if !stem.is_empty() => (stem, ext),
+      _ => (values.orig_name, ""),
+    };
+
+    self
+      .parts
+      .iter()
+      .map(|part| match part {
+        Part::Literal(literal) => literal.clone(),
+        Part::Token(token) => sanitize(&match token {
+          Token::CameraSerial => values.camera_serial.unwrap_or("unknown").to_owned(),
+          Token::CameraModel => values.camera_model.to_owned(),
+          Token::CaptureTime => {
+            let (year, month, day, hour, minute, second) = clock::to_civil(values.capture_time);
+            format!("{year:04}{month:02}{day:02}_{hour:02}{minute:02}{second:02}")
+          }
+          Token::Seq { width } => format!("{:0width$}", values.seq),
+          Token::OrigName => values.orig_name.to_owned(),
+          Token::OrigStem => stem.to_owned(),
+          Token::Ext => ext.to_owned(),
+        }),
+      })
+      .collect()
+  }
+
+  /// Path in `dir` for `values` which doesn't exist yet
+  ///
+  /// If the rendered name exists, `_1`, `_2`, ... is added before the extension.
+  pub fn unique_path(&self, dir: &Path, values: &NamingValues) -> PathBuf {
+    let path = dir.join(self.render(values));
+
+    if !path.exists() {
+      return path;
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let ext = path.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+
+    (1..)
+      .map(|n| path.with_file_name(format!("{stem}_{n}{ext}")))
+      .find(|path| !path.exists())
+      .unwrap_or(path)
+  }
+}
+
+impl FromStr for NamingTemplate {
+  type Err = Error;
+
+  fn from_str(template: &str) -> Result<Self> {
+    Self::parse(template)
+  }
+}
+
+impl fmt::Display for NamingTemplate {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.template)
+  }
+}
+
+fn parse_token(token: &str) -> Option<Token> {
+  Some(match token {
+    "camera_serial" => Token::CameraSerial,
+    "camera_model" => Token::CameraModel,
+    "capture_time" => Token::CaptureTime,
+    "seq" => Token::Seq { width: 1 },
+    "orig_name" => Token::OrigName,
+    "orig_stem" => Token::OrigStem,
+    "ext" => Token::Ext,
+    _ => Token::Seq { width: token.strip_prefix("seq:")?.parse().ok()? },
+  })
+}
+
+/// Replace characters which would change the directory of the file
+fn sanitize(value: &str) -> String {
+  let value = value.replace(['/', '\\', '\0'], "_");
+
+  if value == ".." {
+    "_".to_owned()
+  } else {
+    value
+  }
+}
+
+/// Model and serial number of the camera, must be called from a [`Task`](crate::task::Task)
+pub(crate) unsafe fn camera_identity(
+  camera: *mut libgphoto2_sys::Camera,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<(String, Option<String>)> {
+  Ok((camera_abilities(camera)?.model().into_owned(), serial_number(camera, context)))
+}
+
+/// Download a file to the path of `template` in `dir`, must be called from a [`Task`](crate::task::Task)
+pub(crate) unsafe fn download_named(
+  camera: *mut libgphoto2_sys::Camera,
+  folder: &str,
+  values: &NamingValues,
+  dir: &Path,
+  template: &NamingTemplate,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<PathBuf> {
+  let path = template.unique_path(dir, values);
+
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent)?;
+  }
+
+  get_camera_file(camera, folder, values.orig_name, FileType::Normal, Some(&path), context)?;
+
+  Ok(path)
+}
+
+#[cfg(all(test, feature = "test"))]
+mod tests {
+  use super::{NamingTemplate, NamingValues};
+  use std::fs;
+
+  fn values(orig_name: &str) -> NamingValues<'_> {
+    NamingValues {
+      camera_serial: Some("12/34"),
+      camera_model: "Canon EOS 80D",
+      // 2024-01-31 14:25:01 UTC
+      capture_time: 1_706_711_101,
+      seq: 7,
+      orig_name,
+    }
+  }
+
+  fn render(template: &str, orig_name: &str) -> String {
+    NamingTemplate::parse(template).unwrap().render(&values(orig_name))
+  }
+
+  #[test]
+  fn test_render() {
+    assert_eq!(render("{capture_time}_{seq:4}.{ext}", "IMG_0001.JPG"), "20240131_142501_0007.JPG");
+    assert_eq!(render("{camera_model}/{orig_name}", "IMG_0001.JPG"), "Canon EOS 80D/IMG_0001.JPG");
+    assert_eq!(render("{{{seq}}}", "IMG_0001.JPG"), "{7}");
+    assert_eq!(render("{orig_stem}-{ext}", "archive.tar.gz"), "archive.tar-gz");
+    assert_eq!(render("{orig_stem}.{ext}", "README"), "README.");
+    assert_eq!(render("{orig_stem}.{ext}", ".hidden"), ".hidden.");
+  }
+
+  #[test]
+  fn test_sanitize() {
+    // Separators in values never change the directory of the file
+    assert_eq!(render("{camera_serial}", "IMG_0001.JPG"), "12_34");
+    assert_eq!(render("out/{orig_name}", ".."), "out/_");
+    assert_eq!(render("{orig_name}", "..\\x"), ".._x");
+
+    let values = NamingValues { camera_serial: None, ..values("IMG_0001.JPG") };
+    assert_eq!(NamingTemplate::parse("{camera_serial}").unwrap().render(&values), "unknown");
+  }
+
+  #[test]
+  fn test_parse_errors() {
+    for template in ["", "{seq", "seq}", "{unknown}", "{seq:x}", "{}"] {
+      assert!(NamingTemplate::parse(template).is_err(), "{template:?}");
+    }
+
+    let template: NamingTemplate = "{seq:3}.{ext}".parse().unwrap();
+    assert_eq!(template.to_string(), "{seq:3}.{ext}");
+  }
+
+  #[test]
+  fn test_unique_path() {
+    let dir = std::env::temp_dir().join(format!("gphoto2-rs-naming-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let template = NamingTemplate::parse("{orig_name}").unwrap();
+    let values = values("IMG_0001.JPG");
+
+    assert_eq!(template.unique_path(&dir, &values), dir.join("IMG_0001.JPG"));
+    fs::write(dir.join("IMG_0001.JPG"), "").unwrap();
+    assert_eq!(template.unique_path(&dir, &values), dir.join("IMG_0001_1.JPG"));
+    fs::write(dir.join("IMG_0001_1.JPG"), "").unwrap();
+    assert_eq!(template.unique_path(&dir, &values), dir.join("IMG_0001_2.JPG"));
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
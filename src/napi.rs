@@ -0,0 +1,204 @@
+//! Node.js bindings for Electron tethering apps
+//!
+//! The bindings are a [N-API](https://nodejs.org/api/n-api.html) addon built with
+//! `cargo rustc --release --features napi --crate-type cdylib`, the library is renamed to
+//! `gphoto2.node` to be loaded by Node.js. All methods return promises which resolve when the
+//! [`Task`](crate::task::Task) of the call finished, the camera is still only used on the
+//! libgphoto2 thread so the event loop of the app is never blocked.
+//!
+//! ```js
+//! const gphoto2 = require("./gphoto2.node");
+//!
+//! const camera = await gphoto2.autodetectCamera();
+//! console.log(await camera.summary());
+//!
+//! const { folder, name } = await camera.captureImage();
+//! await camera.downloadTo(folder, name, `/tmp/${name}`);
+//!
+//! await camera.setConfig("iso", { type: "choice", value: "800" });
+//! console.log(await camera.getConfig("iso"));
+//! ```
+
+// The napi macros add undocumented public functions to the structs
+#![allow(missing_docs)]
+
+use crate::{file::CameraFilePath, ops::ConfigValue, widget::Widget, Camera, Context, Error};
+use napi::bindgen_prelude::{Buffer, Either3};
+use napi_derive::napi;
+use std::path::Path;
+
+impl From<Error> for napi::Error {
+  fn from(error: Error) -> Self {
+    napi::Error::from_reason(error.to_string())
+  }
+}
+
+/// Path of a file on the camera
+#[napi(object, js_name = "CameraFilePath")]
+pub struct JsCameraFilePath {
+  /// Folder of the file
+  pub folder: String,
+  /// Name of the file
+  pub name: String,
+}
+
+impl From<CameraFilePath> for JsCameraFilePath {
+  fn from(path: CameraFilePath) -> Self {
+    Self { folder: path.folder().into_owned(), name: path.name().into_owned() }
+  }
+}
+
+/// A [`ConfigValue`], the `type` is the name of the variant in lowercase (`text`, `range`,
+/// `toggle`, `choice` or `date`)
+#[napi(object, js_name = "ConfigValue")]
+pub struct JsConfigValue {
+  /// Type of the value
+  #[napi(js_name = "type")]
+  pub ty: String,
+  /// A string for texts and choices, a number for ranges and dates, a boolean for toggles
+  pub value: Either3<String, f64, bool>,
+}
+
+impl From<ConfigValue> for JsConfigValue {
+  fn from(value: ConfigValue) -> Self {
+    // JS numbers are f64, timestamps fit into their 53 bit mantissa
+    #[allow(clippy::as_conversions)]
+    let (ty, value) = match value {
+      ConfigValue::Text(text) => ("text", Either3::A(text)),
+      ConfigValue::Range(number) => ("range", Either3::B(number.into())),
+      ConfigValue::Toggle(toggled) => ("toggle", Either3::C(toggled)),
+      ConfigValue::Choice(choice) => ("choice", Either3::A(choice)),
+      ConfigValue::Date(timestamp) => ("date", Either3::B(timestamp as f64)),
+    };
+
+    Self { ty: ty.to_owned(), value }
+  }
+}
+
+impl TryFrom<JsConfigValue> for ConfigValue {
+  type Error = Error;
+
+  fn try_from(value: JsConfigValue) -> Result<Self, Error> {
+    // JS numbers are f64, ranges are f32 and dates whole seconds
+    #[allow(clippy::as_conversions)]
+    Ok(match (value.ty.as_str(), value.value) {
+      ("text", Either3::A(text)) => Self::Text(text),
+      ("range", Either3::B(number)) => Self::Range(number as f32),
+      ("toggle", Either3::C(toggled)) => Self::Toggle(toggled),
+      ("choice", Either3::A(choice)) => Self::Choice(choice),
+      ("date", Either3::B(timestamp)) if timestamp.fract() == 0.0 => Self::Date(timestamp as i64),
+      (ty, value) => return Err(Error::from(format!("Invalid {ty} value {value:?}"))),
+    })
+  }
+}
+
+/// Configuration of the camera, returned by `getConfig`
+#[napi(object, js_name = "Config")]
+pub struct JsConfig {
+  /// Name of the configuration
+  pub name: String,
+  /// Label of the configuration
+  pub label: String,
+  /// Whether the configuration can't be changed
+  pub readonly: bool,
+  /// Current value, missing for sections, buttons and toggles in an unknown state
+  pub value: Option<JsConfigValue>,
+  /// Choices of radio widgets
+  pub choices: Option<Vec<String>>,
+}
+
+impl From<&Widget> for JsConfig {
+  fn from(widget: &Widget) -> Self {
+    let choices = match widget {
+      Widget::Radio(radio) => Some(radio.choices_iter().collect()),
+      _ => None,
+    };
+
+    Self {
+      name: widget.name(),
+      label: widget.label(),
+      readonly: widget.readonly(),
+      value: ConfigValue::from_widget(widget).ok().map(JsConfigValue::from),
+      choices,
+    }
+  }
+}
+
+/// Detect the first connected camera
+#[napi]
+pub async fn autodetect_camera() -> napi::Result<JsCamera> {
+  let camera = Context::new()?.autodetect_camera().await?;
+
+  Ok(JsCamera { camera })
+}
+
+/// Camera of the Node.js bindings, see the [module](self) documentation
+#[napi(js_name = "Camera")]
+pub struct JsCamera {
+  camera: Camera,
+}
+
+#[napi]
+impl JsCamera {
+  /// Summary of the camera, see [`Camera::summary`]
+  #[napi]
+  pub async fn summary(&self) -> napi::Result<String> {
+    Ok(self.camera.summary().await?)
+  }
+
+  /// Capture an image, resolves to the path of the image on the camera
+  #[napi]
+  pub async fn capture_image(&self) -> napi::Result<JsCameraFilePath> {
+    Ok(self.camera.capture_image().await?.into())
+  }
+
+  /// Trigger a capture without waiting for the image
+  #[napi]
+  pub async fn trigger_capture(&self) -> napi::Result<()> {
+    Ok(self.camera.trigger_capture().await?)
+  }
+
+  /// Capture a preview image, resolves to the JPEG data
+  #[napi]
+  pub async fn capture_preview(&self) -> napi::Result<Buffer> {
+    let preview = self.camera.capture_preview().await?;
+
+    Ok(preview.get_data(&self.camera.context).await?.into_vec().into())
+  }
+
+  /// Download a file of the camera into memory
+  #[napi]
+  pub async fn download(&self, folder: String, name: String) -> napi::Result<Buffer> {
+    let file = self.camera.fs().download(folder.as_str(), &name).await?;
+
+    Ok(file.get_data(&self.camera.context).await?.into_vec().into())
+  }
+
+  /// Download a file of the camera to `path`
+  #[napi]
+  pub async fn download_to(&self, folder: String, name: String, path: String) -> napi::Result<()> {
+    self.camera.fs().download_to(folder.as_str(), &name, Path::new(&path)).await?;
+
+    Ok(())
+  }
+
+  /// Configuration with the name `key`
+  #[napi]
+  pub async fn get_config(&self, key: String) -> napi::Result<JsConfig> {
+    let widget = self.camera.config_key::<Widget>(&key).await?;
+
+    Ok(JsConfig::from(&widget))
+  }
+
+  /// Change the configuration `key`, `value` must have the type of the configuration
+  #[napi]
+  pub async fn set_config(&self, key: String, value: JsConfigValue) -> napi::Result<()> {
+    let value = ConfigValue::try_from(value)?;
+    let widget = self.camera.config_key::<Widget>(&key).await?;
+
+    value.apply_to(&widget)?;
+    self.camera.set_config(&widget).await?;
+
+    Ok(())
+  }
+}
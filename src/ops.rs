@@ -0,0 +1,155 @@
+//! Camera operations as a trait
+//!
+//! [`CameraOps`] covers the operations most applications need (capturing, configuration,
+//! files and events) with plain Rust types. Code written against it can be tested with
+//! [`MockCamera`](crate::mock::MockCamera) instead of a real camera, or used with a camera of
+//! another machine with `RemoteCamera` of the `remote` feature.
+//!
+//! ```no_run
+//! use gphoto2::{ops::CameraOps, Context, Result};
+//!
+//! fn capture_and_download(camera: &impl CameraOps) -> Result<Vec<u8>> {
+//!   let path = camera.capture_image()?;
+//!   camera.download(&path.folder(), &path.name())
+//! }
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//! let image = capture_and_download(&camera)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+  camera::CameraEvent, clock, file::CameraFilePath, widget::Widget, Camera, Error, Result,
+};
+use std::{mem, time::Duration};
+
+/// Value of a single configuration
+///
+/// With the `serde` feature values are (de)serialized with their type, like
+/// `{"type": "choice", "value": "400"}`, the same format is used by the `service` feature.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value", rename_all = "lowercase"))]
+pub enum ConfigValue {
+  /// Value of a [`TextWidget`](crate::widget::TextWidget)
+  Text(String),
+  /// Value of a [`RangeWidget`](crate::widget::RangeWidget)
+  Range(f32),
+  /// Value of a [`ToggleWidget`](crate::widget::ToggleWidget)
+  Toggle(bool),
+  /// Selected choice of a [`RadioWidget`](crate::widget::RadioWidget)
+  Choice(String),
+  /// Unix timestamp of a [`DateWidget`](crate::widget::DateWidget)
+  ///
+  /// 64 bit, even though libgphoto2 stores 32 bit timestamps. Setting dates after 2038 fails
+  /// like [`DateWidget::set_datetime`](crate::widget::DateWidget::set_datetime).
+  Date(i64),
+}
+
+impl ConfigValue {
+  /// Read the value of a widget, groups and buttons have no value
+  pub fn from_widget(widget: &Widget) -> Result<Self> {
+    Ok(match widget {
+      Widget::Text(widget) => Self::Text(widget.value()),
+      Widget::Range(widget) => Self::Range(widget.value()),
+      Widget::Toggle(widget) => {
+        Self::Toggle(widget.toggled().ok_or("The toggle has an unknown state")?)
+      }
+      Widget::Radio(widget) => Self::Choice(widget.choice()),
+      Widget::Date(widget) => Self::Date(widget.timestamp().into()),
+      Widget::Group(_) | Widget::Button(_) => {
+        return Err(Error::from(format!("Config {} has no value", widget.name())))
+      }
+    })
+  }
+
+  /// Write this value to a widget of the same type
+  pub fn apply_to(&self, widget: &Widget) -> Result<()> {
+    match (self, widget) {
+      (Self::Text(value), Widget::Text(widget)) => widget.set_value(value)?,
+      (Self::Range(value), Widget::Range(widget)) => widget.set_value(*value)?,
+      (Self::Toggle(value), Widget::Toggle(widget)) => widget.set_toggled(*value)?,
+      (Self::Choice(value), Widget::Radio(widget)) => widget.set_choice(value)?,
+      (Self::Date(value), Widget::Date(widget)) => {
+        widget.set_datetime(clock::from_timestamp(*value))?
+      }
+      _ => return Err(Error::from(format!("Can't set {self:?} on {widget:?}"))),
+    }
+
+    Ok(())
+  }
+
+  /// Replace this value with `value` of the same type, like [`ConfigValue::apply_to`] does for
+  /// widgets
+  pub(crate) fn update(&mut self, value: &Self) -> Result<()> {
+    if mem::discriminant(self) != mem::discriminant(value) {
+      return Err(Error::from(format!("Can't set {value:?} on {self:?}")));
+    }
+
+    *self = value.clone();
+    Ok(())
+  }
+}
+
+/// Operations of a camera
+///
+/// All methods block until the operation finished.
+pub trait CameraOps {
+  /// Capture an image, see [`Camera::capture_image`]
+  fn capture_image(&self) -> Result<CameraFilePath>;
+
+  /// Get the value of a configuration, see [`Camera::config_key`]
+  fn config_value(&self, key: &str) -> Result<ConfigValue>;
+
+  /// Set the value of a configuration, see [`Camera::set_config`]
+  fn set_config_value(&self, key: &str, value: &ConfigValue) -> Result<()>;
+
+  /// Names of the files in a folder
+  fn list_files(&self, folder: &str) -> Result<Vec<String>>;
+
+  /// Download the contents of a file
+  fn download(&self, folder: &str, file: &str) -> Result<Vec<u8>>;
+
+  /// Delete a file
+  fn delete_file(&self, folder: &str, file: &str) -> Result<()>;
+
+  /// Wait for an event, see [`Camera::wait_event`]
+  fn wait_event(&self, timeout: Duration) -> Result<CameraEvent>;
+}
+
+impl CameraOps for Camera {
+  fn capture_image(&self) -> Result<CameraFilePath> {
+    Camera::capture_image(self).wait()
+  }
+
+  fn config_value(&self, key: &str) -> Result<ConfigValue> {
+    ConfigValue::from_widget(&self.config_key::<Widget>(key).wait()?)
+  }
+
+  fn set_config_value(&self, key: &str, value: &ConfigValue) -> Result<()> {
+    let widget = self.config_key::<Widget>(key).wait()?;
+    value.apply_to(&widget)?;
+
+    self.set_config_owned(widget).wait()
+  }
+
+  fn list_files(&self, folder: &str) -> Result<Vec<String>> {
+    Ok(self.fs().list_files(folder).wait()?.collect())
+  }
+
+  fn download(&self, folder: &str, file: &str) -> Result<Vec<u8>> {
+    let file = self.fs().download(folder, file).wait()?;
+
+    Ok(file.get_data(&self.context).wait()?.into_vec())
+  }
+
+  fn delete_file(&self, folder: &str, file: &str) -> Result<()> {
+    self.fs().delete_file(folder, file).wait()
+  }
+
+  fn wait_event(&self, timeout: Duration) -> Result<CameraEvent> {
+    Camera::wait_event(self, timeout).wait()
+  }
+}
@@ -0,0 +1,264 @@
+//! Image orientation
+//!
+//! Cameras store portrait captures in landscape and record the rotation in the EXIF
+//! orientation tag instead. [`Orientation::detect`] reads this tag from JPEG or TIFF based
+//! (most raw formats) files and [`Orientation::transform_rgb24`] applies it to decoded pixels.
+//!
+//! ```no_run
+//! use gphoto2::{Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let context = Context::new()?;
+//! let camera = context.autodetect_camera().wait()?;
+//!
+//! let path = camera.capture_image().wait()?;
+//! let file = camera.fs().download(&path.folder(), &path.name()).wait()?;
+//!
+//! if let Some(orientation) = file.orientation(&context).wait()? {
+//!   println!("Orientation: {orientation:?}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+/// EXIF tag number of the orientation
+const ORIENTATION_TAG: u16 = 0x0112;
+
+/// Orientation of an image, as stored in the EXIF orientation tag
+///
+/// The variants describe the transformation needed to display the image upright,
+/// rotations are clockwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Orientation {
+  /// Already upright
+  Normal,
+  /// Mirrored horizontally
+  FlipHorizontal,
+  /// Rotated by 180°
+  Rotate180,
+  /// Mirrored vertically
+  FlipVertical,
+  /// Mirrored along the top-left to bottom-right diagonal
+  Transpose,
+  /// Must be rotated by 90°
+  Rotate90,
+  /// Mirrored along the top-right to bottom-left diagonal
+  Transverse,
+  /// Must be rotated by 270°
+  Rotate270,
+}
+
+impl Orientation {
+  /// Convert the value of the EXIF orientation tag (1 to 8)
+  pub fn from_exif_value(value: u16) -> Option<Self> {
+    Some(match value {
+      1 => Self::Normal,
+      2 => Self::FlipHorizontal,
+      3 => Self::Rotate180,
+      4 => Self::FlipVertical,
+      5 => Self::Transpose,
+      6 => Self::Rotate90,
+      7 => Self::Transverse,
+      8 => Self::Rotate270,
+      _ => return None,
+    })
+  }
+
+  /// Read the orientation from a JPEG file, a TIFF based file or a raw EXIF blob
+  ///
+  /// Returns [`None`] if the data contains no (valid) orientation tag.
+  pub fn detect(data: &[u8]) -> Option<Self> {
+    if data.starts_with(&[0xff, 0xd8]) {
+      Self::from_tiff(jpeg_exif_segment(data)?)
+    } else if let Some(tiff) = data.strip_prefix(b"Exif\0\0") {
+      Self::from_tiff(tiff)
+    } else {
+      Self::from_tiff(data)
+    }
+  }
+
+  /// Whether width and height are swapped when displaying the image
+  pub fn swaps_dimensions(&self) -> bool {
+    matches!(self, Self::Transpose | Self::Rotate90 | Self::Transverse | Self::Rotate270)
+  }
+
+  /// Transform RGB24 pixels of a `width` x `height` image so it is displayed upright
+  ///
+  /// Returns the transformed pixels together with their width and height.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `pixels` is shorter than `width * height * 3` bytes.
+  pub fn transform_rgb24(
+    &self,
+    pixels: &[u8],
+    width: usize,
+    height: usize,
+  ) -> (Vec<u8>, usize, usize) {
+    let (out_width, out_height) =
+      if self.swaps_dimensions() { (height, width) } else { (width, height) };
+    let mut out = Vec::with_capacity(pixels.len());
+
+    for y in 0..out_height {
+      for x in 0..out_width {
+        let (src_x, src_y) = match self {
+          Self::Normal => (x, y),
+          Self::FlipHorizontal => (width - 1 - x, y),
+          Self::Rotate180 => (width - 1 - x, height - 1 - y),
+          Self::FlipVertical => (x, height - 1 - y),
+          Self::Transpose => (y, x),
+          Self::Rotate90 => (y, height - 1 - x),
+          Self::Transverse => (width - 1 - y, height - 1 - x),
+          Self::Rotate270 => (width - 1 - y, x),
+        };
+
+        let src = (src_y * width + src_x) * 3;
+        out.extend_from_slice(&pixels[src..src + 3]);
+      }
+    }
+
+    (out, out_width, out_height)
+  }
+
+  /// Find the orientation tag in the first IFD of a TIFF structure
+  fn from_tiff(tiff: &[u8]) -> Option<Self> {
+    let big_endian = match tiff.get(..4)? {
+      b"II*\0" => false,
+      b"MM\0*" => true,
+      _ => return None,
+    };
+
+    let read_u16 = |offset: usize| -> Option<u16> {
+      let bytes = tiff.get(offset..offset + 2)?.try_into().ok()?;
+      Some(if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    };
+    let read_u32 = |offset: usize| -> Option<u32> {
+      let bytes = tiff.get(offset..offset + 4)?.try_into().ok()?;
+      Some(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    };
+
+    let ifd = usize::try_from(read_u32(4)?).ok()?;
+    let entries = read_u16(ifd)?;
+
+    (0..usize::from(entries))
+      .map(|entry| ifd + 2 + entry * 12)
+      .find(|&entry| read_u16(entry) == Some(ORIENTATION_TAG))
+      .and_then(|entry| read_u16(entry + 8))
+      .and_then(Self::from_exif_value)
+  }
+}
+
+/// TIFF structure of the EXIF segment (APP1) of a JPEG file
+fn jpeg_exif_segment(jpeg: &[u8]) -> Option<&[u8]> {
+  let mut offset = 2;
+
+  loop {
+    let marker = jpeg.get(offset..offset + 2)?;
+    if marker[0] != 0xff {
+      return None;
+    }
+
+    // Start of scan, no metadata follows
+    if marker[1] == 0xda {
+      return None;
+    }
+
+    let length =
+      usize::from(u16::from_be_bytes(jpeg.get(offset + 2..offset + 4)?.try_into().ok()?));
+    let segment = jpeg.get(offset + 4..offset + 2 + length)?;
+
+    if marker[1] == 0xe1 {
+      if let Some(tiff) = segment.strip_prefix(b"Exif\0\0") {
+        return Some(tiff);
+      }
+    }
+
+    offset += 2 + length;
+  }
+}
+
+#[cfg(all(test, feature = "test"))]
+mod tests {
+  use super::*;
+
+  /// TIFF structure whose first IFD has a make and an orientation tag
+  fn tiff(big_endian: bool, orientation: u16) -> Vec<u8> {
+    let u16_bytes = |value: u16| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+    let u32_bytes = |value: u32| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+
+    let mut tiff = if big_endian { b"MM\0*".to_vec() } else { b"II*\0".to_vec() };
+    tiff.extend(u32_bytes(8));
+    tiff.extend(u16_bytes(2));
+    for (tag, value) in [(0x010f, 0), (ORIENTATION_TAG, orientation)] {
+      tiff.extend(u16_bytes(tag));
+      // SHORT, one value, left-justified in the value field
+      tiff.extend(u16_bytes(3));
+      tiff.extend(u32_bytes(1));
+      tiff.extend(u16_bytes(value));
+      tiff.extend([0, 0]);
+    }
+    tiff.extend(u32_bytes(0));
+
+    tiff
+  }
+
+  fn jpeg(tiff: &[u8]) -> Vec<u8> {
+    let mut jpeg = vec![0xff, 0xd8];
+    // An APP0 segment before the EXIF segment
+    jpeg.extend([0xff, 0xe0, 0, 4, b'J', b'F']);
+    jpeg.extend([0xff, 0xe1]);
+    jpeg.extend(u16::try_from(2 + 6 + tiff.len()).unwrap().to_be_bytes());
+    jpeg.extend(b"Exif\0\0");
+    jpeg.extend(tiff);
+    jpeg.extend([0xff, 0xda, 0, 2]);
+
+    jpeg
+  }
+
+  #[test]
+  fn test_detect() {
+    for big_endian in [false, true] {
+      for value in 1..=8 {
+        let expected = Orientation::from_exif_value(value);
+        let tiff = tiff(big_endian, value);
+
+        assert_eq!(Orientation::detect(&tiff), expected);
+        assert_eq!(Orientation::detect(&[b"Exif\0\0".as_slice(), &tiff].concat()), expected);
+        assert_eq!(Orientation::detect(&jpeg(&tiff)), expected);
+      }
+    }
+
+    assert_eq!(Orientation::detect(&tiff(false, 9)), None);
+    assert_eq!(Orientation::detect(&tiff(true, 6)[..20]), None);
+    assert_eq!(Orientation::detect(b"not an image"), None);
+    assert_eq!(Orientation::detect(&[0xff, 0xd8, 0xff, 0xda, 0, 2]), None);
+  }
+
+  #[test]
+  fn test_transform_rgb24() {
+    // 2x3 image, the red channel numbers the pixels row by row:
+    // 0 1
+    // 2 3
+    // 4 5
+    let pixels: Vec<u8> = (0..6).flat_map(|pixel| [pixel, 10, 20]).collect();
+
+    for (orientation, size, expected) in [
+      (Orientation::Normal, (2, 3), [0, 1, 2, 3, 4, 5]),
+      (Orientation::FlipHorizontal, (2, 3), [1, 0, 3, 2, 5, 4]),
+      (Orientation::Rotate180, (2, 3), [5, 4, 3, 2, 1, 0]),
+      (Orientation::FlipVertical, (2, 3), [4, 5, 2, 3, 0, 1]),
+      (Orientation::Transpose, (3, 2), [0, 2, 4, 1, 3, 5]),
+      (Orientation::Rotate90, (3, 2), [4, 2, 0, 5, 3, 1]),
+      (Orientation::Transverse, (3, 2), [5, 3, 1, 4, 2, 0]),
+      (Orientation::Rotate270, (3, 2), [1, 3, 5, 0, 2, 4]),
+    ] {
+      let (out, width, height) = orientation.transform_rgb24(&pixels, 2, 3);
+      let expected: Vec<u8> = expected.into_iter().flat_map(|pixel| [pixel, 10, 20]).collect();
+
+      assert_eq!((width, height), size, "{orientation:?}");
+      assert_eq!(out, expected, "{orientation:?}");
+      assert_eq!(orientation.swaps_dimensions(), size == (3, 2));
+    }
+  }
+}
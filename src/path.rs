@@ -0,0 +1,255 @@
+//! Folder paths on the camera
+//!
+//! libgphoto2 expects absolute folders without duplicate or trailing slashes, otherwise most
+//! drivers fail with [`ErrorKind::BadParameters`](crate::error::ErrorKind::BadParameters).
+//! [`CameraPath`] validates and normalizes folders before they are passed to the driver.
+//!
+//! ```no_run
+//! use gphoto2::{path::CameraPath, Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//!
+//! let dcim = CameraPath::new("/store_00010001//DCIM/")?;
+//! assert_eq!(dcim.as_str(), "/store_00010001/DCIM");
+//!
+//! for folder in camera.fs().list_folders(&dcim).wait()? {
+//!   println!("{}", dcim.join(&folder)?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{Error, Result};
+use std::{borrow::Borrow, fmt, ops::Deref, str::FromStr};
+
+/// Normalized absolute folder path on a camera
+///
+/// Duplicate slashes, trailing slashes and `.` are removed, `..` is resolved.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "String", into = "String"))]
+pub struct CameraPath(String);
+
+fn invalid(path: &str, reason: &str) -> Error {
+  Error::new(
+    libgphoto2_sys::GP_ERROR_BAD_PARAMETERS,
+    Some(format!("Invalid camera path {path:?}: {reason}")),
+  )
+}
+
+impl CameraPath {
+  /// Validate and normalize an absolute path
+  pub fn new(path: &str) -> Result<Self> {
+    if !path.starts_with('/') {
+      return Err(invalid(path, "must be absolute"));
+    }
+
+    Self::root().join_segments(path, path)
+  }
+
+  /// The root folder `/`
+  pub fn root() -> Self {
+    Self("/".to_owned())
+  }
+
+  /// Whether this is the root folder
+  pub fn is_root(&self) -> bool {
+    self.0 == "/"
+  }
+
+  /// The path as string
+  pub fn as_str(&self) -> &str {
+    &self.0
+  }
+
+  /// Append a relative path, like a folder name returned by
+  /// [`CameraFS::list_folders`](crate::filesys::CameraFS::list_folders)
+  pub fn join(&self, relative: &str) -> Result<Self> {
+    if relative.starts_with('/') {
+      return Err(invalid(relative, "can't join an absolute path"));
+    }
+
+    self.clone().join_segments(relative, relative)
+  }
+
+  /// The parent folder, [`None`] for the root folder
+  pub fn parent(&self) -> Option<Self> {
+    let (parent, _) = self.0.rsplit_once('/').filter(|_| !self.is_root())?;
+
+    Some(if parent.is_empty() { Self::root() } else { Self(parent.to_owned()) })
+  }
+
+  /// Name of the last folder, [`None`] for the root folder
+  pub fn name(&self) -> Option<&str> {
+    self.components().last()
+  }
+
+  /// Folder names from the root
+  pub fn components(&self) -> impl Iterator<Item = &str> {
+    self.0.split('/').filter(|segment| !segment.is_empty())
+  }
+
+  fn join_segments(mut self, segments: &str, original: &str) -> Result<Self> {
+    if segments.contains('\0') {
+      return Err(invalid(original, "contains a NUL character"));
+    }
+
+    for segment in segments.split('/') {
+      match segment {
+        "" | "." => {}
+        ".." => self = self.parent().ok_or_else(|| invalid(original, "leaves the root folder"))?,
+        name => {
+          if !self.is_root() {
+            self.0.push('/');
+          }
+          self.0.push_str(name);
+        }
+      }
+    }
+
+    Ok(self)
+  }
+}
+
+impl Default for CameraPath {
+  fn default() -> Self {
+    Self::root()
+  }
+}
+
+impl fmt::Display for CameraPath {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.0)
+  }
+}
+
+impl fmt::Debug for CameraPath {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Debug::fmt(&self.0, f)
+  }
+}
+
+impl Deref for CameraPath {
+  type Target = str;
+
+  fn deref(&self) -> &str {
+    &self.0
+  }
+}
+
+impl AsRef<str> for CameraPath {
+  fn as_ref(&self) -> &str {
+    &self.0
+  }
+}
+
+impl Borrow<str> for CameraPath {
+  fn borrow(&self) -> &str {
+    &self.0
+  }
+}
+
+impl FromStr for CameraPath {
+  type Err = Error;
+
+  fn from_str(path: &str) -> Result<Self> {
+    Self::new(path)
+  }
+}
+
+impl TryFrom<&str> for CameraPath {
+  type Error = Error;
+
+  fn try_from(path: &str) -> Result<Self> {
+    Self::new(path)
+  }
+}
+
+impl TryFrom<String> for CameraPath {
+  type Error = Error;
+
+  fn try_from(path: String) -> Result<Self> {
+    Self::new(&path)
+  }
+}
+
+impl From<CameraPath> for String {
+  fn from(path: CameraPath) -> Self {
+    path.0
+  }
+}
+
+/// Folder arguments of [`CameraFS`](crate::filesys::CameraFS), either a [`CameraPath`] or a
+/// string which is validated with [`CameraPath::new`]
+pub trait IntoCameraPath {
+  /// Validate and normalize the path
+  fn into_camera_path(self) -> Result<CameraPath>;
+}
+
+impl IntoCameraPath for CameraPath {
+  fn into_camera_path(self) -> Result<CameraPath> {
+    Ok(self)
+  }
+}
+
+impl IntoCameraPath for String {
+  fn into_camera_path(self) -> Result<CameraPath> {
+    CameraPath::new(&self)
+  }
+}
+
+impl<T: AsRef<str> + ?Sized> IntoCameraPath for &T {
+  fn into_camera_path(self) -> Result<CameraPath> {
+    CameraPath::new(self.as_ref())
+  }
+}
+
+#[cfg(all(test, feature = "test"))]
+mod tests {
+  use super::CameraPath;
+  use crate::error::ErrorKind;
+
+  #[test]
+  fn test_normalize() {
+    let path = |path| CameraPath::new(path).unwrap();
+
+    assert_eq!(path("/").as_str(), "/");
+    assert_eq!(path("//").as_str(), "/");
+    assert_eq!(path("/store_00010001//DCIM/").as_str(), "/store_00010001/DCIM");
+    assert_eq!(path("/a/./b/../c").as_str(), "/a/c");
+    assert_eq!(path("/a/..").as_str(), "/");
+    assert!(path("/").is_root());
+  }
+
+  #[test]
+  fn test_invalid() {
+    for invalid in ["", "DCIM", "/..", "/a/../..", "/a\0b"] {
+      let error = CameraPath::new(invalid).unwrap_err();
+      assert_eq!(error.kind(), ErrorKind::BadParameters, "{invalid:?}");
+    }
+  }
+
+  #[test]
+  fn test_join() {
+    let dcim = CameraPath::new("/store_00010001/DCIM").unwrap();
+
+    assert_eq!(dcim.join("100CANON").unwrap().as_str(), "/store_00010001/DCIM/100CANON");
+    assert_eq!(dcim.join("../MISC/").unwrap().as_str(), "/store_00010001/MISC");
+    assert_eq!(CameraPath::root().join("a").unwrap().as_str(), "/a");
+    assert!(dcim.join("/DCIM").is_err());
+    assert!(dcim.join("../../..").is_err());
+  }
+
+  #[test]
+  fn test_parent_and_name() {
+    let path = CameraPath::new("/store_00010001/DCIM").unwrap();
+
+    assert_eq!(path.name(), Some("DCIM"));
+    assert_eq!(path.components().collect::<Vec<_>>(), ["store_00010001", "DCIM"]);
+    assert_eq!(path.parent().unwrap().as_str(), "/store_00010001");
+    assert_eq!(path.parent().unwrap().parent(), Some(CameraPath::root()));
+    assert_eq!(CameraPath::root().parent(), None);
+    assert_eq!(CameraPath::root().name(), None);
+  }
+}
@@ -0,0 +1,80 @@
+//! Differences of libgphoto2 between platforms
+//!
+//! On Windows libgphoto2 finds every camera driver twice, which doubles the progress of loading
+//! the drivers and detects cameras twice, and it uses backslashes in disk ports and in messages.
+//! The values are changed here to match the other platforms.
+
+use std::{borrow::Cow, cell::RefCell, collections::HashSet, os::raw::c_void};
+
+/// Start of the progress message while libgphoto2 loads the camera drivers
+const LOADING_DRIVERS_MESSAGE: &str = "Loading camera drivers from";
+
+thread_local! {
+  /// Progresses of loading drivers by handler, only used on the worker
+  static HALVED_PROGRESS: RefCell<HashSet<(*mut c_void, u32)>> = RefCell::default();
+}
+
+/// Whether libgphoto2 finds the camera drivers twice
+const DUPLICATE_DRIVERS: bool = cfg!(windows);
+
+/// Disk port paths with forward slashes, like `disk:C:/DCIM`
+pub(crate) fn normalize_port_path(path: &str) -> Cow<'_, str> {
+  if cfg!(windows) && path.starts_with("disk:") {
+    Cow::Owned(path.replace('\\', "/"))
+  } else {
+    Cow::Borrowed(path)
+  }
+}
+
+/// Port path as known by libgphoto2, the inverse of [`normalize_port_path`]
+pub(crate) fn native_port_path(path: &str) -> Cow<'_, str> {
+  if cfg!(windows) && path.starts_with("disk:") {
+    Cow::Owned(path.replace('/', "\\"))
+  } else {
+    Cow::Borrowed(path)
+  }
+}
+
+/// Message of a progress with forward slashes in paths
+pub(crate) fn normalize_message(message: String) -> String {
+  if cfg!(windows) {
+    message.replace('\\', "/")
+  } else {
+    message
+  }
+}
+
+/// Start a progress of the handler at `data`, the duplicate drivers are not counted
+pub(crate) fn start_progress(
+  data: *mut c_void,
+  target: f32,
+  message: String,
+  start: impl FnOnce(f32, String) -> u32,
+) -> u32 {
+  let message = normalize_message(message);
+
+  if DUPLICATE_DRIVERS && message.starts_with(LOADING_DRIVERS_MESSAGE) {
+    let id = start(target / 2.0, message);
+    HALVED_PROGRESS.with(|halved| halved.borrow_mut().insert((data, id)));
+
+    id
+  } else {
+    start(target, message)
+  }
+}
+
+/// Progress value reported to the handler at `data`
+pub(crate) fn progress_value(data: *mut c_void, id: u32, current: f32) -> f32 {
+  if DUPLICATE_DRIVERS && HALVED_PROGRESS.with(|halved| halved.borrow().contains(&(data, id))) {
+    current / 2.0
+  } else {
+    current
+  }
+}
+
+/// Forget a progress once it stopped
+pub(crate) fn stop_progress(data: *mut c_void, id: u32) {
+  if DUPLICATE_DRIVERS {
+    HALVED_PROGRESS.with(|halved| halved.borrow_mut().remove(&(data, id)));
+  }
+}
@@ -0,0 +1,413 @@
+//! Distribute work over multiple cameras and contexts
+//!
+//! A [`CameraPool`] runs every submitted job on the next idle camera.
+//! Cameras which fail too many jobs in a row are benched for a while, so a single flaky
+//! camera doesn't fail a whole batch.
+//!
+//! All libgphoto2 calls run on a single worker thread, which the cameras of a pool share as
+//! well. Jobs are distributed over the cameras, but their transfers don't run in parallel:
+//! the pool helps with slow cameras (like waiting for captures), not with throughput of I/O.
+//!
+//! A [`ContextPool`] keeps contexts with loaded driver lists for services which open a camera
+//! for every request, see its documentation.
+//!
+//! ```no_run
+//! use gphoto2::{pool::CameraPool, Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let context = Context::new()?;
+//!
+//! let mut cameras = Vec::new();
+//! for descriptor in context.list_cameras().wait()? {
+//!   cameras.push(context.get_camera(&descriptor).wait()?);
+//! }
+//!
+//! let pool = CameraPool::new(cameras);
+//! let jobs: Vec<_> = (0..100).map(|_| pool.submit(|camera| camera.capture_image().wait())).collect();
+//!
+//! for job in jobs {
+//!   let path = job.wait()?;
+//!   println!("Captured {}", path.name());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{Camera, Context, Error, Result};
+use crossbeam_channel::{bounded, unbounded, Receiver, RecvTimeoutError, Sender};
+use std::{
+  ops::Deref,
+  panic::{catch_unwind, AssertUnwindSafe},
+  sync::{Arc, Mutex, MutexGuard, PoisonError},
+  thread::{self, JoinHandle},
+  time::{Duration, Instant},
+};
+
+/// Returns whether the job succeeded, the result itself is sent through the [`JobHandle`]
+type Job = Box<dyn FnOnce(&Camera) -> bool + Send>;
+
+/// When cameras are taken out of the pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchPolicy {
+  /// Number of failed jobs in a row after which a camera is benched
+  pub max_consecutive_failures: u32,
+  /// How long a benched camera doesn't receive jobs, too large durations bench it until the
+  /// pool is dropped
+  pub duration: Duration,
+}
+
+impl Default for BenchPolicy {
+  fn default() -> Self {
+    Self { max_consecutive_failures: 3, duration: Duration::from_secs(60) }
+  }
+}
+
+/// Statistics of a camera in a [`CameraPool`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CameraStats {
+  /// Model of the camera
+  pub model: String,
+  /// Successfully completed jobs
+  pub completed: u64,
+  /// Failed jobs
+  pub failures: u64,
+  /// Jobs failed since the last successful job (or the end of the last bench)
+  pub consecutive_failures: u32,
+  /// Whether the camera currently doesn't receive jobs
+  pub benched: bool,
+}
+
+struct Member {
+  camera: Camera,
+  model: String,
+  state: Mutex<MemberState>,
+}
+
+#[derive(Default)]
+struct MemberState {
+  completed: u64,
+  failures: u64,
+  consecutive_failures: u32,
+  bench: Option<Bench>,
+}
+
+/// How long a camera is benched
+#[derive(Clone, Copy)]
+enum Bench {
+  Until(Instant),
+  /// The bench duration overflowed the clock
+  Indefinitely,
+}
+
+impl Member {
+  fn state(&self) -> MutexGuard<'_, MemberState> {
+    self.state.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  fn record(&self, success: bool, policy: &BenchPolicy) {
+    let mut state = self.state();
+
+    if success {
+      state.completed += 1;
+      state.consecutive_failures = 0;
+    } else {
+      state.failures += 1;
+      state.consecutive_failures += 1;
+
+      if state.consecutive_failures >= policy.max_consecutive_failures {
+        log::warn!("Benching camera {} for {:?}", self.model, policy.duration);
+
+        state.consecutive_failures = 0;
+        state.bench = Some(match Instant::now().checked_add(policy.duration) {
+          Some(until) => Bench::Until(until),
+          None => Bench::Indefinitely,
+        });
+      }
+    }
+  }
+
+  /// Run jobs until the pool is dropped
+  fn run(&self, jobs: Receiver<Job>, shutdown: Receiver<()>, policy: BenchPolicy) {
+    loop {
+      let bench = self.state().bench;
+
+      match bench {
+        Some(Bench::Until(until)) => {
+          match shutdown.recv_timeout(until.saturating_duration_since(Instant::now())) {
+            Err(RecvTimeoutError::Timeout) => self.state().bench = None,
+            _ => return,
+          }
+        }
+        Some(Bench::Indefinitely) => {
+          // Only returns once the pool is dropped
+          let _ = shutdown.recv();
+          return;
+        }
+        None => {}
+      }
+
+      let Ok(job) = jobs.recv() else {
+        return;
+      };
+
+      let success = catch_unwind(AssertUnwindSafe(|| job(&self.camera))).unwrap_or(false);
+      self.record(success, &policy);
+    }
+  }
+}
+
+/// Pool of cameras processing jobs
+///
+/// Dropping the pool waits until the cameras which aren't benched finished the submitted jobs.
+/// Benched cameras stop right away, if all cameras are benched the remaining jobs are dropped
+/// and [`JobHandle::wait`] fails for them. The cameras share the libgphoto2 worker thread, see
+/// the [module](self) documentation.
+pub struct CameraPool {
+  members: Vec<Arc<Member>>,
+  jobs: Option<Sender<Job>>,
+  shutdown: Option<Sender<()>>,
+  threads: Vec<JoinHandle<()>>,
+}
+
+impl Drop for CameraPool {
+  fn drop(&mut self) {
+    self.jobs.take();
+    self.shutdown.take();
+
+    for thread in self.threads.drain(..) {
+      // Panics in jobs are caught, so this can't fail
+      let _ = thread.join();
+    }
+  }
+}
+
+impl CameraPool {
+  /// Create a pool with the default [`BenchPolicy`]
+  pub fn new(cameras: impl IntoIterator<Item = Camera>) -> Self {
+    Self::with_bench_policy(cameras, BenchPolicy::default())
+  }
+
+  /// Create a pool benching cameras according to `policy`
+  pub fn with_bench_policy(cameras: impl IntoIterator<Item = Camera>, policy: BenchPolicy) -> Self {
+    let (jobs_tx, jobs_rx) = unbounded::<Job>();
+    let (shutdown_tx, shutdown_rx) = bounded::<()>(0);
+
+    let members: Vec<_> = cameras
+      .into_iter()
+      .map(|camera| {
+        // Only used for logging and the stats, empty if the driver fails
+        let model =
+          camera.abilities().map(|abilities| abilities.model().into_owned()).unwrap_or_default();
+        Arc::new(Member { camera, model, state: Default::default() })
+      })
+      .collect();
+
+    let threads = members
+      .iter()
+      .map(|member| {
+        let member = member.clone();
+        let jobs = jobs_rx.clone();
+        let shutdown = shutdown_rx.clone();
+
+        thread::spawn(move || member.run(jobs, shutdown, policy))
+      })
+      .collect();
+
+    Self { members, jobs: Some(jobs_tx), shutdown: Some(shutdown_tx), threads }
+  }
+
+  /// Number of cameras in the pool
+  pub fn len(&self) -> usize {
+    self.members.len()
+  }
+
+  /// Whether the pool has no cameras (submitted jobs never run)
+  pub fn is_empty(&self) -> bool {
+    self.members.is_empty()
+  }
+
+  /// Run `job` on the next idle camera
+  ///
+  /// Jobs returning an error count as failures of the camera they ran on.
+  pub fn submit<T, F>(&self, job: F) -> JobHandle<T>
+  where
+    T: 'static + Send,
+    F: FnOnce(&Camera) -> Result<T> + 'static + Send,
+  {
+    let (tx, rx) = bounded(1);
+
+    let job: Job = Box::new(move |camera| {
+      let result = job(camera);
+      let success = result.is_ok();
+      let _ = tx.send(result);
+
+      success
+    });
+
+    if let Some(jobs) = &self.jobs {
+      let _ = jobs.send(job);
+    }
+
+    JobHandle { rx }
+  }
+
+  /// Statistics of every camera, in the order they were added
+  pub fn stats(&self) -> Vec<CameraStats> {
+    self
+      .members
+      .iter()
+      .map(|member| {
+        let state = member.state();
+
+        CameraStats {
+          model: member.model.clone(),
+          completed: state.completed,
+          failures: state.failures,
+          consecutive_failures: state.consecutive_failures,
+          benched: match state.bench {
+            Some(Bench::Until(until)) => until > Instant::now(),
+            Some(Bench::Indefinitely) => true,
+            None => false,
+          },
+        }
+      })
+      .collect()
+  }
+}
+
+/// Result of a job submitted to a [`CameraPool`]
+pub struct JobHandle<T> {
+  rx: Receiver<Result<T>>,
+}
+
+impl<T> JobHandle<T> {
+  /// Block until the job finished
+  pub fn wait(self) -> Result<T> {
+    self.rx.recv().unwrap_or_else(|_| Err(Error::from("The job panicked or was never run")))
+  }
+}
+
+type ContextFactory = dyn Fn() -> Result<Context> + Send + Sync;
+
+struct ContextPoolShared {
+  idle: Mutex<Vec<Context>>,
+  factory: Box<ContextFactory>,
+  max_idle: usize,
+}
+
+impl ContextPoolShared {
+  /// Create a context and load its driver lists
+  fn warm_up(&self) -> Result<Context> {
+    let context = (self.factory)()?;
+
+    context.abilities_list().wait()?;
+    context.port_info_list().wait()?;
+
+    Ok(context)
+  }
+}
+
+/// Contexts with loaded driver lists, handed out with [`checkout`](Self::checkout)
+///
+/// Creating a [`Context`] and loading the camera and port drivers takes a while, which adds up
+/// for web services opening a camera for every short request. A checked out context returns
+/// to the pool when the [`PooledContext`] is dropped, unless cameras or other clones of the
+/// context are still alive, then it is discarded so its handlers aren't shared.
+///
+/// ```no_run
+/// use gphoto2::{pool::ContextPool, Result};
+///
+/// # fn main() -> Result<()> {
+/// let pool = ContextPool::new(4)?;
+///
+/// // For every request
+/// let context = pool.checkout()?;
+/// let summary = context.autodetect_camera().wait()?.summary().wait()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ContextPool {
+  shared: Arc<ContextPoolShared>,
+}
+
+impl ContextPool {
+  /// Create `size` contexts with [`Context::new`], at most `size` idle contexts are kept
+  pub fn new(size: usize) -> Result<Self> {
+    Self::with_factory(size, Context::new)
+  }
+
+  /// Create `size` contexts with `factory`, like a [`ContextBuilder`](crate::context::ContextBuilder)
+  pub fn with_factory<F>(size: usize, factory: F) -> Result<Self>
+  where
+    F: Fn() -> Result<Context> + Send + Sync + 'static,
+  {
+    let shared = ContextPoolShared {
+      idle: Mutex::new(Vec::new()),
+      factory: Box::new(factory),
+      max_idle: size,
+    };
+
+    let contexts = (0..size).map(|_| shared.warm_up()).collect::<Result<_>>()?;
+    *shared.idle.lock().unwrap_or_else(PoisonError::into_inner) = contexts;
+
+    Ok(Self { shared: Arc::new(shared) })
+  }
+
+  /// Take an idle context, or create a new one if all are checked out
+  pub fn checkout(&self) -> Result<PooledContext> {
+    let idle = self.shared.idle.lock().unwrap_or_else(PoisonError::into_inner).pop();
+
+    let context = match idle {
+      Some(context) => context,
+      None => self.shared.warm_up()?,
+    };
+
+    Ok(PooledContext { context: Some(context), pool: self.shared.clone() })
+  }
+
+  /// Number of contexts ready to be checked out
+  pub fn idle(&self) -> usize {
+    self.shared.idle.lock().unwrap_or_else(PoisonError::into_inner).len()
+  }
+}
+
+/// Context checked out of a [`ContextPool`], returned to the pool when dropped
+pub struct PooledContext {
+  context: Option<Context>,
+  pool: Arc<ContextPoolShared>,
+}
+
+impl PooledContext {
+  /// Take the context out of the pool for good
+  pub fn detach(mut self) -> Context {
+    self.context.take().expect("The context is only taken once")
+  }
+}
+
+impl Deref for PooledContext {
+  type Target = Context;
+
+  fn deref(&self) -> &Context {
+    self.context.as_ref().expect("The context is only taken once")
+  }
+}
+
+impl Drop for PooledContext {
+  fn drop(&mut self) {
+    let Some(context) = self.context.take() else {
+      return;
+    };
+
+    if context.is_shared() {
+      log::debug!("Discarding a pooled context which is still used by cameras");
+      return;
+    }
+
+    let mut idle = self.pool.idle.lock().unwrap_or_else(PoisonError::into_inner);
+    if idle.len() < self.pool.max_idle {
+      idle.push(context);
+    }
+  }
+}
@@ -15,12 +15,36 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! ## USB devices
+//!
+//! USB ports have paths like `usb:001,004`, [`PortInfo::usb_bus_device`] parses the bus and
+//! device number. They identify the device the same way as `lsusb` and udev do. With the
+//! `rusb` feature, `UsbBusDevice::device_info` looks up the vendor and product ID of the
+//! device.
+//!
+//! ```no_run
+//! use gphoto2::{Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//!
+//! if let Some(usb) = camera.port_info()?.usb_bus_device() {
+//!   println!("Bus {:03} Device {:03}", usb.bus, usb.device);
+//! }
+//! # Ok(())
+//! # }
+//! ```
 
+#[cfg(feature = "rusb")]
+use crate::Error;
 use crate::{
-  helper::{as_ref, chars_to_string},
+  helper::{as_ref, chars_to_string, to_c_string},
+  platform,
+  task::{BackgroundPtr, Task},
   try_gp_internal, Result,
 };
-use std::{fmt, marker::PhantomData};
+use std::{ffi, fmt, marker::PhantomData};
 
 /// Type of the port
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -46,47 +70,136 @@ pub enum PortType {
   UsbScsi,
 }
 
+/// Bus and device number of a USB port
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UsbBusDevice {
+  /// Number of the USB bus
+  pub bus: u8,
+  /// Address of the device on the bus
+  pub device: u8,
+}
+
+/// USB device found by [`UsbBusDevice::device_info`]
+#[cfg(feature = "rusb")]
+#[derive(Debug, Clone)]
+pub struct UsbDeviceInfo {
+  /// Vendor ID
+  pub vendor_id: u16,
+  /// Product ID
+  pub product_id: u16,
+  /// Negotiated connection speed
+  pub speed: rusb::Speed,
+  /// The device, which can be used to read further descriptors
+  pub device: rusb::Device<rusb::GlobalContext>,
+}
+
+impl UsbBusDevice {
+  /// Parse a port path like `usb:001,004`
+  ///
+  /// Returns [`None`] for other ports and for the generic `usb:` port.
+  pub fn parse(path: &str) -> Option<Self> {
+    let (bus, device) = path.strip_prefix("usb:")?.split_once(',')?;
+
+    Some(Self { bus: bus.parse().ok()?, device: device.parse().ok()? })
+  }
+
+  /// Look up the USB device, [`None`] if it is not connected anymore
+  #[cfg(feature = "rusb")]
+  pub fn device_info(&self) -> Result<Option<UsbDeviceInfo>> {
+    let usb_error =
+      |error: rusb::Error| Error::from(format!("Could not read USB devices: {error}"));
+
+    for device in rusb::devices().map_err(usb_error)?.iter() {
+      if device.bus_number() != self.bus || device.address() != self.device {
+        continue;
+      }
+
+      let descriptor = device.device_descriptor().map_err(usb_error)?;
+
+      return Ok(Some(UsbDeviceInfo {
+        vendor_id: descriptor.vendor_id(),
+        product_id: descriptor.product_id(),
+        speed: device.speed(),
+        device,
+      }));
+    }
+
+    Ok(None)
+  }
+}
+
+impl fmt::Display for UsbBusDevice {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "usb:{:03},{:03}", self.bus, self.device)
+  }
+}
+
 /// Information about the port
 ///
 /// ## Information
 ///  - [`name`](PortInfo::name): Name of the port
 ///  - [`path`](PortInfo::path): Path of the port
 ///  - [`port_type`](PortInfo::port_type): Type of the port
+///
+/// The information is read when the port info is fetched, so the getters don't call libgphoto2.
 pub struct PortInfo<'a> {
   pub(crate) inner: libgphoto2_sys::GPPortInfo,
+  name: String,
+  path: String,
+  port_type: Option<PortType>,
   _phantom: std::marker::PhantomData<&'a ()>,
 }
 
+// The pointer is only passed to libgphoto2 on the libgphoto2 thread
+unsafe impl Send for PortInfo<'_> {}
+
 impl PortInfo<'_> {
   // Unsafe because we bind a pointer to an unbounded lifetime.
   // The caller must be sure to bind the result to the lifetime
-  // of the PortInfo owner.
-  pub(crate) unsafe fn new(inner: libgphoto2_sys::GPPortInfo) -> Self {
-    Self { inner, _phantom: PhantomData }
+  // of the PortInfo owner. Must be called from a [`Task`].
+  pub(crate) unsafe fn new(inner: libgphoto2_sys::GPPortInfo) -> Result<Self> {
+    try_gp_internal!(gp_port_info_get_name(inner, &out name)?);
+    try_gp_internal!(gp_port_info_get_path(inner, &out path)?);
+    try_gp_internal!(gp_port_info_get_type(inner, &out port_type)?);
+
+    Ok(Self {
+      inner,
+      name: chars_to_string(name),
+      path: platform::normalize_port_path(&chars_to_string(path)).into_owned(),
+      port_type: PortType::new(port_type),
+      _phantom: PhantomData,
+    })
   }
 }
 
-pub(crate) struct PortInfoList {
-  pub(crate) inner: *mut libgphoto2_sys::GPPortInfoList,
+/// Ports found by the installed port drivers
+///
+/// Each [`Context`](crate::Context) keeps one in
+/// [`Context::port_info_list`](crate::Context::port_info_list).
+pub struct PortInfoList {
+  pub(crate) inner: BackgroundPtr<libgphoto2_sys::GPPortInfoList>,
 }
 
 impl Drop for PortInfoList {
   fn drop(&mut self) {
-    try_gp_internal!(gp_port_info_list_free(self.inner).unwrap());
+    let inner = self.inner;
+
+    unsafe { Task::new(move || libgphoto2_sys::gp_port_info_list_free(*inner)) }.background();
   }
 }
 
 impl fmt::Debug for PortInfo<'_> {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     f.debug_struct("PortInfo")
-      .field("name", &self.name())
-      .field("path", &self.path())
-      .field("port_type", &self.port_type())
+      .field("name", &self.name)
+      .field("path", &self.path)
+      .field("port_type", &self.port_type)
       .finish()
   }
 }
 
-as_ref!(PortInfoList -> libgphoto2_sys::GPPortInfoList, *self.inner);
+as_ref!(PortInfoList -> libgphoto2_sys::GPPortInfoList, *self.inner.any_thread());
 
 as_ref!(PortInfo<'_> -> libgphoto2_sys::GPPortInfo, self.inner);
 
@@ -110,23 +223,22 @@ impl PortType {
 impl PortInfo<'_> {
   /// Name of the port
   pub fn name(&self) -> String {
-    try_gp_internal!(gp_port_info_get_name(self.inner, &out name).unwrap());
-
-    chars_to_string(name)
+    self.name.clone()
   }
 
   /// Path of the port
   pub fn path(&self) -> String {
-    try_gp_internal!(gp_port_info_get_path(self.inner, &out path).unwrap());
-
-    chars_to_string(path)
+    self.path.clone()
   }
 
   /// [Port type](PortType)
   pub fn port_type(&self) -> Option<PortType> {
-    try_gp_internal!(gp_port_info_get_type(self.inner, &out port_type).unwrap());
+    self.port_type.clone()
+  }
 
-    PortType::new(port_type)
+  /// Bus and device number of USB ports
+  pub fn usb_bus_device(&self) -> Option<UsbBusDevice> {
+    UsbBusDevice::parse(&self.path)
   }
 }
 
@@ -136,12 +248,22 @@ impl PortInfoList {
     try_gp_internal!(gp_port_info_list_new(&out port_info_list)?);
     try_gp_internal!(gp_port_info_list_load(port_info_list)?);
 
-    Ok(Self { inner: port_info_list })
+    Ok(Self { inner: BackgroundPtr(port_info_list) })
   }
 
   pub(crate) fn get_port_info(&self, p: i32) -> Result<PortInfo<'_>> {
-    try_gp_internal!(gp_port_info_list_get_info(self.inner, p, &out port_info)?);
+    try_gp_internal!(gp_port_info_list_get_info(*self.inner, p, &out port_info)?);
+
+    unsafe { PortInfo::new(port_info) }
+  }
+
+  /// Port with the given path, must be called from a [`Task`](crate::task::Task)
+  pub(crate) fn lookup_path(&self, path: &str) -> Result<PortInfo<'_>> {
+    try_gp_internal!(let index = gp_port_info_list_lookup_path(
+      *self.inner,
+      to_c_string!(platform::native_port_path(path).as_ref())
+    )?);
 
-    Ok(unsafe { PortInfo::new(port_info) })
+    self.get_port_info(index)
   }
 }
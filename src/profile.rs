@@ -0,0 +1,95 @@
+//! Copy settings between cameras
+//!
+//! A [`CameraProfile`] contains every writable configuration of a camera. With the `serde`
+//! feature it can be stored in a file and applied to another body of the same model.
+//!
+//! ```no_run
+//! use gphoto2::{Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let context = Context::new()?;
+//! let mut cameras = context.list_cameras().wait()?;
+//!
+//! let source = context.get_camera(&cameras.next().ok_or("No cameras found")?).wait()?;
+//! let target = context.get_camera(&cameras.next().ok_or("Only one camera found")?).wait()?;
+//!
+//! let profile = source.export_profile()?;
+//! for (name, error) in target.import_profile(&profile)? {
+//!   println!("Could not set {name}: {error}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+  ops::ConfigValue,
+  widget::{Widget, WidgetBase},
+  Camera, Error, Result,
+};
+
+/// Name of the configuration containing the serial number
+pub(crate) const SERIAL_NUMBER_KEY: &str = "serialnumber";
+
+/// Writable configuration of a camera
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CameraProfile {
+  /// Model of the camera the profile was exported from
+  pub model: String,
+  /// Serial number of the camera, if it reports one
+  pub serial: Option<String>,
+  /// Configuration values by name, in the order of the configuration tree
+  pub values: Vec<(String, ConfigValue)>,
+}
+
+impl CameraProfile {
+  pub(crate) fn export(camera: &Camera) -> Result<Self> {
+    let config = camera.config().wait()?;
+
+    let serial = match config.get_child_by_name(SERIAL_NUMBER_KEY) {
+      Ok(Widget::Text(serial)) => Some(serial.value()),
+      _ => None,
+    };
+
+    let values = config
+      .leaves()
+      .filter_map(|(_, widget)| Some((widget.name(), ConfigValue::from_widget(&widget).ok()?)))
+      .collect();
+
+    Ok(Self { model: camera.abilities()?.model().into_owned(), serial, values })
+  }
+
+  /// Apply the profile, returning the configurations which could not be set
+  pub(crate) fn import(&self, camera: &Camera) -> Result<Vec<(String, Error)>> {
+    let model = camera.abilities()?.model().into_owned();
+    if model != self.model {
+      return Err(Error::from(format!("Profile of {} can't be imported to a {model}", self.model)));
+    }
+
+    let config = camera.config().wait()?;
+    let mut failures = Vec::new();
+
+    for (name, value) in &self.values {
+      let Ok(widget) = config.get_child_by_name(name) else {
+        continue;
+      };
+
+      if widget.readonly()
+        || ConfigValue::from_widget(&widget).is_ok_and(|current| current == *value)
+      {
+        continue;
+      }
+
+      let result = value.apply_to(&widget).and_then(|()| {
+        let base: &WidgetBase = &widget;
+        camera.set_config(base).wait()
+      });
+
+      if let Err(error) = result {
+        failures.push((name.clone(), error));
+      }
+    }
+
+    Ok(failures)
+  }
+}
@@ -0,0 +1,91 @@
+//! Property change events of PTP cameras
+//!
+//! The `ptp2` driver reports changed device properties as unknown events with a text like
+//! `PTP Property d20b changed`. Newer versions add the configuration name and the new value for
+//! some cameras, like `PTP Property d101 changed, "aperture" to "5.6"`.
+
+/// Configuration names of common property codes, used when the event doesn't contain the name
+const CONFIG_NAMES: &[(u16, &str)] = &[
+  // Standard PTP properties
+  (0x5001, "batterylevel"),
+  (0x5003, "imagesize"),
+  (0x5004, "imagequality"),
+  (0x5005, "whitebalance"),
+  (0x5007, "f-number"),
+  (0x5008, "focallength"),
+  (0x500a, "focusmode"),
+  (0x500b, "exposuremetermode"),
+  (0x500c, "flashmode"),
+  (0x500d, "shutterspeed"),
+  (0x500e, "expprogram"),
+  (0x500f, "iso"),
+  (0x5010, "exposurecompensation"),
+  (0x5011, "datetime"),
+  (0x5013, "capturemode"),
+  // Canon EOS
+  (0xd101, "aperture"),
+  (0xd102, "shutterspeed"),
+  (0xd103, "iso"),
+  (0xd104, "exposurecompensation"),
+  (0xd105, "autoexposuremode"),
+  (0xd106, "drivemode"),
+  (0xd107, "meteringmode"),
+  (0xd108, "focusmode"),
+  (0xd109, "whitebalance"),
+  (0xd10a, "colortemperature"),
+  (0xd110, "picturestyle"),
+];
+
+/// Configuration name of a property code
+fn config_name(code: u16) -> Option<&'static str> {
+  CONFIG_NAMES.iter().find(|(known, _)| *known == code).map(|(_, name)| *name)
+}
+
+/// Property code and configuration name of a property change event
+pub(crate) fn parse_change(event: &str) -> Option<(u16, Option<String>)> {
+  let rest = event.strip_prefix("PTP Property ")?;
+  let (code, rest) = rest.split_once(' ')?;
+  let details = rest.strip_prefix("changed")?;
+
+  let code = u16::from_str_radix(code, 16).ok()?;
+
+  let name = details
+    .strip_prefix(", \"")
+    .and_then(|details| details.split_once('"'))
+    .map(|(name, _)| name)
+    .filter(|name| !name.is_empty());
+
+  Some((code, name.or_else(|| config_name(code)).map(str::to_owned)))
+}
+
+#[cfg(all(test, feature = "test"))]
+mod tests {
+  use super::parse_change;
+
+  #[test]
+  fn test_parse_change() {
+    assert_eq!(parse_change("PTP Property d20b changed"), Some((0xd20b, None)));
+    assert_eq!(
+      parse_change("PTP Property 500D changed"),
+      Some((0x500d, Some("shutterspeed".into())))
+    );
+    assert_eq!(
+      parse_change("PTP Property d101 changed, \"aperture\" to \"5.6\""),
+      Some((0xd101, Some("aperture".into())))
+    );
+    // The name in the event wins over the known names
+    assert_eq!(
+      parse_change("PTP Property 5007 changed, \"fnumber\" to \"8\""),
+      Some((0x5007, Some("fnumber".into())))
+    );
+    assert_eq!(
+      parse_change("PTP Property 5010 changed, \"\" to \"0\""),
+      Some((0x5010, Some("exposurecompensation".into())))
+    );
+
+    assert_eq!(parse_change("PTP Property xyz changed"), None);
+    assert_eq!(parse_change("PTP Property 12345 changed"), None);
+    assert_eq!(parse_change("PTP Property d20b"), None);
+    assert_eq!(parse_change("Unknown PTP Event c189"), None);
+  }
+}
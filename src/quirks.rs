@@ -0,0 +1,98 @@
+//! Per-model quirks
+//!
+//! Different camera models (or drivers) sometimes expose the same setting under a different
+//! configuration name. This module holds a process-wide table of aliases which is consulted
+//! whenever a single widget is looked up by name, like in
+//! [`Camera::config_key`](crate::Camera::config_key).
+//!
+//! ## Registering an alias
+//! ```no_run
+//! use gphoto2::{quirks, widget::RadioWidget, Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! // On this model `iso` is called `isospeed`
+//! quirks::register_config_alias("Canon EOS 5D Mark II", "iso", "isospeed");
+//!
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//! let iso = camera.config_key::<RadioWidget>("iso").wait()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+  borrow::Cow,
+  collections::BTreeMap,
+  sync::{PoisonError, RwLock},
+};
+
+/// Aliases keyed by (model, alias), the value is the actual widget name
+static CONFIG_ALIASES: RwLock<BTreeMap<(String, String), String>> = RwLock::new(BTreeMap::new());
+
+/// Register `alias` as another name for the configuration widget `name` on cameras of `model`
+///
+/// The model must match [`Abilities::model`](crate::abilities::Abilities::model) exactly.
+/// Registering the same alias twice for a model replaces the previous mapping.
+pub fn register_config_alias(model: &str, alias: &str, name: &str) {
+  CONFIG_ALIASES
+    .write()
+    .unwrap_or_else(PoisonError::into_inner)
+    .insert((model.to_owned(), alias.to_owned()), name.to_owned());
+}
+
+/// Remove an alias previously registered with [`register_config_alias`]
+///
+/// Returns the name the alias was pointing to, if there was one
+pub fn unregister_config_alias(model: &str, alias: &str) -> Option<String> {
+  CONFIG_ALIASES
+    .write()
+    .unwrap_or_else(PoisonError::into_inner)
+    .remove(&(model.to_owned(), alias.to_owned()))
+}
+
+/// Resolve a configuration key for a camera model
+///
+/// Returns the aliased name if one is registered for this model, otherwise the key itself.
+pub fn resolve_config_key<'a>(model: &str, key: &'a str) -> Cow<'a, str> {
+  match CONFIG_ALIASES
+    .read()
+    .unwrap_or_else(PoisonError::into_inner)
+    .get(&(model.to_owned(), key.to_owned()))
+  {
+    Some(name) => Cow::Owned(name.clone()),
+    None => Cow::Borrowed(key),
+  }
+}
+
+/// List all registered aliases as `(model, alias, name)`
+pub fn config_aliases() -> Vec<(String, String, String)> {
+  CONFIG_ALIASES
+    .read()
+    .unwrap_or_else(PoisonError::into_inner)
+    .iter()
+    .map(|((model, alias), name)| (model.clone(), alias.clone(), name.clone()))
+    .collect()
+}
+
+#[cfg(all(test, feature = "test"))]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_aliases() {
+    let model = "gphoto2-rs test model";
+
+    assert_eq!(resolve_config_key(model, "iso"), "iso");
+
+    register_config_alias(model, "iso", "isospeed");
+    assert_eq!(resolve_config_key(model, "iso"), "isospeed");
+    assert_eq!(resolve_config_key("Other model", "iso"), "iso");
+    assert!(config_aliases().contains(&(model.into(), "iso".into(), "isospeed".into())));
+
+    register_config_alias(model, "iso", "isovalue");
+    assert_eq!(resolve_config_key(model, "iso"), "isovalue");
+
+    assert_eq!(unregister_config_alias(model, "iso").as_deref(), Some("isovalue"));
+    assert_eq!(unregister_config_alias(model, "iso"), None);
+    assert_eq!(resolve_config_key(model, "iso"), "iso");
+  }
+}
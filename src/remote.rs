@@ -0,0 +1,175 @@
+//! Camera of a remote `CameraService`
+//!
+//! [`RemoteCamera`] implements [`CameraOps`] by sending the operations over TCP to a camera
+//! served by the `service` feature, so code written against [`CameraOps`] works the same with a
+//! local camera and a camera connected to another machine. The client itself doesn't call
+//! libgphoto2, the operations run on the worker thread of the serving process.
+//!
+//! The crate still links libgphoto2 with this feature (errors and paths are libgphoto2 types),
+//! so clients can't be built for targets without libgphoto2 like `wasm32` yet.
+//!
+//! ```no_run
+//! use gphoto2::{ops::CameraOps, remote::RemoteCamera, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = RemoteCamera::connect("raspberrypi:5555")?;
+//!
+//! let path = camera.capture_image()?;
+//! let image = camera.download(&path.folder(), &path.name())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+  base64,
+  camera::CameraEvent,
+  file::CameraFilePath,
+  ops::{CameraOps, ConfigValue},
+  Error, Result,
+};
+use serde_json::{json, Value};
+use std::{
+  io::{BufRead, BufReader, Write},
+  net::{TcpStream, ToSocketAddrs},
+  os::raw::c_int,
+  sync::{Mutex, PoisonError},
+  time::Duration,
+};
+
+struct Connection {
+  reader: BufReader<TcpStream>,
+  writer: TcpStream,
+  next_id: u64,
+}
+
+/// Client of a `CameraService` of the `service` feature, see the [module](self) documentation
+pub struct RemoteCamera {
+  connection: Mutex<Connection>,
+}
+
+impl RemoteCamera {
+  /// Connect to the service listening on `address`
+  pub fn connect(address: impl ToSocketAddrs) -> Result<Self> {
+    Self::from_stream(TcpStream::connect(address)?)
+  }
+
+  /// Use an established connection to a service
+  pub fn from_stream(stream: TcpStream) -> Result<Self> {
+    let writer = stream.try_clone()?;
+
+    Ok(Self {
+      connection: Mutex::new(Connection { reader: BufReader::new(stream), writer, next_id: 0 }),
+    })
+  }
+
+  /// Call a method of the service and return its result
+  ///
+  /// The methods are listed in the `service` module documentation. Calls from several threads
+  /// are sent one after another.
+  pub fn call(&self, method: &str, params: Value) -> Result<Value> {
+    let mut connection = self.connection.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let id = connection.next_id;
+    connection.next_id += 1;
+
+    let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+    writeln!(connection.writer, "{request}")?;
+
+    let mut line = String::new();
+    if connection.reader.read_line(&mut line)? == 0 {
+      return Err(Error::new(
+        libgphoto2_sys::GP_ERROR_IO,
+        Some("The service closed the connection".to_owned()),
+      ));
+    }
+
+    let mut response: Value = serde_json::from_str(&line).map_err(invalid_response)?;
+
+    if let Some(error) = response.get("error") {
+      let code = error.get("code").and_then(Value::as_i64).unwrap_or_default();
+      let message = error.get("message").and_then(Value::as_str).unwrap_or_default();
+
+      return Err(Error::new(
+        c_int::try_from(code).unwrap_or(libgphoto2_sys::GP_ERROR),
+        Some(message.to_owned()),
+      ));
+    }
+
+    Ok(response.get_mut("result").map(Value::take).unwrap_or_default())
+  }
+
+  /// Summary of the camera, see [`Camera::summary`](crate::Camera::summary)
+  pub fn summary(&self) -> Result<String> {
+    from_value(self.call("summary", Value::Null)?)
+  }
+
+  /// Trigger a capture without waiting for the image
+  pub fn trigger_capture(&self) -> Result<()> {
+    self.call("trigger_capture", Value::Null).map(drop)
+  }
+
+  /// Capture a preview image and return its JPEG data
+  pub fn capture_preview(&self) -> Result<Vec<u8>> {
+    decode_data(&self.call("capture_preview", Value::Null)?)
+  }
+
+  /// Names of the subfolders of a folder
+  pub fn list_folders(&self, folder: &str) -> Result<Vec<String>> {
+    from_value(self.call("list_folders", json!({ "folder": folder }))?)
+  }
+}
+
+impl CameraOps for RemoteCamera {
+  fn capture_image(&self) -> Result<CameraFilePath> {
+    from_value(self.call("capture_image", Value::Null)?)
+  }
+
+  fn config_value(&self, key: &str) -> Result<ConfigValue> {
+    let config = self.call("get_config", json!({ "key": key }))?;
+    if config.get("type").is_none() {
+      return Err(Error::from(format!("Config {key} has no value")));
+    }
+
+    from_value(config)
+  }
+
+  fn set_config_value(&self, key: &str, value: &ConfigValue) -> Result<()> {
+    let mut params = serde_json::to_value(value).map_err(|error| Error::from(error.to_string()))?;
+    params["key"] = json!(key);
+
+    self.call("set_config", params).map(drop)
+  }
+
+  fn list_files(&self, folder: &str) -> Result<Vec<String>> {
+    from_value(self.call("list_files", json!({ "folder": folder }))?)
+  }
+
+  fn download(&self, folder: &str, file: &str) -> Result<Vec<u8>> {
+    decode_data(&self.call("download", json!({ "folder": folder, "name": file }))?)
+  }
+
+  fn delete_file(&self, folder: &str, file: &str) -> Result<()> {
+    self.call("delete_file", json!({ "folder": folder, "name": file })).map(drop)
+  }
+
+  fn wait_event(&self, timeout: Duration) -> Result<CameraEvent> {
+    let timeout = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+
+    from_value(self.call("wait_event", json!({ "timeout_ms": timeout }))?)
+  }
+}
+
+fn invalid_response(error: impl ToString) -> Error {
+  Error::new(
+    libgphoto2_sys::GP_ERROR_CORRUPTED_DATA,
+    Some(format!("Invalid response of the service: {}", error.to_string())),
+  )
+}
+
+fn from_value<T: serde::de::DeserializeOwned>(value: Value) -> Result<T> {
+  serde_json::from_value(value).map_err(invalid_response)
+}
+
+fn decode_data(value: &Value) -> Result<Vec<u8>> {
+  value.as_str().and_then(base64::decode).ok_or_else(|| invalid_response("Invalid file data"))
+}
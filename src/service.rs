@@ -0,0 +1,343 @@
+//! Controlling a camera over the network
+//!
+//! A [`CameraService`] serves a camera over TCP with [JSON-RPC 2.0](https://www.jsonrpc.org/specification),
+//! one request or response object per line. This way a camera connected to a Raspberry Pi can be
+//! used from another machine, for example with `nc` through an SSH tunnel:
+//!
+//! ```text
+//! $ ssh -fN -L 5555:localhost:5555 raspberrypi
+//! $ echo '{"jsonrpc": "2.0", "id": 1, "method": "capture_image"}' | nc localhost 5555
+//! {"jsonrpc":"2.0","id":1,"result":{"name":"IMG_0001.JPG","folder":"/store_00010001/DCIM/100CANON"}}
+//! ```
+//!
+//! # Security
+//!
+//! **The service has no authentication and no encryption.** Anyone who can connect to it
+//! can take pictures, download and delete files and change the settings of the camera. Bind it
+//! to `localhost` and reach it through an SSH tunnel or a VPN, only bind it to other addresses
+//! on networks where every host is trusted.
+//!
+//! Requests longer than [`CameraService::max_line_length`] end their connection, and
+//! connections beyond [`CameraService::max_connections`] are closed right away.
+//!
+//! Each request runs the matching [`Task`](crate::task::Task) to completion before the response
+//! is sent. Events are not pushed, clients poll them with `wait_event`.
+//!
+//! | Method            | Parameters                          | Result                           |
+//! |-------------------|-------------------------------------|----------------------------------|
+//! | `summary`         |                                     | Summary text                     |
+//! | `capture_image`   |                                     | `{"folder", "name"}`             |
+//! | `trigger_capture` |                                     | `null`                           |
+//! | `capture_preview` |                                     | JPEG data in base64              |
+//! | `list_files`      | `{"folder"}`                        | File names                       |
+//! | `list_folders`    | `{"folder"}`                        | Folder names                     |
+//! | `download`        | `{"folder", "name"}`                | File data in base64              |
+//! | `delete_file`     | `{"folder", "name"}`                | `null`                           |
+//! | `get_config`      | `{"key"}`                           | `{"name", "label", "readonly", "choices", "type", "value"}` |
+//! | `set_config`      | `{"key", "type", "value"}`          | `null`                           |
+//! | `wait_event`      | `{"timeout_ms"}` (1000 by default)  | The event                        |
+//!
+//! Configuration values have the format of a serialized [`ConfigValue`], a `type` (`text`,
+//! `range`, `toggle`, `choice` or `date`) and a `value`. Sections, buttons and toggles in an
+//! unknown state have neither. `set_config` needs the type of the configuration, for example
+//! `{"key": "iso", "type": "choice", "value": "400"}`.
+//!
+//! Errors of libgphoto2 are returned with their `GP_ERROR_*` code, which doesn't overlap with
+//! the codes reserved by JSON-RPC.
+//!
+//! ```no_run
+//! use gphoto2::{service::CameraService, Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//!
+//! CameraService::new(camera).serve("localhost:5555")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{base64, ops::ConfigValue, widget::Widget, Camera, Error, Result};
+use serde_json::{json, Value};
+use std::{
+  io::{BufRead, BufReader, Read, Write},
+  net::{TcpListener, TcpStream, ToSocketAddrs},
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+  },
+  thread,
+  time::Duration,
+};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Error response of a request
+struct RpcError {
+  code: i64,
+  message: String,
+}
+
+impl RpcError {
+  fn invalid_params(message: impl Into<String>) -> Self {
+    Self { code: INVALID_PARAMS, message: message.into() }
+  }
+}
+
+impl From<Error> for RpcError {
+  fn from(error: Error) -> Self {
+    Self { code: error.code().into(), message: error.to_string() }
+  }
+}
+
+/// Serves a camera over TCP, see the [module](self) documentation
+#[derive(Clone)]
+pub struct CameraService {
+  camera: Camera,
+  max_line_length: usize,
+  max_connections: usize,
+  /// Number of connections handled by [`CameraService::serve_listener`]
+  connections: Arc<AtomicUsize>,
+}
+
+/// Counts a connection until dropped
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl Drop for ConnectionSlot {
+  fn drop(&mut self) {
+    self.0.fetch_sub(1, Ordering::AcqRel);
+  }
+}
+
+impl CameraService {
+  /// Serve `camera`, with requests of up to 64 KiB and up to 8 connections
+  pub fn new(camera: Camera) -> Self {
+    Self {
+      camera,
+      max_line_length: 64 * 1024,
+      max_connections: 8,
+      connections: Arc::new(AtomicUsize::new(0)),
+    }
+  }
+
+  /// Maximum length of a request in bytes, longer requests end their connection
+  pub fn max_line_length(mut self, max_line_length: usize) -> Self {
+    self.max_line_length = max_line_length;
+    self
+  }
+
+  /// Maximum number of connections handled at the same time, further connections are closed
+  pub fn max_connections(mut self, max_connections: usize) -> Self {
+    self.max_connections = max_connections;
+    self
+  }
+
+  /// Listen on `address` and handle connections until an error occurs
+  pub fn serve(&self, address: impl ToSocketAddrs) -> Result<()> {
+    self.serve_listener(TcpListener::bind(address)?)
+  }
+
+  /// Handle the connections of `listener`, each in its own thread
+  ///
+  /// The camera runs one operation at a time, requests of several clients are queued.
+  pub fn serve_listener(&self, listener: TcpListener) -> Result<()> {
+    for stream in listener.incoming() {
+      let stream = stream?;
+      let peer = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+
+      if self.connections.fetch_add(1, Ordering::AcqRel) >= self.max_connections {
+        self.connections.fetch_sub(1, Ordering::AcqRel);
+        log::warn!(
+          "Closed the connection to {peer}, {} connections are open",
+          self.max_connections
+        );
+        continue;
+      }
+
+      let slot = ConnectionSlot(self.connections.clone());
+      let service = self.clone();
+
+      thread::spawn(move || {
+        let _slot = slot;
+
+        if let Err(error) = service.handle_connection(stream) {
+          log::warn!("Connection to {peer} failed: {error}");
+        }
+      });
+    }
+
+    Ok(())
+  }
+
+  /// Answer the requests of a single client until it disconnects
+  ///
+  /// A request longer than [`CameraService::max_line_length`] is answered with an error
+  /// and ends the connection.
+  pub fn handle_connection(&self, stream: TcpStream) -> Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+
+    loop {
+      line.clear();
+      let limit = u64::try_from(self.max_line_length).unwrap_or(u64::MAX).saturating_add(1);
+      if reader.by_ref().take(limit).read_until(b'\n', &mut line)? == 0 {
+        break;
+      }
+
+      // The line break doesn't count towards the limit
+      let length = line.len() - usize::from(line.ends_with(b"\n"));
+      if length > self.max_line_length {
+        let error = RpcError {
+          code: INVALID_REQUEST,
+          message: format!("Requests may be at most {} bytes long", self.max_line_length),
+        };
+        writeln!(writer, "{}", response(Value::Null, Err(error)))?;
+        break;
+      }
+
+      let Ok(line) = std::str::from_utf8(&line) else {
+        let error = RpcError { code: PARSE_ERROR, message: "Request is not UTF-8".to_owned() };
+        writeln!(writer, "{}", response(Value::Null, Err(error)))?;
+        continue;
+      };
+      if line.trim().is_empty() {
+        continue;
+      }
+
+      let response = self.handle_request(line);
+      writeln!(writer, "{response}")?;
+    }
+
+    Ok(())
+  }
+
+  /// Answer a single JSON-RPC request
+  pub fn handle_request(&self, request: &str) -> String {
+    let request: Value = match serde_json::from_str(request) {
+      Ok(request) => request,
+      Err(error) => {
+        return response(
+          Value::Null,
+          Err(RpcError { code: PARSE_ERROR, message: error.to_string() }),
+        )
+      }
+    };
+
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+      return response(
+        id,
+        Err(RpcError { code: INVALID_REQUEST, message: "Missing method".to_owned() }),
+      );
+    };
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    response(id, self.call(method, &params))
+  }
+
+  fn call(&self, method: &str, params: &Value) -> std::result::Result<Value, RpcError> {
+    let camera = &self.camera;
+
+    Ok(match method {
+      "summary" => json!(camera.summary().wait()?),
+      "capture_image" => path_json(&camera.capture_image().wait()?),
+      "trigger_capture" => {
+        camera.trigger_capture().wait()?;
+        Value::Null
+      }
+      "capture_preview" => {
+        let preview = camera.capture_preview().wait()?;
+        json!(base64::encode(&preview.get_data(&camera.context).wait()?))
+      }
+      "list_files" => {
+        json!(camera.fs().list_files(str_param(params, "folder")?).wait()?.collect::<Vec<_>>())
+      }
+      "list_folders" => {
+        json!(camera.fs().list_folders(str_param(params, "folder")?).wait()?.collect::<Vec<_>>())
+      }
+      "download" => {
+        let file =
+          camera.fs().download(str_param(params, "folder")?, str_param(params, "name")?).wait()?;
+        json!(base64::encode(&file.get_data(&camera.context).wait()?))
+      }
+      "delete_file" => {
+        camera.fs().delete_file(str_param(params, "folder")?, str_param(params, "name")?).wait()?;
+        Value::Null
+      }
+      "get_config" => widget_json(&camera.config_key::<Widget>(str_param(params, "key")?).wait()?),
+      "set_config" => {
+        let widget = camera.config_key::<Widget>(str_param(params, "key")?).wait()?;
+        let value: ConfigValue = serde_json::from_value(
+          json!({ "type": params.get("type"), "value": params.get("value") }),
+        )
+        .map_err(|error| RpcError::invalid_params(format!("Invalid value: {error}")))?;
+
+        value.apply_to(&widget)?;
+        camera.set_config(&widget).wait()?;
+        Value::Null
+      }
+      "wait_event" => {
+        let timeout = params.get("timeout_ms").and_then(Value::as_u64).unwrap_or(1000);
+        let event = camera.wait_event(Duration::from_millis(timeout)).wait()?;
+
+        serde_json::to_value(event).map_err(|error| Error::from(error.to_string()))?
+      }
+      _ => {
+        return Err(RpcError {
+          code: METHOD_NOT_FOUND,
+          message: format!("Unknown method {method}"),
+        })
+      }
+    })
+  }
+}
+
+fn response(id: Value, result: std::result::Result<Value, RpcError>) -> String {
+  match result {
+    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+    Err(error) => json!({
+      "jsonrpc": "2.0",
+      "id": id,
+      "error": { "code": error.code, "message": error.message },
+    }),
+  }
+  .to_string()
+}
+
+fn str_param<'a>(params: &'a Value, name: &str) -> std::result::Result<&'a str, RpcError> {
+  params
+    .get(name)
+    .and_then(Value::as_str)
+    .ok_or_else(|| RpcError::invalid_params(format!("Missing string parameter {name}")))
+}
+
+fn path_json(path: &crate::file::CameraFilePath) -> Value {
+  json!({ "folder": path.folder(), "name": path.name() })
+}
+
+fn widget_json(widget: &Widget) -> Value {
+  let choices = match widget {
+    Widget::Radio(radio) => Some(radio.choices_iter().collect::<Vec<_>>()),
+    _ => None,
+  };
+  let mut config = json!({
+    "name": widget.name(),
+    "label": widget.label(),
+    "readonly": widget.readonly(),
+    "choices": choices,
+  });
+
+  // Sections, buttons and unknown toggles have neither a type nor a value
+  if let Ok(value) = ConfigValue::from_widget(widget) {
+    if let (Some(config), Ok(Value::Object(value))) =
+      (config.as_object_mut(), serde_json::to_value(value))
+    {
+      config.extend(value);
+    }
+  }
+
+  config
+}
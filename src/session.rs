@@ -0,0 +1,209 @@
+//! Matching trigger captures to the files they produced
+//!
+//! [`Camera::trigger_capture`](crate::Camera::trigger_capture) returns before the image is
+//! saved, the file is only reported later by a [`CameraEvent::NewFile`] event. A
+//! [`CaptureSession`] numbers the triggers and assigns the reported files to them in order, so
+//! several shots can be in flight at once.
+//!
+//! ```no_run
+//! use gphoto2::{session::CaptureSession, Context, Result};
+//! use std::time::Duration;
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//! let session = CaptureSession::new(camera.clone());
+//!
+//! let shots = (0..3).map(|_| session.trigger()).collect::<Result<Vec<_>>>()?;
+//!
+//! for seq in shots {
+//!   let shot = session.await_result(seq, Duration::from_secs(10))?;
+//!   for file in &shot.files {
+//!     println!("Shot {seq}: {}", file.name());
+//!   }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{camera::CameraEvent, file::CameraFilePath, Camera, Error, Result};
+use std::{
+  collections::{HashMap, HashSet, VecDeque},
+  sync::{Mutex, PoisonError},
+  time::{Duration, Instant},
+};
+
+/// Longest wait for a single event, so expired shots are noticed
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Files of a finished shot
+#[derive(Debug, Clone)]
+pub struct Shot {
+  /// Sequence number returned by [`CaptureSession::trigger`]
+  pub seq: u64,
+  /// Files reported for this shot, in the order of their events
+  pub files: Vec<CameraFilePath>,
+  /// When the capture was triggered
+  pub triggered_at: Instant,
+  /// When the last file was reported
+  pub completed_at: Instant,
+}
+
+struct PendingShot {
+  seq: u64,
+  triggered_at: Instant,
+  files: Vec<CameraFilePath>,
+}
+
+#[derive(Default)]
+struct SessionState {
+  next_seq: u64,
+  /// Triggered shots waiting for files, oldest first
+  pending: VecDeque<PendingShot>,
+  finished: HashMap<u64, Shot>,
+  /// Shots which got no file in time
+  expired: HashSet<u64>,
+  /// Files reported while no shot was waiting, e.g. taken with the shutter button
+  unmatched: Vec<CameraFilePath>,
+}
+
+/// Triggers captures and assigns the reported files to them, see the [module](self) documentation
+pub struct CaptureSession {
+  camera: Camera,
+  files_per_shot: usize,
+  shot_timeout: Duration,
+  state: Mutex<SessionState>,
+}
+
+impl CaptureSession {
+  /// Start a session expecting one file per shot within 30 seconds
+  pub fn new(camera: Camera) -> Self {
+    Self {
+      camera,
+      files_per_shot: 1,
+      shot_timeout: Duration::from_secs(30),
+      state: Default::default(),
+    }
+  }
+
+  /// Number of files each shot produces, e.g. 2 for RAW + JPEG
+  pub fn files_per_shot(mut self, files: usize) -> Self {
+    self.files_per_shot = files.max(1);
+    self
+  }
+
+  /// Time after the trigger after which a shot without all files is given up
+  ///
+  /// Files arriving later are assigned to the next shot, so this should be longer than the
+  /// slowest expected capture.
+  pub fn shot_timeout(mut self, timeout: Duration) -> Self {
+    self.shot_timeout = timeout;
+    self
+  }
+
+  /// The camera of this session
+  pub fn camera(&self) -> &Camera {
+    &self.camera
+  }
+
+  /// Trigger a capture and return its sequence number
+  pub fn trigger(&self) -> Result<u64> {
+    // Held while triggering, so the order of the sequence numbers matches the triggers
+    let mut state = self.lock_state();
+
+    self.camera.trigger_capture().wait()?;
+
+    let seq = state.next_seq;
+    state.next_seq += 1;
+    state.pending.push_back(PendingShot { seq, triggered_at: Instant::now(), files: Vec::new() });
+
+    Ok(seq)
+  }
+
+  /// Wait until all files of shot `seq` were reported
+  ///
+  /// Reads the camera events while waiting, files of other shots are kept for their own
+  /// calls. Each shot can only be taken once.
+  pub fn await_result(&self, seq: u64, timeout: Duration) -> Result<Shot> {
+    let deadline = Instant::now().checked_add(timeout).ok_or("Timeout is too large")?;
+
+    loop {
+      {
+        let mut state = self.lock_state();
+        self.expire_shots(&mut state);
+
+        if let Some(shot) = state.finished.remove(&seq) {
+          return Ok(shot);
+        }
+        if state.expired.remove(&seq) {
+          return Err(Error::new(
+            libgphoto2_sys::GP_ERROR_TIMEOUT,
+            Some(format!("Shot {seq} got no file within {:?}", self.shot_timeout)),
+          ));
+        }
+        if !state.pending.iter().any(|shot| shot.seq == seq) {
+          return Err(Error::new(
+            libgphoto2_sys::GP_ERROR_BAD_PARAMETERS,
+            Some(format!("Shot {seq} was not triggered or already taken")),
+          ));
+        }
+      }
+
+      let remaining = deadline.saturating_duration_since(Instant::now());
+      if remaining.is_zero() {
+        return Err(Error::new(
+          libgphoto2_sys::GP_ERROR_TIMEOUT,
+          Some(format!("Shot {seq} didn't finish in time")),
+        ));
+      }
+
+      let event = self.camera.wait_event(remaining.min(EVENT_POLL_INTERVAL)).wait()?;
+
+      if let CameraEvent::NewFile(path) = event {
+        self.assign_file(path);
+      }
+    }
+  }
+
+  /// Files which were reported while no shot was waiting
+  pub fn take_unmatched(&self) -> Vec<CameraFilePath> {
+    std::mem::take(&mut self.lock_state().unmatched)
+  }
+
+  fn lock_state(&self) -> std::sync::MutexGuard<'_, SessionState> {
+    self.state.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  fn expire_shots(&self, state: &mut SessionState) {
+    let now = Instant::now();
+
+    while let Some(shot) = state.pending.front() {
+      if now.saturating_duration_since(shot.triggered_at) < self.shot_timeout {
+        break;
+      }
+
+      let seq = shot.seq;
+      state.pending.pop_front();
+      state.expired.insert(seq);
+      log::warn!("Shot {seq} got no file within {:?}", self.shot_timeout);
+    }
+  }
+
+  /// Give a file to the oldest shot still waiting for files
+  fn assign_file(&self, path: CameraFilePath) {
+    let mut state = self.lock_state();
+    self.expire_shots(&mut state);
+
+    let Some(shot) = state.pending.front_mut() else {
+      state.unmatched.push(path);
+      return;
+    };
+
+    shot.files.push(path);
+
+    if shot.files.len() >= self.files_per_shot {
+      let PendingShot { seq, triggered_at, files } = state.pending.pop_front().unwrap();
+
+      state.finished.insert(seq, Shot { seq, files, triggered_at, completed_at: Instant::now() });
+    }
+  }
+}
@@ -0,0 +1,531 @@
+//! Typed camera settings
+//!
+//! Drivers name the choices of common settings differently (`Daylight`, `Sunny`, `Fine`, ...).
+//! [`CameraSettings`] maps them to enums, so applications don't have to know the spelling
+//! of every camera. Choices which don't match a known value are kept as `Other`.
+//!
+//! ```no_run
+//! use gphoto2::{settings::WhiteBalance, Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//!
+//! println!("Available: {:?}", camera.settings().white_balances().wait()?);
+//! camera.settings().set_white_balance(WhiteBalance::Daylight).wait()?;
+//!
+//! // Switches the white balance to the color temperature mode if needed
+//! camera.settings().set_color_temperature(5600).wait()?;
+
+//! // Exposure values are parsed from the spellings of any camera, see the exposure module
+//! camera.settings().set_shutter_speed("1/250".parse()?).wait()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+  camera::{get_single_config, set_single_config},
+  exposure::{Aperture, ExposureCompensation, Iso, ShutterSpeed},
+  task::Task,
+  widget::{RadioWidget, Widget},
+  Camera, Error, Result,
+};
+use std::{fmt, str::FromStr};
+
+/// Setting with a fixed list of choices
+trait ChoiceSetting: Sized + PartialEq + fmt::Debug + Send + 'static {
+  /// Config keys used by different drivers, in the order they are tried
+  const KEYS: &'static [&'static str];
+
+  fn from_choice(choice: &str) -> Self;
+}
+
+/// Setting with numeric choices, the closest choice is used when setting a value
+trait NumericSetting: FromStr + Clone + PartialEq + fmt::Debug + Send + 'static {
+  /// Config keys used by different drivers, in the order they are tried
+  const KEYS: &'static [&'static str];
+
+  /// Distance between two values, [`None`] if they can't be compared (like auto and a number)
+  fn distance(&self, other: &Self) -> Option<f64>;
+}
+
+/// Distance of two positive values on a logarithmic scale, like in stops
+fn log_distance(a: f64, b: f64) -> f64 {
+  (a.ln() - b.ln()).abs()
+}
+
+impl NumericSetting for ShutterSpeed {
+  const KEYS: &'static [&'static str] = &["shutterspeed", "shutterspeed2"];
+
+  fn distance(&self, other: &Self) -> Option<f64> {
+    match (self.seconds(), other.seconds()) {
+      (Some(a), Some(b)) => Some(log_distance(a, b)),
+      _ => (self == other).then_some(0.0),
+    }
+  }
+}
+
+impl NumericSetting for Aperture {
+  const KEYS: &'static [&'static str] = &["aperture", "f-number"];
+
+  fn distance(&self, other: &Self) -> Option<f64> {
+    match (self.f_number(), other.f_number()) {
+      (Some(a), Some(b)) => Some(log_distance(a, b)),
+      _ => (self == other).then_some(0.0),
+    }
+  }
+}
+
+impl NumericSetting for Iso {
+  const KEYS: &'static [&'static str] = &["iso", "isospeed"];
+
+  fn distance(&self, other: &Self) -> Option<f64> {
+    match (self.value(), other.value()) {
+      (Some(a), Some(b)) => Some(log_distance(a.into(), b.into())),
+      _ => (self == other).then_some(0.0),
+    }
+  }
+}
+
+impl NumericSetting for ExposureCompensation {
+  const KEYS: &'static [&'static str] = &["exposurecompensation"];
+
+  fn distance(&self, other: &Self) -> Option<f64> {
+    Some((self.stops() - other.stops()).abs())
+  }
+}
+
+/// White balance presets
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WhiteBalance {
+  /// Automatic white balance
+  Auto,
+  /// Direct sunlight
+  Daylight,
+  /// Shade
+  Shade,
+  /// Cloudy sky
+  Cloudy,
+  /// Tungsten (incandescent) light
+  Tungsten,
+  /// Fluorescent light
+  Fluorescent,
+  /// Flash
+  Flash,
+  /// Custom (measured) white balance
+  Custom,
+  /// Fixed color temperature, see [`CameraSettings::set_color_temperature`]
+  ColorTemperature,
+  /// Choice which doesn't match any of the above
+  Other(String),
+}
+
+impl ChoiceSetting for WhiteBalance {
+  const KEYS: &'static [&'static str] = &["whitebalance"];
+
+  fn from_choice(choice: &str) -> Self {
+    let lower = choice.to_lowercase();
+    let has = |keywords: &[&str]| keywords.iter().any(|keyword| lower.contains(keyword));
+
+    if has(&["auto", "awb"]) {
+      Self::Auto
+    } else if has(&["temperature", "kelvin"]) {
+      Self::ColorTemperature
+    } else if has(&["daylight", "sunny", "fine"]) {
+      Self::Daylight
+    } else if has(&["shade", "shadow"]) {
+      Self::Shade
+    } else if has(&["cloud"]) {
+      Self::Cloudy
+    } else if has(&["tungsten", "incandescent"]) {
+      Self::Tungsten
+    } else if has(&["fluorescent"]) {
+      Self::Fluorescent
+    } else if has(&["flash"]) {
+      Self::Flash
+    } else if has(&["custom", "manual", "preset"]) {
+      Self::Custom
+    } else {
+      Self::Other(choice.to_owned())
+    }
+  }
+}
+
+/// Picture style (Canon), picture control (Nikon) or film simulation (Fujifilm)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PictureStyle {
+  /// Chosen by the camera
+  Auto,
+  /// Standard
+  Standard,
+  /// Portrait
+  Portrait,
+  /// Landscape
+  Landscape,
+  /// Neutral
+  Neutral,
+  /// Faithful
+  Faithful,
+  /// Monochrome
+  Monochrome,
+  /// Fine detail
+  FineDetail,
+  /// Vendor specific or user defined style
+  Other(String),
+}
+
+impl ChoiceSetting for PictureStyle {
+  const KEYS: &'static [&'static str] = &["picturestyle", "picturecontrol", "filmsimulation"];
+
+  fn from_choice(choice: &str) -> Self {
+    let lower = choice.to_lowercase();
+    let has = |keywords: &[&str]| keywords.iter().any(|keyword| lower.contains(keyword));
+
+    // User defined styles are named after the style they are based on
+    if has(&["user"]) {
+      Self::Other(choice.to_owned())
+    } else if has(&["auto"]) {
+      Self::Auto
+    } else if has(&["standard"]) {
+      Self::Standard
+    } else if has(&["portrait"]) {
+      Self::Portrait
+    } else if has(&["landscape"]) {
+      Self::Landscape
+    } else if has(&["neutral"]) {
+      Self::Neutral
+    } else if has(&["faithful"]) {
+      Self::Faithful
+    } else if has(&["monochrome"]) {
+      Self::Monochrome
+    } else if has(&["fine detail", "finedetail"]) {
+      Self::FineDetail
+    } else {
+      Self::Other(choice.to_owned())
+    }
+  }
+}
+
+/// Config key of the color temperature in Kelvin
+const COLOR_TEMPERATURE_KEY: &str = "colortemperature";
+
+/// Typed access to common settings of a camera, created with [`Camera::settings`]
+pub struct CameraSettings<'a> {
+  pub(crate) camera: &'a Camera,
+}
+
+/// Get the first existing config of `keys`, settings which don't exist are reported as
+/// [`ErrorKind::NotSupported`](crate::error::ErrorKind::NotSupported)
+unsafe fn find_config(
+  camera: *mut libgphoto2_sys::Camera,
+  keys: &[&str],
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<Widget> {
+  for key in keys {
+    if let Ok(widget) = get_single_config(camera, key, context) {
+      return Ok(widget);
+    }
+  }
+
+  Err(Error::new(
+    libgphoto2_sys::GP_ERROR_NOT_SUPPORTED,
+    Some(format!("The camera has none of the configs {}", keys.join(", "))),
+  ))
+}
+
+unsafe fn find_radio<T: ChoiceSetting>(
+  camera: *mut libgphoto2_sys::Camera,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<RadioWidget> {
+  find_config(camera, T::KEYS, context)?.try_into()
+}
+
+unsafe fn set_radio<T: ChoiceSetting>(
+  camera: *mut libgphoto2_sys::Camera,
+  widget: &RadioWidget,
+  value: &T,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<()> {
+  let choice =
+    widget.choices_iter().find(|choice| T::from_choice(choice) == *value).ok_or_else(|| {
+      Error::new(
+        libgphoto2_sys::GP_ERROR_NOT_SUPPORTED,
+        Some(format!("Unsupported choice {value:?}")),
+      )
+    })?;
+
+  widget.set_choice_forced(&choice)?;
+  set_single_config(camera, widget, context)
+}
+
+unsafe fn find_numeric<T: NumericSetting>(
+  camera: *mut libgphoto2_sys::Camera,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<RadioWidget> {
+  find_config(camera, T::KEYS, context)?.try_into()
+}
+
+impl CameraSettings<'_> {
+  fn get_numeric<T: NumericSetting>(&self) -> Task<Result<T>> {
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        let choice = find_numeric::<T>(*camera, *context)?.choice();
+        choice.parse().map_err(|_| Error::from(format!("Unknown choice {choice:?}")))
+      })
+    }
+    .camera(self.camera)
+  }
+
+  fn set_numeric<T: NumericSetting>(&self, value: T) -> Task<Result<()>> {
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        let widget = find_numeric::<T>(*camera, *context)?;
+
+        let (_, closest) = widget
+          .choices_as::<T>()
+          .into_iter()
+          .filter_map(|(choice, parsed)| Some((parsed.distance(&value)?, choice)))
+          .min_by(|(a, _), (b, _)| a.total_cmp(b))
+          .ok_or_else(|| {
+            Error::new(
+              libgphoto2_sys::GP_ERROR_NOT_SUPPORTED,
+              Some(format!("Unsupported choice {value:?}")),
+            )
+          })?;
+
+        widget.set_choice_forced(&closest)?;
+        set_single_config(*camera, &widget, *context)
+      })
+    }
+    .camera(self.camera)
+  }
+
+  fn numeric_choices<T: NumericSetting>(&self) -> Task<Result<Vec<T>>> {
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        let mut choices: Vec<T> = Vec::new();
+
+        for (_, choice) in find_numeric::<T>(*camera, *context)?.choices_as::<T>() {
+          if !choices.contains(&choice) {
+            choices.push(choice);
+          }
+        }
+
+        Ok(choices)
+      })
+    }
+    .camera(self.camera)
+  }
+
+  fn get<T: ChoiceSetting>(&self) -> Task<Result<T>> {
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe { Task::new(move || Ok(T::from_choice(&find_radio::<T>(*camera, *context)?.choice()))) }
+      .camera(self.camera)
+  }
+
+  fn set<T: ChoiceSetting>(&self, value: T) -> Task<Result<()>> {
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe {
+      Task::new(move || set_radio(*camera, &find_radio::<T>(*camera, *context)?, &value, *context))
+    }
+    .camera(self.camera)
+  }
+
+  fn choices<T: ChoiceSetting>(&self) -> Task<Result<Vec<T>>> {
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        let widget = find_radio::<T>(*camera, *context)?;
+        let mut choices: Vec<T> = Vec::new();
+
+        for choice in widget.choices_iter().map(|choice| T::from_choice(&choice)) {
+          if !choices.contains(&choice) {
+            choices.push(choice);
+          }
+        }
+
+        Ok(choices)
+      })
+    }
+    .camera(self.camera)
+  }
+
+  /// Get the white balance
+  pub fn white_balance(&self) -> Task<Result<WhiteBalance>> {
+    self.get()
+  }
+
+  /// Set the white balance, fails if the camera doesn't offer it
+  pub fn set_white_balance(&self, white_balance: WhiteBalance) -> Task<Result<()>> {
+    self.set(white_balance)
+  }
+
+  /// White balances offered by the camera
+  pub fn white_balances(&self) -> Task<Result<Vec<WhiteBalance>>> {
+    self.choices()
+  }
+
+  /// Get the color temperature in Kelvin used by [`WhiteBalance::ColorTemperature`]
+  pub fn color_temperature(&self) -> Task<Result<u32>> {
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        let widget = find_config(*camera, &[COLOR_TEMPERATURE_KEY], *context)?;
+
+        let kelvin = match &widget {
+          #[allow(clippy::as_conversions)]
+          Widget::Range(range) => Some(range.value().round() as u32),
+          Widget::Text(text) => text.value().trim().parse().ok(),
+          Widget::Radio(radio) => parse_kelvin(&radio.choice()),
+          _ => None,
+        };
+
+        kelvin.ok_or_else(|| Error::from(format!("Unknown color temperature {widget:?}")))
+      })
+    }
+    .camera(self.camera)
+  }
+
+  /// Set the color temperature in Kelvin
+  ///
+  /// The white balance is switched to [`WhiteBalance::ColorTemperature`] first if the camera
+  /// has that choice. Cameras with a fixed list of temperatures use the closest one.
+  pub fn set_color_temperature(&self, kelvin: u32) -> Task<Result<()>> {
+    let camera = self.camera.camera;
+    let context = self.camera.context.inner();
+
+    unsafe {
+      Task::new(move || {
+        if let Ok(white_balance) = find_radio::<WhiteBalance>(*camera, *context) {
+          let current = WhiteBalance::from_choice(&white_balance.choice());
+          let supported = white_balance
+            .choices_iter()
+            .any(|choice| WhiteBalance::from_choice(&choice) == WhiteBalance::ColorTemperature);
+
+          if supported && current != WhiteBalance::ColorTemperature {
+            set_radio(*camera, &white_balance, &WhiteBalance::ColorTemperature, *context)?;
+          }
+        }
+
+        let widget = find_config(*camera, &[COLOR_TEMPERATURE_KEY], *context)?;
+
+        match &widget {
+          #[allow(clippy::as_conversions)]
+          Widget::Range(range) => range.set_value_forced(kelvin as f32),
+          Widget::Text(text) => text.set_value_forced(&kelvin.to_string())?,
+          Widget::Radio(radio) => {
+            let closest = radio
+              .choices_iter()
+              .filter_map(|choice| Some((parse_kelvin(&choice)?.abs_diff(kelvin), choice)))
+              .min_by_key(|(difference, _)| *difference)
+              .ok_or("The camera has no color temperature choices")?;
+
+            radio.set_choice_forced(&closest.1)?;
+          }
+          _ => return Err(Error::from(format!("Unsupported color temperature {widget:?}"))),
+        }
+
+        set_single_config(*camera, &widget, *context)
+      })
+    }
+    .camera(self.camera)
+  }
+
+  /// Get the picture style
+  pub fn picture_style(&self) -> Task<Result<PictureStyle>> {
+    self.get()
+  }
+
+  /// Set the picture style, fails if the camera doesn't offer it
+  pub fn set_picture_style(&self, picture_style: PictureStyle) -> Task<Result<()>> {
+    self.set(picture_style)
+  }
+
+  /// Picture styles offered by the camera
+  pub fn picture_styles(&self) -> Task<Result<Vec<PictureStyle>>> {
+    self.choices()
+  }
+
+  /// Get the shutter speed
+  pub fn shutter_speed(&self) -> Task<Result<ShutterSpeed>> {
+    self.get_numeric()
+  }
+
+  /// Set the shutter speed, cameras without the exact value use the closest one
+  pub fn set_shutter_speed(&self, shutter_speed: ShutterSpeed) -> Task<Result<()>> {
+    self.set_numeric(shutter_speed)
+  }
+
+  /// Shutter speeds offered by the camera in the current mode
+  pub fn shutter_speeds(&self) -> Task<Result<Vec<ShutterSpeed>>> {
+    self.numeric_choices()
+  }
+
+  /// Get the aperture
+  pub fn aperture(&self) -> Task<Result<Aperture>> {
+    self.get_numeric()
+  }
+
+  /// Set the aperture, cameras without the exact value use the closest one
+  pub fn set_aperture(&self, aperture: Aperture) -> Task<Result<()>> {
+    self.set_numeric(aperture)
+  }
+
+  /// Apertures offered by the camera and lens
+  pub fn apertures(&self) -> Task<Result<Vec<Aperture>>> {
+    self.numeric_choices()
+  }
+
+  /// Get the ISO speed
+  pub fn iso(&self) -> Task<Result<Iso>> {
+    self.get_numeric()
+  }
+
+  /// Set the ISO speed, cameras without the exact value use the closest one
+  pub fn set_iso(&self, iso: Iso) -> Task<Result<()>> {
+    self.set_numeric(iso)
+  }
+
+  /// ISO speeds offered by the camera
+  pub fn isos(&self) -> Task<Result<Vec<Iso>>> {
+    self.numeric_choices()
+  }
+
+  /// Get the exposure compensation
+  pub fn exposure_compensation(&self) -> Task<Result<ExposureCompensation>> {
+    self.get_numeric()
+  }
+
+  /// Set the exposure compensation, cameras without the exact value use the closest one
+  pub fn set_exposure_compensation(&self, compensation: ExposureCompensation) -> Task<Result<()>> {
+    self.set_numeric(compensation)
+  }
+
+  /// Exposure compensations offered by the camera
+  pub fn exposure_compensations(&self) -> Task<Result<Vec<ExposureCompensation>>> {
+    self.numeric_choices()
+  }
+}
+
+/// Parse choices like `5600` or `5600K`
+fn parse_kelvin(choice: &str) -> Option<u32> {
+  choice.trim().trim_end_matches(['K', 'k']).trim().parse().ok()
+}
@@ -0,0 +1,88 @@
+//! Persistent settings of libgphoto2
+//!
+//! libgphoto2 keeps a small key-value store in `~/.gphoto/settings`, which is used by
+//! drivers for options that must survive restarts (like the GUID used to pair
+//! with WiFi cameras over PTP/IP). Settings are grouped by an ID, usually the driver name.
+//!
+//! ```no_run
+//! use gphoto2::{settings_store, Result};
+//!
+//! # fn main() -> Result<()> {
+//! settings_store::set("ptp2_ip", "guid", "ab:cd:ef:01:23:45:67:89:ab:cd:ef:01:23:45:67:89").wait()?;
+//!
+//! if let Some(guid) = settings_store::get("ptp2_ip", "guid").wait()? {
+//!   println!("Pairing with GUID {guid}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+  helper::{chars_to_string, to_c_string},
+  task::Task,
+  try_gp_internal, Error, Result,
+};
+use std::{ffi, os::raw::c_char};
+
+/// Size of the buffers libgphoto2 stores IDs, keys and values in (including the NUL byte)
+const MAX_LEN: usize = 256;
+
+fn check_len(name: &str, value: &str) -> Result<()> {
+  if value.len() >= MAX_LEN {
+    Err(Error::from(format!("Setting {name} must be shorter than {MAX_LEN} bytes")))
+  } else {
+    Ok(())
+  }
+}
+
+/// Get a setting, returns [`None`] if it isn't set
+pub fn get(id: &str, key: &str) -> Task<Result<Option<String>>> {
+  let id = id.to_owned();
+  let key = key.to_owned();
+
+  unsafe {
+    Task::new(move || {
+      check_len("id", &id)?;
+      check_len("key", &key)?;
+
+      let mut value: [c_char; MAX_LEN] = [0; MAX_LEN];
+
+      let status = libgphoto2_sys::gp_setting_get(
+        to_c_string!(id).cast_mut(),
+        to_c_string!(key).cast_mut(),
+        value.as_mut_ptr(),
+      );
+
+      // Missing settings are reported as a generic error
+      if status == libgphoto2_sys::GP_ERROR {
+        return Ok(None);
+      }
+      Error::check(status)?;
+
+      Ok(Some(chars_to_string(value.as_ptr())))
+    })
+  }
+}
+
+/// Set a setting, the settings file is written immediately
+pub fn set(id: &str, key: &str, value: &str) -> Task<Result<()>> {
+  let id = id.to_owned();
+  let key = key.to_owned();
+  let value = value.to_owned();
+
+  unsafe {
+    Task::new(move || {
+      check_len("id", &id)?;
+      check_len("key", &key)?;
+      check_len("value", &value)?;
+
+      try_gp_internal!(gp_setting_set(
+        to_c_string!(id).cast_mut(),
+        to_c_string!(key).cast_mut(),
+        to_c_string!(value).cast_mut()
+      )?);
+
+      Ok(())
+    })
+  }
+}
@@ -0,0 +1,408 @@
+//! Bounded streams of frames and events
+//!
+//! [`LiveView::preview_stream`](crate::liveview::LiveView::preview_stream) and
+//! [`Camera::events`](crate::Camera::events) run the camera on a background thread and buffer
+//! the results in a bounded queue. What happens when the consumer is slower than the camera is
+//! decided by the [`OverflowPolicy`]: preview frames are only useful while they are recent,
+//! while file events must not be lost.
+//!
+//! Streams are iterators, with the `futures` feature they also implement
+//! [`futures_core::Stream`] for async consumers.
+//!
+//! ```no_run
+//! use gphoto2::{stream::{OverflowPolicy, StreamOptions}, Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//!
+//! let events = camera.events(StreamOptions::new(64, OverflowPolicy::Block));
+//! for event in events {
+//!   println!("{:?}", event?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+  collections::VecDeque,
+  sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+  task::Waker,
+  thread,
+  time::{Duration, Instant},
+};
+
+/// What to do with a new item when the buffer of a [`Stream`] is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverflowPolicy {
+  /// Wait until the consumer takes an item, the camera is not polled meanwhile
+  Block,
+  /// Drop the oldest buffered item, the consumer always gets the most recent items
+  DropOldest,
+  /// Drop the new item
+  DropNewest,
+}
+
+/// Buffering of a [`Stream`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StreamOptions {
+  capacity: usize,
+  policy: OverflowPolicy,
+}
+
+impl StreamOptions {
+  /// Buffer up to `capacity` items (at least one), handling overflows with `policy`
+  pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+    Self { capacity: capacity.max(1), policy }
+  }
+
+  /// Default for preview frames, only the most recent frame is kept
+  pub fn latest() -> Self {
+    Self::new(1, OverflowPolicy::DropOldest)
+  }
+
+  /// Maximum number of buffered items
+  pub fn capacity(&self) -> usize {
+    self.capacity
+  }
+
+  /// What happens when the buffer is full
+  pub fn policy(&self) -> OverflowPolicy {
+    self.policy
+  }
+}
+
+struct QueueState<T> {
+  items: VecDeque<T>,
+  dropped: u64,
+  /// Set when the consumer is gone
+  closed: bool,
+  /// Set when the producer won't push any more items
+  finished: bool,
+  /// Async consumer waiting for the next item
+  waker: Option<Waker>,
+}
+
+/// Queue shared between the producing thread and the [`Stream`]
+pub(crate) struct BoundedQueue<T> {
+  state: Mutex<QueueState<T>>,
+  changed: Condvar,
+  options: StreamOptions,
+}
+
+impl<T> BoundedQueue<T> {
+  fn new(options: StreamOptions) -> Self {
+    Self {
+      state: Mutex::new(QueueState {
+        items: VecDeque::with_capacity(options.capacity),
+        dropped: 0,
+        closed: false,
+        finished: false,
+        waker: None,
+      }),
+      changed: Condvar::new(),
+      options,
+    }
+  }
+
+  fn state(&self) -> MutexGuard<'_, QueueState<T>> {
+    self.state.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  /// Add an item according to the overflow policy, returns false once the consumer is gone
+  pub(crate) fn push(&self, item: T) -> bool {
+    let mut state = self.state();
+
+    while state.items.len() >= self.options.capacity && !state.closed {
+      match self.options.policy {
+        OverflowPolicy::Block => {
+          state = self.changed.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+        OverflowPolicy::DropOldest => {
+          state.items.pop_front();
+          state.dropped += 1;
+        }
+        OverflowPolicy::DropNewest => {
+          state.dropped += 1;
+          return true;
+        }
+      }
+    }
+
+    if state.closed {
+      return false;
+    }
+
+    state.items.push_back(item);
+    self.notify(state);
+    true
+  }
+
+  /// Add the last item (the error which ended the stream), which is never dropped
+  ///
+  /// The buffer may exceed its capacity by this item.
+  pub(crate) fn push_last(&self, item: T) {
+    let mut state = self.state();
+
+    if !state.closed {
+      state.items.push_back(item);
+      self.notify(state);
+    }
+  }
+
+  /// Wake blocking and async consumers
+  fn notify(&self, mut state: MutexGuard<'_, QueueState<T>>) {
+    let waker = state.waker.take();
+    drop(state);
+
+    self.changed.notify_all();
+    if let Some(waker) = waker {
+      waker.wake();
+    }
+  }
+
+  /// Whether the consumer is gone
+  pub(crate) fn is_closed(&self) -> bool {
+    self.state().closed
+  }
+
+  fn pop(&self, deadline: Option<Instant>) -> Option<T> {
+    let mut state = self.state();
+
+    loop {
+      if let Some(item) = state.items.pop_front() {
+        self.changed.notify_all();
+        return Some(item);
+      }
+
+      if state.finished {
+        return None;
+      }
+
+      state = match deadline {
+        Some(deadline) => {
+          let remaining = deadline.saturating_duration_since(Instant::now());
+          if remaining.is_zero() {
+            return None;
+          }
+
+          self.changed.wait_timeout(state, remaining).unwrap_or_else(PoisonError::into_inner).0
+        }
+        None => self.changed.wait(state).unwrap_or_else(PoisonError::into_inner),
+      };
+    }
+  }
+
+  fn finish(&self) {
+    let mut state = self.state();
+    state.finished = true;
+    self.notify(state);
+  }
+
+  fn close(&self) {
+    self.state().closed = true;
+    self.changed.notify_all();
+  }
+}
+
+/// Finishes the queue when dropped, so the consumer doesn't wait forever if the producer panics
+struct FinishGuard<'a, T>(&'a BoundedQueue<T>);
+
+impl<T> Drop for FinishGuard<'_, T> {
+  fn drop(&mut self) {
+    self.0.finish();
+  }
+}
+
+/// Items produced by a background thread, see the [module documentation](self)
+///
+/// The stream ends after the first error. Dropping it stops the background thread once its
+/// current camera call returned, without waiting for it.
+pub struct Stream<T> {
+  queue: Arc<BoundedQueue<T>>,
+}
+
+impl<T: Send + 'static> Stream<T> {
+  /// Run `produce` on a new thread, which pushes items until it returns or the stream is dropped
+  pub(crate) fn spawn<F>(options: StreamOptions, produce: F) -> Self
+  where
+    F: FnOnce(&BoundedQueue<T>) + Send + 'static,
+  {
+    let queue = Arc::new(BoundedQueue::new(options));
+
+    // Detached, the thread notices the closed queue after its current camera call
+    thread::spawn({
+      let queue = queue.clone();
+
+      move || {
+        let _finish = FinishGuard(&queue);
+        produce(&queue);
+      }
+    });
+
+    Self { queue }
+  }
+}
+
+impl<T> Stream<T> {
+  /// Wait up to `timeout` for the next item
+  ///
+  /// Returns [`None`] if the timeout elapsed or the stream ended.
+  pub fn next_timeout(&mut self, timeout: Duration) -> Option<T> {
+    self.queue.pop(Some(Instant::now().checked_add(timeout)?))
+  }
+
+  /// Take the next item if one is buffered
+  pub fn try_next(&mut self) -> Option<T> {
+    self.queue.pop(Some(Instant::now()))
+  }
+
+  /// Number of buffered items
+  pub fn buffered(&self) -> usize {
+    self.queue.state().items.len()
+  }
+
+  /// Number of items dropped because the buffer was full
+  pub fn dropped(&self) -> u64 {
+    self.queue.state().dropped
+  }
+
+  /// Buffering of this stream
+  pub fn options(&self) -> StreamOptions {
+    self.queue.options
+  }
+}
+
+impl<T> Iterator for Stream<T> {
+  type Item = T;
+
+  fn next(&mut self) -> Option<T> {
+    self.queue.pop(None)
+  }
+}
+
+#[cfg(feature = "futures")]
+impl<T> futures_core::Stream for Stream<T> {
+  type Item = T;
+
+  fn poll_next(
+    self: std::pin::Pin<&mut Self>,
+    cx: &mut std::task::Context<'_>,
+  ) -> std::task::Poll<Option<T>> {
+    let queue = &self.queue;
+    let mut state = queue.state();
+
+    if let Some(item) = state.items.pop_front() {
+      drop(state);
+      // Wakes a producer blocked on a full buffer
+      queue.changed.notify_all();
+      return std::task::Poll::Ready(Some(item));
+    }
+
+    if state.finished {
+      return std::task::Poll::Ready(None);
+    }
+
+    state.waker = Some(cx.waker().clone());
+    std::task::Poll::Pending
+  }
+}
+
+impl<T> Drop for Stream<T> {
+  fn drop(&mut self) {
+    self.queue.close();
+  }
+}
+
+#[cfg(all(test, feature = "test"))]
+mod tests {
+  use super::*;
+  use std::sync::mpsc;
+
+  fn stream(capacity: usize, policy: OverflowPolicy) -> (Stream<u32>, Arc<BoundedQueue<u32>>) {
+    let queue = Arc::new(BoundedQueue::new(StreamOptions::new(capacity, policy)));
+
+    (Stream { queue: queue.clone() }, queue)
+  }
+
+  fn drain(stream: &mut Stream<u32>) -> Vec<u32> {
+    std::iter::from_fn(|| stream.try_next()).collect()
+  }
+
+  #[test]
+  fn test_drop_oldest() {
+    let (mut stream, queue) = stream(2, OverflowPolicy::DropOldest);
+
+    assert!((1..=5).all(|item| queue.push(item)));
+    assert_eq!((stream.buffered(), stream.dropped()), (2, 3));
+    assert_eq!(drain(&mut stream), [4, 5]);
+  }
+
+  #[test]
+  fn test_drop_newest() {
+    let (mut stream, queue) = stream(2, OverflowPolicy::DropNewest);
+
+    assert!((1..=5).all(|item| queue.push(item)));
+    assert_eq!((stream.buffered(), stream.dropped()), (2, 3));
+
+    // The last item is kept even though the buffer is full
+    queue.push_last(6);
+    assert_eq!(drain(&mut stream), [1, 2, 6]);
+  }
+
+  #[test]
+  fn test_block() {
+    let (mut stream, queue) = stream(1, OverflowPolicy::Block);
+    let (pushed, wait_pushed) = mpsc::channel();
+
+    thread::spawn(move || {
+      for item in 1..=2 {
+        queue.push(item);
+        pushed.send(item).unwrap();
+      }
+    });
+
+    assert_eq!(wait_pushed.recv().unwrap(), 1);
+    assert!(wait_pushed.recv_timeout(Duration::from_millis(100)).is_err());
+    assert_eq!(stream.dropped(), 0);
+
+    assert_eq!(stream.next_timeout(Duration::from_secs(5)), Some(1));
+    assert_eq!(wait_pushed.recv_timeout(Duration::from_secs(5)), Ok(2));
+    assert_eq!(stream.next_timeout(Duration::from_secs(5)), Some(2));
+  }
+
+  #[test]
+  fn test_closed() {
+    let (stream, queue) = stream(1, OverflowPolicy::Block);
+    assert!(queue.push(1));
+
+    // Unblocks a producer waiting for space
+    let producer = thread::spawn({
+      let queue = queue.clone();
+      move || queue.push(2)
+    });
+    thread::sleep(Duration::from_millis(50));
+    drop(stream);
+
+    assert!(!producer.join().unwrap());
+    assert!(queue.is_closed());
+    assert!(!queue.push(3));
+  }
+
+  #[test]
+  fn test_spawn() {
+    let stream = Stream::spawn(StreamOptions::new(2, OverflowPolicy::Block), |queue| {
+      (1..=5).all(|item| queue.push(item));
+    });
+    assert_eq!(stream.collect::<Vec<_>>(), [1, 2, 3, 4, 5]);
+
+    // A panicking producer still ends the stream
+    let mut stream = Stream::<u32>::spawn(StreamOptions::latest(), |queue| {
+      queue.push(1);
+      panic!("producer panicked");
+    });
+    let start = Instant::now();
+    assert_eq!(stream.next_timeout(Duration::from_secs(10)), Some(1));
+    assert_eq!(stream.next_timeout(Duration::from_secs(10)), None);
+    assert!(start.elapsed() < Duration::from_secs(10));
+  }
+}
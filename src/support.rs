@@ -0,0 +1,295 @@
+//! What this crate can do with a camera
+//!
+//! [`Camera::support_matrix`](crate::Camera::support_matrix) combines the driver's abilities,
+//! the configuration the camera exposes and the [`quirks`] table into a single
+//! overview. Its [`Display`](fmt::Display) output is meant to be pasted into bug reports.
+//!
+//! ```no_run
+//! use gphoto2::{Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//!
+//! println!("{}", camera.support_matrix()?);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+  abilities::{Abilities, CameraDriverStatus},
+  camera::{camera_abilities, get_single_config, CaptureTarget},
+  clock, movie, quirks,
+  widget::GroupWidget,
+  Camera, Error, Result,
+};
+use std::fmt;
+
+/// Configurations used for bulb exposures, in order of preference
+const BULB_KEYS: &[&str] = &["bulb", "eosremoterelease"];
+/// Configurations used to record movies on cameras which can't capture videos directly
+const MOVIE_KEYS: &[&str] = &["movie", "movierecordtarget"];
+
+/// Feature of a camera, see [`Camera::can`](crate::Camera::can)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Capability {
+  /// [`Camera::capture_image`](crate::Camera::capture_image)
+  CaptureImage,
+  /// [`Camera::trigger_capture`](crate::Camera::trigger_capture)
+  TriggerCapture,
+  /// [`Camera::live_view`](crate::Camera::live_view), the viewfinder config is optional
+  LiveView,
+  /// Bulb exposures through a config
+  Bulb,
+  /// [`Camera::start_movie`](crate::Camera::start_movie) or [`Camera::capture_movie`](crate::Camera::capture_movie)
+  Movie,
+  /// [`Camera::capture_sound`](crate::Camera::capture_sound)
+  Audio,
+  /// Reading and writing the configuration
+  Configure,
+  /// [`Camera::set_capture_target`](crate::Camera::set_capture_target)
+  CaptureTarget,
+  /// [`Camera::clock`](crate::Camera::clock) and [`Camera::sync_clock`](crate::Camera::sync_clock)
+  Clock,
+  /// Downloading raw data of files
+  RawDownload,
+  /// Downloading EXIF data of files
+  Exif,
+  /// Deleting files
+  DeleteFiles,
+  /// Uploading files
+  UploadFiles,
+}
+
+impl Capability {
+  fn description(self) -> &'static str {
+    match self {
+      Self::CaptureImage => "capture images",
+      Self::TriggerCapture => "trigger captures",
+      Self::LiveView => "capture previews",
+      Self::Bulb => "take bulb exposures",
+      Self::Movie => "record movies",
+      Self::Audio => "record audio",
+      Self::Configure => "be configured",
+      Self::CaptureTarget => "change the capture target",
+      Self::Clock => "set its clock",
+      Self::RawDownload => "download raw data",
+      Self::Exif => "download EXIF data",
+      Self::DeleteFiles => "delete files",
+      Self::UploadFiles => "upload files",
+    }
+  }
+}
+
+/// Whether a feature is supported
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Support {
+  /// Supported by the driver
+  Yes,
+  /// Supported by changing the given configuration
+  Via(String),
+  /// Not supported
+  No,
+}
+
+impl Support {
+  /// Whether the feature can be used at all
+  pub fn is_supported(&self) -> bool {
+    !matches!(self, Self::No)
+  }
+
+  fn from_flag(flag: bool) -> Self {
+    if flag {
+      Self::Yes
+    } else {
+      Self::No
+    }
+  }
+}
+
+impl fmt::Display for Support {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Yes => f.write_str("yes"),
+      Self::Via(key) => write!(f, "via {key}"),
+      Self::No => f.write_str("no"),
+    }
+  }
+}
+
+/// Overview of the features supported for a camera
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SupportMatrix {
+  /// Camera model
+  pub model: String,
+  /// Stability of the driver
+  pub driver_status: CameraDriverStatus,
+  /// Capturing images
+  pub capture_image: Support,
+  /// Triggering captures without waiting for the image
+  pub trigger_capture: Support,
+  /// Capturing previews, used for live view
+  pub live_view: Support,
+  /// Bulb exposures
+  pub bulb: Support,
+  /// Recording movies
+  pub movie: Support,
+  /// Recording audio
+  pub audio: Support,
+  /// Reading and writing the configuration
+  pub configure: Support,
+  /// Downloading raw data of files
+  pub raw_download: Support,
+  /// Downloading EXIF data of files
+  pub exif: Support,
+  /// Deleting files
+  pub delete_files: Support,
+  /// Uploading files
+  pub upload_files: Support,
+  /// Config aliases registered for this model as `(alias, name)`
+  pub config_aliases: Vec<(String, String)>,
+}
+
+impl SupportMatrix {
+  pub(crate) fn probe(camera: &Camera) -> Result<Self> {
+    let abilities = camera.abilities()?;
+    let model = abilities.model().into_owned();
+    let camera_ops = abilities.camera_operations();
+    let file_ops = abilities.file_operations();
+    let folder_ops = abilities.folder_operations();
+
+    let config = if camera_ops.configure() { Some(camera.config().wait()?) } else { None };
+    let via_config = |keys: &[&str]| {
+      let config = config.as_ref()?;
+      keys
+        .iter()
+        .find_map(|key| has_config(config, &model, key).then(|| Support::Via(key.to_string())))
+    };
+
+    let movie = if camera_ops.capture_video() {
+      Support::Yes
+    } else {
+      via_config(MOVIE_KEYS).unwrap_or(Support::No)
+    };
+
+    let config_aliases = quirks::config_aliases()
+      .into_iter()
+      .filter(|(alias_model, _, _)| *alias_model == model)
+      .map(|(_, alias, name)| (alias, name))
+      .collect();
+
+    Ok(Self {
+      driver_status: abilities.driver_status(),
+      capture_image: Support::from_flag(camera_ops.capture_image()),
+      trigger_capture: Support::from_flag(camera_ops.trigger_capture()),
+      live_view: Support::from_flag(camera_ops.capture_preview()),
+      bulb: via_config(BULB_KEYS).unwrap_or(Support::No),
+      movie,
+      audio: Support::from_flag(camera_ops.capture_audio()),
+      configure: Support::from_flag(camera_ops.configure()),
+      raw_download: Support::from_flag(file_ops.raw()),
+      exif: Support::from_flag(file_ops.exif()),
+      delete_files: Support::from_flag(file_ops.delete()),
+      upload_files: Support::from_flag(folder_ops.put_file()),
+      config_aliases,
+      model,
+    })
+  }
+}
+
+impl fmt::Display for SupportMatrix {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "model: {}", self.model)?;
+    writeln!(f, "driver status: {:?}", self.driver_status)?;
+    writeln!(f, "capture image: {}", self.capture_image)?;
+    writeln!(f, "trigger capture: {}", self.trigger_capture)?;
+    writeln!(f, "live view: {}", self.live_view)?;
+    writeln!(f, "bulb: {}", self.bulb)?;
+    writeln!(f, "movie: {}", self.movie)?;
+    writeln!(f, "audio: {}", self.audio)?;
+    writeln!(f, "configure: {}", self.configure)?;
+    writeln!(f, "raw download: {}", self.raw_download)?;
+    writeln!(f, "exif: {}", self.exif)?;
+    writeln!(f, "delete files: {}", self.delete_files)?;
+    write!(f, "upload files: {}", self.upload_files)?;
+
+    for (alias, name) in &self.config_aliases {
+      write!(f, "\nconfig alias: {alias} -> {name}")?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Check a single capability from the abilities and the configuration, must be called from a task
+pub(crate) unsafe fn capability_support(
+  camera: *mut libgphoto2_sys::Camera,
+  capability: Capability,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<Support> {
+  Ok(support_with_abilities(camera, &camera_abilities(camera)?, capability, context))
+}
+
+unsafe fn support_with_abilities(
+  camera: *mut libgphoto2_sys::Camera,
+  abilities: &Abilities,
+  capability: Capability,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Support {
+  let camera_ops = abilities.camera_operations();
+  let file_ops = abilities.file_operations();
+
+  let via_config = |keys: &[&str]| {
+    if !camera_ops.configure() {
+      return Support::No;
+    }
+
+    keys
+      .iter()
+      .find(|key| get_single_config(camera, key, context).is_ok())
+      .map_or(Support::No, |key| Support::Via(key.to_string()))
+  };
+
+  match capability {
+    Capability::CaptureImage => Support::from_flag(camera_ops.capture_image()),
+    Capability::TriggerCapture => Support::from_flag(camera_ops.trigger_capture()),
+    Capability::LiveView => Support::from_flag(camera_ops.capture_preview()),
+    Capability::Bulb => via_config(BULB_KEYS),
+    Capability::Movie if camera_ops.capture_video() => Support::Yes,
+    Capability::Movie => via_config(movie::RECORD_KEYS),
+    Capability::Audio => Support::from_flag(camera_ops.capture_audio()),
+    Capability::Configure => Support::from_flag(camera_ops.configure()),
+    Capability::CaptureTarget => via_config(&[CaptureTarget::KEY]),
+    Capability::Clock => via_config(&clock::CLOCK_KEYS),
+    Capability::RawDownload => Support::from_flag(file_ops.raw()),
+    Capability::Exif => Support::from_flag(file_ops.exif()),
+    Capability::DeleteFiles => Support::from_flag(file_ops.delete()),
+    Capability::UploadFiles => Support::from_flag(abilities.folder_operations().put_file()),
+  }
+}
+
+/// Fail with [`ErrorKind::NotSupported`](crate::error::ErrorKind::NotSupported) if the camera
+/// doesn't have a capability, must be called from a task
+pub(crate) unsafe fn require_capability(
+  camera: *mut libgphoto2_sys::Camera,
+  capability: Capability,
+  context: *mut libgphoto2_sys::GPContext,
+) -> Result<()> {
+  let abilities = camera_abilities(camera)?;
+
+  if support_with_abilities(camera, &abilities, capability, context).is_supported() {
+    return Ok(());
+  }
+
+  Err(Error::new(
+    libgphoto2_sys::GP_ERROR_NOT_SUPPORTED,
+    Some(format!("The {} can't {}", abilities.model(), capability.description())),
+  ))
+}
+
+/// Whether the configuration contains `key` (after resolving aliases)
+fn has_config(config: &GroupWidget, model: &str, key: &str) -> bool {
+  config.get_child_by_name(&quirks::resolve_config_key(model, key)).is_ok()
+}
@@ -1,45 +1,265 @@
 //! Allows thread safe interaction with libgphoto2
 
+#[cfg(feature = "tracing")]
+use crate::camera::camera_abilities;
 use crate::{
-  context::{CancelHandler, ProgressHandler},
-  thread::{TaskFunc, ThreadManager, THREAD_MANAGER},
-  Context,
+  context::{CancelFn, CancelHandler, ProgressEvent, ProgressFn, ProgressHandler},
+  metrics::Operation,
+  thread::{
+    in_handler, is_worker_thread, DelayFunc, Job, LockQueue, TaskFunc, ThreadManager,
+    THREAD_MANAGER,
+  },
+  Camera, Context,
 };
-use crossbeam_channel::{bounded, Receiver, RecvError, Sender};
+use crossbeam_channel::{bounded, Receiver, RecvError, Sender, TryRecvError};
+#[cfg(feature = "tracing")]
+use std::panic::Location;
 use std::{
+  fmt,
   future::Future,
   ops::Deref,
+  os::raw::c_int,
+  panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
   sync::{
     atomic::{AtomicBool, Ordering},
-    Arc,
+    Arc, Mutex, PoisonError,
   },
   task::{Poll, Waker},
+  time::{Duration, Instant},
 };
 
+const TASK_PANICKED: &str = "The libgphoto2 task panicked";
+
 type ToBeRunTask<T> = Option<(Box<dyn FnOnce() -> T + Send>, Sender<T>)>;
 
 #[derive(Clone, Copy)]
 pub(crate) struct BackgroundPtr<T>(pub *mut T);
 
+/// A libgphoto2 reference shared by all clones of a wrapper
+///
+/// Cloning the wrapper only clones the [`Arc`], so it never waits for the libgphoto2 thread.
+/// The reference is released in the background once the last clone is dropped.
+pub(crate) struct SharedRef<T: 'static> {
+  ptr: BackgroundPtr<T>,
+  unref: unsafe extern "C" fn(*mut T) -> c_int,
+}
+
 /// Allows awaiting (or blocking) libgphoto2 function responses
+///
+/// # Reentrancy
+///
+/// All tasks run one after another on a single libgphoto2 thread. Tasks started on that thread,
+/// for example by a [progress handler](crate::context::ProgressHandler), run immediately instead
+/// of being queued, since waiting for them would block the thread forever. They share the
+/// cancellation and progress handlers of the running task.
+///
+/// Context handlers are called in the middle of a libgphoto2 operation, most drivers fail if
+/// they are used again at that point. Handlers should only pass the information on (e.g. over a
+/// channel) and leave camera operations to other threads.
 pub struct Task<T> {
   rx: Receiver<T>,
-  cancel: Arc<AtomicBool>,
+  cancel: Arc<CancelFlag>,
   set_waker: Sender<Waker>,
   waker_set: bool,
   task: ToBeRunTask<T>,
-  context: Option<BackgroundPtr<libgphoto2_sys::GPContext>>,
+  context: Option<Context>,
+  /// Id of the camera the task operates on, the task waits while another thread locks it
+  camera_id: Option<usize>,
+  /// Backoff of the busy governor of the camera, the worker runs other tasks in the meantime
+  delay: Option<DelayFunc>,
   progress_handler: Option<Box<dyn ProgressHandler>>,
+  cancel_tokens: Vec<CancelToken>,
   recv_waker: Option<Receiver<Waker>>,
+  /// Where the task was created, recorded in its span
+  #[cfg(feature = "tracing")]
+  location: &'static Location<'static>,
+  /// Camera whose model is recorded in the span
+  #[cfg(feature = "tracing")]
+  camera: Option<BackgroundPtr<libgphoto2_sys::Camera>>,
+}
+
+struct TaskCancelHandler {
+  flag: Arc<CancelFlag>,
+  tokens: Vec<CancelToken>,
+  /// Set if the context has an operation timeout
+  deadline: Option<Instant>,
+  context_cancel: Option<Arc<Mutex<CancelFn>>>,
+}
+
+/// Cancellation state of a task
+#[derive(Default)]
+pub(crate) struct CancelFlag {
+  cancelled: AtomicBool,
+  requested_at: Mutex<Option<Instant>>,
+}
+
+impl CancelFlag {
+  fn request(&self) {
+    self.request_at(Instant::now());
+  }
+
+  fn request_at(&self, at: Instant) {
+    self.requested_at.lock().unwrap_or_else(PoisonError::into_inner).get_or_insert(at);
+    self.cancelled.store(true, Ordering::Relaxed);
+  }
+
+  fn requested_at(&self) -> Option<Instant> {
+    *self.requested_at.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  fn is_cancelled(&self) -> bool {
+    self.cancelled.load(Ordering::Relaxed)
+  }
+
+  /// Time since the cancellation was requested
+  fn elapsed(&self) -> Option<Duration> {
+    self.requested_at().map(|at| at.elapsed())
+  }
+}
+
+/// Cancels several tasks at once, like all downloads of a batch
+///
+/// Tasks are added with [`Task::with_cancel_token`]. Cancelling the token cancels the tasks
+/// which are running or not started yet, as well as tasks added later. Clones of a token share
+/// the state, [`CancelToken::child`] creates a token which is also cancelled with its parent.
+///
+/// ```no_run
+/// use gphoto2::{task::CancelToken, Context, Result};
+///
+/// # fn main() -> Result<()> {
+/// let camera = Context::new()?.autodetect_camera().wait()?;
+/// let token = CancelToken::new();
+///
+/// let stop = token.clone();
+/// std::thread::spawn(move || {
+///   std::thread::sleep(std::time::Duration::from_secs(10));
+///   stop.cancel();
+/// });
+///
+/// for name in camera.fs().list_files("/store_00010001/DCIM/100CANON").wait()? {
+///   let file = camera
+///     .fs()
+///     .download("/store_00010001/DCIM/100CANON", &name)
+///     .with_cancel_token(&token)
+///     .wait()?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct CancelToken {
+  flag: Arc<CancelFlag>,
+  /// Cancelled once this passed, used by [`OperationBudget`]
+  deadline: Option<Instant>,
+  parent: Option<Box<CancelToken>>,
+}
+
+impl CancelToken {
+  /// Create a token which is not cancelled
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Create a token which is cancelled together with this one, but can be cancelled on its own
+  pub fn child(&self) -> Self {
+    Self { flag: Default::default(), deadline: None, parent: Some(Box::new(self.clone())) }
+  }
+
+  /// Cancel all tasks using this token or one of its children
+  pub fn cancel(&self) {
+    self.flag.request();
+  }
+
+  /// Whether this token or one of its parents was cancelled
+  pub fn is_cancelled(&self) -> bool {
+    self.requested_at().is_some()
+  }
+
+  /// When the cancellation was requested, including cancellations of the parents
+  fn requested_at(&self) -> Option<Instant> {
+    let own = self.flag.requested_at();
+    let expired = self.deadline.filter(|&deadline| Instant::now() >= deadline);
+    let parent = self.parent.as_ref().and_then(|parent| parent.requested_at());
+
+    own.into_iter().chain(expired).chain(parent).min()
+  }
 }
 
-struct TaskCancelHandler(Arc<AtomicBool>);
+impl fmt::Debug for CancelToken {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("CancelToken").field("cancelled", &self.is_cancelled()).finish()
+  }
+}
+
+/// Total time limit of several tasks
+///
+/// Tasks added with [`Task::with_budget`] are cancelled once the time since the budget was
+/// created exceeds the limit, so they fail with [`ErrorKind::Cancelled`](crate::error::ErrorKind::Cancelled).
+/// Like other cancellations this only takes effect when the driver checks for it.
+///
+/// ```no_run
+/// use gphoto2::{task::OperationBudget, Context, Result};
+/// use std::time::Duration;
+///
+/// # fn main() -> Result<()> {
+/// let camera = Context::new()?.autodetect_camera().wait()?;
+///
+/// // Capture and download must finish in 10 seconds
+/// let budget = OperationBudget::new(Duration::from_secs(10));
+///
+/// let path = camera.capture_image().with_budget(&budget).wait()?;
+/// let file = camera.fs().download(&path.folder(), &path.name()).with_budget(&budget).wait()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct OperationBudget {
+  token: CancelToken,
+  start: Instant,
+  limit: Duration,
+}
+
+impl OperationBudget {
+  /// Start a budget of `limit`
+  pub fn new(limit: Duration) -> Self {
+    let start = Instant::now();
+    let token = CancelToken { deadline: start.checked_add(limit), ..Default::default() };
+
+    Self { token, start, limit }
+  }
+
+  /// Time since the budget was created
+  pub fn elapsed(&self) -> Duration {
+    self.start.elapsed()
+  }
+
+  /// Time left until the tasks are cancelled
+  pub fn remaining(&self) -> Duration {
+    self.limit.saturating_sub(self.elapsed())
+  }
+
+  /// Whether the time ran out or the budget was cancelled
+  pub fn is_exhausted(&self) -> bool {
+    self.token.is_cancelled()
+  }
+
+  /// Cancel the tasks of this budget right away
+  pub fn cancel(&self) {
+    self.token.cancel();
+  }
+
+  /// Token cancelled with this budget, for example to derive a [child](CancelToken::child)
+  pub fn cancel_token(&self) -> &CancelToken {
+    &self.token
+  }
+}
 
 impl<T> Task<T>
 where
   T: 'static + Send,
 {
   /// Starts a new task
+  #[track_caller]
   pub(crate) unsafe fn new(fun: impl FnOnce() -> T + 'static + Send) -> Self {
     ThreadManager::ensure_started();
 
@@ -48,76 +268,262 @@ where
 
     Self {
       rx,
-      cancel: Arc::new(AtomicBool::new(false)),
+      cancel: Default::default(),
       set_waker: tx_waker,
       recv_waker: Some(rx_waker),
       waker_set: false,
       task: Some((Box::new(fun), tx)),
       context: None,
+      camera_id: None,
+      delay: None,
       progress_handler: None,
+      cancel_tokens: Vec::new(),
+      #[cfg(feature = "tracing")]
+      location: Location::caller(),
+      #[cfg(feature = "tracing")]
+      camera: None,
     }
   }
 
-  pub(crate) fn context(mut self, context: BackgroundPtr<libgphoto2_sys::GPContext>) -> Self {
-    self.context = Some(context);
+  /// Run `fun` as a single task on the libgphoto2 thread
+  ///
+  /// Tasks waited for inside of `fun` run inline (see [reentrancy](Task#reentrancy)), so
+  /// helpers can combine several camera operations without tasks of other threads in between.
+  /// The nested tasks use the handlers of this task, their own progress handlers and
+  /// cancellation are ignored.
+  ///
+  /// ```no_run
+  /// use gphoto2::{task::Task, Context, Result};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let context = Context::new()?;
+  /// let camera = context.autodetect_camera().wait()?;
+  ///
+  /// let file = Task::run_nested(&context, move || {
+  ///   let path = camera.capture_image().wait()?;
+  ///   camera.fs().download(&path.folder(), &path.name()).wait()
+  /// })
+  /// .wait()?;
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn run_nested(context: &Context, fun: impl FnOnce() -> T + 'static + Send) -> Self {
+    unsafe { Self::new(fun) }.context(context)
+  }
+
+  /// Run `fun` on the libgphoto2 thread with the `GPContext` of `context`
+  ///
+  /// Raw pointers (like [`WidgetBase::as_raw`](crate::widget::WidgetBase::as_raw)) must only be
+  /// passed to libgphoto2 from such a task. Move the wrappers into `fun` and get their pointers
+  /// there, so they are alive until the call finished.
+  #[cfg(feature = "raw")]
+  pub fn raw(
+    context: &Context,
+    fun: impl FnOnce(*mut libgphoto2_sys::GPContext) -> T + 'static + Send,
+  ) -> Self {
+    let context_ptr = context.inner();
+
+    unsafe { Self::new(move || fun(*context_ptr)) }.context(context)
+  }
+
+  pub(crate) fn context(mut self, context: &Context) -> Self {
+    self.context = Some(context.clone());
+
+    self
+  }
+
+  /// Mark the task as an operation of the camera with this id, see [`Camera::lock`]
+  pub(crate) fn camera_id(mut self, camera_id: usize) -> Self {
+    self.camera_id = Some(camera_id);
 
     self
   }
 
   fn start_task(&mut self) {
+    // Waiting for a queued task would block the worker forever
+    if is_worker_thread() {
+      self.run_inline();
+    } else {
+      self.queue_task();
+    }
+  }
+
+  /// Run a task started on the worker thread right away, with the handlers of the running task
+  fn run_inline(&mut self) {
     if let Some((fun, tx)) = self.task.take() {
-      let mut opt_context_ptr = self.context.take();
+      if in_handler() {
+        log::error!(
+          "A task was started inside a context handler, libgphoto2 is not reentrant and may fail"
+        );
+      }
+
+      tx.send(fun()).ok();
+    }
+  }
+
+  fn queue_task(&mut self) {
+    if let Some((fun, tx)) = self.task.take() {
+      let context = self.context.take();
       let recv_waker = self.recv_waker.take();
       let progress_handler = self.progress_handler.take();
       let cancel = self.cancel.clone();
+      let cancel_tokens = std::mem::take(&mut self.cancel_tokens);
+      let camera_id = self.camera_id;
+      let delay = self.delay.take();
+      // Operations started by the holder of a lock run in its queue, also without the guard
+      let lock_queue = context
+        .as_ref()
+        .and_then(Context::lock_queue)
+        .or_else(|| camera_id.and_then(LockQueue::held));
+
+      // Created here, so the span of the caller is the parent
+      #[cfg(feature = "tracing")]
+      let span = tracing::info_span!(
+        "gphoto2_task",
+        location = %self.location,
+        camera = tracing::field::Empty,
+        duration_ms = tracing::field::Empty,
+        result = tracing::field::Empty,
+      );
+      #[cfg(feature = "tracing")]
+      let camera = self.camera;
 
       #[allow(unused_must_use)]
       let task: TaskFunc = Box::new(move || {
-        let mut context = None;
-
-        if let Some(context_ptr) = opt_context_ptr.as_mut() {
-          let mut task_context = Context::from_ptr(*context_ptr);
-
-          let cancel_handler = TaskCancelHandler(cancel);
-          task_context.set_cancel_handler(cancel_handler);
-
-          if let Some(progress_handler) = progress_handler {
-            task_context.set_progress_handlers(progress_handler)
+        #[cfg(feature = "tracing")]
+        let _span = {
+          if let Some(abilities) =
+            camera.and_then(|camera| unsafe { camera_abilities(*camera) }.ok())
+          {
+            span.record("camera", abilities.model().as_ref());
           }
 
-          context = Some(task_context);
+          span.entered()
+        };
+        #[cfg(feature = "tracing")]
+        let start = Instant::now();
+
+        if let Some(context) = &context {
+          let cancel_handler = TaskCancelHandler {
+            flag: cancel.clone(),
+            tokens: cancel_tokens,
+            deadline: context.operation_timeout().map(|timeout| Instant::now() + timeout),
+            context_cancel: context.default_cancel_fn(),
+          };
+          context.set_cancel_handler(cancel_handler);
+
+          if let Some(progress_handler) =
+            progress_handler.or_else(|| context.default_progress_handler())
+          {
+            context.set_progress_handlers(progress_handler)
+          }
         }
 
-        let result = fun();
+        // Caught so the handlers are unset and the waiter is woken, the worker reports the panic
+        let result = catch_unwind(AssertUnwindSafe(fun));
 
-        if let Some(context) = context.as_mut() {
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("duration_ms", start.elapsed().as_secs_f64() * 1000.0);
+
+        if let Some(context) = &context {
           context.unset_cancel_handlers();
           context.unset_progress_handlers();
+
+          if let Some(latency) = cancel.elapsed() {
+            context.record_cancellation_latency(latency);
+          }
         }
 
-        tx.send(result);
+        let panic = match result {
+          Ok(result) => {
+            tx.send(result);
+            None
+          }
+          Err(panic) => Some(panic),
+        };
+        // Disconnects the channel of a panicked task before the waiter polls it again
+        drop(tx);
+
         if let Some(waker) = recv_waker.and_then(|w| w.try_recv().ok()) {
           waker.wake();
         }
+
+        if let Some(panic) = panic {
+          resume_unwind(panic);
+        }
       });
 
+      let (task, delay) = match lock_queue {
+        Some(lock_queue) => match lock_queue.send((task, delay)) {
+          Ok(()) => return,
+          Err(task) => task,
+        },
+        None => (task, delay),
+      };
+
       if let Some(manager) = THREAD_MANAGER.read().unwrap().as_ref() {
-        manager.spawn_task(task);
+        manager.spawn(Job::Task { fun: task, camera: camera_id, delay });
       }
     }
   }
 
   /// Block until the response if available
+  ///
+  /// Panics if the task panicked.
   pub fn wait(self) -> T {
-    self.try_wait().unwrap()
+    self.try_wait().expect(TASK_PANICKED)
   }
 
   /// Try blocking until a result is available
+  ///
+  /// Fails if the task panicked.
   pub fn try_wait(mut self) -> Result<T, RecvError> {
     self.start_task();
     self.rx.recv()
   }
 
+  /// Whether the result is available, starts the task if needed
+  ///
+  /// Once the result was taken with [`Task::try_get`] or [`Task::wait_timeout`],
+  /// this returns `false` again.
+  pub fn is_finished(&mut self) -> bool {
+    self.start_task();
+    !self.rx.is_empty()
+  }
+
+  /// Take the result if it is available, without blocking
+  ///
+  /// Starts the task on the first call, so this can be polled from an event loop:
+  ///
+  /// ```no_run
+  /// use gphoto2::{Context, Result};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let camera = Context::new()?.autodetect_camera().wait()?;
+  /// let mut capture = camera.capture_image();
+  ///
+  /// let path = loop {
+  ///   if let Some(path) = capture.try_get() {
+  ///     break path?;
+  ///   }
+  ///   // Draw the next frame
+  /// };
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn try_get(&mut self) -> Option<T> {
+    self.start_task();
+    self.rx.try_recv().ok()
+  }
+
+  /// Block until the result is available or `timeout` elapsed
+  ///
+  /// Returns [`None`] on timeout, the task keeps running and can be waited for again.
+  pub fn wait_timeout(&mut self, timeout: Duration) -> Option<T> {
+    self.start_task();
+    self.rx.recv_timeout(timeout).ok()
+  }
+
   /// Set the progress handler for the task
   ///
   /// Must be called before the task is started
@@ -139,14 +545,152 @@ where
     self
   }
 
+  /// Set a closure receiving the progress of the task
+  ///
+  /// This is a simpler alternative to [`Task::with_progress_handler`],
+  /// the closure receives a [`ProgressEvent`] for every start, update and stop.
+  ///
+  /// Must be called before the task is started
+  ///
+  /// ```no_run
+  /// use gphoto2::{Context, Result};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let camera = Context::new()?.autodetect_camera().wait()?;
+  /// let file = camera.capture_image().wait()?;
+  ///
+  /// let data = camera
+  ///   .fs()
+  ///   .download(&file.folder(), &file.name())
+  ///   .on_progress(|event| println!("{:.0}%", event.fraction() * 100.0))
+  ///   .wait()?;
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn on_progress<F>(mut self, func: F) -> Self
+  where
+    F: FnMut(ProgressEvent) + 'static + Send,
+  {
+    self.progress_handler = Some(Box::new(ProgressFn::new(func)));
+    self
+  }
+
   /// Request the current task to be cancelled
+  ///
+  /// Cancellation is cooperative: drivers check for it while transferring data, and
+  /// [`Camera::wait_event`](crate::Camera::wait_event) checks it between short polling intervals.
+  /// The time it took for the task to finish after this call is reported by
+  /// [`Context::cancellation_latency`].
   pub fn cancel(&self) {
-    self.cancel.store(true, Ordering::Relaxed);
+    self.cancel.request();
+  }
+
+  /// Also cancel the task when `token` is cancelled
+  ///
+  /// A task can have several tokens, it is cancelled by the first one.
+  pub fn with_cancel_token(mut self, token: &CancelToken) -> Self {
+    self.cancel_tokens.push(token.clone());
+    self
+  }
+
+  /// Cancel the task when `budget` runs out, see [`OperationBudget`]
+  pub fn with_budget(self, budget: &OperationBudget) -> Self {
+    self.with_cancel_token(budget.cancel_token())
+  }
+
+  /// Start the task now and return a handle to its result
+  ///
+  /// Other tasks (like status queries on the same camera) can be started while this one is
+  /// running, they are run after it. Dropping the handle doesn't stop the task, its result
+  /// is discarded.
+  ///
+  /// ```no_run
+  /// use gphoto2::{Context, Result};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let camera = Context::new()?.autodetect_camera().wait()?;
+  ///
+  /// let download = camera.fs().download("/store_00010001/DCIM/100CANON", "IMG_0001.JPG").detach();
+  /// let storages = camera.storages().wait()?;
+  ///
+  /// let file = download.wait()?;
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn detach(mut self) -> JoinHandle<T> {
+    self.start_task();
+    JoinHandle { task: self }
   }
 
   /// Starts the task in background
   pub(crate) fn background(&mut self) {
-    self.start_task();
+    self.queue_task();
+  }
+}
+
+/// Handle to a running task, see [`Task::detach`]
+///
+/// Like a [`Task`], the handle can be awaited.
+pub struct JoinHandle<T> {
+  task: Task<T>,
+}
+
+impl<T> JoinHandle<T>
+where
+  T: 'static + Send,
+{
+  /// Block until the result is available
+  pub fn wait(self) -> T {
+    self.task.wait()
+  }
+
+  /// Whether the result is available, see [`Task::is_finished`]
+  pub fn is_finished(&mut self) -> bool {
+    self.task.is_finished()
+  }
+
+  /// Take the result if it is available, without blocking
+  pub fn try_get(&mut self) -> Option<T> {
+    self.task.try_get()
+  }
+
+  /// Block until the result is available or `timeout` elapsed
+  pub fn wait_timeout(&mut self, timeout: Duration) -> Option<T> {
+    self.task.wait_timeout(timeout)
+  }
+
+  /// Request the task to be cancelled, see [`Task::cancel`]
+  pub fn cancel(&self) {
+    self.task.cancel();
+  }
+}
+
+impl<T> Future for JoinHandle<T>
+where
+  T: 'static + Send,
+{
+  type Output = T;
+
+  fn poll(
+    mut self: std::pin::Pin<&mut Self>,
+    cx: &mut std::task::Context<'_>,
+  ) -> std::task::Poll<Self::Output> {
+    std::pin::Pin::new(&mut self.task).poll(cx)
+  }
+}
+
+/// Run `fun` on the libgphoto2 thread and wait for its result
+///
+/// Calls `fun` directly when already running on the libgphoto2 thread (from inside a task),
+/// so this can be used by functions called from both sides.
+pub(crate) fn run_on_worker<T>(fun: impl FnOnce() -> T + 'static + Send) -> T
+where
+  T: 'static + Send,
+{
+  if is_worker_thread() {
+    fun()
+  } else {
+    unsafe { Task::new(fun) }.wait()
   }
 }
 
@@ -168,17 +712,129 @@ where
 
     self.start_task();
 
-    if let Ok(value) = self.rx.try_recv() {
-      Poll::Ready(value)
-    } else {
-      Poll::Pending
+    match self.rx.try_recv() {
+      Ok(value) => Poll::Ready(value),
+      Err(TryRecvError::Empty) => Poll::Pending,
+      Err(TryRecvError::Disconnected) => panic!("{TASK_PANICKED}"),
     }
   }
 }
 
 impl CancelHandler for TaskCancelHandler {
   fn cancel(&mut self) -> bool {
-    self.0.load(Ordering::Relaxed)
+    if self.flag.is_cancelled() {
+      return true;
+    }
+
+    if let Some(requested_at) = self.tokens.iter().filter_map(CancelToken::requested_at).min() {
+      self.flag.request_at(requested_at);
+      return true;
+    }
+
+    let cancel = self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+      || self
+        .context_cancel
+        .as_ref()
+        .is_some_and(|cancel| cancel.lock().unwrap_or_else(PoisonError::into_inner)());
+
+    // Recorded like an explicit cancellation, so the latency is reported
+    if cancel {
+      self.flag.request();
+    }
+
+    cancel
+  }
+}
+
+impl<T> Task<crate::Result<T>>
+where
+  T: 'static + Send,
+{
+  /// Run the task with the context of `camera`
+  ///
+  /// With the `tracing` feature, the model of the camera and the result are added to the span.
+  pub(crate) fn camera(self, camera: &Camera) -> Self {
+    let mut task = self.context(&camera.context).camera_id(camera.state.id);
+
+    if let Some(governor) = camera.state.governor() {
+      if let Some((fun, tx)) = task.task.take() {
+        let governor = governor.clone();
+        let fun = move || governor.run(fun);
+
+        task.task = Some((Box::new(fun), tx));
+      }
+
+      // Tasks started inline on the worker can't wait, they run right away
+      task.delay = Some(Arc::new(move || governor.backoff_until()));
+    }
+
+    #[cfg(feature = "tracing")]
+    if let Some((fun, tx)) = task.task.take() {
+      let fun = move || {
+        let result = fun();
+
+        let kind = match &result {
+          Ok(_) => "ok".to_owned(),
+          Err(error) => format!("{:?}", error.kind()),
+        };
+        tracing::Span::current().record("result", kind);
+
+        result
+      };
+
+      task.task = Some((Box::new(fun), tx));
+      task.camera = Some(camera.camera);
+    }
+
+    task
+  }
+
+  /// Record the task in the metrics of its context, must be called after [`Task::context`]
+  #[cfg_attr(not(feature = "metrics"), allow(unused_mut, unused_variables))]
+  pub(crate) fn metric(mut self, operation: Operation) -> Self {
+    #[cfg(feature = "metrics")]
+    if let (Some(context), Some((fun, tx))) = (&self.context, self.task.take()) {
+      let metrics = context.metrics_recorder();
+      let fun = move || {
+        let start = Instant::now();
+        let result = fun();
+        metrics.record(operation, start.elapsed(), result.is_ok());
+
+        result
+      };
+
+      self.task = Some((Box::new(fun), tx));
+    }
+
+    self
+  }
+}
+
+impl<T: 'static> SharedRef<T> {
+  /// Take over a reference of `ptr`, which is released with `unref`
+  pub(crate) fn new(
+    ptr: BackgroundPtr<T>,
+    unref: unsafe extern "C" fn(*mut T) -> c_int,
+  ) -> Arc<Self> {
+    Arc::new(Self { ptr, unref })
+  }
+}
+
+impl<T: 'static> Drop for SharedRef<T> {
+  fn drop(&mut self) {
+    let (ptr, unref) = (BackgroundPtr(self.ptr.0), self.unref);
+
+    unsafe { Task::new(move || unref(*ptr)) }.background();
+  }
+}
+
+impl<T> BackgroundPtr<T> {
+  /// Get the pointer on any thread
+  ///
+  /// Only for returning the pointer and for libgphoto2 calls which just access memory owned
+  /// by the wrapper, like reading the entries of a list which no other thread can see.
+  pub(crate) fn any_thread(&self) -> *mut T {
+    self.0
   }
 }
 
@@ -186,6 +842,11 @@ impl<T> Deref for BackgroundPtr<T> {
   type Target = *mut T;
 
   fn deref(&self) -> &Self::Target {
+    debug_assert!(
+      is_worker_thread(),
+      "libgphoto2 pointer used outside of the libgphoto2 thread, run the call in a task"
+    );
+
     &self.0
   }
 }
@@ -1,45 +1,313 @@
 use std::{
-  sync::{Once, RwLock},
+  cell::Cell,
+  panic::{catch_unwind, AssertUnwindSafe},
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex, Once, PoisonError, RwLock,
+  },
   thread,
-  thread::JoinHandle,
+  thread::{JoinHandle, ThreadId},
+  time::Instant,
 };
 
-use crossbeam_channel::{unbounded, Receiver, Sender};
+use crossbeam_channel::{unbounded, Receiver, Select, Sender};
 
 pub static THREAD_MANAGER: RwLock<Option<ThreadManager>> = RwLock::new(None);
+static START: Once = Once::new();
+/// Locks which didn't end yet, with the camera and the thread which holds them
+static LOCKS: Mutex<Vec<(usize, ThreadId, Arc<LockQueue>)>> = Mutex::new(Vec::new());
+
+thread_local! {
+  static IS_WORKER: Cell<bool> = const { Cell::new(false) };
+  /// Number of context handlers currently running on this thread
+  static HANDLER_DEPTH: Cell<u32> = const { Cell::new(0) };
+}
 
 pub type TaskFunc = Box<dyn FnOnce() + Send>;
+/// Time until which a task has to wait (like the backoff of a busy camera), checked right
+/// before it runs
+pub type DelayFunc = Arc<dyn Fn() -> Option<Instant> + Send + Sync>;
+/// Task sent to a [`LockQueue`]
+pub type LockTask = (TaskFunc, Option<DelayFunc>);
+
+/// Work queued on the worker thread
+pub enum Job {
+  /// Run a task, operations of a camera are deferred while the camera is locked
+  Task { fun: TaskFunc, camera: Option<usize>, delay: Option<DelayFunc> },
+  /// Only run the operations of the camera from a [`LockQueue`] until it ends
+  Lock { camera: usize, tasks: Receiver<Option<LockTask>> },
+}
+
+/// Tasks of a camera lock, which run without other operations of the camera in between
+pub struct LockQueue {
+  /// Number of guards sharing the lock
+  guards: AtomicUsize,
+  sender: Mutex<Option<Sender<Option<LockTask>>>>,
+}
+
+impl LockQueue {
+  /// Create a queue for the current thread and put it in line on the worker
+  pub fn start(camera: usize) -> Arc<Self> {
+    ThreadManager::ensure_started();
+
+    let (sender, tasks) = unbounded();
+    let queue = Arc::new(Self { guards: AtomicUsize::new(1), sender: Mutex::new(Some(sender)) });
+
+    LOCKS.lock().unwrap_or_else(PoisonError::into_inner).push((
+      camera,
+      thread::current().id(),
+      queue.clone(),
+    ));
+    if let Some(manager) = THREAD_MANAGER.read().unwrap().as_ref() {
+      manager.spawn(Job::Lock { camera, tasks });
+    }
+
+    queue
+  }
+
+  /// Lock of `camera` held by the current thread
+  pub fn held(camera: usize) -> Option<Arc<Self>> {
+    let thread = thread::current().id();
+
+    LOCKS
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .iter()
+      .find(|(locked, holder, _)| *locked == camera && *holder == thread)
+      .map(|(_, _, queue)| queue.clone())
+  }
+
+  /// Queue a task, returns it back if the lock already ended
+  pub fn send(&self, task: LockTask) -> Result<(), LockTask> {
+    match &*self.sender.lock().unwrap_or_else(PoisonError::into_inner) {
+      Some(sender) => sender.send(Some(task)).map_err(|error| error.0.unwrap()),
+      None => Err(task),
+    }
+  }
+
+  /// Whether the lock didn't end yet
+  pub fn is_active(&self) -> bool {
+    self.sender.lock().unwrap_or_else(PoisonError::into_inner).is_some()
+  }
+
+  /// Add a guard sharing the lock
+  pub fn enter(&self) {
+    self.guards.fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// Drop a guard, the lock ends with the last one
+  pub fn leave(&self) {
+    if self.guards.fetch_sub(1, Ordering::AcqRel) == 1 {
+      self.end();
+    }
+  }
+
+  /// Let the worker continue with other tasks once the queued ones finished
+  fn end(&self) {
+    LOCKS
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .retain(|(_, _, queue)| !std::ptr::eq(&**queue, self));
+
+    if let Some(sender) = self.sender.lock().unwrap_or_else(PoisonError::into_inner).take() {
+      sender.send(None).ok();
+    }
+  }
+}
 
 pub struct ThreadManager {
   _handle: JoinHandle<()>,
-  send_task: Sender<TaskFunc>,
+  send_job: Sender<Job>,
 }
 
 impl ThreadManager {
   pub fn ensure_started() {
-    static START: Once = Once::new();
+    Self::start(None);
+  }
+
+  /// Start the worker thread, returns `false` if it was already running
+  pub fn start(stack_size: Option<usize>) -> bool {
+    let mut started = false;
 
-    START.call_once(|| *THREAD_MANAGER.write().unwrap() = Some(ThreadManager::new().unwrap()));
+    START.call_once(|| {
+      *THREAD_MANAGER.write().unwrap() = Some(ThreadManager::new(stack_size).unwrap());
+      started = true;
+    });
+
+    started
   }
 
-  fn new() -> Result<Self, std::io::Error> {
-    let (send_task, receive_task) = unbounded();
+  fn new(stack_size: Option<usize>) -> Result<Self, std::io::Error> {
+    let (send_job, receive_job) = unbounded();
+
+    let mut builder = thread::Builder::new().name("gphoto2".to_string()); // Give the thread a name for debugging
+    if let Some(stack_size) = stack_size {
+      builder = builder.stack_size(stack_size);
+    }
 
-    let thread_handle = thread::Builder::new()
-      .name("gphoto2".to_string()) // Give the thread a name for debugging
-      .spawn(move || start_thread(receive_task))?;
+    let thread_handle = builder.spawn(move || start_thread(receive_job))?;
 
-    Ok(Self { _handle: thread_handle, send_task })
+    Ok(Self { _handle: thread_handle, send_job })
   }
 
   #[allow(unused_must_use)]
-  pub fn spawn_task(&self, task: TaskFunc) {
-    self.send_task.send(task);
+  pub fn spawn(&self, job: Job) {
+    self.send_job.send(job);
+  }
+}
+
+/// Whether the current thread is the one running all libgphoto2 calls
+pub fn is_worker_thread() -> bool {
+  IS_WORKER.with(Cell::get)
+}
+
+/// Whether a context handler called by libgphoto2 is running on the current thread
+pub fn in_handler() -> bool {
+  HANDLER_DEPTH.with(Cell::get) > 0
+}
+
+/// Marks the current thread as running a context handler until dropped
+pub struct HandlerScope(());
+
+impl HandlerScope {
+  pub fn enter() -> Self {
+    HANDLER_DEPTH.with(|depth| depth.set(depth.get() + 1));
+    Self(())
+  }
+}
+
+impl Drop for HandlerScope {
+  fn drop(&mut self) {
+    HANDLER_DEPTH.with(|depth| depth.set(depth.get().saturating_sub(1)));
+  }
+}
+
+fn start_thread(recv_job: Receiver<Job>) {
+  IS_WORKER.with(|is_worker| is_worker.set(true));
+
+  let mut worker = Worker::default();
+
+  loop {
+    // Checked on every iteration, so a steady stream of jobs doesn't hold back delayed ones
+    worker.dispatch_ready();
+
+    let event = {
+      let mut select = Select::new();
+      select.recv(&recv_job);
+      for lock in &worker.locks {
+        select.recv(&lock.tasks);
+      }
+
+      let operation = match worker.next_ready() {
+        Some(deadline) => match select.select_deadline(deadline) {
+          Ok(operation) => operation,
+          Err(_) => continue,
+        },
+        None => select.select(),
+      };
+      match operation.index() {
+        0 => match operation.recv(&recv_job) {
+          Ok(job) => Event::Job(job),
+          Err(_) => break,
+        },
+        index => match operation.recv(&worker.locks[index - 1].tasks) {
+          Ok(Some((fun, delay))) => Event::Job(Job::Task { fun, camera: None, delay }),
+          // The lock ended (or was dropped without ending, which can't happen in practice)
+          _ => Event::LockEnded(index - 1),
+        },
+      }
+    };
+
+    match event {
+      Event::Job(job) => worker.dispatch(job),
+      Event::LockEnded(index) => worker.end_lock(index),
+    }
+  }
+}
+
+/// What the worker received
+enum Event {
+  Job(Job),
+  /// The lock at this index of [`Worker::locks`] ended
+  LockEnded(usize),
+}
+
+/// A camera lock running on the worker
+struct ActiveLock {
+  camera: usize,
+  tasks: Receiver<Option<LockTask>>,
+}
+
+/// Locks running on the worker and the jobs waiting for them
+#[derive(Default)]
+struct Worker {
+  locks: Vec<ActiveLock>,
+  /// Jobs of locked cameras, in the order they were queued
+  deferred: Vec<Job>,
+  /// Jobs which have to wait until the time returned by their [`DelayFunc`]
+  delayed: Vec<(Instant, Job)>,
+}
+
+impl Worker {
+  fn is_locked(&self, camera: Option<usize>) -> bool {
+    camera.is_some_and(|camera| self.locks.iter().any(|lock| lock.camera == camera))
+  }
+
+  /// Run a job, or defer it until the lock of its camera ended or its delay passed
+  fn dispatch(&mut self, job: Job) {
+    match job {
+      Job::Task { fun, camera, delay } if !self.is_locked(camera) => {
+        match delay.as_ref().and_then(|delay| delay()) {
+          Some(until) => self.delayed.push((until, Job::Task { fun, camera, delay })),
+          None => run_task(fun),
+        }
+      }
+      Job::Lock { camera, tasks } if !self.is_locked(Some(camera)) => {
+        self.locks.push(ActiveLock { camera, tasks })
+      }
+      job => self.deferred.push(job),
+    }
+  }
+
+  /// Dispatch the delayed jobs whose time came, keeping their order
+  ///
+  /// Their delay is checked again, so they wait longer if the camera was busy in the meantime.
+  fn dispatch_ready(&mut self) {
+    let now = Instant::now();
+    if self.delayed.iter().all(|(until, _)| *until > now) {
+      return;
+    }
+
+    for (until, job) in std::mem::take(&mut self.delayed) {
+      if until > now {
+        self.delayed.push((until, job));
+      } else {
+        self.dispatch(job);
+      }
+    }
+  }
+
+  /// Time when the next delayed job can run
+  fn next_ready(&self) -> Option<Instant> {
+    self.delayed.iter().map(|(until, _)| *until).min()
+  }
+
+  fn end_lock(&mut self, index: usize) {
+    self.locks.remove(index);
+
+    // Jobs of other locked cameras are deferred again, keeping their order
+    for job in std::mem::take(&mut self.deferred) {
+      self.dispatch(job);
+    }
   }
 }
 
-fn start_thread(recv_task: Receiver<TaskFunc>) {
-  while let Ok(fun) = recv_task.recv() {
-    fun()
+/// Run a task, a panic only fails this task instead of stopping the worker
+///
+/// The sender of the result is dropped while unwinding, so waiting for the task panics on the
+/// thread which started it.
+fn run_task(fun: TaskFunc) {
+  if catch_unwind(AssertUnwindSafe(fun)).is_err() {
+    log::error!("A libgphoto2 task panicked");
   }
 }
@@ -0,0 +1,70 @@
+//! Previews of files on the camera
+//!
+//! Cameras store small previews of their files, usually JPEG but some older models use
+//! other formats (like PPM). With the `image` feature, `Thumbnail::decode` picks the right
+//! decoder, so galleries don't have to handle the camera specific formats.
+//!
+//! ```no_run
+//! use gphoto2::{Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//!
+//! for file in camera.fs().list_files("/store_00010001/DCIM/100CANON").wait()? {
+//!   let thumbnail = camera.fs().thumbnail("/store_00010001/DCIM/100CANON", &file).wait()?;
+//!   println!("{file}: {} bytes of {}", thumbnail.data().len(), thumbnail.mime_type());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(feature = "image")]
+use crate::{Error, Result};
+
+/// Preview of a file, see [`CameraFS::thumbnail`](crate::filesys::CameraFS::thumbnail)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+  mime_type: String,
+  data: Box<[u8]>,
+}
+
+impl Thumbnail {
+  pub(crate) fn new(mime_type: String, data: Box<[u8]>) -> Self {
+    Self { mime_type, data }
+  }
+
+  /// Mime type reported by the camera, like `image/jpeg`
+  pub fn mime_type(&self) -> &str {
+    &self.mime_type
+  }
+
+  /// Encoded data of the preview
+  pub fn data(&self) -> &[u8] {
+    &self.data
+  }
+
+  /// Take the encoded data of the preview
+  pub fn into_data(self) -> Box<[u8]> {
+    self.data
+  }
+
+  /// Decode the preview
+  #[cfg(feature = "image")]
+  pub fn decode(&self) -> Result<image::DynamicImage> {
+    // Cameras don't always report the correct type, so fall back to the file contents
+    let format = image::ImageFormat::from_mime_type(&self.mime_type)
+      .map_or_else(|| image::guess_format(&self.data), Ok)
+      .map_err(|e| Error::from(format!("Unknown preview format {}: {e}", self.mime_type)))?;
+
+    image::load_from_memory_with_format(&self.data, format)
+      .map_err(|e| Error::from(format!("Could not decode preview: {e}")))
+  }
+
+  /// Decode the preview to RGB with 8 bits per channel, returns `(width, height, pixels)`
+  #[cfg(feature = "image")]
+  pub fn decode_rgb8(&self) -> Result<(u32, u32, Vec<u8>)> {
+    let rgb = self.decode()?.into_rgb8();
+
+    Ok((rgb.width(), rgb.height(), rgb.into_raw()))
+  }
+}
@@ -0,0 +1,123 @@
+//! Runtime information about the loaded libgphoto2
+//!
+//! ```no_run
+//! use gphoto2::version::LibraryVersion;
+//!
+//! let features = gphoto2::library_features();
+//!
+//! println!("Using libgphoto2 {}", features.version);
+//! if features.version < LibraryVersion::new(2, 5, 10) {
+//!   println!("Single configs are slow with this version");
+//! }
+//! ```
+
+use crate::helper::chars_to_string;
+use std::{fmt, os::raw::c_char};
+
+/// Version of libgphoto2 which introduced `gp_camera_get_single_config`
+const SINGLE_CONFIG_VERSION: LibraryVersion = LibraryVersion::new(2, 5, 10);
+
+/// Numeric version of a library
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LibraryVersion {
+  /// Major version
+  pub major: u32,
+  /// Minor version
+  pub minor: u32,
+  /// Patch version
+  pub patch: u32,
+}
+
+impl LibraryVersion {
+  /// Create a version from its parts
+  pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+    Self { major, minor, patch }
+  }
+
+  /// Parse a version like `2.5.31` (suffixes like `-dev` or a fourth part are ignored)
+  pub fn parse(version: &str) -> Option<Self> {
+    let mut parts = version.split('.').map(|part| {
+      let digits = part.find(|c: char| !c.is_ascii_digit()).unwrap_or(part.len());
+      part[..digits].parse::<u32>().ok()
+    });
+
+    let major = parts.next()??;
+    let minor = parts.next().flatten().unwrap_or(0);
+    let patch = parts.next().flatten().unwrap_or(0);
+
+    Some(Self { major, minor, patch })
+  }
+}
+
+impl fmt::Display for LibraryVersion {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+  }
+}
+
+/// Version and optional capabilities of the loaded libgphoto2
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LibraryFeatures {
+  /// Version of libgphoto2
+  pub version: LibraryVersion,
+  /// Version of libgphoto2_port
+  pub port_version: Option<LibraryVersion>,
+  /// Whether single configs can be get and set without fetching the full configuration
+  pub single_config: bool,
+  /// Whether libgphoto2 was built with libexif
+  pub exif: bool,
+  /// Whether serial ports are locked with lockdev
+  pub port_locking: bool,
+  /// Build flags reported by libgphoto2 (like `EXIF` or `no ltdl`)
+  pub flags: Vec<String>,
+  /// Build flags reported by libgphoto2_port
+  pub port_flags: Vec<String>,
+}
+
+/// Read a `NULL` terminated version list, the first entry is the version itself
+unsafe fn version_list(list: *mut *const c_char) -> Vec<String> {
+  let mut entries = Vec::new();
+
+  if list.is_null() {
+    return entries;
+  }
+
+  let mut entry = list;
+  while !(*entry).is_null() {
+    entries.push(chars_to_string(*entry));
+    entry = entry.add(1);
+  }
+
+  entries
+}
+
+pub(crate) fn library_features() -> LibraryFeatures {
+  use libgphoto2_sys::GPVersionVerbosity;
+
+  let mut flags = unsafe {
+    version_list(libgphoto2_sys::gp_library_version(GPVersionVerbosity::GP_VERSION_SHORT))
+  };
+  let mut port_flags = unsafe {
+    version_list(libgphoto2_sys::gp_port_library_version(GPVersionVerbosity::GP_VERSION_SHORT))
+  };
+
+  let version = (!flags.is_empty())
+    .then(|| flags.remove(0))
+    .and_then(|version| LibraryVersion::parse(&version))
+    .unwrap_or(LibraryVersion::new(0, 0, 0));
+  let port_version = (!port_flags.is_empty())
+    .then(|| port_flags.remove(0))
+    .and_then(|version| LibraryVersion::parse(&version));
+
+  LibraryFeatures {
+    version,
+    port_version,
+    single_config: version >= SINGLE_CONFIG_VERSION,
+    exif: flags.iter().any(|flag| flag == "EXIF"),
+    port_locking: port_flags.iter().any(|flag| flag == "LOCKDEV"),
+    flags,
+    port_flags,
+  }
+}
@@ -0,0 +1,294 @@
+//! Use a camera as a webcam
+//!
+//! Requires the `webcam` feature.
+//!
+//! [`Camera::webcam`] starts a [`LiveView`], picks the closest preview size the camera
+//! offers and decodes every preview frame to RGB scaled (and letterboxed) to the requested size.
+//! Frames are rotated upright according to their EXIF orientation, unless disabled with
+//! [`Webcam::set_auto_rotate`].
+//! The frames can either be consumed with [`Webcam::run`] or, on Linux, written to a
+//! [v4l2loopback](https://github.com/umlaeute/v4l2loopback) device:
+//!
+//! ```no_run
+//! use gphoto2::{Context, Result};
+//!
+//! # fn main() -> Result<()> {
+//! let camera = Context::new()?.autodetect_camera().wait()?;
+//!
+//! # #[cfg(target_os = "linux")]
+//! camera.webcam(1280, 720)?.run_v4l2loopback("/dev/video2")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+  liveview::LiveView, orientation::Orientation, widget::RadioWidget, Camera, Error, Result,
+};
+use std::ops::ControlFlow;
+
+/// Name of the config selecting the live view size (if the camera supports it)
+const PREVIEW_SIZE_KEY: &str = "liveviewsize";
+
+/// Decoded RGB24 frame
+#[derive(Debug, Clone)]
+pub struct RgbFrame {
+  width: u32,
+  height: u32,
+  data: Vec<u8>,
+  orientation: Option<Orientation>,
+}
+
+impl RgbFrame {
+  /// Frame width in pixels
+  pub fn width(&self) -> u32 {
+    self.width
+  }
+
+  /// Frame height in pixels
+  pub fn height(&self) -> u32 {
+    self.height
+  }
+
+  /// Pixel data, 3 bytes per pixel without any padding
+  pub fn data(&self) -> &[u8] {
+    &self.data
+  }
+
+  /// Orientation the camera reported for the preview
+  ///
+  /// The pixels are already rotated accordingly if auto rotation is enabled.
+  pub fn orientation(&self) -> Option<Orientation> {
+    self.orientation
+  }
+}
+
+/// Camera streaming decoded frames of a fixed size
+pub struct Webcam {
+  live_view: LiveView,
+  width: u32,
+  height: u32,
+  auto_rotate: bool,
+}
+
+impl Webcam {
+  pub(crate) fn new(camera: &Camera, width: u32, height: u32) -> Result<Self> {
+    if width == 0 || height == 0 {
+      return Err(Error::from("Webcam frames can not be empty"));
+    }
+
+    negotiate_preview_size(camera, width, height)?;
+
+    Ok(Self { live_view: camera.live_view().wait()?, width, height, auto_rotate: true })
+  }
+
+  /// Enable or disable rotating frames according to their orientation, enabled by default
+  pub fn set_auto_rotate(&mut self, auto_rotate: bool) {
+    self.auto_rotate = auto_rotate;
+  }
+
+  /// Underlying live view
+  pub fn live_view(&self) -> &LiveView {
+    &self.live_view
+  }
+
+  /// Capture and decode the next frame
+  pub fn next_frame(&self) -> Result<RgbFrame> {
+    let jpeg = self.live_view.capture_frame().wait()?;
+
+    let mut decoder = jpeg_decoder::Decoder::new(&*jpeg);
+    let pixels = decoder.decode().map_err(|e| format!("Could not decode preview: {e}"))?;
+    let info = decoder.info().ok_or("Preview has no image info")?;
+
+    let rgb = match info.pixel_format {
+      jpeg_decoder::PixelFormat::RGB24 => pixels,
+      jpeg_decoder::PixelFormat::L8 => pixels.iter().flat_map(|&l| [l, l, l]).collect(),
+      format => return Err(Error::from(format!("Unsupported preview pixel format {format:?}"))),
+    };
+
+    let orientation = Orientation::detect(&jpeg);
+    let (rgb, width, height) = match orientation {
+      Some(orientation) if self.auto_rotate => {
+        orientation.transform_rgb24(&rgb, usize::from(info.width), usize::from(info.height))
+      }
+      _ => (rgb, usize::from(info.width), usize::from(info.height)),
+    };
+
+    let data = letterbox(&rgb, (width, height), (self.width.try_into()?, self.height.try_into()?));
+
+    Ok(RgbFrame { width: self.width, height: self.height, data, orientation })
+  }
+
+  /// Pass frames to `on_frame` until it returns [`ControlFlow::Break`] or an error occurs
+  pub fn run<F>(&self, mut on_frame: F) -> Result<()>
+  where
+    F: FnMut(&RgbFrame) -> ControlFlow<()>,
+  {
+    loop {
+      if on_frame(&self.next_frame()?).is_break() {
+        return Ok(());
+      }
+    }
+  }
+
+  /// Write frames to a v4l2loopback device until an error occurs
+  ///
+  /// The output format of the device is set to RGB24 with the size of this webcam.
+  #[cfg(target_os = "linux")]
+  pub fn run_v4l2loopback(&self, device: impl AsRef<std::path::Path>) -> Result<()> {
+    use std::io::Write;
+
+    let mut device = std::fs::OpenOptions::new().write(true).open(device)?;
+    v4l2::set_output_format(&device, self.width, self.height)?;
+
+    loop {
+      device.write_all(self.next_frame()?.data())?;
+    }
+  }
+}
+
+impl Camera {
+  /// Use the camera as a webcam producing `width` x `height` frames
+  ///
+  /// See the [`webcam`](crate::webcam) module for an example.
+  pub fn webcam(&self, width: u32, height: u32) -> Result<Webcam> {
+    Webcam::new(self, width, height)
+  }
+}
+
+/// Select the smallest preview size covering the requested size
+///
+/// Only choices formatted like `640x480` are considered, other cameras are left unchanged.
+fn negotiate_preview_size(camera: &Camera, width: u32, height: u32) -> Result<()> {
+  let Ok(preview_size) = camera.config_key::<RadioWidget>(PREVIEW_SIZE_KEY).wait() else {
+    return Ok(());
+  };
+
+  let sizes: Vec<_> = preview_size
+    .choices_iter()
+    .filter_map(|choice| {
+      let (w, h) = choice.split_once('x')?;
+      Some((w.trim().parse::<u32>().ok()?, h.trim().parse::<u32>().ok()?, choice))
+    })
+    .collect();
+
+  let area = |&&(w, h, _): &&(u32, u32, String)| u64::from(w) * u64::from(h);
+  let best = sizes
+    .iter()
+    .filter(|(w, h, _)| *w >= width && *h >= height)
+    .min_by_key(area)
+    .or_else(|| sizes.iter().max_by_key(area));
+
+  if let Some((_, _, choice)) = best {
+    if *choice != preview_size.choice() {
+      preview_size.set_choice(choice)?;
+      camera.set_config(&preview_size).wait()?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Scale an RGB24 image to fit into `dst` keeping its aspect ratio, filling the borders with black
+fn letterbox(
+  src: &[u8],
+  (src_w, src_h): (usize, usize),
+  (dst_w, dst_h): (usize, usize),
+) -> Vec<u8> {
+  let mut out = vec![0; dst_w * dst_h * 3];
+
+  if src_w == 0 || src_h == 0 {
+    return out;
+  }
+
+  let (fit_w, fit_h) = if src_w * dst_h <= dst_w * src_h {
+    ((src_w * dst_h / src_h).max(1), dst_h)
+  } else {
+    (dst_w, (src_h * dst_w / src_w).max(1))
+  };
+  let (offset_x, offset_y) = ((dst_w - fit_w) / 2, (dst_h - fit_h) / 2);
+
+  for y in 0..fit_h {
+    let src_row = y * src_h / fit_h * src_w;
+    let dst_row = (y + offset_y) * dst_w + offset_x;
+
+    for x in 0..fit_w {
+      let src_px = (src_row + x * src_w / fit_w) * 3;
+      let dst_px = (dst_row + x) * 3;
+      out[dst_px..dst_px + 3].copy_from_slice(&src[src_px..src_px + 3]);
+    }
+  }
+
+  out
+}
+
+#[cfg(target_os = "linux")]
+mod v4l2 {
+  //! Minimal bindings to set the output format of a video device
+
+  use crate::Result;
+  use std::{fs::File, os::unix::io::AsRawFd};
+
+  const VIDIOC_S_FMT: libc::Ioctl = 0xc0d0_5605;
+  const V4L2_BUF_TYPE_VIDEO_OUTPUT: u32 = 2;
+  const V4L2_FIELD_NONE: u32 = 1;
+  const V4L2_COLORSPACE_SRGB: u32 = 8;
+  const V4L2_PIX_FMT_RGB24: u32 = u32::from_le_bytes(*b"RGB3");
+
+  #[repr(C)]
+  #[derive(Clone, Copy)]
+  struct PixFormat {
+    width: u32,
+    height: u32,
+    pixelformat: u32,
+    field: u32,
+    bytesperline: u32,
+    sizeimage: u32,
+    colorspace: u32,
+    priv_: u32,
+    flags: u32,
+    ycbcr_enc: u32,
+    quantization: u32,
+    xfer_func: u32,
+  }
+
+  #[repr(C)]
+  union FormatUnion {
+    pix: PixFormat,
+    // The kernel union contains pointers, which makes it 8 byte aligned
+    raw_data: [u64; 25],
+  }
+
+  #[repr(C)]
+  struct Format {
+    type_: u32,
+    fmt: FormatUnion,
+  }
+
+  pub(super) fn set_output_format(device: &File, width: u32, height: u32) -> Result<()> {
+    let bytesperline = width.checked_mul(3).ok_or("Frame is too large")?;
+    let sizeimage = bytesperline.checked_mul(height).ok_or("Frame is too large")?;
+
+    let mut format =
+      Format { type_: V4L2_BUF_TYPE_VIDEO_OUTPUT, fmt: FormatUnion { raw_data: [0; 25] } };
+    format.fmt.pix = PixFormat {
+      width,
+      height,
+      pixelformat: V4L2_PIX_FMT_RGB24,
+      field: V4L2_FIELD_NONE,
+      bytesperline,
+      sizeimage,
+      colorspace: V4L2_COLORSPACE_SRGB,
+      priv_: 0,
+      flags: 0,
+      ycbcr_enc: 0,
+      quantization: 0,
+      xfer_func: 0,
+    };
+
+    if unsafe { libc::ioctl(device.as_raw_fd(), VIDIOC_S_FMT, &mut format) } < 0 {
+      return Err(std::io::Error::last_os_error().into());
+    }
+
+    Ok(())
+  }
+}
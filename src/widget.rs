@@ -16,14 +16,20 @@
 //! ```
 
 use crate::{
+  clock,
   helper::{as_ref, chars_to_string, to_c_string},
-  task::{BackgroundPtr, Task},
+  task::{run_on_worker, BackgroundPtr, SharedRef, Task},
   try_gp_internal, Camera, Error, Result,
 };
+use libgphoto2_sys::CameraWidgetType;
 use std::{
   ffi, fmt,
+  marker::PhantomData,
   ops::{Range, RangeInclusive},
   os::raw::{c_char, c_int, c_void},
+  str::FromStr,
+  sync::Arc,
+  time::SystemTime,
 };
 
 /// Iterator over the children of a widget
@@ -54,98 +60,237 @@ impl ExactSizeIterator for WidgetIterator<'_> {
 ///
 /// Normally you shouldn't use this type directly but should access its
 /// properties via [`Widget`] or specific typed widgets instead.
+///
+/// Like all other libgphoto2 calls, reading and changing widgets runs on the libgphoto2 thread,
+/// so widgets can be shared between threads.
+#[derive(Clone)]
 pub struct WidgetBase {
   pub(crate) inner: BackgroundPtr<libgphoto2_sys::CameraWidget>,
+  _reference: Arc<SharedRef<libgphoto2_sys::CameraWidget>>,
 }
 
-impl Clone for WidgetBase {
-  fn clone(&self) -> Self {
-    try_gp_internal!(gp_widget_ref(*self.inner).unwrap());
-    Self { inner: self.inner }
+impl WidgetBase {
+  /// Take over a reference of `widget`
+  pub(crate) fn new(widget: BackgroundPtr<libgphoto2_sys::CameraWidget>) -> Self {
+    Self { inner: widget, _reference: SharedRef::new(widget, libgphoto2_sys::gp_widget_unref) }
   }
-}
 
-impl Drop for WidgetBase {
-  fn drop(&mut self) {
-    let widget_ptr = self.inner;
-    unsafe {
-      Task::new(move || {
-        try_gp_internal!(gp_widget_unref(*widget_ptr).unwrap());
-      })
-    }
-    .background();
+  /// Run `fun` with the widget on the libgphoto2 thread
+  fn with_widget<T>(
+    &self,
+    fun: impl FnOnce(*mut libgphoto2_sys::CameraWidget) -> T + 'static + Send,
+  ) -> T
+  where
+    T: 'static + Send,
+  {
+    let widget = self.inner;
+    run_on_worker(move || fun(*widget))
   }
-}
 
-impl WidgetBase {
-  fn as_ptr(&self) -> *mut libgphoto2_sys::CameraWidget {
-    *self.inner
+  /// Run `fun` with the widget on the libgphoto2 thread, for calls which only fail for invalid
+  /// widgets
+  ///
+  /// The error is turned into a panic after the task finished, so it doesn't unwind on the
+  /// libgphoto2 thread.
+  fn with_widget_unwrap<T>(
+    &self,
+    fun: impl FnOnce(*mut libgphoto2_sys::CameraWidget) -> Result<T> + 'static + Send,
+  ) -> T
+  where
+    T: 'static + Send,
+  {
+    self.with_widget(fun).unwrap()
+  }
+
+  /// Properties and value of the widget, with the whole tree below it if `recursive`
+  fn data(&self, recursive: bool) -> WidgetData {
+    self.with_widget_unwrap(move |widget| unsafe { WidgetData::read(widget, None, recursive) })
+  }
+
+  /// Wrap a `CameraWidget` created outside of this crate
+  ///
+  /// The returned widget takes over one reference of `widget`, call `gp_widget_ref` before if
+  /// the caller keeps using the pointer.
+  ///
+  /// # Safety
+  ///
+  /// `widget` must be a valid `CameraWidget` pointer and the caller must own the reference
+  /// passed to this function.
+  #[cfg(feature = "raw")]
+  pub unsafe fn from_raw(widget: *mut libgphoto2_sys::CameraWidget) -> Self {
+    Self::new(BackgroundPtr(widget))
+  }
+
+  /// Get the `CameraWidget` pointer, which stays valid as long as this widget is alive
+  ///
+  /// libgphoto2 functions must only be called with the pointer inside of [`Task::raw`].
+  #[cfg(feature = "raw")]
+  pub fn as_raw(&self) -> *mut libgphoto2_sys::CameraWidget {
+    self.inner.any_thread()
   }
 
   /// Get exact widget type.
   fn ty(&self) -> libgphoto2_sys::CameraWidgetType {
-    try_gp_internal!(gp_widget_get_type(*self.inner, &out widget_type).unwrap());
-    widget_type
+    self.with_widget_unwrap(|widget| {
+      try_gp_internal!(gp_widget_get_type(widget, &out widget_type)?);
+      Ok(widget_type)
+    })
   }
 
   /// If true, the widget cannot be written
   pub fn readonly(&self) -> bool {
-    try_gp_internal!(gp_widget_get_readonly(*self.inner, &out readonly).unwrap());
-    readonly == 1
+    self.with_widget_unwrap(|widget| {
+      try_gp_internal!(gp_widget_get_readonly(widget, &out readonly)?);
+      Ok(readonly == 1)
+    })
   }
 
   /// Get the widget label
   pub fn label(&self) -> String {
-    try_gp_internal!(gp_widget_get_label(*self.inner, &out label).unwrap());
-    chars_to_string(label)
+    self.with_widget_unwrap(|widget| {
+      try_gp_internal!(gp_widget_get_label(widget, &out label)?);
+      Ok(chars_to_string(label))
+    })
   }
 
   /// Get the widget name
   pub fn name(&self) -> String {
-    try_gp_internal!(gp_widget_get_name(*self.inner, &out name).unwrap());
-    chars_to_string(name)
+    self.with_widget_unwrap(|widget| {
+      try_gp_internal!(gp_widget_get_name(widget, &out name)?);
+      Ok(chars_to_string(name))
+    })
   }
 
   /// Get the widget id
   pub fn id(&self) -> i32 {
-    try_gp_internal!(gp_widget_get_id(*self.inner, &out id).unwrap());
-    id
+    self.with_widget_unwrap(|widget| {
+      try_gp_internal!(gp_widget_get_id(widget, &out id)?);
+      Ok(id)
+    })
   }
 
   /// Get information about the widget
   pub fn info(&self) -> String {
-    try_gp_internal!(gp_widget_get_info(*self.inner, &out info).unwrap());
-    chars_to_string(info)
+    self.with_widget_unwrap(|widget| {
+      try_gp_internal!(gp_widget_get_info(widget, &out info)?);
+      Ok(chars_to_string(info))
+    })
   }
 
-  fn fmt_fields(&self, f: &mut fmt::DebugStruct) {
-    f.field("id", &self.id())
-      .field("name", &self.name())
-      .field("label", &self.label())
-      .field("readonly", &self.readonly());
+  /// Section containing the widget, [`None`] for the root of the tree
+  pub fn parent(&self) -> Option<GroupWidget> {
+    let parent = self.with_widget_unwrap(|widget| {
+      try_gp_internal!(gp_widget_get_parent(widget, &out parent)?);
+      Ok(BackgroundPtr(parent))
+    });
+
+    if parent.is_null() {
+      return None;
+    }
+
+    Widget::new_shared(parent).try_into().ok()
+  }
+
+  /// Root of the tree containing the widget (the widget itself if it has no parent)
+  pub fn root(&self) -> Widget {
+    let root = self.with_widget_unwrap(|widget| {
+      try_gp_internal!(gp_widget_get_root(widget, &out root)?);
+      Ok(BackgroundPtr(root))
+    });
+
+    Widget::new_shared(root)
+  }
+
+  /// Names of the widget and its parents like `/main/capturesettings/shutterspeed`
+  ///
+  /// Widgets fetched with [`Camera::config_key`](crate::Camera::config_key) may not
+  /// be attached to a tree, their path only contains their own name.
+  pub fn path(&self) -> String {
+    self.with_widget_unwrap(|widget| unsafe { widget_path(widget) })
   }
 
-  unsafe fn raw_value<T>(&self) -> T {
-    let mut value = std::mem::MaybeUninit::<T>::uninit();
-    try_gp_internal!(gp_widget_get_value(*self.inner, value.as_mut_ptr().cast::<c_void>()).unwrap());
-    value.assume_init()
+  /// Read the value, `T` must match the widget type
+  unsafe fn raw_value<T: 'static + Send>(&self) -> T {
+    self.with_widget_unwrap(|widget| unsafe { get_value::<T>(widget) })
   }
 
-  unsafe fn set_raw_value<T>(&self, value: *const T) {
-    try_gp_internal!(gp_widget_set_value(*self.inner, value.cast::<c_void>()).unwrap());
+  /// Read the value of a text, radio or menu widget
+  fn string_value(&self) -> String {
+    self.with_widget_unwrap(|widget| unsafe { get_string_value(widget) })
+  }
+
+  /// Setters fail for read-only widgets, the `_forced` variants skip this check
+  fn check_writable(&self) -> Result<()> {
+    if self.readonly() {
+      return Err(Error::new(
+        libgphoto2_sys::GP_ERROR_NOT_SUPPORTED,
+        Some(format!("Widget {} is read-only", self.name())),
+      ));
+    }
+
+    Ok(())
+  }
+
+  /// Set the value, `T` must match the widget type
+  unsafe fn set_raw_value<T: 'static + Send>(&self, value: T) {
+    self.with_widget_unwrap(move |widget| {
+      try_gp_internal!(gp_widget_set_value(widget, std::ptr::addr_of!(value).cast::<c_void>())?);
+      Ok(())
+    })
+  }
+
+  /// Set the value of a text, radio or menu widget
+  fn set_string_value(&self, value: &str) -> Result<()> {
+    let value = ffi::CString::new(value)?;
+
+    // libgphoto2 copies the string, which can fail
+    self.with_widget(move |widget| {
+      try_gp_internal!(gp_widget_set_value(widget, value.as_ptr().cast::<c_void>())?);
+
+      Ok(())
+    })
+  }
+
+  /// Copy the value of this widget to another widget of the same type
+  pub(crate) fn copy_value_to(&self, target: &WidgetBase) -> Result<()> {
+    if self.ty() != target.ty() {
+      return Err(Error::from(format!("Widget {} changed its type", self.name())));
+    }
+
+    unsafe {
+      match self.ty() {
+        CameraWidgetType::GP_WIDGET_TEXT
+        | CameraWidgetType::GP_WIDGET_RADIO
+        | CameraWidgetType::GP_WIDGET_MENU => target.set_string_value(&self.string_value())?,
+        CameraWidgetType::GP_WIDGET_RANGE => target.set_raw_value::<f32>(self.raw_value::<f32>()),
+        CameraWidgetType::GP_WIDGET_TOGGLE | CameraWidgetType::GP_WIDGET_DATE => {
+          target.set_raw_value::<c_int>(self.raw_value::<c_int>())
+        }
+        // Setting a button presses it
+        CameraWidgetType::GP_WIDGET_BUTTON => target.with_widget(|widget| {
+          try_gp_internal!(gp_widget_set_changed(widget, 1)?);
+          Ok::<_, Error>(())
+        })?,
+        CameraWidgetType::GP_WIDGET_WINDOW | CameraWidgetType::GP_WIDGET_SECTION => {}
+      }
+    }
+
+    Ok(())
   }
 }
 
 impl fmt::Debug for WidgetBase {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let data = self.data(false);
+
     let mut f = f.debug_struct("WidgetBase");
-    f.field("type", &self.ty());
-    self.fmt_fields(&mut f);
+    f.field("type", &data.ty);
+    data.fmt_fields(&mut f);
     f.finish()
   }
 }
 
-as_ref!(WidgetBase -> libgphoto2_sys::CameraWidget, **self.inner);
+as_ref!(WidgetBase -> libgphoto2_sys::CameraWidget, *self.inner.any_thread());
 
 macro_rules! join_strings {
   ($delim:literal, $first:expr $(, $rest:expr)*) => {
@@ -166,7 +311,7 @@ macro_rules! typed_widgets {
 
     impl Widget {
       pub(crate) fn new_owned(widget: BackgroundPtr<libgphoto2_sys::CameraWidget>) -> Self {
-        let inner = WidgetBase { inner: widget };
+        let inner = WidgetBase::new(widget);
 
         match inner.ty() {
           $($(libgphoto2_sys::CameraWidgetType::$gp_name)|+ => Widget::$variant($name { inner }),)*
@@ -221,6 +366,15 @@ macro_rules! typed_widgets {
       }
     }
 
+    impl Widget {
+      /// Untyped widget, without taking another reference
+      pub(crate) fn into_base(self) -> WidgetBase {
+        match self {
+          $(Self::$variant(widget) => widget.inner),*
+        }
+      }
+    }
+
     $(
       #[doc = concat!(
         "Widget representing ",
@@ -240,12 +394,8 @@ macro_rules! typed_widgets {
 
       impl std::fmt::Debug for $name {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-          let mut f = f.debug_struct(stringify!($name));
-          // Add fields from core WidgetBase.
-          self.inner.fmt_fields(&mut f);
-          // Add fields widget itself wishes to report.
-          $name::fmt_fields(self, &mut f);
-          f.finish()
+          // Children are only listed by `{:#?}`
+          self.inner.data(f.alternate()).fmt(f)
         }
       }
 
@@ -270,22 +420,358 @@ typed_widgets!(
   DateWidget, Date = GP_WIDGET_DATE;
 );
 
+/// Number of a numeric choice like `1/250`, `0.8s`, `f/5.6` or `+0.3`
+pub(crate) fn parse_number(choice: &str) -> Option<f64> {
+  let choice = choice.trim();
+  let choice = choice
+    .strip_prefix("f/")
+    .or_else(|| choice.strip_prefix("F/"))
+    .or_else(|| choice.strip_prefix(['f', 'F']))
+    .unwrap_or(choice);
+  let choice = choice.trim_end_matches(['s', '"']).trim();
+
+  let number = match choice.split_once('/') {
+    Some((numerator, denominator)) => {
+      numerator.trim().parse::<f64>().ok()? / denominator.trim().parse::<f64>().ok()?
+    }
+    None => choice.parse::<f64>().ok()?,
+  };
+
+  Some(number).filter(|number| number.is_finite())
+}
+
+/// Description of a widget for generating a control for it, see [`GroupWidget::spec_tree`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WidgetSpec {
+  /// [Path](WidgetBase::path) of the widget
+  pub path: String,
+  /// Name of the widget
+  pub name: String,
+  /// Label of the widget, meant to be shown to users
+  pub label: String,
+  /// Help text of the widget, often empty
+  pub info: String,
+  /// Whether the value can't be changed
+  pub readonly: bool,
+  /// Type of the widget with its constraints and current value
+  #[cfg_attr(feature = "serde", serde(flatten))]
+  pub kind: WidgetKind,
+}
+
+/// Type of a [`WidgetSpec`], serialized as the `type` field
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+  feature = "serde",
+  derive(serde::Serialize),
+  serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum WidgetKind {
+  /// Section containing other widgets, like a tab
+  Section {
+    /// Specs of the children
+    children: Vec<WidgetSpec>,
+  },
+  /// Free text, like a text field
+  Text {
+    /// Current text
+    value: String,
+  },
+  /// Number in a range, like a slider
+  Range {
+    /// Smallest value
+    min: f32,
+    /// Largest value
+    max: f32,
+    /// Increment between values
+    step: f32,
+    /// Current value
+    value: f32,
+  },
+  /// On or off, like a checkbox
+  Toggle {
+    /// Current state, [`None`] if unknown
+    value: Option<bool>,
+  },
+  /// One of a list of choices, like a dropdown
+  Radio {
+    /// Available choices
+    choices: Vec<String>,
+    /// Current choice
+    value: String,
+  },
+  /// Action, like a button
+  Button,
+  /// Date and time
+  Date {
+    /// Current unix timestamp
+    value: i64,
+  },
+}
+
 /// Helper that prints `[_; count]` when using `{:?}` or the given list when using `{:#?}`.
-struct MaybeListFmt<F>(F);
+struct MaybeListFmt<'a, T>(usize, &'a [T]);
 
-impl<Iter: IntoIterator, F: Fn() -> Iter> fmt::Debug for MaybeListFmt<F>
-where
-  Iter::IntoIter: ExactSizeIterator,
-  Iter::Item: fmt::Debug,
-{
+impl<T: fmt::Debug> fmt::Debug for MaybeListFmt<'_, T> {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    let list = (self.0)();
     if f.alternate() {
-      f.debug_list().entries(list).finish()
+      f.debug_list().entries(self.1).finish()
     } else {
-      write!(f, "[_; {}]", list.into_iter().len())
+      write!(f, "[_; {}]", self.0)
+    }
+  }
+}
+
+/// Properties and value of a widget, read in a single task for [`fmt::Debug`],
+/// [`Widget::spec`] and [`Widget::to_text`]
+struct WidgetData {
+  ty: CameraWidgetType,
+  path: String,
+  id: c_int,
+  name: String,
+  label: String,
+  info: String,
+  readonly: bool,
+  value: WidgetDataValue,
+}
+
+enum WidgetDataValue {
+  /// The children are only read by recursive reads
+  Group {
+    count: usize,
+    children: Vec<WidgetData>,
+  },
+  Text(String),
+  Range {
+    range: RangeInclusive<f32>,
+    step: f32,
+    value: f32,
+  },
+  Toggle(ToggleValue),
+  Radio {
+    choices: Vec<String>,
+    choice: String,
+  },
+  Button,
+  Date(c_int),
+}
+
+impl WidgetData {
+  /// Read `widget` and the tree below it if `recursive`, must be called on the libgphoto2 thread
+  ///
+  /// The path of children is built from `parent_path`, the path of the first widget is looked up.
+  unsafe fn read(
+    widget: *mut libgphoto2_sys::CameraWidget,
+    parent_path: Option<&str>,
+    recursive: bool,
+  ) -> Result<Self> {
+    try_gp_internal!(gp_widget_get_type(widget, &out ty)?);
+    try_gp_internal!(gp_widget_get_id(widget, &out id)?);
+    try_gp_internal!(gp_widget_get_name(widget, &out name)?);
+    try_gp_internal!(gp_widget_get_label(widget, &out label)?);
+    try_gp_internal!(gp_widget_get_info(widget, &out info)?);
+    try_gp_internal!(gp_widget_get_readonly(widget, &out readonly)?);
+
+    let name = chars_to_string(name);
+    let path = match parent_path {
+      Some(parent_path) => format!("{parent_path}/{name}"),
+      None => widget_path(widget)?,
+    };
+
+    let value = match ty {
+      CameraWidgetType::GP_WIDGET_WINDOW | CameraWidgetType::GP_WIDGET_SECTION => {
+        try_gp_internal!(let count = gp_widget_count_children(widget)?);
+
+        let children = if recursive {
+          (0..count)
+            .map(|i| {
+              try_gp_internal!(gp_widget_get_child(widget, i, &out child)?);
+              Self::read(child, Some(&path), true)
+            })
+            .collect::<Result<_>>()?
+        } else {
+          Vec::new()
+        };
+
+        WidgetDataValue::Group { count: count.try_into()?, children }
+      }
+      CameraWidgetType::GP_WIDGET_TEXT => WidgetDataValue::Text(get_string_value(widget)?),
+      CameraWidgetType::GP_WIDGET_RANGE => {
+        try_gp_internal!(gp_widget_get_range(widget, &out min, &out max, &out step)?);
+        WidgetDataValue::Range { range: min..=max, step, value: get_value(widget)? }
+      }
+      CameraWidgetType::GP_WIDGET_TOGGLE => {
+        WidgetDataValue::Toggle(ToggleValue::from_raw(get_value(widget)?))
+      }
+      CameraWidgetType::GP_WIDGET_RADIO | CameraWidgetType::GP_WIDGET_MENU => {
+        WidgetDataValue::Radio { choices: get_choices(widget)?, choice: get_string_value(widget)? }
+      }
+      CameraWidgetType::GP_WIDGET_BUTTON => WidgetDataValue::Button,
+      CameraWidgetType::GP_WIDGET_DATE => WidgetDataValue::Date(get_value(widget)?),
+    };
+
+    Ok(Self {
+      ty,
+      path,
+      id,
+      name,
+      label: chars_to_string(label),
+      info: chars_to_string(info),
+      readonly: readonly == 1,
+      value,
+    })
+  }
+
+  fn fmt_fields(&self, f: &mut fmt::DebugStruct) {
+    f.field("id", &self.id)
+      .field("name", &self.name)
+      .field("label", &self.label)
+      .field("readonly", &self.readonly);
+  }
+
+  fn into_spec(self) -> WidgetSpec {
+    let kind = match self.value {
+      WidgetDataValue::Group { children, .. } => {
+        WidgetKind::Section { children: children.into_iter().map(Self::into_spec).collect() }
+      }
+      WidgetDataValue::Text(value) => WidgetKind::Text { value },
+      WidgetDataValue::Range { range, step, value } => {
+        WidgetKind::Range { min: *range.start(), max: *range.end(), step, value }
+      }
+      WidgetDataValue::Toggle(value) => WidgetKind::Toggle { value: value.as_bool() },
+      WidgetDataValue::Radio { choices, choice } => WidgetKind::Radio { choices, value: choice },
+      WidgetDataValue::Button => WidgetKind::Button,
+      WidgetDataValue::Date(value) => WidgetKind::Date { value: value.into() },
+    };
+
+    WidgetSpec {
+      path: self.path,
+      name: self.name,
+      label: self.label,
+      info: self.info,
+      readonly: self.readonly,
+      kind,
+    }
+  }
+
+  /// Entry of the widget in the format of `gphoto2 --list-all-config`, the children of groups
+  fn write_text(&self, out: &mut String) -> fmt::Result {
+    use fmt::Write;
+
+    let ty = match &self.value {
+      WidgetDataValue::Group { children, .. } => {
+        return children.iter().try_for_each(|child| child.write_text(out));
+      }
+      WidgetDataValue::Text(_) => "TEXT",
+      WidgetDataValue::Range { .. } => "RANGE",
+      WidgetDataValue::Toggle(_) => "TOGGLE",
+      WidgetDataValue::Radio { .. } if self.ty == CameraWidgetType::GP_WIDGET_MENU => "MENU",
+      WidgetDataValue::Radio { .. } => "RADIO",
+      WidgetDataValue::Button => "BUTTON",
+      WidgetDataValue::Date(_) => "DATE",
+    };
+
+    writeln!(out, "{}", self.path)?;
+    writeln!(out, "Label: {}", self.label)?;
+    writeln!(out, "Readonly: {}", u8::from(self.readonly))?;
+    writeln!(out, "Type: {ty}")?;
+
+    match &self.value {
+      WidgetDataValue::Text(value) => writeln!(out, "Current: {value}")?,
+      WidgetDataValue::Range { range, step, value } => {
+        writeln!(out, "Current: {value}")?;
+        writeln!(out, "Bottom: {}", range.start())?;
+        writeln!(out, "Top: {}", range.end())?;
+        writeln!(out, "Step: {step}")?;
+      }
+      WidgetDataValue::Toggle(value) => writeln!(out, "Current: {}", value.to_raw())?,
+      WidgetDataValue::Radio { choices, choice } => {
+        writeln!(out, "Current: {choice}")?;
+        for (i, choice) in choices.iter().enumerate() {
+          writeln!(out, "Choice: {i} {choice}")?;
+        }
+      }
+      WidgetDataValue::Date(timestamp) => writeln!(out, "Current: {timestamp}")?,
+      WidgetDataValue::Group { .. } | WidgetDataValue::Button => {}
     }
+
+    writeln!(out, "END")
+  }
+}
+
+impl fmt::Debug for WidgetData {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let name = match self.value {
+      WidgetDataValue::Group { .. } => "GroupWidget",
+      WidgetDataValue::Text(_) => "TextWidget",
+      WidgetDataValue::Range { .. } => "RangeWidget",
+      WidgetDataValue::Toggle(_) => "ToggleWidget",
+      WidgetDataValue::Radio { .. } => "RadioWidget",
+      WidgetDataValue::Button => "ButtonWidget",
+      WidgetDataValue::Date(_) => "DateWidget",
+    };
+
+    let mut f = f.debug_struct(name);
+    self.fmt_fields(&mut f);
+
+    match &self.value {
+      WidgetDataValue::Group { count, children } => {
+        f.field("children", &MaybeListFmt(*count, children))
+      }
+      WidgetDataValue::Text(value) => f.field("value", value),
+      WidgetDataValue::Range { range, step, value } => {
+        f.field("range", range).field("step", step).field("value", value)
+      }
+      WidgetDataValue::Toggle(value) => f.field("toggled", &value.as_bool()),
+      WidgetDataValue::Radio { choices, choice } => {
+        f.field("choices", &MaybeListFmt(choices.len(), choices)).field("choice", choice)
+      }
+      WidgetDataValue::Button => &mut f,
+      WidgetDataValue::Date(timestamp) => f.field("timestamp", timestamp),
+    };
+
+    f.finish()
+  }
+}
+
+/// Read the value of a widget, `T` must match the widget type
+unsafe fn get_value<T>(widget: *mut libgphoto2_sys::CameraWidget) -> Result<T> {
+  let mut value = std::mem::MaybeUninit::<T>::uninit();
+  try_gp_internal!(gp_widget_get_value(widget, value.as_mut_ptr().cast::<c_void>())?);
+  Ok(value.assume_init())
+}
+
+/// Read the value of a text, radio or menu widget
+unsafe fn get_string_value(widget: *mut libgphoto2_sys::CameraWidget) -> Result<String> {
+  Ok(chars_to_string(get_value::<*const c_char>(widget)?))
+}
+
+/// Choices of a radio or menu widget
+unsafe fn get_choices(widget: *mut libgphoto2_sys::CameraWidget) -> Result<Vec<String>> {
+  try_gp_internal!(let count = gp_widget_count_choices(widget)?);
+
+  (0..count)
+    .map(|i| {
+      try_gp_internal!(gp_widget_get_choice(widget, i, &out choice)?);
+      Ok(chars_to_string(choice))
+    })
+    .collect()
+}
+
+/// Names of the widget and its parents, see [`WidgetBase::path`]
+unsafe fn widget_path(widget: *mut libgphoto2_sys::CameraWidget) -> Result<String> {
+  let mut names = Vec::new();
+  let mut current = widget;
+
+  while !current.is_null() {
+    try_gp_internal!(gp_widget_get_name(current, &out name)?);
+    try_gp_internal!(gp_widget_get_parent(current, &out parent)?);
+
+    names.push(chars_to_string(name));
+    current = parent;
   }
+
+  Ok(names.iter().rev().map(|name| format!("/{name}")).collect())
 }
 
 impl GroupWidget {
@@ -294,61 +780,205 @@ impl GroupWidget {
     WidgetIterator { parent_widget: self, range: 0..self.children_count() }
   }
 
+  /// All writable widgets below this group (recursively), together with their [path](WidgetBase::path)
+  ///
+  /// Sections and read-only widgets are skipped, buttons are included.
+  ///
+  /// ```no_run
+  /// use gphoto2::{Context, Result};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let camera = Context::new()?.autodetect_camera().wait()?;
+  ///
+  /// for (path, widget) in camera.config().wait()?.leaves() {
+  ///   println!("{path}: {widget:?}");
+  /// }
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn leaves(&self) -> impl Iterator<Item = (String, Widget)> {
+    fn collect(group: &GroupWidget, leaves: &mut Vec<(String, Widget)>) {
+      for child in group.children_iter() {
+        match child {
+          Widget::Group(group) => collect(&group, leaves),
+          _ if child.readonly() => {}
+          _ => leaves.push((child.path(), child)),
+        }
+      }
+    }
+
+    let mut leaves = Vec::new();
+    collect(self, &mut leaves);
+
+    leaves.into_iter()
+  }
+
+  /// Text dump of all widgets below this group in the format of `gphoto2 --list-all-config`
+  ///
+  /// Each widget is listed with its path, label, read-only flag, type, current value and
+  /// choices or range, sections are only part of the paths. The output is meant for bug reports
+  /// and command line tools.
+  ///
+  /// ```text
+  /// /main/imgsettings/iso
+  /// Label: ISO Speed
+  /// Readonly: 0
+  /// Type: RADIO
+  /// Current: 100
+  /// Choice: 0 100
+  /// Choice: 1 200
+  /// END
+  /// ```
+  pub fn to_text_tree(&self) -> String {
+    let mut out = String::new();
+    // Writing to a string can't fail
+    let _ = self.data(true).write_text(&mut out);
+
+    out
+  }
+
+  /// Spec of this group and all widgets below it, e.g. to generate a settings UI
+  ///
+  /// Unlike [`GroupWidget::leaves`], read-only widgets are included so they can be shown as
+  /// disabled controls.
+  ///
+  /// ```no_run
+  /// use gphoto2::{widget::WidgetKind, Context, Result};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let camera = Context::new()?.autodetect_camera().wait()?;
+  ///
+  /// if let WidgetKind::Section { children } = camera.config().wait()?.spec_tree().kind {
+  ///   for section in children {
+  ///     println!("Tab {}", section.label);
+  ///   }
+  /// }
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn spec_tree(&self) -> WidgetSpec {
+    self.data(true).into_spec()
+  }
+
   /// Counts the children of the widget
   pub fn children_count(&self) -> usize {
-    try_gp_internal!(let count = gp_widget_count_children(self.as_ptr()).unwrap());
-    count.try_into().unwrap()
+    self.with_widget_unwrap(|widget| {
+      try_gp_internal!(let count = gp_widget_count_children(widget)?);
+      Ok(count.try_into()?)
+    })
   }
 
   /// Gets a child by its index
   pub fn get_child(&self, index: usize) -> Result<Widget> {
-    try_gp_internal!(gp_widget_get_child(self.as_ptr(), index.try_into()?, &out child)?);
+    let index = index.try_into()?;
 
-    Ok(Widget::new_shared(BackgroundPtr(child)))
+    self.get_child_with(move |widget| {
+      try_gp_internal!(gp_widget_get_child(widget, index, &out child)?);
+      Ok(BackgroundPtr(child))
+    })
   }
 
   /// Get a child by its id
   pub fn get_child_by_id(&self, id: usize) -> Result<Widget> {
-    try_gp_internal!(gp_widget_get_child_by_id(self.as_ptr(), id.try_into()?, &out child)?);
+    let id = id.try_into()?;
 
-    Ok(Widget::new_shared(BackgroundPtr(child)))
+    self.get_child_with(move |widget| {
+      try_gp_internal!(gp_widget_get_child_by_id(widget, id, &out child)?);
+      Ok(BackgroundPtr(child))
+    })
   }
 
   /// Get a child by its label
   pub fn get_child_by_label(&self, label: &str) -> Result<Widget> {
-    try_gp_internal!(gp_widget_get_child_by_label(self.as_ptr(), to_c_string!(label), &out child)?);
+    let label = label.to_owned();
 
-    Ok(Widget::new_shared(BackgroundPtr(child)))
+    self.get_child_with(move |widget| {
+      try_gp_internal!(gp_widget_get_child_by_label(widget, to_c_string!(label), &out child)?);
+      Ok(BackgroundPtr(child))
+    })
   }
 
   /// Get a child by its name
   pub fn get_child_by_name(&self, name: &str) -> Result<Widget> {
-    try_gp_internal!(gp_widget_get_child_by_name(self.as_ptr(), to_c_string!(name), &out child)?);
+    let name = name.to_owned();
 
-    Ok(Widget::new_shared(BackgroundPtr(child)))
+    self.get_child_with(move |widget| {
+      try_gp_internal!(gp_widget_get_child_by_name(widget, to_c_string!(name), &out child)?);
+      Ok(BackgroundPtr(child))
+    })
   }
 
-  fn fmt_fields(&self, f: &mut fmt::DebugStruct) {
-    f.field("children", &MaybeListFmt(|| self.children_iter()));
+  /// Find a widget below this group (recursively) by its name
+  ///
+  /// Unlike [`get_child_by_name`](Self::get_child_by_name), which depends on the lookup of the
+  /// driver, this searches the tree depth-first in Rust, so it behaves the same for all drivers.
+  /// Visits every widget of the tree in the worst case, each taking a call on the libgphoto2
+  /// thread.
+  pub fn find_descendant_by_name(&self, name: &str) -> Result<Widget> {
+    self.find_descendant(|widget| widget.name() == name).ok_or_else(|| {
+      Error::new(libgphoto2_sys::GP_ERROR_BAD_PARAMETERS, Some(format!("No widget named {name}")))
+    })
+  }
+
+  /// Find a widget below this group (recursively) by its label
+  ///
+  /// See [`find_descendant_by_name`](Self::find_descendant_by_name).
+  pub fn find_descendant_by_label(&self, label: &str) -> Result<Widget> {
+    self.find_descendant(|widget| widget.label() == label).ok_or_else(|| {
+      Error::new(
+        libgphoto2_sys::GP_ERROR_BAD_PARAMETERS,
+        Some(format!("No widget labeled {label}")),
+      )
+    })
+  }
+
+  /// First widget in depth-first order for which `matches` is true
+  fn find_descendant(&self, matches: impl Fn(&Widget) -> bool + Copy) -> Option<Widget> {
+    self.children_iter().find_map(|child| {
+      if matches(&child) {
+        return Some(child);
+      }
+
+      match &child {
+        Widget::Group(group) => group.find_descendant(matches),
+        _ => None,
+      }
+    })
+  }
+
+  /// Look up a child on the libgphoto2 thread and take a reference to it
+  fn get_child_with(
+    &self,
+    lookup: impl FnOnce(
+        *mut libgphoto2_sys::CameraWidget,
+      ) -> Result<BackgroundPtr<libgphoto2_sys::CameraWidget>>
+      + 'static
+      + Send,
+  ) -> Result<Widget> {
+    Ok(Widget::new_shared(self.with_widget(lookup)?))
   }
 }
 
 impl TextWidget {
   /// Get the value of the widget.
   pub fn value(&self) -> String {
-    chars_to_string(unsafe { self.raw_value::<*const c_char>() })
+    self.string_value()
   }
 
   /// Set the value of the widget.
+  ///
+  /// Fails with [`ErrorKind::NotSupported`](crate::error::ErrorKind::NotSupported)
+  /// if the widget is read-only.
   pub fn set_value(&self, value: &str) -> Result<()> {
-    unsafe {
-      self.set_raw_value::<c_char>(to_c_string!(value));
-    }
-    Ok(())
+    self.check_writable()?;
+    self.set_value_forced(value)
   }
 
-  fn fmt_fields(&self, f: &mut fmt::DebugStruct) {
-    f.field("value", &self.value());
+  /// Set the value of the widget, even if it is read-only
+  ///
+  /// Some drivers report writable widgets as read-only.
+  pub fn set_value_forced(&self, value: &str) -> Result<()> {
+    self.set_string_value(value)
   }
 }
 
@@ -359,92 +989,220 @@ impl RangeWidget {
   }
 
   /// Set the value of the widget.
-  pub fn set_value(&self, value: f32) {
-    unsafe { self.set_raw_value::<f32>(&value) }
+  ///
+  /// Fails with [`ErrorKind::NotSupported`](crate::error::ErrorKind::NotSupported)
+  /// if the widget is read-only.
+  pub fn set_value(&self, value: f32) -> Result<()> {
+    self.check_writable()?;
+    self.set_value_forced(value);
+    Ok(())
+  }
+
+  /// Set the value of the widget, even if it is read-only
+  pub fn set_value_forced(&self, value: f32) {
+    unsafe { self.set_raw_value::<f32>(value) }
   }
 
   /// Get the range and increment step of the widget.
   pub fn range_and_step(&self) -> (RangeInclusive<f32>, f32) {
-    try_gp_internal!(gp_widget_get_range(self.as_ptr(), &out min, &out max, &out step).unwrap());
-    (min..=max, step)
+    self.with_widget_unwrap(|widget| {
+      try_gp_internal!(gp_widget_get_range(widget, &out min, &out max, &out step)?);
+      Ok((min..=max, step))
+    })
   }
+}
 
-  fn fmt_fields(&self, f: &mut fmt::DebugStruct) {
-    let (range, step) = self.range_and_step();
-    f.field("range", &range).field("step", &step).field("value", &self.value());
+/// State of a [`ToggleWidget`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ToggleValue {
+  /// Not toggled
+  Off,
+  /// Toggled
+  On,
+  /// State is unknown or not available, stored as `2` by libgphoto2
+  Indeterminate,
+}
+
+impl ToggleValue {
+  /// The state as [`bool`], [`None`] if indeterminate
+  pub fn as_bool(self) -> Option<bool> {
+    match self {
+      Self::Off => Some(false),
+      Self::On => Some(true),
+      Self::Indeterminate => None,
+    }
+  }
+
+  fn from_raw(raw: c_int) -> Self {
+    match raw {
+      0 => Self::Off,
+      1 => Self::On,
+      _ => Self::Indeterminate,
+    }
+  }
+
+  fn to_raw(self) -> c_int {
+    match self {
+      Self::Off => 0,
+      Self::On => 1,
+      Self::Indeterminate => 2,
+    }
+  }
+}
+
+impl From<bool> for ToggleValue {
+  fn from(toggled: bool) -> Self {
+    if toggled {
+      Self::On
+    } else {
+      Self::Off
+    }
   }
 }
 
 impl ToggleWidget {
   /// Check if the widget is toggled.
   pub fn toggled(&self) -> Option<bool> {
-    let value = unsafe { self.raw_value::<c_int>() };
-    match value {
-      0 => Some(false),
-      1 => Some(true),
-      _ => None,
-    }
+    self.value().as_bool()
+  }
+
+  /// State of the widget, values other than `0` and `1` are [`ToggleValue::Indeterminate`]
+  pub fn value(&self) -> ToggleValue {
+    ToggleValue::from_raw(unsafe { self.raw_value::<c_int>() })
+  }
+
+  /// Set the state of the widget, including [`ToggleValue::Indeterminate`]
+  ///
+  /// Fails with [`ErrorKind::NotSupported`](crate::error::ErrorKind::NotSupported)
+  /// if the widget is read-only.
+  pub fn set_value(&self, value: ToggleValue) -> Result<()> {
+    self.check_writable()?;
+    self.set_value_forced(value);
+    Ok(())
+  }
+
+  /// Set the state of the widget, even if it is read-only
+  pub fn set_value_forced(&self, value: ToggleValue) {
+    unsafe { self.set_raw_value::<c_int>(value.to_raw()) }
   }
 
   /// Set the toggled state of the widget.
-  pub fn set_toggled(&self, value: bool) {
-    unsafe { self.set_raw_value::<c_int>(&value.into()) }
+  ///
+  /// Fails with [`ErrorKind::NotSupported`](crate::error::ErrorKind::NotSupported)
+  /// if the widget is read-only.
+  pub fn set_toggled(&self, value: bool) -> Result<()> {
+    self.check_writable()?;
+    self.set_toggled_forced(value);
+    Ok(())
   }
 
-  fn fmt_fields(&self, f: &mut fmt::DebugStruct) {
-    f.field("toggled", &self.toggled());
+  /// Set the toggled state of the widget, even if it is read-only
+  pub fn set_toggled_forced(&self, value: bool) {
+    self.set_value_forced(value.into())
   }
 }
 
 /// Iterator over the choices of a [`RadioWidget`].
 pub struct ChoicesIter<'a> {
-  widget: &'a RadioWidget,
-  range: Range<c_int>,
+  choices: std::vec::IntoIter<String>,
+  _widget: PhantomData<&'a RadioWidget>,
 }
 
 impl<'a> Iterator for ChoicesIter<'a> {
   type Item = String;
 
   fn next(&mut self) -> Option<Self::Item> {
-    self.range.next().map(|i| {
-      try_gp_internal!(gp_widget_get_choice(self.widget.as_ptr(), i, &out choice).unwrap());
-      chars_to_string(choice)
-    })
+    self.choices.next()
   }
 
   fn size_hint(&self) -> (usize, Option<usize>) {
-    self.range.size_hint()
+    self.choices.size_hint()
   }
 }
 
 impl ExactSizeIterator for ChoicesIter<'_> {
   fn len(&self) -> usize {
-    self.range.len()
+    self.choices.len()
   }
 }
 
 impl RadioWidget {
   /// Get list of the available choices.
+  ///
+  /// All choices are read at once.
   pub fn choices_iter(&self) -> ChoicesIter<'_> {
-    try_gp_internal!(let choice_count = gp_widget_count_choices(self.as_ptr()).unwrap());
-    ChoicesIter { widget: self, range: 0..choice_count }
+    let choices = self.with_widget_unwrap(|widget| unsafe { get_choices(widget) });
+    ChoicesIter { choices: choices.into_iter(), _widget: PhantomData }
   }
 
   /// Get the current choice.
   pub fn choice(&self) -> String {
-    chars_to_string(unsafe { self.raw_value::<*const c_char>() })
+    self.string_value()
   }
 
   /// Set the current choice.
+  ///
+  /// Fails with [`ErrorKind::NotSupported`](crate::error::ErrorKind::NotSupported)
+  /// if the widget is read-only.
   pub fn set_choice(&self, value: &str) -> Result<()> {
-    unsafe {
-      self.set_raw_value::<c_char>(to_c_string!(value));
-    }
-    Ok(())
+    self.check_writable()?;
+    self.set_choice_forced(value)
   }
 
-  fn fmt_fields(&self, f: &mut fmt::DebugStruct) {
-    f.field("choices", &MaybeListFmt(|| self.choices_iter())).field("choice", &self.choice());
+  /// Set the current choice, even if the widget is read-only
+  pub fn set_choice_forced(&self, value: &str) -> Result<()> {
+    self.set_string_value(value)
+  }
+
+  /// Choices which can be parsed as `T`, together with the choice
+  ///
+  /// Choices which fail to parse, like `Auto` in a list of ISO values, are skipped.
+  pub fn choices_as<T: FromStr>(&self) -> Vec<(String, T)> {
+    self
+      .choices_iter()
+      .filter_map(|choice| {
+        let value = choice.trim().parse().ok()?;
+        Some((choice, value))
+      })
+      .collect()
+  }
+
+  /// Set the numeric choice nearest to `value`, if it is at most `tolerance` away
+  ///
+  /// Choices are parsed as numbers in the usual spellings of cameras, like `1/250`, `0.8s`,
+  /// `f/5.6` or `+0.3`, choices without a number are ignored. Returns the selected choice.
+  ///
+  /// ```no_run
+  /// use gphoto2::{widget::RadioWidget, Context, Result};
+  ///
+  /// # fn main() -> Result<()> {
+  /// let camera = Context::new()?.autodetect_camera().wait()?;
+  ///
+  /// // Selects 1/250 if the camera doesn't have 1/200
+  /// let shutter_speed = camera.config_key::<RadioWidget>("shutterspeed").wait()?;
+  /// shutter_speed.set_numeric_choice(1.0 / 200.0, 0.002)?;
+  /// camera.set_config(&shutter_speed).wait()?;
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn set_numeric_choice(&self, value: f64, tolerance: f64) -> Result<String> {
+    let nearest = self
+      .choices_iter()
+      .filter_map(|choice| Some((parse_number(&choice)?, choice)))
+      .map(|(number, choice)| ((number - value).abs(), choice))
+      .filter(|(distance, _)| *distance <= tolerance)
+      .min_by(|(a, _), (b, _)| a.total_cmp(b))
+      .map(|(_, choice)| choice)
+      .ok_or_else(|| {
+        Error::new(
+          libgphoto2_sys::GP_ERROR_BAD_PARAMETERS,
+          Some(format!("{} has no choice within {tolerance} of {value}", self.name())),
+        )
+      })?;
+
+    self.set_choice(&nearest)?;
+    Ok(nearest)
   }
 }
 
@@ -455,30 +1213,139 @@ impl DateWidget {
   }
 
   /// Set the widget's value as a UNIX timestamp.
-  pub fn set_timestamp(&self, value: c_int) {
-    unsafe { self.set_raw_value::<c_int>(&value) }
+  ///
+  /// Fails with [`ErrorKind::NotSupported`](crate::error::ErrorKind::NotSupported)
+  /// if the widget is read-only.
+  pub fn set_timestamp(&self, value: c_int) -> Result<()> {
+    self.check_writable()?;
+    self.set_timestamp_forced(value);
+    Ok(())
   }
 
-  fn fmt_fields(&self, f: &mut fmt::DebugStruct) {
-    f.field("timestamp", &self.timestamp());
+  /// Set the widget's value as a UNIX timestamp, even if it is read-only
+  pub fn set_timestamp_forced(&self, value: c_int) {
+    unsafe { self.set_raw_value::<c_int>(value) }
+  }
+
+  /// Get the widget's value as [`SystemTime`]
+  pub fn datetime(&self) -> SystemTime {
+    clock::from_timestamp(self.timestamp().into())
+  }
+
+  /// Set the widget's value as [`SystemTime`]
+  ///
+  /// libgphoto2 stores dates as 32 bit timestamps, times after 2038 fail with
+  /// [`ErrorKind::BadParameters`](crate::error::ErrorKind::BadParameters). Read-only widgets fail
+  /// like with [`DateWidget::set_timestamp`].
+  pub fn set_datetime(&self, time: SystemTime) -> Result<()> {
+    self.check_writable()?;
+    self.set_datetime_forced(time)
+  }
+
+  /// Set the widget's value as [`SystemTime`], even if it is read-only
+  pub fn set_datetime_forced(&self, time: SystemTime) -> Result<()> {
+    let timestamp = clock::to_timestamp(time)?;
+    let timestamp = c_int::try_from(timestamp).map_err(|_| {
+      Error::new(
+        libgphoto2_sys::GP_ERROR_BAD_PARAMETERS,
+        Some(format!("Timestamp {timestamp} doesn't fit into a date widget")),
+      )
+    })?;
+
+    self.set_timestamp_forced(timestamp);
+    Ok(())
+  }
+
+  /// Get the widget's value as [`chrono::DateTime`]
+  #[cfg(feature = "chrono")]
+  pub fn chrono_datetime(&self) -> chrono::DateTime<chrono::Utc> {
+    self.datetime().into()
+  }
+
+  /// Set the widget's value as [`chrono::DateTime`], see [`DateWidget::set_datetime`]
+  #[cfg(feature = "chrono")]
+  pub fn set_chrono_datetime<Tz: chrono::TimeZone>(
+    &self,
+    time: chrono::DateTime<Tz>,
+  ) -> Result<()> {
+    self.set_datetime(time.into())
   }
 }
 
 impl ButtonWidget {
   /// Press the button.
+  ///
+  /// Buttons without a callback are pressed with [`ButtonWidget::press_via_config`].
   pub fn press(&self, camera: &Camera) -> Result<()> {
-    let callback = unsafe { self.raw_value::<libgphoto2_sys::CameraWidgetCallback>() }
-      .ok_or("Button without callback")?;
-    Error::check(unsafe { callback(*camera.camera, self.as_ptr(), *camera.context.inner) })?;
-    Ok(())
+    let Some(callback) = (unsafe { self.raw_value::<libgphoto2_sys::CameraWidgetCallback>() })
+    else {
+      return self.press_via_config(camera);
+    };
+
+    let widget = self.inner.clone();
+    let camera_ptr = camera.camera;
+    let context = camera.context.inner();
+
+    unsafe {
+      Task::new(move || Error::check(callback(*camera_ptr, *widget.inner, *context)).map(|_| ()))
+    }
+    .camera(camera)
+    .wait()
   }
 
-  fn fmt_fields(&self, _f: &mut fmt::DebugStruct) {}
+  /// Press the button by marking it as changed and setting it on the camera
+  ///
+  /// This is how drivers without button callbacks expect buttons to be pressed.
+  pub fn press_via_config(&self, camera: &Camera) -> Result<()> {
+    self.with_widget(|widget| {
+      try_gp_internal!(gp_widget_set_changed(widget, 1)?);
+      Ok::<_, Error>(())
+    })?;
+    camera.set_config(self).wait()
+  }
 }
 
 impl Widget {
+  /// Entry of this widget in [`GroupWidget::to_text_tree`], the whole tree below groups
+  pub fn to_text(&self) -> String {
+    let mut out = String::new();
+    // Writing to a string can't fail
+    let _ = self.data(true).write_text(&mut out);
+
+    out
+  }
+
+  /// Spec of this widget, the whole tree below groups
+  pub fn spec(&self) -> WidgetSpec {
+    self.data(true).into_spec()
+  }
+
   pub(crate) fn new_shared(widget: BackgroundPtr<libgphoto2_sys::CameraWidget>) -> Self {
-    try_gp_internal!(gp_widget_ref(*widget).unwrap());
+    run_on_worker(move || {
+      try_gp_internal!(gp_widget_ref(*widget)?);
+      Ok::<_, Error>(())
+    })
+    .unwrap();
     Self::new_owned(widget)
   }
 }
+
+#[cfg(all(test, feature = "test"))]
+mod tests {
+  use super::parse_number;
+
+  #[test]
+  fn test_parse_number() {
+    assert_eq!(parse_number("1/250"), Some(0.004));
+    assert_eq!(parse_number("0.8s"), Some(0.8));
+    assert_eq!(parse_number("2\""), Some(2.0));
+    assert_eq!(parse_number("f/5.6"), Some(5.6));
+    assert_eq!(parse_number("F8"), Some(8.0));
+    assert_eq!(parse_number(" +0.3 "), Some(0.3));
+    assert_eq!(parse_number("-1 / 3"), Some(-1.0 / 3.0));
+
+    assert_eq!(parse_number("Auto"), None);
+    assert_eq!(parse_number("1/0"), None);
+    assert_eq!(parse_number(""), None);
+  }
+}